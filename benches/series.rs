@@ -0,0 +1,66 @@
+use chrono::{Duration, NaiveDate};
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use polars::prelude::*;
+use vfunds::api::DailySeries;
+
+fn daily_series(days: i64) -> DailySeries {
+    let start = NaiveDate::from_ymd_opt(2010, 1, 1).unwrap();
+    let dates: Vec<NaiveDate> = (0..days).map(|i| start + Duration::days(i)).collect();
+    let closes: Vec<f64> = (0..days).map(|i| 10.0 + (i as f64 / 10.0).sin()).collect();
+
+    let df = df!("date" => dates, "close" => closes).unwrap();
+    DailySeries::from_dataframe(df, "date").unwrap()
+}
+
+/// Dates of a year looked up in increasing order, as in backtests
+fn lookup_dates() -> Vec<NaiveDate> {
+    let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    (0..365).map(|i| start + Duration::days(i)).collect()
+}
+
+fn bench_get_latest_value(c: &mut Criterion) {
+    let series = daily_series(5000);
+    let dates = lookup_dates();
+
+    c.bench_function("get_latest_value", |b| {
+        b.iter(|| {
+            for date in &dates {
+                black_box(series.get_latest_value::<f64>(date, 15, true, "close"));
+            }
+        })
+    });
+}
+
+fn bench_get_latest_values(c: &mut Criterion) {
+    let series = daily_series(5000);
+    let dates = lookup_dates();
+
+    c.bench_function("get_latest_values", |b| {
+        b.iter(|| {
+            for date in &dates {
+                black_box(series.get_latest_values::<f64>(date, true, "close", 20));
+            }
+        })
+    });
+}
+
+fn bench_get_value(c: &mut Criterion) {
+    let series = daily_series(5000);
+    let dates = lookup_dates();
+
+    c.bench_function("get_value", |b| {
+        b.iter(|| {
+            for date in &dates {
+                black_box(series.get_value::<f64>(date, "close"));
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_get_latest_value,
+    bench_get_latest_values,
+    bench_get_value
+);
+criterion_main!(benches);
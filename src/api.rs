@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     io::{ErrorKind, Write},
     path::{Path, PathBuf},
@@ -11,14 +11,13 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CONFIG, CONFIG_PATH, Config, VERSION, WORKSPACE, backtest,
-    data::series::DailySeries,
+    CONFIG, CONFIG_PATH, Config, VERSION, WORKSPACE, backtest, cache, data,
     ds::*,
     error::*,
-    financial::{get_ticker_kline, get_ticker_kline_with_ds, get_ticker_title},
-    notifier,
+    financial::{self, get_ticker_kline, get_ticker_kline_with_ds, get_ticker_title},
+    notifier, prefetch, quality,
     spec::{FofDefinition, FundDefinition},
-    ticker::Ticker,
+    ticker::{Ticker, TickersIndex},
     utils::{
         self,
         datetime::{date_from_str, date_to_str},
@@ -29,10 +28,26 @@ pub type BacktestCvOptions = backtest::BacktestCvOptions;
 pub type BacktestEvent = backtest::BacktestEvent;
 pub type BacktestMetrics = backtest::BacktestMetrics;
 pub type BacktestOptions = backtest::BacktestOptions;
+pub type BacktestPnl = backtest::BacktestPnl;
 pub type BacktestResult = backtest::BacktestResult;
 pub type BacktestStream = backtest::BacktestStream;
+pub type CacheEntry = cache::CacheEntry;
+pub type DailySeries = data::series::DailySeries;
+pub type FeeSchedule = backtest::fee::FeeSchedule;
+pub type CacheStat = cache::CacheStat;
+pub type Currency = financial::fx::Currency;
+pub type InterestRate = financial::economy::InterestRate;
+pub type MembershipStat = cache::membership::MembershipStat;
+pub type MemoryCacheStat = cache::memory::MemoryCacheStat;
+pub type KlineIssue = quality::KlineIssue;
+pub type KlineIssueKind = quality::KlineIssueKind;
+pub type LocalFileFormat = local::LocalFileFormat;
 pub type Notification = notifier::Notification;
 pub type NotificationType = notifier::NotificationType;
+pub type TickerMatch = financial::search::TickerMatch;
+
+/// How many tickers to fetch at once, as requests in flight are bounded per data source
+pub const MAX_FETCH_CONCURRENCY: usize = limiter::MAX_CONCURRENCY;
 
 #[derive(Serialize, Deserialize)]
 pub struct BacktestOutputResult {
@@ -41,6 +56,12 @@ pub struct BacktestOutputResult {
     pub portfolio: BacktestOutputPortfolio,
     pub metrics: BacktestMetrics,
 
+    #[serde(default)]
+    pub fees: BTreeMap<String, f64>,
+
+    #[serde(default)]
+    pub pnl: BacktestPnl,
+
     #[serde(default)]
     pub order_dates: Vec<NaiveDate>,
 
@@ -82,7 +103,9 @@ pub async fn backtest(
     for (vfund_name, vfund) in vfunds {
         let stream = match vfund {
             Vfund::Fof(fof_definition) => {
-                backtest::fof::backtest_fof(&fof_definition, options).await?
+                let workspace = { WORKSPACE.read().await.clone() };
+                backtest::fof::backtest_fof(&vfund_name, &fof_definition, options, &workspace)
+                    .await?
             }
             Vfund::Fund(fund_definition) => {
                 backtest::fund::backtest_fund(&fund_definition, options).await?
@@ -117,7 +140,9 @@ pub async fn backtest_cv(
     for (vfund_name, vfund) in vfunds {
         let stream = match vfund {
             Vfund::Fof(fof_definition) => {
-                backtest::fof::backtest_fof_cv(&fof_definition, cv_options).await?
+                let workspace = { WORKSPACE.read().await.clone() };
+                backtest::fof::backtest_fof_cv(&vfund_name, &fof_definition, cv_options, &workspace)
+                    .await?
             }
             Vfund::Fund(fund_definition) => {
                 backtest::fund::backtest_fund_cv(&fund_definition, cv_options).await?
@@ -129,6 +154,40 @@ pub async fn backtest_cv(
     Ok(streams)
 }
 
+pub async fn cache_entries(expired_only: bool) -> VfResult<Vec<CacheEntry>> {
+    cache::entries(expired_only).await
+}
+
+pub async fn cache_stats() -> VfResult<Vec<CacheStat>> {
+    cache::stats().await
+}
+
+/// Check quality of the ticker's kline, and mark the ticker if `mark` and it is bad, or unmark
+/// it otherwise, so backtests warn when they use it
+pub async fn check_kline(
+    ticker: &Ticker,
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+    mark: bool,
+) -> VfResult<Vec<KlineIssue>> {
+    let issues = quality::check_kline(ticker, start_date, end_date).await?;
+
+    if mark {
+        let bad_kinds: Vec<String> = issues
+            .iter()
+            .filter(|issue| issue.is_bad())
+            .map(|issue| issue.kind.to_string())
+            .collect();
+        if bad_kinds.is_empty() {
+            cache::marks::unmark(ticker).await?;
+        } else {
+            cache::marks::mark(ticker, &bad_kinds.join(",")).await?;
+        }
+    }
+
+    Ok(issues)
+}
+
 pub async fn check() -> VfResult<Vec<(&'static str, Option<VfError>)>> {
     let (qmt_result, tushare_result) = tokio::join!(qmt::check_api(), tushare::check_api());
 
@@ -138,6 +197,30 @@ pub async fn check() -> VfResult<Vec<(&'static str, Option<VfError>)>> {
     ])
 }
 
+/// Start the mock server of QMT and Tushare APIs on fixtures, and use it instead of the
+/// configured services in the current process
+#[cfg(feature = "mock")]
+pub async fn use_mock_server(fixtures_dir: &Path, port: u16) -> VfResult<std::net::SocketAddr> {
+    let addr = crate::ds::mock::start(fixtures_dir, port).await?;
+    crate::ds::mock::use_server(&addr).await;
+
+    Ok(addr)
+}
+
+/// Export cached data into the local data layout, returns the number of exported files and
+/// the errors of data failed to export
+pub async fn export_local_data(
+    output_dir: Option<&Path>,
+    format: LocalFileFormat,
+) -> VfResult<(usize, Vec<VfError>)> {
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir.to_path_buf(),
+        None => local::local_data_dir().await,
+    };
+
+    local::export(&output_dir, format).await
+}
+
 pub async fn get_config() -> VfResult<Config> {
     let config = { CONFIG.read().await.clone() };
     Ok(config)
@@ -148,6 +231,19 @@ pub async fn get_workspace() -> VfResult<PathBuf> {
     Ok(workspace)
 }
 
+/// Import historical constituents of the index from a CSV file of `date,ticker` rows, returns the
+/// number of snapshots
+pub async fn import_index_membership(index_str: &str, path: &Path) -> VfResult<usize> {
+    let index = TickersIndex::from_str(index_str)?;
+    cache::membership::import(&cache::membership::index_source(&index), path).await
+}
+
+/// Import historical constituents of the sectors from a CSV file of `date,ticker,sector` rows,
+/// returns the number of snapshots
+pub async fn import_sector_membership(sector_prefix: &str, path: &Path) -> VfResult<usize> {
+    cache::membership::import(&cache::membership::sector_source(sector_prefix), path).await
+}
+
 pub async fn load_backtest_results(
     output_dir: &Path,
     vfund_names: &[String],
@@ -226,6 +322,41 @@ pub async fn load_ticker_kline_with_ds(
     get_ticker_kline_with_ds(ticker, ignore_cache, ds_name).await
 }
 
+/// Fee schedule from the file, or `fees.toml` of the workspace if exists, with overrides of
+/// `key=value` e.g. `stock.stamp_duty.rate=0.0005`
+pub async fn load_fee_schedule(path: Option<&Path>, overrides: &[String]) -> VfResult<FeeSchedule> {
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => {
+            let workspace = { WORKSPACE.read().await.clone() };
+            Some(workspace.join("fees.toml")).filter(|path| path.is_file())
+        }
+    };
+
+    let mut schedule = match path {
+        Some(path) => {
+            if !path.is_file() {
+                return Err(VfError::NotExists {
+                    code: "FEE_SCHEDULE_NOT_EXISTS",
+                    message: format!("Fee schedule '{}' not exists", path.display()),
+                });
+            }
+            FeeSchedule::from_file(&path)?
+        }
+        None => FeeSchedule::default(),
+    };
+
+    for item in overrides {
+        let (key, value) = item.split_once('=').ok_or_else(|| VfError::Invalid {
+            code: "INVALID_FEE",
+            message: format!("Invalid fee '{item}', expected 'key=value'"),
+        })?;
+        schedule.set(key.trim(), value.trim())?;
+    }
+
+    Ok(schedule)
+}
+
 pub async fn load_vfunds() -> VfResult<Vec<(String, Vfund)>> {
     let mut vfunds: Vec<(String, Vfund)> = vec![];
 
@@ -284,17 +415,89 @@ pub async fn load_vfunds() -> VfResult<Vec<(String, Vfund)>> {
     Ok(vfunds)
 }
 
+/// Snapshot count and date range of historical membership per source
+pub async fn membership_stats() -> VfResult<Vec<MembershipStat>> {
+    cache::membership::stats().await
+}
+
+/// Statistics of the in-process cache since the process started
+pub fn memory_cache_stats() -> Vec<MemoryCacheStat> {
+    cache::memory::stats()
+}
+
 pub async fn notify(notification: &Notification) -> VfResult<()> {
     notifier::notify(notification).await
 }
 
 pub async fn parse_ticker_title(ticker_str: &str) -> VfResult<(Ticker, String)> {
-    let ticker = Ticker::from_str(ticker_str)?;
+    let ticker = financial::search::resolve_ticker(ticker_str).await?;
     let title = get_ticker_title(&ticker).await;
 
     Ok((ticker, title))
 }
 
+pub async fn prefetch_ticker(ticker: &Ticker) -> VfResult<()> {
+    prefetch::prefetch_ticker(ticker).await
+}
+
+/// Tickers needed by backtesting vfunds during the date range, funds of FOFs are included
+pub async fn prefetch_tickers(
+    vfund_names: &[String],
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+) -> VfResult<Vec<Ticker>> {
+    let vfunds = load_vfunds().await?;
+
+    let mut pending_names: Vec<String> = if vfund_names.is_empty() {
+        vfunds.iter().map(|(name, _)| name.to_string()).collect()
+    } else {
+        vfund_names.to_vec()
+    };
+    let mut visited_names: Vec<String> = vec![];
+    let mut fund_definitions: Vec<FundDefinition> = vec![];
+    while let Some(name) = pending_names.pop() {
+        if visited_names.contains(&name) {
+            continue;
+        }
+
+        match vfunds.iter().find(|(v, _)| *v == name) {
+            Some((_, Vfund::Fof(fof_definition))) => {
+                pending_names.extend(fof_definition.funds.keys().cloned());
+            }
+            Some((_, Vfund::Fund(fund_definition))) => {
+                fund_definitions.push(fund_definition.clone());
+            }
+            None => {
+                return Err(VfError::NotExists {
+                    code: "VFUND_NOT_EXISTS",
+                    message: format!("Vfund '{name}' not exists"),
+                });
+            }
+        }
+
+        visited_names.push(name);
+    }
+
+    prefetch::prefetch_tickers(&fund_definitions, start_date, end_date).await
+}
+
+/// Delete cache entries matching all given conditions, returns the number of deleted entries
+pub async fn purge_cache(
+    prefix: Option<&str>,
+    age_days: Option<u64>,
+    expired_only: bool,
+) -> VfResult<u64> {
+    let updated_before =
+        age_days.map(|days| Local::now().naive_local() - chrono::Duration::days(days as i64));
+
+    cache::purge(prefix, updated_before.as_ref(), expired_only).await
+}
+
+/// Tickers matching names, partial names, pinyin initials or symbol prefixes, best matches first
+pub async fn search_tickers(query: &str) -> VfResult<Vec<TickerMatch>> {
+    financial::search::search_tickers(query).await
+}
+
 pub async fn set_config(key: &str, value: &str) -> VfResult<Config> {
     let mut config = { CONFIG.read().await.clone() };
 
@@ -308,9 +511,37 @@ pub async fn set_config(key: &str, value: &str) -> VfResult<Config> {
         "tushare_token" => {
             config.tushare_token = value.to_string();
         }
+        "tushare_points" => {
+            config.tushare_points = parse_rate_config(key, value)?;
+        }
+        "qmt_rate_limit" => {
+            config.qmt_rate_limit = parse_rate_config(key, value)?;
+        }
         "wecom_webhook" => {
             config.wecom_webhook = value.to_string();
         }
+        "local_data_dir" => {
+            config.local_data_dir = value.to_string();
+        }
+        "data_sources" => {
+            config.data_sources = parse_data_sources(value)?;
+        }
+        key if key.starts_with("data_sources.") => {
+            let dataset = &key["data_sources.".len()..];
+            let dataset = Dataset::from_str(dataset).map_err(|_| VfError::Invalid {
+                code: "INVALID_DATASET",
+                message: format!("Invalid dataset '{dataset}'"),
+            })?;
+
+            let data_sources = parse_data_sources(value)?;
+            if data_sources.is_empty() {
+                config.dataset_sources.remove(&dataset.to_string());
+            } else {
+                config
+                    .dataset_sources
+                    .insert(dataset.to_string(), data_sources);
+            }
+        }
         _ => {
             return Err(VfError::Invalid {
                 code: "INVALID_CONFIG_KEY",
@@ -347,6 +578,8 @@ pub async fn output_backtest(
                     .collect(),
             },
             metrics: backtest_result.metrics.clone(),
+            fees: backtest_result.fees.clone(),
+            pnl: backtest_result.pnl,
             order_dates: backtest_result.order_dates.clone(),
             version: VERSION.to_string(),
         };
@@ -393,3 +626,27 @@ pub async fn output_backtest(
 
     Ok(())
 }
+
+pub async fn vacuum_cache() -> VfResult<()> {
+    cache::vacuum().await
+}
+
+fn parse_rate_config(key: &str, value: &str) -> VfResult<u32> {
+    value.trim().parse::<u32>().map_err(|_| VfError::Invalid {
+        code: "INVALID_CONFIG_VALUE",
+        message: format!("Invalid value '{value}' of config key '{key}'"),
+    })
+}
+
+fn parse_data_sources(value: &str) -> VfResult<Vec<String>> {
+    let mut data_sources: Vec<String> = vec![];
+
+    for name in value.split(',').map(|s| s.trim().to_lowercase()) {
+        if !name.is_empty() {
+            get_data_source(&name)?;
+            data_sources.push(name);
+        }
+    }
+
+    Ok(data_sources)
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{Display, Formatter},
 };
 
@@ -8,8 +8,14 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::{
+    backtest::fee::{FeeSchedule, FeeSide},
     error::*,
-    financial::get_ticker_title,
+    financial::{
+        economy::{InterestRate, get_mean_interest_rate},
+        fx::Currency,
+        get_ticker_title,
+    },
+    spec::FiltersReport,
     ticker::Ticker,
     utils::{
         datetime::date_to_str,
@@ -22,7 +28,9 @@ use crate::{
     },
 };
 
+pub mod fee;
 pub mod fof;
+pub mod fof_rule;
 pub mod fund;
 
 #[derive(Clone, Debug)]
@@ -186,11 +194,11 @@ pub struct BacktestMetrics {
 }
 
 impl BacktestMetrics {
-    pub fn from_daily_value(
+    pub async fn from_daily_value(
         trade_dates_value: &Vec<(NaiveDate, f64)>,
         no_position_dates: &[NaiveDate],
         options: &BacktestOptions,
-    ) -> Self {
+    ) -> VfResult<Self> {
         let mut calendar_year_returns: HashMap<i32, f64> = HashMap::new();
         {
             let mut prev_value = options.init_cash;
@@ -225,24 +233,33 @@ impl BacktestMetrics {
             trade_dates_value.len() as u64,
         );
 
-        let daily_values: Vec<f64> = trade_dates_value
+        let (position_dates, daily_values): (Vec<NaiveDate>, Vec<f64>) = trade_dates_value
             .iter()
             .filter(|(d, _)| !no_position_dates.contains(d))
-            .map(|(_, v)| *v)
-            .collect();
+            .copied()
+            .unzip();
+        let risk_free_rate = match &options.risk_free_series {
+            Some(rate) => {
+                get_mean_interest_rate(rate, &position_dates, options.risk_free_rate * 100.0)
+                    .await?
+                    .map(|r| r / 100.0)
+                    .unwrap_or(options.risk_free_rate)
+            }
+            None => options.risk_free_rate,
+        };
         let max_drawdown = calc_max_drawdown(&daily_values);
         let annualized_volatility = calc_annualized_volatility_std(&daily_values);
         let win_rate = calc_win_rate(&daily_values);
         let profit_factor = calc_profit_factor(&daily_values);
-        let sharpe_ratio = calc_sharpe_ratio(&daily_values, options.risk_free_rate);
+        let sharpe_ratio = calc_sharpe_ratio(&daily_values, risk_free_rate);
         let calmar_ratio = if let (Some(arr), Some(mdd)) = (annualized_return_rate, max_drawdown) {
             if mdd > 0.0 { Some(arr / mdd) } else { None }
         } else {
             None
         };
-        let sortino_ratio = calc_sortino_ratio(&daily_values, options.risk_free_rate);
+        let sortino_ratio = calc_sortino_ratio(&daily_values, risk_free_rate);
 
-        Self {
+        Ok(Self {
             last_trade_date: trade_dates_value.last().map(|(d, _)| *d),
             unbroken_date,
             trade_days: trade_dates_value.len(),
@@ -256,6 +273,22 @@ impl BacktestMetrics {
             sharpe_ratio,
             calmar_ratio,
             sortino_ratio,
+        })
+    }
+}
+
+/// Profit and loss in the base currency, split into the ones of asset prices and of FX rates
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct BacktestPnl {
+    pub asset: f64,
+    pub fx: f64,
+}
+
+impl BacktestPnl {
+    pub fn from_total(total_return: f64, fx: f64) -> Self {
+        Self {
+            asset: total_return - fx,
+            fx,
         }
     }
 }
@@ -276,10 +309,15 @@ pub struct BacktestOptions {
     pub position_tolerance: f64,
 
     pub risk_free_rate: f64,
-    pub stamp_duty_rate: f64,
-    pub stamp_duty_min_fee: f64,
-    pub broker_commission_rate: f64,
-    pub broker_commission_min_fee: f64,
+    /// Interest rate whose mean on the dates with positions replaces `risk_free_rate` in the
+    /// Sharpe and Sortino ratios, and `risk_free_rate` applies to dates without the rate
+    #[serde(default)]
+    pub risk_free_series: Option<InterestRate>,
+    #[serde(default)]
+    pub fees: FeeSchedule,
+    /// Currency of cash and values, prices of tickers quoted in other currencies are converted
+    #[serde(default)]
+    pub base_currency: Currency,
 }
 
 impl BacktestOptions {
@@ -304,20 +342,8 @@ impl BacktestOptions {
             panic!("risk_free_rate must >= 0");
         }
 
-        if self.stamp_duty_rate < 0.0 || self.stamp_duty_rate >= 1.0 {
-            panic!("stamp_duty_rate must >= 0 and < 1");
-        }
-
-        if self.stamp_duty_min_fee < 0.0 {
-            panic!("stamp_duty_min_fee must >= 0");
-        }
-
-        if self.broker_commission_rate < 0.0 || self.broker_commission_rate >= 1.0 {
-            panic!("broker_commission_rate must >= 0 and < 1");
-        }
-
-        if self.broker_commission_min_fee < 0.0 {
-            panic!("broker_commission_min_fee must >= 0");
+        if let Err(err) = self.fees.validate() {
+            panic!("{err}");
         }
     }
 }
@@ -328,6 +354,9 @@ pub struct BacktestResult {
     pub options: BacktestOptions,
     pub final_cash: f64,
     pub final_positions_value: HashMap<Ticker, f64>,
+    /// Total fees paid by component, e.g. `stamp_duty`
+    pub fees: BTreeMap<String, f64>,
+    pub pnl: BacktestPnl,
     pub metrics: BacktestMetrics,
     pub no_position_dates: Vec<NaiveDate>,
     pub order_dates: Vec<NaiveDate>,
@@ -358,31 +387,113 @@ impl BacktestStream {
     }
 }
 
-fn calc_buy_fee(value: f64, options: &BacktestOptions) -> f64 {
-    let broker_commission = value * options.broker_commission_rate;
-    if broker_commission > options.broker_commission_min_fee {
-        broker_commission
-    } else {
-        options.broker_commission_min_fee
+/// Estimated fee of buying, without adding to the totals of fees paid
+fn calc_buy_fee(value: f64, ticker: &Ticker, options: &BacktestOptions) -> f64 {
+    calc_fees(value, ticker, FeeSide::Buy, options)
+        .iter()
+        .map(|(_, fee)| fee)
+        .sum()
+}
+
+/// Estimated fee of selling, without adding to the totals of fees paid
+fn calc_sell_fee(value: f64, ticker: &Ticker, options: &BacktestOptions) -> f64 {
+    calc_fees(value, ticker, FeeSide::Sell, options)
+        .iter()
+        .map(|(_, fee)| fee)
+        .sum()
+}
+
+fn calc_fees(
+    value: f64,
+    ticker: &Ticker,
+    side: FeeSide,
+    options: &BacktestOptions,
+) -> Vec<(String, f64)> {
+    options
+        .fees
+        .calc(value, &ticker.r#type, Some(ticker.exchange_mic()), side)
+}
+
+/// Add fees of a trade to the totals and return their sum
+fn pay_fees(fees: Vec<(String, f64)>, fee_totals: &mut BTreeMap<String, f64>) -> f64 {
+    let mut sum = 0.0;
+    for (component, fee) in fees {
+        sum += fee;
+        *fee_totals.entry(component).or_insert(0.0) += fee;
     }
+    sum
 }
 
-fn calc_sell_fee(value: f64, options: &BacktestOptions) -> f64 {
-    let stamp_duty = value * options.stamp_duty_rate;
-    let stamp_duty_fee = if stamp_duty > options.stamp_duty_min_fee {
-        stamp_duty
-    } else {
-        options.stamp_duty_min_fee
-    };
+async fn notify_fees(
+    event_sender: &Sender<BacktestEvent>,
+    date: &NaiveDate,
+    fee_totals: &BTreeMap<String, f64>,
+) -> VfResult<()> {
+    if !fee_totals.is_empty() {
+        let mut fees_str = fee_totals
+            .iter()
+            .map(|(component, fee)| format!("{component}=${fee:.2}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        fees_str.push_str(&format!(" => ${:.2}", fee_totals.values().sum::<f64>()));
 
-    let broker_commission = value * options.broker_commission_rate;
-    let broker_commission_fee = if broker_commission > options.broker_commission_min_fee {
-        broker_commission
-    } else {
-        options.broker_commission_min_fee
-    };
+        let _ = event_sender
+            .send(BacktestEvent::Info {
+                title: "[Fees]".to_string(),
+                message: fees_str,
+                date: Some(*date),
+            })
+            .await;
+    }
+
+    Ok(())
+}
+
+async fn notify_pnl(
+    event_sender: &Sender<BacktestEvent>,
+    date: &NaiveDate,
+    pnl: &BacktestPnl,
+) -> VfResult<()> {
+    if pnl.fx != 0.0 {
+        let _ = event_sender
+            .send(BacktestEvent::Info {
+                title: "[PnL]".to_string(),
+                message: format!(
+                    "asset=${:.2} fx=${:.2} => ${:.2}",
+                    pnl.asset,
+                    pnl.fx,
+                    pnl.asset + pnl.fx
+                ),
+                date: Some(*date),
+            })
+            .await;
+    }
 
-    stamp_duty_fee + broker_commission_fee
+    Ok(())
+}
+
+async fn notify_filters_report(
+    event_sender: &Sender<BacktestEvent>,
+    date: &NaiveDate,
+    report: &FiltersReport,
+) -> VfResult<()> {
+    let mut remaining_count = report.universe_count;
+    let mut report_str = format!("[Universe] {}", report.universe_count);
+    for (filter_name, removed_count) in &report.removed_counts {
+        remaining_count -= removed_count;
+        report_str.push_str(&format!(" {filter_name}(-{removed_count})"));
+    }
+    report_str.push_str(&format!(" => {remaining_count}"));
+
+    let _ = event_sender
+        .send(BacktestEvent::Info {
+            title: "[Filters]".to_string(),
+            message: report_str,
+            date: Some(*date),
+        })
+        .await;
+
+    Ok(())
 }
 
 async fn notify_portfolio(
@@ -523,3 +634,46 @@ fn sort_cv_results_list(
 
     cv_scores
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_calc_fee() {
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        let stock = Ticker::from_str("000001.SZ").unwrap();
+        let etf = Ticker::from_str("510300.SH").unwrap();
+        assert_eq!(calc_buy_fee(100_000.0, &stock, &options), 20.0);
+        assert_eq!(calc_sell_fee(100_000.0, &stock, &options), 120.0);
+        assert_eq!(calc_buy_fee(10_000.0, &stock, &options), 5.0);
+        assert_eq!(calc_sell_fee(100_000.0, &etf, &options), 5.0);
+
+        let mut fee_totals: BTreeMap<String, f64> = BTreeMap::new();
+        pay_fees(
+            calc_fees(100_000.0, &stock, FeeSide::Buy, &options),
+            &mut fee_totals,
+        );
+        let paid = pay_fees(
+            calc_fees(100_000.0, &stock, FeeSide::Sell, &options),
+            &mut fee_totals,
+        );
+        assert_eq!(paid, 120.0);
+        assert_eq!(fee_totals.get("broker_commission"), Some(&40.0));
+        assert_eq!(fee_totals.get("stamp_duty"), Some(&100.0));
+    }
+}
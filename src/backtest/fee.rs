@@ -0,0 +1,384 @@
+//! Fee schedules of trades
+//!
+//! Fees are made of components (e.g. `broker_commission`, `stamp_duty`), each charged at a rate
+//! of the traded value but no less than its minimal fee, on buys, sells or both. Components are
+//! keyed by ticker type (e.g. `stock`), and those on an exchange (e.g. `stock.exchanges.XSHG`)
+//! override the ones of the ticker type. In TOML:
+//!
+//! ```toml
+//! [stock]
+//! broker_commission = { rate = 0.0002, min = 5 }
+//! stamp_duty = { rate = 0.001, min = 1, side = "sell" }
+//!
+//! [stock.exchanges.XSHG]
+//! transfer_fee = { rate = 0.00001 }
+//! ```
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{VfError, VfResult},
+    ticker::TickerType,
+};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSide {
+    #[default]
+    Both,
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct FeeRule {
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default)]
+    pub side: FeeSide,
+}
+
+impl FeeRule {
+    fn new(rate: f64, min: f64, side: FeeSide) -> Self {
+        Self { rate, min, side }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TickerTypeFees {
+    #[serde(flatten)]
+    pub components: BTreeMap<String, FeeRule>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exchanges: BTreeMap<String, BTreeMap<String, FeeRule>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FeeSchedule(BTreeMap<String, TickerTypeFees>);
+
+impl Default for FeeSchedule {
+    /// Indexes are not traded but held as proxies, and money funds are traded free
+    fn default() -> Self {
+        let mut schedule = Self(BTreeMap::new());
+
+        schedule.insert_rule(
+            "stock",
+            None,
+            "broker_commission",
+            FeeRule::new(0.0002, 5.0, FeeSide::Both),
+        );
+        schedule.insert_rule(
+            "stock",
+            None,
+            "stamp_duty",
+            FeeRule::new(0.001, 1.0, FeeSide::Sell),
+        );
+        schedule.insert_rule(
+            "stock",
+            Some("XSHG"),
+            "transfer_fee",
+            FeeRule::new(0.00001, 0.0, FeeSide::Both),
+        );
+        schedule.insert_rule(
+            "stock",
+            Some("XHKG"),
+            "stamp_duty",
+            FeeRule::new(0.001, 1.0, FeeSide::Both),
+        );
+        schedule.insert_rule(
+            "stock",
+            Some("XHKG"),
+            "trading_fee",
+            FeeRule::new(0.0000565, 0.01, FeeSide::Both),
+        );
+        schedule.insert_rule(
+            "stock",
+            Some("XHKG"),
+            "sfc_levy",
+            FeeRule::new(0.000027, 0.01, FeeSide::Both),
+        );
+        schedule.insert_rule(
+            "stock",
+            Some("XHKG"),
+            "frc_levy",
+            FeeRule::new(0.0000015, 0.0, FeeSide::Both),
+        );
+
+        schedule.insert_rule(
+            "conv_bond",
+            None,
+            "broker_commission",
+            FeeRule::new(0.00005, 1.0, FeeSide::Both),
+        );
+
+        for ticker_type in ["etf", "lof", "reit"] {
+            schedule.insert_rule(
+                ticker_type,
+                None,
+                "broker_commission",
+                FeeRule::new(0.00005, 0.0, FeeSide::Both),
+            );
+        }
+
+        schedule
+    }
+}
+
+impl FeeSchedule {
+    /// Rules in the file override the default ones component by component
+    pub fn from_file(path: &Path) -> VfResult<Self> {
+        let loaded: Self = confy::load_path(path)?;
+
+        let mut schedule = Self::default();
+        for (ticker_type, fees) in loaded.0 {
+            for (component, rule) in fees.components {
+                schedule.insert_rule(&ticker_type, None, &component, rule);
+            }
+            for (exchange, rules) in fees.exchanges {
+                for (component, rule) in rules {
+                    schedule.insert_rule(&ticker_type, Some(&exchange), &component, rule);
+                }
+            }
+        }
+        schedule.validate()?;
+
+        Ok(schedule)
+    }
+
+    /// Fees of each component charged, no exchange for rules of the ticker type only
+    pub fn calc(
+        &self,
+        value: f64,
+        ticker_type: &TickerType,
+        exchange: Option<&str>,
+        side: FeeSide,
+    ) -> Vec<(String, f64)> {
+        let mut rules: BTreeMap<&str, &FeeRule> = BTreeMap::new();
+        if let Some(fees) = self.0.get(&ticker_type.to_string()) {
+            rules.extend(fees.components.iter().map(|(k, v)| (k.as_str(), v)));
+            if let Some(exchange) = exchange
+                && let Some(exchange_rules) = fees.exchanges.get(exchange)
+            {
+                rules.extend(exchange_rules.iter().map(|(k, v)| (k.as_str(), v)));
+            }
+        }
+
+        rules
+            .into_iter()
+            .filter(|(_, rule)| rule.side == FeeSide::Both || rule.side == side)
+            .filter_map(|(component, rule)| {
+                let fee = (value * rule.rate).max(rule.min);
+                if fee > 0.0 {
+                    Some((component.to_string(), fee))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Set a field of a rule by key `{ticker_type}[.{exchange}].{component}.{rate|min|side}`,
+    /// e.g. `stock.XSHG.transfer_fee.rate`
+    pub fn set(&mut self, key: &str, value: &str) -> VfResult<()> {
+        let invalid = |message: String| VfError::Invalid {
+            code: "INVALID_FEE",
+            message,
+        };
+
+        let parts: Vec<&str> = key.split('.').collect();
+        let (ticker_type, exchange, component, field) = match parts[..] {
+            [ticker_type, component, field] => (ticker_type, None, component, field),
+            [ticker_type, exchange, component, field] => {
+                (ticker_type, Some(exchange), component, field)
+            }
+            _ => return Err(invalid(format!("Invalid fee key '{key}'"))),
+        };
+
+        if TickerType::from_str(ticker_type).is_err() {
+            return Err(invalid(format!("Invalid ticker type '{ticker_type}'")));
+        }
+
+        let mut rule = self
+            .rule(ticker_type, exchange, component)
+            .cloned()
+            .unwrap_or_default();
+        match field {
+            "rate" | "min" => {
+                let value = value
+                    .parse::<f64>()
+                    .map_err(|_| invalid(format!("Invalid fee value '{value}' of '{key}'")))?;
+                if field == "rate" {
+                    rule.rate = value;
+                } else {
+                    rule.min = value;
+                }
+            }
+            "side" => {
+                rule.side = match value {
+                    "both" => FeeSide::Both,
+                    "buy" => FeeSide::Buy,
+                    "sell" => FeeSide::Sell,
+                    _ => return Err(invalid(format!("Invalid fee side '{value}' of '{key}'"))),
+                };
+            }
+            _ => return Err(invalid(format!("Invalid fee key '{key}'"))),
+        }
+        check_rule(&format!("{ticker_type}.{component}"), &rule)?;
+        self.insert_rule(ticker_type, exchange, component, rule);
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> VfResult<()> {
+        for (ticker_type, fees) in &self.0 {
+            if TickerType::from_str(ticker_type).is_err() {
+                return Err(VfError::Invalid {
+                    code: "INVALID_FEE",
+                    message: format!("Invalid ticker type '{ticker_type}'"),
+                });
+            }
+
+            let exchange_rules = fees.exchanges.iter().flat_map(|(exchange, rules)| {
+                rules
+                    .iter()
+                    .map(move |(component, rule)| (format!("{exchange}.{component}"), rule))
+            });
+            for (name, rule) in fees
+                .components
+                .iter()
+                .map(|(component, rule)| (component.to_string(), rule))
+                .chain(exchange_rules)
+            {
+                check_rule(&format!("{ticker_type}.{name}"), rule)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rule(&self, ticker_type: &str, exchange: Option<&str>, component: &str) -> Option<&FeeRule> {
+        let fees = self.0.get(ticker_type)?;
+        match exchange {
+            Some(exchange) => fees.exchanges.get(exchange)?.get(component),
+            None => fees.components.get(component),
+        }
+    }
+
+    fn insert_rule(
+        &mut self,
+        ticker_type: &str,
+        exchange: Option<&str>,
+        component: &str,
+        rule: FeeRule,
+    ) {
+        let fees = self.0.entry(ticker_type.to_string()).or_default();
+        let rules = match exchange {
+            Some(exchange) => fees.exchanges.entry(exchange.to_string()).or_default(),
+            None => &mut fees.components,
+        };
+        rules.insert(component.to_string(), rule);
+    }
+}
+
+fn check_rule(name: &str, rule: &FeeRule) -> VfResult<()> {
+    if rule.rate < 0.0 || rule.rate >= 1.0 {
+        return Err(VfError::Invalid {
+            code: "INVALID_FEE",
+            message: format!("Fee rate of '{name}' must >= 0 and < 1"),
+        });
+    }
+
+    if rule.min < 0.0 {
+        return Err(VfError::Invalid {
+            code: "INVALID_FEE",
+            message: format!("Minimal fee of '{name}' must >= 0"),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn sum_fees(fees: Vec<(String, f64)>) -> f64 {
+        fees.iter().map(|(_, fee)| fee).sum()
+    }
+
+    #[test]
+    fn test_fee_schedule() {
+        let mut schedule = FeeSchedule::default();
+
+        let fees = schedule.calc(100_000.0, &TickerType::Stock, Some("XSHG"), FeeSide::Sell);
+        assert_eq!(
+            fees.iter().map(|(c, _)| c.as_str()).collect::<Vec<_>>(),
+            vec!["broker_commission", "stamp_duty", "transfer_fee"]
+        );
+        assert!((sum_fees(fees) - 121.0).abs() < 1e-9);
+
+        let fees = schedule.calc(100_000.0, &TickerType::Stock, Some("XSHE"), FeeSide::Buy);
+        assert_eq!(sum_fees(fees), 20.0);
+
+        let fees = schedule.calc(100_000.0, &TickerType::Stock, Some("XHKG"), FeeSide::Buy);
+        assert!((sum_fees(fees) - 128.5).abs() < 1e-9);
+
+        let fees = schedule.calc(
+            100_000.0,
+            &TickerType::ConvBond,
+            Some("XSHG"),
+            FeeSide::Sell,
+        );
+        assert_eq!(sum_fees(fees), 5.0);
+        let fees = schedule.calc(10_000.0, &TickerType::ConvBond, Some("XSHE"), FeeSide::Buy);
+        assert_eq!(sum_fees(fees), 1.0);
+
+        let fees = schedule.calc(100_000.0, &TickerType::Etf, Some("XSHG"), FeeSide::Sell);
+        assert_eq!(sum_fees(fees), 5.0);
+        let fees = schedule.calc(
+            100_000.0,
+            &TickerType::MoneyFund,
+            Some("XSHG"),
+            FeeSide::Sell,
+        );
+        assert!(fees.is_empty());
+
+        schedule.set("stock.stamp_duty.rate", "0.0005").unwrap();
+        schedule.set("stock.XSHG.transfer_fee.rate", "0").unwrap();
+        let fees = schedule.calc(100_000.0, &TickerType::Stock, Some("XSHG"), FeeSide::Sell);
+        assert_eq!(sum_fees(fees), 70.0);
+
+        assert!(schedule.set("stock.stamp_duty.rate", "1.5").is_err());
+        assert!(schedule.set("bond.stamp_duty.rate", "0.001").is_err());
+        assert!(schedule.set("stock.stamp_duty", "0.001").is_err());
+        assert!(schedule.set("stock.stamp_duty.side", "short").is_err());
+    }
+
+    #[test]
+    fn test_fee_schedule_from_file() {
+        let schedule = FeeSchedule::from_file(&PathBuf::from("example/fees.toml")).unwrap();
+
+        let fees = schedule.calc(100_000.0, &TickerType::Stock, Some("XSHG"), FeeSide::Sell);
+        assert!((sum_fees(fees) - 76.0).abs() < 1e-9);
+
+        let fees = schedule.calc(100_000.0, &TickerType::ConvBond, Some("XSHE"), FeeSide::Buy);
+        assert_eq!(sum_fees(fees), 5.0);
+
+        let path = std::env::temp_dir().join("vfunds_test_fees_invalid.toml");
+        std::fs::write(
+            &path,
+            "[stocks]\nbroker_commission = { rate = 0.0002, min = 5 }\n",
+        )
+        .unwrap();
+        let result = FeeSchedule::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+}
@@ -1,98 +1,49 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
     str::FromStr,
     time::Instant,
 };
 
 use chrono::{Duration, NaiveDate};
+use futures::future::BoxFuture;
 use itertools::Itertools;
 use tokio::sync::mpsc;
 
 use crate::{
-    CHANNEL_BUFFER_DEFAULT, WORKSPACE,
-    backtest::{fund::backtest_funds, *},
+    CHANNEL_BUFFER_DEFAULT,
+    backtest::{
+        fee::FeeSide,
+        fof_rule::{FofRule, FundsSeries, calc_targets_weight},
+        fund::backtest_funds,
+        *,
+    },
     spec::*,
+    ticker::TickerType,
     utils::{
         datetime::{date_to_str, secs_to_human_str},
         stats::mean,
     },
 };
 
+/// Children of the FOF are resolved in the workspace by names
 pub async fn backtest_fof(
+    fof_name: &str,
     fof_definition: &FofDefinition,
     options: &BacktestOptions,
+    workspace: &Path,
 ) -> VfResult<BacktestStream> {
     options.check();
 
     let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
 
+    let fof_name = fof_name.to_string();
     let fof_definition = fof_definition.clone();
     let options = options.clone();
+    let workspace = workspace.to_path_buf();
 
     tokio::spawn(async move {
-        let single_run = async |fof_definition: &FofDefinition,
-                                options: &BacktestOptions|
-               -> VfResult<BacktestResult> {
-            let valid_funds: Vec<_> = fof_definition
-                .funds
-                .iter()
-                .filter(|(_, w)| **w > 0.0)
-                .collect();
-            if !valid_funds.is_empty() {
-                let workspace = { WORKSPACE.read().await.clone() };
-
-                let mut funds: Vec<(String, FundDefinition)> = vec![];
-                let mut funds_weight: Vec<(String, f64)> = vec![];
-                for (fund_name, fund_weight) in &valid_funds {
-                    let fund_path = workspace.join(format!("{fund_name}.fund.toml"));
-                    let fund_definition = FundDefinition::from_file(&fund_path)?;
-                    funds.push((fund_name.to_string(), fund_definition));
-                    funds_weight.push((fund_name.to_string(), **fund_weight));
-                }
-                let funds_result = backtest_funds(&funds, options, &sender).await?;
-
-                let no_position_dates =
-                    calc_no_position_dates_value_from_funds_result(&funds_result);
-                let order_dates = calc_order_dates_value_from_funds_result(&funds_result);
-
-                let trade_dates_value = calc_trade_dates_value_from_funds_result(
-                    &funds_result,
-                    &funds_weight,
-                    fof_definition.frequency.to_days(),
-                    options,
-                    &sender,
-                )
-                .await;
-
-                Ok(BacktestResult {
-                    title: Some(fof_definition.title.clone()),
-                    options: options.clone(),
-                    final_cash: 0.0,
-                    final_positions_value: HashMap::new(),
-                    metrics: BacktestMetrics::from_daily_value(
-                        &trade_dates_value,
-                        &no_position_dates,
-                        options,
-                    ),
-                    no_position_dates,
-                    order_dates,
-                    trade_dates_value,
-                })
-            } else {
-                Ok(BacktestResult {
-                    title: Some(fof_definition.title.clone()),
-                    options: options.clone(),
-                    final_cash: options.init_cash,
-                    final_positions_value: HashMap::new(),
-                    metrics: BacktestMetrics::default(),
-                    no_position_dates: vec![],
-                    order_dates: vec![],
-                    trade_dates_value: vec![],
-                })
-            }
-        };
-
-        match single_run(&fof_definition, &options).await {
+        match run_fof(&fof_definition, &options, &sender, &workspace, &[fof_name]).await {
             Ok(result) => {
                 let _ = sender.send(BacktestEvent::Result(Box::new(result))).await;
             }
@@ -106,15 +57,19 @@ pub async fn backtest_fof(
 }
 
 pub async fn backtest_fof_cv(
+    fof_name: &str,
     fof_definition: &FofDefinition,
     cv_options: &BacktestCvOptions,
+    workspace: &Path,
 ) -> VfResult<BacktestStream> {
     cv_options.base_options.check();
 
     let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
 
+    let fof_name = fof_name.to_string();
     let fof_definition = fof_definition.clone();
     let cv_options = cv_options.clone();
+    let workspace = workspace.to_path_buf();
 
     tokio::spawn(async move {
         let process = async || -> VfResult<()> {
@@ -125,7 +80,15 @@ pub async fn backtest_fof_cv(
                     .filter(|(_, w)| **w > 0.0)
                     .collect();
                 if !valid_funds.is_empty() {
-                    let workspace = { WORKSPACE.read().await.clone() };
+                    let fund_names: Vec<String> = valid_funds
+                        .iter()
+                        .map(|(name, _)| name.to_string())
+                        .collect();
+                    let fof_rules: Vec<FofRule> = fof_definition
+                        .rules
+                        .iter()
+                        .map(FofRule::from_definition)
+                        .collect();
 
                     let mut funds_result_map: HashMap<NaiveDate, Vec<(String, BacktestResult)>> =
                         HashMap::new();
@@ -134,13 +97,14 @@ pub async fn backtest_fof_cv(
                         let mut options = cv_options.base_options.clone();
                         options.start_date = *cv_start_date;
 
-                        let mut funds: Vec<(String, FundDefinition)> = vec![];
-                        for (fund_name, _) in &valid_funds {
-                            let fund_path = workspace.join(format!("{fund_name}.fund.toml"));
-                            let fund_definition = FundDefinition::from_file(&fund_path)?;
-                            funds.push((fund_name.to_string(), fund_definition));
-                        }
-                        let funds_result = backtest_funds(&funds, &options, &sender).await?;
+                        let funds_result = backtest_children(
+                            &fund_names,
+                            &options,
+                            &sender,
+                            &workspace,
+                            std::slice::from_ref(&fof_name),
+                        )
+                        .await?;
 
                         funds_result_map.insert(*cv_start_date, funds_result.clone());
                     }
@@ -206,26 +170,40 @@ pub async fn backtest_fof_cv(
                                     let order_dates =
                                         calc_order_dates_value_from_funds_result(funds_result);
 
+                                    let mut fee_totals: BTreeMap<String, f64> = BTreeMap::new();
                                     let trade_dates_value =
                                         calc_trade_dates_value_from_funds_result(
                                             funds_result,
                                             &funds_weight,
                                             fof_definition.frequency.to_days(),
+                                            &fof_rules,
                                             &options,
                                             &sender,
+                                            None,
+                                            &mut fee_totals,
                                         )
                                         .await;
 
+                                    let metrics = BacktestMetrics::from_daily_value(
+                                        &trade_dates_value,
+                                        &no_position_dates,
+                                        &options,
+                                    )
+                                    .await?;
+                                    let pnl = calc_pnl_from_funds_result(
+                                        funds_result,
+                                        &funds_weight,
+                                        metrics.total_return,
+                                    );
+
                                     let result = BacktestResult {
                                         title: Some(fof_definition.title.clone()),
                                         options: options.clone(),
                                         final_cash: 0.0,
                                         final_positions_value: HashMap::new(),
-                                        metrics: BacktestMetrics::from_daily_value(
-                                            &trade_dates_value,
-                                            &no_position_dates,
-                                            &options,
-                                        ),
+                                        fees: fee_totals,
+                                        pnl,
+                                        metrics,
                                         no_position_dates,
                                         order_dates,
                                         trade_dates_value,
@@ -367,7 +345,7 @@ pub async fn backtest_fof_cv(
                     options.start_date = *window_start;
                     options.end_date = *window_end;
 
-                    match backtest_fof(&fof_definition, &options).await {
+                    match backtest_fof(&fof_name, &fof_definition, &options, &workspace).await {
                         Ok(mut stream) => {
                             while let Some(event) = stream.next().await {
                                 match event {
@@ -505,6 +483,138 @@ pub async fn backtest_fof_cv(
     Ok(BacktestStream { receiver })
 }
 
+/// FOFs path is from the top FOF to the current one, for detecting cycles
+fn run_fof<'a>(
+    fof_definition: &'a FofDefinition,
+    options: &'a BacktestOptions,
+    sender: &'a Sender<BacktestEvent>,
+    workspace: &'a Path,
+    fofs_path: &'a [String],
+) -> BoxFuture<'a, VfResult<BacktestResult>> {
+    Box::pin(async move {
+        let valid_funds: Vec<_> = fof_definition
+            .funds
+            .iter()
+            .filter(|(_, w)| **w > 0.0)
+            .collect();
+        if !valid_funds.is_empty() {
+            let fund_names: Vec<String> = valid_funds
+                .iter()
+                .map(|(fund_name, _)| fund_name.to_string())
+                .collect();
+            let funds_weight: Vec<(String, f64)> = valid_funds
+                .iter()
+                .map(|(fund_name, fund_weight)| (fund_name.to_string(), **fund_weight))
+                .collect();
+            let fof_rules: Vec<FofRule> = fof_definition
+                .rules
+                .iter()
+                .map(FofRule::from_definition)
+                .collect();
+
+            let funds_result =
+                backtest_children(&fund_names, options, sender, workspace, fofs_path).await?;
+
+            let no_position_dates = calc_no_position_dates_value_from_funds_result(&funds_result);
+            let order_dates = calc_order_dates_value_from_funds_result(&funds_result);
+
+            let mut fee_totals: BTreeMap<String, f64> = BTreeMap::new();
+            let trade_dates_value = calc_trade_dates_value_from_funds_result(
+                &funds_result,
+                &funds_weight,
+                fof_definition.frequency.to_days(),
+                &fof_rules,
+                options,
+                sender,
+                // Only nested FOFs are named in their rebalance events
+                fofs_path
+                    .last()
+                    .filter(|_| fofs_path.len() > 1)
+                    .map(|s| s.as_str()),
+                &mut fee_totals,
+            )
+            .await;
+            let _ = notify_fees(sender, &options.end_date, &fee_totals).await;
+
+            let metrics =
+                BacktestMetrics::from_daily_value(&trade_dates_value, &no_position_dates, options)
+                    .await?;
+            let pnl =
+                calc_pnl_from_funds_result(&funds_result, &funds_weight, metrics.total_return);
+            let _ = notify_pnl(sender, &options.end_date, &pnl).await;
+
+            Ok(BacktestResult {
+                title: Some(fof_definition.title.clone()),
+                options: options.clone(),
+                final_cash: 0.0,
+                final_positions_value: HashMap::new(),
+                fees: fee_totals,
+                pnl,
+                metrics,
+                no_position_dates,
+                order_dates,
+                trade_dates_value,
+            })
+        } else {
+            Ok(BacktestResult {
+                title: Some(fof_definition.title.clone()),
+                options: options.clone(),
+                final_cash: options.init_cash,
+                final_positions_value: HashMap::new(),
+                fees: BTreeMap::new(),
+                pnl: BacktestPnl::default(),
+                metrics: BacktestMetrics::default(),
+                no_position_dates: vec![],
+                order_dates: vec![],
+                trade_dates_value: vec![],
+            })
+        }
+    })
+}
+
+/// Children are resolved as `{name}.fund.toml` first, then `{name}.fof.toml` which is backtested recursively
+async fn backtest_children(
+    names: &[String],
+    options: &BacktestOptions,
+    sender: &Sender<BacktestEvent>,
+    workspace: &Path,
+    fofs_path: &[String],
+) -> VfResult<Vec<(String, BacktestResult)>> {
+    let mut funds: Vec<(String, FundDefinition)> = vec![];
+    let mut fofs: Vec<(String, FofDefinition)> = vec![];
+    for name in names {
+        let fund_path = workspace.join(format!("{name}.fund.toml"));
+        let fof_path = workspace.join(format!("{name}.fof.toml"));
+        if !fund_path.is_file() && fof_path.is_file() {
+            if fofs_path.contains(name) {
+                return Err(VfError::Invalid {
+                    code: "FOF_CYCLE",
+                    message: format!(
+                        "Cycle of FOFs detected: {} -> {name}",
+                        fofs_path.join(" -> ")
+                    ),
+                });
+            }
+
+            fofs.push((name.to_string(), FofDefinition::from_file(&fof_path)?));
+        } else {
+            funds.push((name.to_string(), FundDefinition::from_file(&fund_path)?));
+        }
+    }
+
+    let mut children_result = backtest_funds(&funds, options, sender).await?;
+    for (fof_name, fof_definition) in &fofs {
+        let mut child_fofs_path = fofs_path.to_vec();
+        child_fofs_path.push(fof_name.to_string());
+
+        let fof_result =
+            run_fof(fof_definition, options, sender, workspace, &child_fofs_path).await?;
+        children_result.push((fof_name.to_string(), fof_result));
+    }
+
+    Ok(children_result)
+}
+
 fn calc_no_position_dates_value_from_funds_result(
     funds_result: &Vec<(String, BacktestResult)>,
 ) -> Vec<NaiveDate> {
@@ -527,6 +637,27 @@ fn calc_no_position_dates_value_from_funds_result(
     }
 }
 
+/// P&L of FX rates approximated as the one of funds by weights, as funds are based on the same
+/// initial cash
+fn calc_pnl_from_funds_result(
+    funds_result: &Vec<(String, BacktestResult)>,
+    funds_weight: &[(String, f64)],
+    total_return: f64,
+) -> BacktestPnl {
+    let weight_sum: f64 = funds_weight.iter().map(|(_, weight)| weight).sum();
+
+    let mut fx = 0.0;
+    if weight_sum > 0.0 {
+        for (fund_name, fund_result) in funds_result {
+            if let Some((_, weight)) = funds_weight.iter().find(|(name, _)| name == fund_name) {
+                fx += fund_result.pnl.fx * weight / weight_sum;
+            }
+        }
+    }
+
+    BacktestPnl::from_total(total_return, fx)
+}
+
 fn calc_order_dates_value_from_funds_result(
     funds_result: &Vec<(String, BacktestResult)>,
 ) -> Vec<NaiveDate> {
@@ -544,14 +675,21 @@ fn calc_order_dates_value_from_funds_result(
     dates
 }
 
+/// Fees of switching between funds on rebalances are charged as stock trades and added to the
+/// totals, those paid inside funds are not
+#[allow(clippy::too_many_arguments)]
 async fn calc_trade_dates_value_from_funds_result(
     funds_result: &Vec<(String, BacktestResult)>,
     funds_weight: &[(String, f64)],
     period_days: u64,
+    fof_rules: &[FofRule],
     options: &BacktestOptions,
     sender: &Sender<BacktestEvent>,
+    fof_name: Option<&str>,
+    fee_totals: &mut BTreeMap<String, f64>,
 ) -> Vec<(NaiveDate, f64)> {
-    // All funds value of trade dates based on the same initial cash
+    // All funds value of trade dates based on the same initial cash, funds of markets on holiday
+    // carry their last values
     let trade_dates_funds_standard_value: HashMap<NaiveDate, HashMap<String, f64>> = {
         let mut funds_value_map = HashMap::new();
 
@@ -567,10 +705,36 @@ async fn calc_trade_dates_value_from_funds_result(
             }
         }
 
+        let mut dates: Vec<NaiveDate> = funds_value_map.keys().copied().collect();
+        dates.sort_unstable();
+
+        let mut last_funds_value: HashMap<String, f64> = HashMap::new();
+        for date in dates {
+            if let Some(funds_value) = funds_value_map.get_mut(&date) {
+                for (fund_name, value) in &last_funds_value {
+                    funds_value.entry(fund_name.to_string()).or_insert(*value);
+                }
+                last_funds_value.clone_from(funds_value);
+            }
+        }
+
         funds_value_map
     };
 
-    let funds_weight_sum: f64 = funds_weight.iter().map(|(_, w)| *w).sum();
+    let funds_series: FundsSeries = funds_result
+        .iter()
+        .map(|(fund_name, fund_result)| {
+            let mut series = fund_result.trade_dates_value.clone();
+            series.sort_by_key(|(date, _)| *date);
+            (fund_name.to_string(), series)
+        })
+        .collect();
+    let has_drift_rule = fof_rules.iter().any(|rule| rule.is_drift());
+
+    let rebalance_title = match fof_name {
+        Some(fof_name) => format!("[Rebalance {fof_name}]"),
+        None => "[Rebalance]".to_string(),
+    };
 
     let mut trade_dates_value: Vec<(NaiveDate, f64)> = vec![];
 
@@ -578,6 +742,7 @@ async fn calc_trade_dates_value_from_funds_result(
         date: NaiveDate,
         funds_value: HashMap<String, f64>,
         funds_standard_value: HashMap<String, f64>,
+        cash: f64,
     }
 
     let mut optional_period_start: Option<PeriodStart> = None;
@@ -597,28 +762,87 @@ async fn calc_trade_dates_value_from_funds_result(
                     }
                 }
 
+                let total_value = funds_value.values().sum::<f64>() + period_start.cash;
+
                 // Check frequency
                 let days = (date - period_start.date).num_days();
-                if period_days > 0 && days >= period_days as i64 {
+                let is_period_end = period_days > 0 && days >= period_days as i64;
+
+                // Check drift, the targets may also move with the rules
+                let mut drift_exceeded: Option<f64> = None;
+                let optional_targets_weight = if is_period_end || has_drift_rule {
+                    let targets_weight =
+                        calc_targets_weight(fof_rules, funds_weight, &funds_series, &date);
+                    if !is_period_end && total_value > 0.0 {
+                        let current_weights: HashMap<String, f64> = funds_value
+                            .iter()
+                            .map(|(fund_name, value)| (fund_name.to_string(), value / total_value))
+                            .collect();
+                        drift_exceeded = fof_rules
+                            .iter()
+                            .find_map(|rule| rule.check_drift(&current_weights, &targets_weight));
+                    }
+
+                    Some(targets_weight)
+                } else {
+                    None
+                };
+
+                if let Some(targets_weight) = optional_targets_weight
+                    && (is_period_end || drift_exceeded.is_some())
+                {
+                    if let Some(drift) = drift_exceeded {
+                        let _ = sender
+                            .send(BacktestEvent::Info {
+                                title: rebalance_title.clone(),
+                                message: format!("[Drift Exceeded] {:.2}%", drift * 100.0),
+                                date: Some(date),
+                            })
+                            .await;
+                    }
+
                     // Rebalance
                     let mut new_funds_value: HashMap<String, f64> = HashMap::new();
                     let mut funds_delta_pct: HashMap<String, f64> = HashMap::new();
 
-                    let total_value = funds_value.values().sum::<f64>();
-                    for (fund_name, fund_weight) in funds_weight.iter() {
-                        let target_fund_value = *fund_weight / funds_weight_sum * total_value;
+                    let mut new_cash = total_value;
+                    for (fund_name, target_weight) in targets_weight.iter() {
+                        let target_fund_value = *target_weight * total_value;
+                        new_cash -= target_fund_value;
 
                         let new_fund_value = if let Some(fund_value) = funds_value.get(fund_name) {
-                            funds_delta_pct.insert(
-                                fund_name.to_string(),
-                                100.0 * (target_fund_value - fund_value) / fund_value,
-                            );
+                            if *fund_value > 0.0 {
+                                funds_delta_pct.insert(
+                                    fund_name.to_string(),
+                                    100.0 * (target_fund_value - fund_value) / fund_value,
+                                );
+                            }
 
                             let delta_value = (target_fund_value - *fund_value).abs();
-                            let fee = calc_buy_fee(delta_value, options)
-                                + calc_sell_fee(delta_value, options);
-
-                            target_fund_value - fee
+                            let fee = if delta_value > 0.0 {
+                                [FeeSide::Buy, FeeSide::Sell]
+                                    .into_iter()
+                                    .map(|side| {
+                                        let fees = options.fees.calc(
+                                            delta_value,
+                                            &TickerType::Stock,
+                                            None,
+                                            side,
+                                        );
+                                        pay_fees(fees, fee_totals)
+                                    })
+                                    .sum()
+                            } else {
+                                0.0
+                            };
+
+                            if target_fund_value >= fee {
+                                target_fund_value - fee
+                            } else {
+                                // Fee of closing the fund is paid from cash
+                                new_cash -= fee - target_fund_value;
+                                0.0
+                            }
                         } else {
                             target_fund_value
                         };
@@ -626,56 +850,77 @@ async fn calc_trade_dates_value_from_funds_result(
                         new_funds_value.insert(fund_name.to_string(), new_fund_value);
                     }
 
+                    let mut message = funds_delta_pct
+                        .iter()
+                        .map(|(fund_name, delta_pct)| {
+                            format!(
+                                "{fund_name}={}{delta_pct:.2}%",
+                                if *delta_pct > 0.0 { "+" } else { "" }
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if new_cash.abs() > total_value * 1e-4 || period_start.cash.abs() > 0.0 {
+                        message
+                            .push_str(&format!(" [Cash {:.2}%]", 100.0 * new_cash / total_value));
+                    }
+
                     let _ = sender
                         .send(BacktestEvent::Info {
-                            title: "[Rebalance]".to_string(),
-                            message: funds_delta_pct
-                                .iter()
-                                .map(|(fund_name, delta_pct)| {
-                                    format!(
-                                        "{fund_name}={}{delta_pct:.2}%",
-                                        if *delta_pct > 0.0 { "+" } else { "" }
-                                    )
-                                })
-                                .collect::<Vec<_>>()
-                                .join(" "),
+                            title: rebalance_title.clone(),
+                            message,
                             date: Some(date),
                         })
                         .await;
 
-                    trade_dates_value.push((date, new_funds_value.values().sum::<f64>()));
+                    trade_dates_value
+                        .push((date, new_funds_value.values().sum::<f64>() + new_cash));
 
                     optional_period_start = Some(PeriodStart {
                         date,
                         funds_value: new_funds_value,
                         funds_standard_value: funds_standard_value.clone(),
+                        cash: if new_cash.abs() > total_value * 1e-9 {
+                            new_cash
+                        } else {
+                            0.0
+                        },
                     });
                 } else {
-                    trade_dates_value.push((date, funds_value.values().sum::<f64>()));
+                    trade_dates_value.push((date, total_value));
                 }
             } else {
                 // Init
+                let targets_weight =
+                    calc_targets_weight(fof_rules, funds_weight, &funds_series, &date);
+
                 let mut funds_value: HashMap<String, f64> = HashMap::new();
                 let mut period_start_funds_standard_value: HashMap<String, f64> = HashMap::new();
                 for (fund_name, fund_standard_value) in funds_standard_value {
-                    if let Some((_, fund_weight)) =
-                        funds_weight.iter().find(|(name, _)| name == fund_name)
+                    if let Some((_, target_weight)) =
+                        targets_weight.iter().find(|(name, _)| name == fund_name)
                     {
-                        funds_value.insert(
-                            fund_name.to_string(),
-                            *fund_weight / funds_weight_sum * fund_standard_value,
-                        );
+                        funds_value
+                            .insert(fund_name.to_string(), *target_weight * fund_standard_value);
                         period_start_funds_standard_value
                             .insert(fund_name.to_string(), *fund_standard_value);
                     }
                 }
 
-                trade_dates_value.push((date, funds_value.values().sum::<f64>()));
+                let targets_weight_sum: f64 = targets_weight.iter().map(|(_, w)| *w).sum();
+                let cash = if targets_weight_sum < 1.0 - 1e-9 {
+                    (1.0 - targets_weight_sum) * options.init_cash
+                } else {
+                    0.0
+                };
+
+                trade_dates_value.push((date, funds_value.values().sum::<f64>() + cash));
 
                 optional_period_start = Some(PeriodStart {
                     date,
                     funds_value,
                     funds_standard_value: period_start_funds_standard_value,
+                    cash,
                 });
             }
         }
@@ -683,3 +928,57 @@ async fn calc_trade_dates_value_from_funds_result(
 
     trade_dates_value
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backtest::fee::FeeSchedule, financial::fx::Currency};
+
+    #[tokio::test]
+    async fn test_fof_cycle() {
+        let workspace = std::env::temp_dir().join("vfunds_test_fof_cycle");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(
+            workspace.join("cycle-a.fof.toml"),
+            "title = \"A\"\n\n[funds]\ncycle-b = 1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.join("cycle-b.fof.toml"),
+            "title = \"B\"\n\n[funds]\ncycle-a = 1\n",
+        )
+        .unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        let fof_definition = FofDefinition::from_file(&workspace.join("cycle-a.fof.toml")).unwrap();
+        let mut stream = backtest_fof("cycle-a", &fof_definition, &options, &workspace)
+            .await
+            .unwrap();
+
+        let mut error: Option<VfError> = None;
+        while let Some(event) = stream.next().await {
+            if let BacktestEvent::Error(err) = event {
+                error = Some(err);
+            }
+        }
+        assert!(matches!(
+            error,
+            Some(VfError::Invalid {
+                code: "FOF_CYCLE",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+
+use crate::{spec::FofRuleDefinition, utils::financial::calc_annualized_volatility_std};
+
+/// Daily value series of child funds, sorted by date
+pub type FundsSeries = HashMap<String, Vec<(NaiveDate, f64)>>;
+
+#[derive(Clone, Debug)]
+pub enum FofRule {
+    /// Rebalance as soon as the mean relative drift from the targets weight exceeds the threshold
+    Drift { drift_threshold: f64 },
+    /// Only keep the child funds with the highest trailing return
+    Momentum {
+        limit: usize,
+        lookback_trade_days: usize,
+        positive_only: bool,
+    },
+    /// Scale the weight of child funds by their inverse volatility
+    RiskParity { lookback_trade_days: usize },
+    /// Scale the total exposure so that the expected volatility meets the target, the rest is held in cash
+    VolatilityTarget {
+        lookback_trade_days: usize,
+        max_exposure: f64,
+        target: f64,
+    },
+}
+
+impl FofRule {
+    pub fn from_definition(definition: &FofRuleDefinition) -> Self {
+        let options = &definition.options;
+
+        match definition.name.as_str() {
+            "drift" => FofRule::Drift {
+                drift_threshold: options.read_f64_gt("drift_threshold", 0.1, 0.0),
+            },
+            "momentum" => FofRule::Momentum {
+                limit: options.read_u64_no_zero("limit", 1) as usize,
+                lookback_trade_days: options.read_u64_no_zero("lookback_trade_days", 63) as usize,
+                positive_only: options.read_bool("positive_only", false),
+            },
+            "risk_parity" => FofRule::RiskParity {
+                lookback_trade_days: options.read_u64_no_zero("lookback_trade_days", 63) as usize,
+            },
+            "volatility_target" => FofRule::VolatilityTarget {
+                lookback_trade_days: options.read_u64_no_zero("lookback_trade_days", 21) as usize,
+                max_exposure: options.read_f64_in_range("max_exposure", 1.0, 0.0..=1.0),
+                target: options.read_f64_gt("target", 0.1, 0.0),
+            },
+            _ => panic!("Unsupported FOF rule: {}", definition.name),
+        }
+    }
+
+    /// Returns the drift if it exceeds the threshold, which should trigger a rebalance
+    pub fn check_drift(
+        &self,
+        current_weights: &HashMap<String, f64>,
+        targets_weight: &[(String, f64)],
+    ) -> Option<f64> {
+        if let FofRule::Drift { drift_threshold } = self {
+            let mut drifts: Vec<f64> = vec![];
+            for (fund_name, target_weight) in targets_weight {
+                let current_weight = current_weights.get(fund_name).copied().unwrap_or(0.0);
+                if *target_weight > 0.0 {
+                    drifts.push(((current_weight - target_weight) / target_weight).abs());
+                } else if current_weight > 0.0 {
+                    drifts.push(1.0);
+                }
+            }
+
+            if !drifts.is_empty() {
+                let drift_mean = drifts.iter().sum::<f64>() / drifts.len() as f64;
+                if drift_mean > *drift_threshold {
+                    return Some(drift_mean);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Adjust the targets weight, funds without enough history leave the weights untouched
+    pub fn apply(
+        &self,
+        targets_weight: &mut [(String, f64)],
+        funds_series: &FundsSeries,
+        date: &NaiveDate,
+    ) {
+        match self {
+            FofRule::Drift { .. } => {}
+            FofRule::Momentum {
+                limit,
+                lookback_trade_days,
+                positive_only,
+            } => {
+                let mut returns: Vec<(String, f64)> = vec![];
+                for (fund_name, weight) in targets_weight.iter() {
+                    if *weight > 0.0 {
+                        let values =
+                            latest_values(funds_series, fund_name, date, *lookback_trade_days);
+                        if let (Some(first), Some(last)) = (values.first(), values.last())
+                            && *first > 0.0
+                        {
+                            returns.push((fund_name.to_string(), last / first - 1.0));
+                        } else {
+                            return;
+                        }
+                    }
+                }
+                returns.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+                let selected: HashMap<String, f64> = returns.into_iter().take(*limit).collect();
+                let weights_sum: f64 = targets_weight.iter().map(|(_, w)| *w).sum();
+                let selected_weights_sum: f64 = targets_weight
+                    .iter()
+                    .filter(|(fund_name, _)| selected.contains_key(fund_name))
+                    .map(|(_, w)| *w)
+                    .sum();
+                if selected_weights_sum > 0.0 {
+                    for (fund_name, weight) in targets_weight.iter_mut() {
+                        *weight = match selected.get(fund_name) {
+                            Some(fund_return) if !*positive_only || *fund_return > 0.0 => {
+                                *weight / selected_weights_sum * weights_sum
+                            }
+                            _ => 0.0,
+                        };
+                    }
+                }
+            }
+            FofRule::RiskParity {
+                lookback_trade_days,
+            } => {
+                let mut inverse_vols: HashMap<String, f64> = HashMap::new();
+                for (fund_name, weight) in targets_weight.iter() {
+                    if *weight > 0.0 {
+                        let values =
+                            latest_values(funds_series, fund_name, date, *lookback_trade_days);
+                        if let Some(vol) = calc_annualized_volatility_std(&values)
+                            && vol > 0.0
+                        {
+                            inverse_vols.insert(fund_name.to_string(), 1.0 / vol);
+                        } else {
+                            return;
+                        }
+                    }
+                }
+
+                let weights_sum: f64 = targets_weight.iter().map(|(_, w)| *w).sum();
+                let scaled_weights_sum: f64 = targets_weight
+                    .iter()
+                    .filter_map(|(fund_name, w)| inverse_vols.get(fund_name).map(|v| w * v))
+                    .sum();
+                if scaled_weights_sum > 0.0 {
+                    for (fund_name, weight) in targets_weight.iter_mut() {
+                        if let Some(inverse_vol) = inverse_vols.get(fund_name) {
+                            *weight = *weight * inverse_vol / scaled_weights_sum * weights_sum;
+                        }
+                    }
+                }
+            }
+            FofRule::VolatilityTarget {
+                lookback_trade_days,
+                max_exposure,
+                target,
+            } => {
+                let weights: Vec<(&String, f64)> = targets_weight
+                    .iter()
+                    .filter(|(_, w)| *w > 0.0)
+                    .map(|(fund_name, w)| (fund_name, *w))
+                    .collect();
+                let weights_sum: f64 = weights.iter().map(|(_, w)| *w).sum();
+                if weights_sum <= 0.0 {
+                    return;
+                }
+
+                // Dates shared by all weighted funds
+                let mut common_dates: Option<HashSet<NaiveDate>> = None;
+                for (fund_name, _) in &weights {
+                    let dates: HashSet<NaiveDate> = funds_series
+                        .get(*fund_name)
+                        .map(|series| {
+                            series
+                                .iter()
+                                .filter(|(d, _)| d <= date)
+                                .map(|(d, _)| *d)
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    common_dates = Some(match common_dates {
+                        Some(common_dates) => common_dates.intersection(&dates).copied().collect(),
+                        None => dates,
+                    });
+                }
+                let mut dates: Vec<NaiveDate> =
+                    common_dates.unwrap_or_default().into_iter().collect();
+                dates.sort_unstable();
+                if dates.len() < lookback_trade_days + 1 {
+                    return;
+                }
+                let dates = &dates[dates.len() - lookback_trade_days - 1..];
+
+                // Buy-and-hold value of the weighted funds over the lookback window
+                let mut portfolio_values: Vec<f64> = vec![0.0; dates.len()];
+                for (fund_name, weight) in &weights {
+                    let values_map: HashMap<NaiveDate, f64> = funds_series
+                        .get(*fund_name)
+                        .map(|series| series.iter().copied().collect())
+                        .unwrap_or_default();
+                    let start_value = values_map.get(&dates[0]).copied().unwrap_or(0.0);
+                    if start_value <= 0.0 {
+                        return;
+                    }
+
+                    for (i, d) in dates.iter().enumerate() {
+                        if let Some(value) = values_map.get(d) {
+                            portfolio_values[i] += weight / weights_sum * value / start_value;
+                        }
+                    }
+                }
+
+                if let Some(vol) = calc_annualized_volatility_std(&portfolio_values)
+                    && vol > 0.0
+                {
+                    let exposure = (target / vol).min(*max_exposure);
+                    for (_, weight) in targets_weight.iter_mut() {
+                        *weight = *weight / weights_sum * exposure;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_drift(&self) -> bool {
+        matches!(self, FofRule::Drift { .. })
+    }
+}
+
+/// Apply the rules in order to the base weights, the remainder to 1 is held in cash
+pub fn calc_targets_weight(
+    rules: &[FofRule],
+    funds_weight: &[(String, f64)],
+    funds_series: &FundsSeries,
+    date: &NaiveDate,
+) -> Vec<(String, f64)> {
+    let funds_weight_sum: f64 = funds_weight.iter().map(|(_, w)| *w).sum();
+    let mut targets_weight: Vec<(String, f64)> = funds_weight
+        .iter()
+        .map(|(fund_name, w)| (fund_name.to_string(), *w / funds_weight_sum))
+        .collect();
+
+    for rule in rules {
+        rule.apply(&mut targets_weight, funds_series, date);
+    }
+
+    targets_weight
+}
+
+fn latest_values(
+    funds_series: &FundsSeries,
+    fund_name: &str,
+    date: &NaiveDate,
+    lookback_trade_days: usize,
+) -> Vec<f64> {
+    if let Some(series) = funds_series.get(fund_name) {
+        let end = series.partition_point(|(d, _)| d <= date);
+        if end > lookback_trade_days {
+            return series[end - lookback_trade_days - 1..end]
+                .iter()
+                .map(|(_, v)| *v)
+                .collect();
+        }
+    }
+
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, options: serde_json::Value) -> FofRule {
+        FofRule::from_definition(&FofRuleDefinition {
+            name: name.to_string(),
+            options: serde_json::from_value(options).unwrap(),
+        })
+    }
+
+    fn series(daily_changes: &[f64]) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let mut value = 1.0;
+        daily_changes
+            .iter()
+            .enumerate()
+            .map(|(i, change)| {
+                value *= 1.0 + change;
+                (start + chrono::Duration::days(i as i64), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fof_rules() {
+        let funds_series: FundsSeries = HashMap::from([
+            (
+                "a".to_string(),
+                series(&[0.01, -0.01, 0.01, -0.01, 0.01, -0.01]),
+            ),
+            (
+                "b".to_string(),
+                series(&[0.02, -0.02, 0.02, -0.02, 0.02, -0.02]),
+            ),
+            (
+                "c".to_string(),
+                series(&[0.0, 0.01, 0.01, 0.01, 0.01, 0.01]),
+            ),
+        ]);
+        let funds_weight = vec![
+            ("a".to_string(), 1.0),
+            ("b".to_string(), 1.0),
+            ("c".to_string(), 2.0),
+        ];
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let weight_of = |weights: &[(String, f64)], name: &str| {
+            weights.iter().find(|(n, _)| n == name).unwrap().1
+        };
+
+        let weights = calc_targets_weight(&[], &funds_weight, &funds_series, &date);
+        assert_eq!(weight_of(&weights, "c"), 0.5);
+
+        let momentum = rule(
+            "momentum",
+            serde_json::json!({"limit": 1, "lookback_trade_days": 4}),
+        );
+        let weights = calc_targets_weight(&[momentum], &funds_weight, &funds_series, &date);
+        assert_eq!(weight_of(&weights, "a"), 0.0);
+        assert!((weight_of(&weights, "c") - 1.0).abs() < 1e-9);
+
+        let risk_parity = rule("risk_parity", serde_json::json!({"lookback_trade_days": 4}));
+        let weights = calc_targets_weight(&[risk_parity], &funds_weight[..2], &funds_series, &date);
+        assert!((weight_of(&weights, "a") - 2.0 / 3.0).abs() < 1e-2);
+
+        let volatility_target = rule(
+            "volatility_target",
+            serde_json::json!({"lookback_trade_days": 4, "target": 0.01}),
+        );
+        let weights = calc_targets_weight(
+            &[volatility_target],
+            &funds_weight[..1],
+            &funds_series,
+            &date,
+        );
+        assert!(weight_of(&weights, "a") < 0.1);
+
+        let drift = rule("drift", serde_json::json!({"drift_threshold": 0.2}));
+        let targets_weight = vec![("a".to_string(), 0.5), ("b".to_string(), 0.5)];
+        let current_weights = HashMap::from([("a".to_string(), 0.55), ("b".to_string(), 0.45)]);
+        assert!(
+            drift
+                .check_drift(&current_weights, &targets_weight)
+                .is_none()
+        );
+        let current_weights = HashMap::from([("a".to_string(), 0.7), ("b".to_string(), 0.3)]);
+        assert!(
+            drift
+                .check_drift(&current_weights, &targets_weight)
+                .is_some()
+        );
+    }
+}
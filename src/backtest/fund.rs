@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     time::Instant,
 };
 
@@ -8,13 +8,19 @@ use itertools::Itertools;
 use tokio::sync::{mpsc, mpsc::Sender};
 
 use crate::{
-    CHANNEL_BUFFER_DEFAULT,
+    CHANNEL_BUFFER_DEFAULT, DAYS_PER_YEAR,
     backtest::*,
+    cache,
     filter::filter_delisted::is_delisted,
-    financial::{market::fetch_trade_dates, *},
+    financial::{
+        fx::{Currency, get_fx_rate, get_last_fx_rate},
+        market::{fetch_trade_dates_union, is_trade_date},
+        stock::fetch_lot_size,
+        *,
+    },
     rule::Rule,
     spec::*,
-    ticker::Ticker,
+    ticker::{Ticker, TickerType},
     utils::{
         datetime::{date_to_str, secs_to_human_str},
         stats::mean,
@@ -27,18 +33,94 @@ pub struct FundBacktestContext<'a> {
     pub portfolio: &'a mut Portfolio,
     pub no_position_dates: &'a mut HashSet<NaiveDate>,
     pub order_dates: &'a mut HashSet<NaiveDate>,
+    pub fee_totals: &'a mut BTreeMap<String, f64>,
 
     suspended_cash: Option<HashMap<Ticker, f64>>,
+    fx_pnl: f64,
+    fx_exposures: HashMap<Currency, (f64, f64)>,
+    /// Dates when positions were entered, from which the tracking cost of index proxies accrues
+    position_entry_dates: HashMap<Ticker, NaiveDate>,
+    /// Universe after filters of the latest date, shared by all rules on the date
+    filtered_tickers: Option<FilteredTickers>,
 }
 
+type FilteredTickers = (
+    NaiveDate,
+    HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>,
+    FiltersReport,
+);
+
 impl FundBacktestContext<'_> {
+    fn pay_fees(&mut self, value: f64, ticker: &Ticker, side: FeeSide) -> f64 {
+        pay_fees(
+            calc_fees(value, ticker, side, self.options),
+            self.fee_totals,
+        )
+    }
+
+    /// Price in the base currency of cash, converted by the FX rate of the date, and prices of
+    /// indexes held as proxies are reduced by the tracking cost
+    async fn get_price(
+        &self,
+        ticker: &Ticker,
+        date: &NaiveDate,
+        price_type: &PriceType,
+    ) -> VfResult<Option<f64>> {
+        if let Some(price) = get_ticker_price(ticker, date, true, price_type).await?
+            && let Some(rate) =
+                get_fx_rate(&ticker.currency(), &self.options.base_currency, date).await?
+        {
+            Ok(Some(
+                price * rate * self.index_tracking_factor(ticker, date),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn index_tracking_factor(&self, ticker: &Ticker, date: &NaiveDate) -> f64 {
+        let cost = self.fund_definition.options.index_tracking_cost;
+        if ticker.r#type != TickerType::Index || cost <= 0.0 {
+            return 1.0;
+        }
+
+        let entry_date = self.position_entry_dates.get(ticker).unwrap_or(date);
+        let years = (*date - *entry_date).num_days().max(0) as f64 / DAYS_PER_YEAR;
+        (1.0 - cost).powf(years)
+    }
+
+    /// Tickers of multi-market funds are traded only on trading days of their own exchanges
+    async fn check_trade_date(
+        &self,
+        ticker: &Ticker,
+        date: &NaiveDate,
+        event_sender: &Sender<BacktestEvent>,
+    ) -> VfResult<bool> {
+        if is_trade_date(ticker, date).await? {
+            return Ok(true);
+        }
+
+        let _ = event_sender
+            .send(BacktestEvent::Warning {
+                title: "[Holiday]".to_string(),
+                message: format!(
+                    "'{ticker}' not traded on holiday of {}",
+                    ticker.exchange_mic()
+                ),
+                date: Some(*date),
+            })
+            .await;
+
+        Ok(false)
+    }
+
     pub async fn calc_total_value(
         &self,
         date: &NaiveDate,
         price_type: &PriceType,
     ) -> VfResult<f64> {
         let positions_value = self.calc_positions_value(date, price_type).await?;
-        let total_value = self.calc_cash() + positions_value.values().sum::<f64>();
+        let total_value = self.calc_cash(date).await? + positions_value.values().sum::<f64>();
 
         Ok(total_value)
     }
@@ -48,6 +130,8 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
+        self.exchange_currency_cash(date).await?;
+
         if !self.portfolio.positions.is_empty() {
             let position_tickers_map = self.position_tickers_map(date).await?;
             let position_weight_sum = position_tickers_map
@@ -70,11 +154,11 @@ impl FundBacktestContext<'_> {
                         if let Some((weight, _)) = position_tickers_map.get(ticker) {
                             let deploy_cash = total_deploy_cash * weight / position_weight_sum;
 
-                            let fee = calc_buy_fee(deploy_cash, self.options);
+                            let fee = calc_buy_fee(deploy_cash, ticker, self.options);
                             let delta_value = deploy_cash - fee;
                             if delta_value > 0.0 {
                                 if let Some(buy_price) =
-                                    get_ticker_price(ticker, date, true, &buy_price_type).await?
+                                    self.get_price(ticker, date, &buy_price_type).await?
                                 {
                                     let target_units =
                                         *units + (delta_value / buy_price).floor() as u64;
@@ -132,11 +216,11 @@ impl FundBacktestContext<'_> {
                 for (ticker, units) in &self.portfolio.positions.clone() {
                     if let Some((weight, _)) = position_tickers_map.get(ticker) {
                         let raise_cash = cash * weight / position_weight_sum;
-                        let fee = calc_sell_fee(raise_cash, self.options);
+                        let fee = calc_sell_fee(raise_cash, ticker, self.options);
                         let delta_value = raise_cash + fee;
 
                         if let Some(sell_price) =
-                            get_ticker_price(ticker, date, true, &sell_price_type).await?
+                            self.get_price(ticker, date, &sell_price_type).await?
                         {
                             let sell_units =
                                 (delta_value / sell_price).ceil().min(*units as f64) as u64;
@@ -203,6 +287,8 @@ impl FundBacktestContext<'_> {
                     self.portfolio.reserved_cash.remove(ticker);
                 }
             }
+
+            self.exchange_currency_cash(date).await?;
         }
 
         // Use mid price for rebalancing
@@ -211,7 +297,7 @@ impl FundBacktestContext<'_> {
         // Only keep tickers with price data
         let mut tickers_weight_price: Vec<(Ticker, f64, f64)> = vec![];
         for (ticker, weight) in targets_weight {
-            if let Some(price) = get_ticker_price(ticker, date, true, &price_type).await? {
+            if let Some(price) = self.get_price(ticker, date, &price_type).await? {
                 tickers_weight_price.push((ticker.clone(), *weight, price));
             } else {
                 let _ = event_sender
@@ -263,7 +349,7 @@ impl FundBacktestContext<'_> {
             }
         }
 
-        let cash = self.calc_cash();
+        let cash = self.calc_cash(date).await?;
         let positions_value = self.calc_positions_value(date, &PriceType::Close).await?;
 
         let _ = notify_portfolio(
@@ -309,11 +395,15 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<f64> {
+        if !self.check_trade_date(ticker, date, event_sender).await? {
+            return Ok(0.0);
+        }
+
         let position_units = *self.portfolio.positions.get(ticker).unwrap_or(&0);
         let cash = if position_units > 0 {
             let sell_units = position_units as f64;
             let value = sell_units * sell_price;
-            let fee = calc_sell_fee(value, self.options);
+            let fee = self.pay_fees(value, ticker, FeeSide::Sell);
             let amount = value - fee;
 
             if make_reserved {
@@ -323,9 +413,10 @@ impl FundBacktestContext<'_> {
                     .and_modify(|v| v.0 += amount)
                     .or_insert((amount, *date));
             } else {
-                self.portfolio.free_cash += amount;
+                self.receive_cash(amount, ticker, date).await?;
             }
             self.portfolio.positions.remove(ticker);
+            self.position_entry_dates.remove(ticker);
 
             self.order_dates.insert(*date);
             let _ = event_sender
@@ -354,7 +445,7 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<f64> {
-        if let Some(sell_price) = get_ticker_price(ticker, date, true, sell_price_type).await? {
+        if let Some(sell_price) = self.get_price(ticker, date, sell_price_type).await? {
             self.position_close_with_price(ticker, make_reserved, sell_price, date, event_sender)
                 .await
         } else {
@@ -393,16 +484,23 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
-        if let Some((reserved_cash, _)) = self.portfolio.reserved_cash.get(ticker) {
-            let delta_value = reserved_cash - calc_buy_fee(*reserved_cash, self.options);
+        if !self.check_trade_date(ticker, date, event_sender).await? {
+            return Ok(());
+        }
 
-            let buy_units = (delta_value / buy_price).floor() as u64;
+        if let Some(&(reserved_cash, _)) = self.portfolio.reserved_cash.get(ticker) {
+            let delta_value = reserved_cash - calc_buy_fee(reserved_cash, ticker, self.options);
+
+            let buy_units = floor_to_lot(
+                (delta_value / buy_price).floor() as u64,
+                fetch_lot_size(ticker).await?,
+            );
             if buy_units > 0 {
                 let value = buy_units as f64 * buy_price;
-                let fee = calc_buy_fee(value, self.options);
+                let fee = self.pay_fees(value, ticker, FeeSide::Buy);
                 let amount = value + fee;
 
-                self.portfolio.free_cash += *reserved_cash - amount;
+                self.portfolio.free_cash += reserved_cash - amount;
                 self.portfolio.reserved_cash.remove(ticker);
 
                 self.portfolio
@@ -410,6 +508,9 @@ impl FundBacktestContext<'_> {
                     .entry(ticker.clone())
                     .and_modify(|v| *v += buy_units)
                     .or_insert(buy_units);
+                self.position_entry_dates
+                    .entry(ticker.clone())
+                    .or_insert(*date);
 
                 self.order_dates.insert(*date);
                 let _ = event_sender
@@ -417,7 +518,7 @@ impl FundBacktestContext<'_> {
                         title: get_ticker_title(ticker).await,
                         amount,
                         price: buy_price,
-                        units: buy_units as u64,
+                        units: buy_units,
                         date: *date,
                     })
                     .await;
@@ -434,7 +535,7 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
-        if let Some(buy_price) = get_ticker_price(ticker, date, true, buy_price_type).await? {
+        if let Some(buy_price) = self.get_price(ticker, date, buy_price_type).await? {
             self.position_entry_reserved_with_price(ticker, buy_price, date, event_sender)
                 .await
         } else {
@@ -475,15 +576,22 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
-        let delta_value = cash - calc_buy_fee(cash, self.options);
+        if !self.check_trade_date(ticker, date, event_sender).await? {
+            return Ok(());
+        }
+
+        let delta_value = cash - calc_buy_fee(cash, ticker, self.options);
 
-        let buy_units = (delta_value / buy_price).floor() as u64;
+        let buy_units = floor_to_lot(
+            (delta_value / buy_price).floor() as u64,
+            fetch_lot_size(ticker).await?,
+        );
         if buy_units > 0 {
             let value = buy_units as f64 * buy_price;
-            let fee = calc_buy_fee(value, self.options);
+            let fee = self.pay_fees(value, ticker, FeeSide::Buy);
             let amount = value + fee;
 
-            self.portfolio.free_cash -= amount;
+            self.pay_cash(amount, ticker, date).await?;
             self.portfolio
                 .positions
                 .entry(ticker.clone())
@@ -513,7 +621,7 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
-        if let Some(buy_price) = get_ticker_price(ticker, date, true, buy_price_type).await? {
+        if let Some(buy_price) = self.get_price(ticker, date, buy_price_type).await? {
             self.position_open_with_price(ticker, cash, buy_price, date, event_sender)
                 .await
         } else {
@@ -568,6 +676,10 @@ impl FundBacktestContext<'_> {
         date: &NaiveDate,
         event_sender: &Sender<BacktestEvent>,
     ) -> VfResult<()> {
+        if !self.check_trade_date(ticker, date, event_sender).await? {
+            return Ok(());
+        }
+
         let position_units = *self.portfolio.positions.get(ticker).unwrap_or(&0);
         let delta_units: i64 = target_units as i64 - position_units as i64;
         if (delta_units as f64).abs()
@@ -576,22 +688,39 @@ impl FundBacktestContext<'_> {
             return Ok(());
         }
 
+        // Trade in whole lots, except selling out the position with odd lots
+        let lot_size = fetch_lot_size(ticker).await?;
+        let delta_units: i64 = if lot_size > 1 && target_units > 0 {
+            let lot_delta_units =
+                delta_units.signum() * floor_to_lot(delta_units.unsigned_abs(), lot_size) as i64;
+            if lot_delta_units == 0 {
+                return Ok(());
+            }
+
+            lot_delta_units
+        } else {
+            delta_units
+        };
+
         let ticker_title = get_ticker_title(ticker).await;
         if delta_units > 0 {
-            if let Some(buy_price) = get_ticker_price(ticker, date, true, buy_price_type).await? {
+            if let Some(buy_price) = self.get_price(ticker, date, buy_price_type).await? {
                 let buy_units = delta_units as u64;
                 let value = buy_units as f64 * buy_price;
 
-                let fee = calc_buy_fee(value, self.options);
+                let fee = self.pay_fees(value, ticker, FeeSide::Buy);
                 let amount = value + fee;
 
-                self.portfolio.free_cash -= amount;
+                self.pay_cash(amount, ticker, date).await?;
 
                 self.portfolio
                     .positions
                     .entry(ticker.clone())
                     .and_modify(|v| *v += buy_units)
                     .or_insert(buy_units);
+                self.position_entry_dates
+                    .entry(ticker.clone())
+                    .or_insert(*date);
 
                 self.order_dates.insert(*date);
                 let _ = event_sender
@@ -613,17 +742,18 @@ impl FundBacktestContext<'_> {
                     .await;
             }
         } else {
-            if let Some(sell_price) = get_ticker_price(ticker, date, true, sell_price_type).await? {
+            if let Some(sell_price) = self.get_price(ticker, date, sell_price_type).await? {
                 let sell_units = delta_units.unsigned_abs();
                 let value = sell_units as f64 * sell_price;
 
-                let fee = calc_sell_fee(value, self.options);
+                let fee = self.pay_fees(value, ticker, FeeSide::Sell);
                 let amount = value - fee;
 
-                self.portfolio.free_cash += amount;
+                self.receive_cash(amount, ticker, date).await?;
 
                 if sell_units == position_units {
                     self.portfolio.positions.remove(ticker);
+                    self.position_entry_dates.remove(ticker);
                 } else {
                     self.portfolio
                         .positions
@@ -679,7 +809,7 @@ impl FundBacktestContext<'_> {
                 })
                 .await;
 
-            let cash = self.calc_cash();
+            let cash = self.calc_cash(date).await?;
             let positions_value = self.calc_positions_value(date, &PriceType::Close).await?;
 
             let _ = notify_portfolio(
@@ -722,7 +852,7 @@ impl FundBacktestContext<'_> {
                 })
                 .await;
 
-            let cash = self.calc_cash();
+            let cash = self.calc_cash(date).await?;
             let positions_value = self.calc_positions_value(date, &PriceType::Close).await?;
 
             let _ = notify_portfolio(
@@ -745,14 +875,146 @@ impl FundBacktestContext<'_> {
         hold_tickers.into_iter().chain(reserved_tickers).collect()
     }
 
-    fn calc_cash(&self) -> f64 {
-        self.portfolio.free_cash
+    /// Accrue P&L of FX rates on the exposures to other currencies since the last accrual, as
+    /// the values of positions and cash in those currencies at close
+    async fn accrue_fx_pnl(&mut self, date: &NaiveDate) -> VfResult<()> {
+        let base_currency = self.options.base_currency;
+
+        let mut exposures: HashMap<Currency, f64> = self.portfolio.currency_cash.clone();
+        for (ticker, units) in &self.portfolio.positions {
+            let currency = ticker.currency();
+            if currency != base_currency
+                && let Some(price) = get_ticker_price(ticker, date, true, &PriceType::Close).await?
+            {
+                *exposures.entry(currency).or_insert(0.0) += *units as f64 * price;
+            }
+        }
+
+        let currencies: HashSet<Currency> = exposures
+            .keys()
+            .chain(self.fx_exposures.keys())
+            .copied()
+            .collect();
+
+        let mut fx_exposures: HashMap<Currency, (f64, f64)> = HashMap::new();
+        for currency in currencies {
+            if let Some(rate) = get_fx_rate(&currency, &base_currency, date).await? {
+                if let Some((prev_exposure, prev_rate)) = self.fx_exposures.get(&currency) {
+                    self.fx_pnl += prev_exposure * (rate - prev_rate);
+                }
+
+                if let Some(exposure) = exposures.get(&currency) {
+                    fx_exposures.insert(currency, (*exposure, rate));
+                }
+            } else if let Some(prev) = self.fx_exposures.get(&currency) {
+                fx_exposures.insert(currency, *prev);
+            }
+        }
+        self.fx_exposures = fx_exposures;
+
+        Ok(())
+    }
+
+    async fn calc_cash(&self, date: &NaiveDate) -> VfResult<f64> {
+        let mut cash = self.portfolio.free_cash
             + self
                 .portfolio
                 .reserved_cash
                 .values()
                 .map(|(cash, _)| cash)
-                .sum::<f64>()
+                .sum::<f64>();
+
+        // Rates are carried forward if stale, which is warned by `stale_fx_rates`
+        for (currency, currency_cash) in &self.portfolio.currency_cash {
+            if let Some((_, rate)) =
+                get_last_fx_rate(currency, &self.options.base_currency, date).await?
+            {
+                cash += currency_cash * rate;
+            } else {
+                return Err(VfError::NoData {
+                    code: "NO_FX_RATE_DATA",
+                    message: format!(
+                        "FX rate of {currency}/{} @{} not exists",
+                        self.options.base_currency,
+                        date_to_str(date)
+                    ),
+                });
+            }
+        }
+
+        Ok(cash)
+    }
+
+    /// Currencies of cash whose FX rates are stale on the date, with the dates of their last
+    /// known rates carried forward
+    async fn stale_fx_rates(&self, date: &NaiveDate) -> VfResult<Vec<(Currency, NaiveDate)>> {
+        let mut stale_rates: Vec<(Currency, NaiveDate)> = vec![];
+        for currency in self.portfolio.currency_cash.keys() {
+            if get_fx_rate(currency, &self.options.base_currency, date)
+                .await?
+                .is_none()
+                && let Some((rate_date, _)) =
+                    get_last_fx_rate(currency, &self.options.base_currency, date).await?
+            {
+                stale_rates.push((*currency, rate_date));
+            }
+        }
+
+        Ok(stale_rates)
+    }
+
+    /// Exchange cash of other currencies into the base currency, so it can be deployed
+    async fn exchange_currency_cash(&mut self, date: &NaiveDate) -> VfResult<()> {
+        for (currency, currency_cash) in self.portfolio.currency_cash.clone() {
+            if let Some(rate) = get_fx_rate(&currency, &self.options.base_currency, date).await? {
+                self.portfolio.free_cash += currency_cash * rate;
+                self.portfolio.currency_cash.remove(&currency);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pay the amount in the base currency for the ticker, by cash of its quote currency first
+    async fn pay_cash(&mut self, amount: f64, ticker: &Ticker, date: &NaiveDate) -> VfResult<()> {
+        let currency = ticker.currency();
+        if currency != self.options.base_currency
+            && let Some(currency_cash) = self.portfolio.currency_cash.get(&currency).copied()
+            && let Some(rate) = get_fx_rate(&currency, &self.options.base_currency, date).await?
+        {
+            let currency_amount = amount / rate;
+            if currency_cash > currency_amount {
+                self.portfolio
+                    .currency_cash
+                    .insert(currency, currency_cash - currency_amount);
+            } else {
+                self.portfolio.currency_cash.remove(&currency);
+                self.portfolio.free_cash -= (currency_amount - currency_cash) * rate;
+            }
+        } else {
+            self.portfolio.free_cash -= amount;
+        }
+
+        Ok(())
+    }
+
+    /// Receive the amount in the base currency for the ticker, kept as cash of its quote currency
+    async fn receive_cash(
+        &mut self,
+        amount: f64,
+        ticker: &Ticker,
+        date: &NaiveDate,
+    ) -> VfResult<()> {
+        let currency = ticker.currency();
+        if currency != self.options.base_currency
+            && let Some(rate) = get_fx_rate(&currency, &self.options.base_currency, date).await?
+        {
+            *self.portfolio.currency_cash.entry(currency).or_insert(0.0) += amount / rate;
+        } else {
+            self.portfolio.free_cash += amount;
+        }
+
+        Ok(())
     }
 
     async fn calc_positions_value(
@@ -763,7 +1025,7 @@ impl FundBacktestContext<'_> {
         let mut positions_value: HashMap<Ticker, f64> = HashMap::new();
 
         for (ticker, units) in &self.portfolio.positions {
-            if let Some(price) = get_ticker_price(ticker, date, true, price_type).await? {
+            if let Some(price) = self.get_price(ticker, date, price_type).await? {
                 positions_value.insert(ticker.clone(), *units as f64 * price);
             } else {
                 let delisted = is_delisted(ticker, date).await?;
@@ -782,11 +1044,43 @@ impl FundBacktestContext<'_> {
         Ok(positions_value)
     }
 
+    /// Tickers of the fund universe after filters on the date
+    pub async fn all_tickers_map(
+        &mut self,
+        date: &NaiveDate,
+    ) -> VfResult<HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>> {
+        let (all_tickers_map, _) = self.all_tickers_map_with_filters_report(date).await?;
+        Ok(all_tickers_map)
+    }
+
+    /// Filters run once per date, as they are costly on large universes
+    async fn all_tickers_map_with_filters_report(
+        &mut self,
+        date: &NaiveDate,
+    ) -> VfResult<(
+        HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>,
+        FiltersReport,
+    )> {
+        if let Some((filtered_date, all_tickers_map, report)) = &self.filtered_tickers
+            && filtered_date == date
+        {
+            return Ok((all_tickers_map.clone(), report.clone()));
+        }
+
+        let (all_tickers_map, report) = self
+            .fund_definition
+            .all_tickers_map_with_filters_report(date)
+            .await?;
+        self.filtered_tickers = Some((*date, all_tickers_map.clone(), report.clone()));
+
+        Ok((all_tickers_map, report))
+    }
+
     async fn position_tickers_map(
-        &self,
+        &mut self,
         date: &NaiveDate,
     ) -> VfResult<HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>> {
-        let all_tickers_map = self.fund_definition.all_tickers_map(date).await?;
+        let all_tickers_map = self.all_tickers_map(date).await?;
         Ok(all_tickers_map
             .into_iter()
             .filter(|(ticker, _)| self.portfolio.positions.contains_key(ticker))
@@ -794,6 +1088,10 @@ impl FundBacktestContext<'_> {
     }
 }
 
+fn floor_to_lot(units: u64, lot_size: u64) -> u64 {
+    units / lot_size.max(1) * lot_size.max(1)
+}
+
 pub async fn backtest_fund(
     fund_definition: &FundDefinition,
     options: &BacktestOptions,
@@ -815,8 +1113,13 @@ pub async fn backtest_fund(
                 portfolio: &mut Portfolio::new(options.init_cash),
                 no_position_dates: &mut HashSet::new(),
                 order_dates: &mut HashSet::new(),
+                fee_totals: &mut BTreeMap::new(),
 
                 suspended_cash: None,
+                fx_pnl: 0.0,
+                fx_exposures: HashMap::new(),
+                position_entry_dates: HashMap::new(),
+                filtered_tickers: None,
             };
 
             let mut rules = fund_definition
@@ -830,7 +1133,12 @@ pub async fn backtest_fund(
             let mut trade_dates_value: Vec<(NaiveDate, f64)> = vec![];
 
             let mut rules_period_start_date: HashMap<usize, NaiveDate> = HashMap::new();
-            let trade_dates = fetch_trade_dates().await?;
+            let mut warned_membership_fallbacks: HashSet<String> = HashSet::new();
+            let mut marked_tickers = cache::marks::marks().await.unwrap_or_default();
+            let mut warned_stale_fx_currencies: HashSet<Currency> = HashSet::new();
+            // Positions are valued on trading days of any exchange, with stale prices of the
+            // exchanges on holiday
+            let trade_dates = fetch_trade_dates_union(&fund_definition.exchanges().await?).await?;
             for date in options.start_date.iter_days().take(days as usize) {
                 if trade_dates.contains(&date) {
                     // Check suspend, when suspended, keep empty positions
@@ -851,6 +1159,7 @@ pub async fn backtest_fund(
                     }
 
                     // Excute rules
+                    let mut filters_reported = false;
                     for (rule_index, rule) in rules.iter_mut().enumerate() {
                         if let Some(period_start_date) = rules_period_start_date.get(&rule_index) {
                             // Check frequency
@@ -865,6 +1174,41 @@ pub async fn backtest_fund(
                             }
                         }
 
+                        // Universes of tickers listed only have nothing to report
+                        if !filters_reported
+                            && (!fund_definition.filters.is_empty()
+                                || !fund_definition.ticker_sources.is_empty())
+                        {
+                            let (_, report) =
+                                context.all_tickers_map_with_filters_report(&date).await?;
+                            if !fund_definition.filters.is_empty() {
+                                let _ = notify_filters_report(&sender, &date, &report).await;
+                            }
+                            for failure in report.failures {
+                                let _ = sender
+                                    .send(BacktestEvent::Warning {
+                                        title: "[Filters]".to_string(),
+                                        message: format!("Failed {failure}"),
+                                        date: Some(date),
+                                    })
+                                    .await;
+                            }
+                            for fallback in report.membership_fallbacks {
+                                if warned_membership_fallbacks.insert(fallback.clone()) {
+                                    let _ = sender
+                                        .send(BacktestEvent::Warning {
+                                            title: "[Universe]".to_string(),
+                                            message: format!(
+                                                "No historical membership of {fallback}, falling back to the current one"
+                                            ),
+                                            date: Some(date),
+                                        })
+                                        .await;
+                                }
+                            }
+                            filters_reported = true;
+                        }
+
                         match rule.exec(&mut context, &date, &sender).await {
                             Ok(_) => {
                                 rules_period_start_date.insert(rule_index, date);
@@ -879,6 +1223,43 @@ pub async fn backtest_fund(
                         context.no_position_dates.insert(date);
                     }
 
+                    // Warn once for each ticker marked with bad data
+                    for ticker in context.portfolio.positions.keys() {
+                        if let Some(reason) = marked_tickers.remove(ticker) {
+                            let _ = sender
+                                .send(BacktestEvent::Warning {
+                                    title: "[Data]".to_string(),
+                                    message: format!(
+                                        "Kline of '{ticker}' is marked bad ({reason}) by data check"
+                                    ),
+                                    date: Some(date),
+                                })
+                                .await;
+                        }
+                    }
+
+                    // Warn once for each currency of cash until its FX rate is fresh again
+                    let stale_fx_rates = context.stale_fx_rates(&date).await?;
+                    warned_stale_fx_currencies
+                        .retain(|currency| stale_fx_rates.iter().any(|(c, _)| c == currency));
+                    for (currency, rate_date) in stale_fx_rates {
+                        if warned_stale_fx_currencies.insert(currency) {
+                            let _ = sender
+                                .send(BacktestEvent::Warning {
+                                    title: "[Data]".to_string(),
+                                    message: format!(
+                                        "FX rate of {currency}/{} is stale, carried forward from {}",
+                                        options.base_currency,
+                                        date_to_str(&rate_date)
+                                    ),
+                                    date: Some(date),
+                                })
+                                .await;
+                        }
+                    }
+
+                    context.accrue_fx_pnl(&date).await?;
+
                     if let Ok(total_value) =
                         context.calc_total_value(&date, &PriceType::Close).await
                     {
@@ -887,7 +1268,7 @@ pub async fn backtest_fund(
                 }
             }
 
-            let final_cash = context.calc_cash();
+            let final_cash = context.calc_cash(&options.end_date).await?;
             let final_positions_value = context
                 .calc_positions_value(&options.end_date, &PriceType::Close)
                 .await?;
@@ -900,6 +1281,7 @@ pub async fn backtest_fund(
                 options.init_cash,
             )
             .await;
+            let _ = notify_fees(&sender, &options.end_date, context.fee_totals).await;
 
             let mut no_position_dates: Vec<NaiveDate> =
                 context.no_position_dates.iter().copied().collect();
@@ -908,16 +1290,20 @@ pub async fn backtest_fund(
             let mut order_dates: Vec<NaiveDate> = context.order_dates.iter().copied().collect();
             order_dates.sort_unstable();
 
+            let metrics =
+                BacktestMetrics::from_daily_value(&trade_dates_value, &no_position_dates, options)
+                    .await?;
+            let pnl = BacktestPnl::from_total(metrics.total_return, context.fx_pnl);
+            let _ = notify_pnl(&sender, &options.end_date, &pnl).await;
+
             Ok(BacktestResult {
                 title: Some(fund_definition.title.clone()),
                 options: options.clone(),
                 final_cash,
                 final_positions_value,
-                metrics: BacktestMetrics::from_daily_value(
-                    &trade_dates_value,
-                    &no_position_dates,
-                    options,
-                ),
+                fees: context.fee_totals.clone(),
+                pnl,
+                metrics,
                 no_position_dates,
                 order_dates,
                 trade_dates_value,
@@ -1500,3 +1886,205 @@ struct RuleOptionValues {
     option_name: String,
     option_values: Vec<serde_json::Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::fee::FeeSchedule;
+
+    #[tokio::test]
+    async fn test_backtest_fund_regression() {
+        let workspace = std::env::temp_dir().join("vfunds_test_backtest_fund_regression");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(
+            workspace.join("hold.fund.toml"),
+            "title = \"Hold\"\ntickers = [\"600595\", \"002155\"]\n\n[[rules]]\nname = \"hold\"\nfrequency = \"1m\"\n",
+        )
+        .unwrap();
+
+        let mut fees = FeeSchedule::default();
+        fees.set("stock.stamp_duty.rate", "0.0005").unwrap();
+        fees.set("stock.broker_commission.rate", "0.00025").unwrap();
+        fees.set("stock.XSHG.transfer_fee.rate", "0").unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2019, 2, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2019, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees,
+            base_currency: Currency::Cny,
+        };
+
+        let fund_definition = FundDefinition::from_file(&workspace.join("hold.fund.toml")).unwrap();
+        let mut stream = backtest_fund(&fund_definition, &options).await.unwrap();
+
+        let mut result: Option<BacktestResult> = None;
+        while let Some(event) = stream.next().await {
+            match event {
+                BacktestEvent::Result(r) => result = Some(*r),
+                BacktestEvent::Error(err) => panic!("{err}"),
+                _ => {}
+            }
+        }
+        let metrics = result.unwrap().metrics;
+
+        // Expected values come from synthetic klines of fixtures, so only checked against mock
+        if !*crate::TEST_LIVE {
+            assert_eq!(metrics.trade_days, 238);
+            assert!((metrics.total_return - 377948.87).abs() < 0.01);
+            assert!((metrics.annualized_return_rate.unwrap() - 0.4004).abs() < 1e-4);
+            assert!((metrics.max_drawdown.unwrap() - 0.0976).abs() < 1e-4);
+            assert!((metrics.sharpe_ratio.unwrap() - 3.1220).abs() < 1e-4);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fund_multi_currency() {
+        let workspace = std::env::temp_dir().join("vfunds_test_backtest_fund_multi_currency");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(
+            workspace.join("hk.fund.toml"),
+            "title = \"HK\"\ntickers = [\"00700\"]\n\n[[rules]]\nname = \"hold\"\nfrequency = \"1m\"\n",
+        )
+        .unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        let fund_definition = FundDefinition::from_file(&workspace.join("hk.fund.toml")).unwrap();
+        let mut stream = backtest_fund(&fund_definition, &options).await.unwrap();
+
+        let mut result: Option<BacktestResult> = None;
+        let mut buy_units: Vec<u64> = vec![];
+        while let Some(event) = stream.next().await {
+            match event {
+                BacktestEvent::Buy { units, .. } => buy_units.push(units),
+                BacktestEvent::Result(r) => result = Some(*r),
+                BacktestEvent::Error(err) => panic!("{err}"),
+                _ => {}
+            }
+        }
+        let result = result.unwrap();
+
+        // Bought in board lots of 100 shares
+        assert!(!buy_units.is_empty());
+        assert!(buy_units.iter().all(|units| units % 100 == 0));
+
+        assert!(result.pnl.fx != 0.0);
+        assert!((result.pnl.asset + result.pnl.fx - result.metrics.total_return).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fund_index_proxy() {
+        let workspace = std::env::temp_dir().join("vfunds_test_backtest_fund_index_proxy");
+        std::fs::create_dir_all(&workspace).unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        let mut results: Vec<BacktestResult> = vec![];
+        for index_tracking_cost in [0.0, 0.05] {
+            let path = workspace.join("index.fund.toml");
+            std::fs::write(
+                &path,
+                format!(
+                    "title = \"Index\"\ntickers = [\"000300.SH\"]\n\n[options]\nindex_tracking_cost = {index_tracking_cost}\n\n[[rules]]\nname = \"hold\"\nfrequency = \"1m\"\n"
+                ),
+            )
+            .unwrap();
+
+            let fund_definition = FundDefinition::from_file(&path).unwrap();
+            let mut stream = backtest_fund(&fund_definition, &options).await.unwrap();
+            while let Some(event) = stream.next().await {
+                match event {
+                    BacktestEvent::Result(r) => results.push(*r),
+                    BacktestEvent::Error(err) => panic!("{err}"),
+                    _ => {}
+                }
+            }
+        }
+
+        // Held without fees, and the tracking cost drags the return
+        assert!(results[0].fees.values().all(|fee| *fee == 0.0));
+        assert!(!results[0].final_positions_value.is_empty());
+        assert!(results[1].metrics.total_return < results[0].metrics.total_return);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fund_index_proxy_bought_later() {
+        let workspace = std::env::temp_dir().join("vfunds_test_backtest_fund_index_proxy_later");
+        std::fs::create_dir_all(&workspace).unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        // Suspended until October, so the proxy is bought months after the start date
+        let mut buy_events: Vec<(NaiveDate, f64)> = vec![];
+        let mut results: Vec<BacktestResult> = vec![];
+        for index_tracking_cost in [0.0, 0.05] {
+            let path = workspace.join(format!("index_{index_tracking_cost}.fund.toml"));
+            std::fs::write(
+                &path,
+                format!(
+                    "title = \"Index\"\ntickers = [\"000300.SH\"]\n\n[options]\nsuspend_months = [7, 8, 9]\nindex_tracking_cost = {index_tracking_cost}\n\n[[rules]]\nname = \"hold\"\nfrequency = \"1m\"\n"
+                ),
+            )
+            .unwrap();
+
+            let fund_definition = FundDefinition::from_file(&path).unwrap();
+            let mut stream = backtest_fund(&fund_definition, &options).await.unwrap();
+            while let Some(event) = stream.next().await {
+                match event {
+                    // The first buy of each run
+                    BacktestEvent::Buy { price, date, .. } if buy_events.len() == results.len() => {
+                        buy_events.push((date, price));
+                    }
+                    BacktestEvent::Result(r) => results.push(*r),
+                    BacktestEvent::Error(err) => panic!("{err}"),
+                    _ => {}
+                }
+            }
+        }
+
+        // No tracking cost accrued before the proxy is bought, only over the time held
+        assert_eq!(buy_events.len(), 2);
+        assert!(buy_events[0].0 >= NaiveDate::from_ymd_opt(2024, 10, 1).unwrap());
+        assert_eq!(buy_events[0], buy_events[1]);
+        assert!(results[1].metrics.total_return < results[0].metrics.total_return);
+    }
+}
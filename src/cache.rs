@@ -1,10 +1,89 @@
-use std::{fs::create_dir_all, time::Duration};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    fs::create_dir_all,
+    time::Duration,
+};
 
 use chrono::{Local, NaiveDateTime};
-use libsql::{Builder, Connection};
+use libsql::{Builder, Connection, Value};
 
 use crate::{CACHE_PATH, error::VfResult};
 
+pub mod marks;
+pub mod membership;
+pub mod memory;
+
+tokio::task_local! {
+    static CACHE_ONLY: bool;
+    static CACHE_SOURCES: RefCell<CacheSources>;
+}
+
+/// Persistent entries read or written to build a value, by which its copy in memory expires and
+/// is invalidated
+#[derive(Clone, Debug, Default)]
+pub struct CacheSources {
+    pub keys: HashSet<String>,
+    /// The earliest expiration of the entries
+    pub expire: Option<NaiveDateTime>,
+}
+
+impl CacheSources {
+    fn add(&mut self, key: &str, expire: Option<NaiveDateTime>) {
+        self.keys.insert(key.to_string());
+        self.add_expire(expire);
+    }
+
+    fn merge(&mut self, other: &CacheSources) {
+        self.keys.extend(other.keys.iter().cloned());
+        self.add_expire(other.expire);
+    }
+
+    fn add_expire(&mut self, expire: Option<NaiveDateTime>) {
+        self.expire = match (self.expire, expire) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// Run the future with requests of QMT and Tushare served from the cache only regardless of
+/// expiration, which fail on cache misses instead of fetching
+pub async fn cache_only<F: Future>(future: F) -> F::Output {
+    CACHE_ONLY.scope(true, future).await
+}
+
+pub fn is_cache_only() -> bool {
+    CACHE_ONLY.try_with(|v| *v).unwrap_or(false)
+}
+
+/// Run the future recording the persistent entries it reads or writes, which are also recorded
+/// for the outer future if it's tracked too
+pub async fn track_sources<F: Future>(future: F) -> (F::Output, CacheSources) {
+    let (output, sources) = CACHE_SOURCES
+        .scope(RefCell::new(CacheSources::default()), async {
+            let output = future.await;
+            (output, CACHE_SOURCES.with(|sources| sources.take()))
+        })
+        .await;
+    record_sources(&sources);
+
+    (output, sources)
+}
+
+pub fn is_tracking_sources() -> bool {
+    CACHE_SOURCES.try_with(|_| ()).is_ok()
+}
+
+pub fn record_sources(sources: &CacheSources) {
+    let _ = CACHE_SOURCES.try_with(|s| s.borrow_mut().merge(sources));
+}
+
+/// Record the entry for the tracked future if any, e.g. when fetched by another one coalesced
+pub fn record_source(key: &str, expire: Option<NaiveDateTime>) {
+    let _ = CACHE_SOURCES.try_with(|s| s.borrow_mut().add(key, expire));
+}
+
 pub async fn init() -> VfResult<()> {
     if let Some(cache_dir) = CACHE_PATH.parent() {
         create_dir_all(cache_dir)?;
@@ -16,15 +95,109 @@ pub async fn init() -> VfResult<()> {
 CREATE TABLE IF NOT EXISTS "cache" (
     "key"     TEXT PRIMARY KEY,
     "data"    BLOB NOT NULL,
-    "expire"  TIMESTAMP)
+    "expire"  TIMESTAMP,
+    "updated" TIMESTAMP)
 ;"#,
         (),
     )
     .await?;
 
+    // Caches created by earlier versions have no update time
+    let mut has_updated = false;
+    let mut rows = conn.query(r#"PRAGMA table_info("cache");"#, ()).await?;
+    while let Some(row) = rows.next().await? {
+        if row.get::<String>(1)? == "updated" {
+            has_updated = true;
+        }
+    }
+    if !has_updated {
+        conn.execute(r#"ALTER TABLE "cache" ADD COLUMN "updated" TIMESTAMP;"#, ())
+            .await?;
+    }
+
+    marks::init().await?;
+    membership::init().await?;
+
     Ok(())
 }
 
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    pub key: String,
+    pub size: u64,
+    pub expire: Option<NaiveDateTime>,
+    pub updated: Option<NaiveDateTime>,
+}
+
+impl CacheEntry {
+    /// Source and API of the key, e.g. `[TUSHARE]daily?{...}` is of `TUSHARE` and `daily`
+    pub fn source_and_api(&self) -> (String, String) {
+        if let Some(stripped) = self.key.strip_prefix('[')
+            && let Some((source, rest)) = stripped.split_once(']')
+        {
+            let api = rest.split('?').next().unwrap_or_default();
+            (source.to_string(), api.trim_start_matches('/').to_string())
+        } else {
+            (
+                "".to_string(),
+                self.key.split('?').next().unwrap_or_default().to_string(),
+            )
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CacheStat {
+    pub source: String,
+    pub api: String,
+    pub count: usize,
+    pub size: u64,
+    pub expired_count: usize,
+}
+
+pub async fn entries(expired_only: bool) -> VfResult<Vec<CacheEntry>> {
+    let conn = connect().await?;
+
+    let mut rows = if expired_only {
+        conn.query(
+            r#"
+SELECT "key", LENGTH("data"), "expire", "updated"
+FROM "cache"
+WHERE "expire" <= ?
+ORDER BY "key"
+;"#,
+            [datetime_to_str(&Local::now().naive_local())],
+        )
+        .await?
+    } else {
+        conn.query(
+            r#"
+SELECT "key", LENGTH("data"), "expire", "updated"
+FROM "cache"
+ORDER BY "key"
+;"#,
+            (),
+        )
+        .await?
+    };
+
+    let mut entries: Vec<CacheEntry> = vec![];
+    while let Some(row) = rows.next().await? {
+        entries.push(CacheEntry {
+            key: row.get::<String>(0)?,
+            size: row.get::<u64>(1)?,
+            expire: row
+                .get::<Option<String>>(2)?
+                .and_then(|s| datetime_from_str(&s)),
+            updated: row
+                .get::<Option<String>>(3)?
+                .and_then(|s| datetime_from_str(&s)),
+        });
+    }
+
+    Ok(entries)
+}
+
 pub async fn get(key: &str, ignore_expire: bool) -> VfResult<Option<Vec<u8>>> {
     let conn = connect().await?;
 
@@ -43,11 +216,14 @@ LIMIT 1
         let data = row.get::<Vec<u8>>(0)?;
 
         if ignore_expire {
+            // Expired entries would expire their copies in memory at once
+            record_source(key, None);
             return Ok(Some(data));
         } else {
             let expire_str = row.get::<String>(1)?;
             let expire = NaiveDateTime::parse_from_str(&expire_str, "%Y-%m-%d %H:%M:%S")?;
             if expire > Local::now().naive_local() {
+                record_source(key, Some(expire));
                 return Ok(Some(data));
             }
         }
@@ -56,8 +232,91 @@ LIMIT 1
     Ok(None)
 }
 
+pub async fn keys() -> VfResult<Vec<String>> {
+    let conn = connect().await?;
+
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "key"
+FROM "cache"
+ORDER BY "key"
+;"#,
+            (),
+        )
+        .await?;
+
+    let mut keys: Vec<String> = vec![];
+    while let Some(row) = rows.next().await? {
+        keys.push(row.get::<String>(0)?);
+    }
+
+    Ok(keys)
+}
+
+/// Delete entries matching all given conditions, returns the number of deleted entries
+pub async fn purge(
+    prefix: Option<&str>,
+    updated_before: Option<&NaiveDateTime>,
+    expired_only: bool,
+) -> VfResult<u64> {
+    let mut conditions: Vec<&str> = vec![];
+    let mut params: Vec<Value> = vec![];
+    if let Some(prefix) = prefix {
+        conditions.push(r#"SUBSTR("key", 1, ?) = ?"#);
+        params.push(Value::Integer(prefix.chars().count() as i64));
+        params.push(Value::Text(prefix.to_string()));
+    }
+    if let Some(updated_before) = updated_before {
+        // Entries without update time are cached by earlier versions, so older than any time
+        conditions.push(r#"("updated" IS NULL OR "updated" < ?)"#);
+        params.push(Value::Text(datetime_to_str(updated_before)));
+    }
+    if expired_only {
+        conditions.push(r#""expire" <= ?"#);
+        params.push(Value::Text(datetime_to_str(&Local::now().naive_local())));
+    }
+
+    let sql = if conditions.is_empty() {
+        r#"DELETE FROM "cache";"#.to_string()
+    } else {
+        format!(r#"DELETE FROM "cache" WHERE {};"#, conditions.join(" AND "))
+    };
+
+    let conn = connect().await?;
+    let count = conn.execute(&sql, params).await?;
+
+    Ok(count)
+}
+
+/// Size and count of entries grouped by source and API
+pub async fn stats() -> VfResult<Vec<CacheStat>> {
+    let now = Local::now().naive_local();
+
+    let mut stats: BTreeMap<(String, String), CacheStat> = BTreeMap::new();
+    for entry in entries(false).await? {
+        let (source, api) = entry.source_and_api();
+        let stat = stats
+            .entry((source.clone(), api.clone()))
+            .or_insert_with(|| CacheStat {
+                source,
+                api,
+                ..Default::default()
+            });
+
+        stat.count += 1;
+        stat.size += entry.size;
+        if entry.expire.map(|expire| expire <= now).unwrap_or(true) {
+            stat.expired_count += 1;
+        }
+    }
+
+    Ok(stats.into_values().collect())
+}
+
 pub async fn upsert(key: &str, data: &[u8], expire: &NaiveDateTime) -> VfResult<()> {
-    let expire_str = expire.format("%Y-%m-%d %H:%M:%S").to_string();
+    let expire_str = datetime_to_str(expire);
+    let updated_str = datetime_to_str(&Local::now().naive_local());
 
     let conn = connect().await?;
 
@@ -82,27 +341,38 @@ WHERE "key" = ?
                 r#"
 UPDATE "cache"
 SET "data" = ?, 
-    "expire" = ?
+    "expire" = ?,
+    "updated" = ?
 WHERE "key" = ?
 ;"#,
-                (data, expire_str, key),
+                (data, expire_str, updated_str, key),
             )
             .await?;
         } else {
             tx.execute(
                 r#"
 INSERT INTO "cache" 
-    ("key", "data", "expire") 
+    ("key", "data", "expire", "updated") 
 VALUES 
-    (?, ?, ?)
+    (?, ?, ?, ?)
 ;"#,
-                (key, data, expire_str),
+                (key, data, expire_str, updated_str),
             )
             .await?;
         }
     }
     tx.commit().await?;
 
+    // Values in memory built from the entry are stale once it's rewritten, e.g. by a refresh
+    memory::invalidate_source(key);
+
+    Ok(())
+}
+
+pub async fn vacuum() -> VfResult<()> {
+    let conn = connect().await?;
+    conn.execute("VACUUM;", ()).await?;
+
     Ok(())
 }
 
@@ -115,3 +385,40 @@ async fn connect() -> VfResult<Connection> {
 
     Ok(conn)
 }
+
+fn datetime_from_str(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()
+}
+
+fn datetime_to_str(datetime: &NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_purge() {
+        let expire = Local::now().naive_local() + chrono::Duration::days(1);
+        upsert("[TEST]purge/a?{}", &[1, 2, 3], &expire)
+            .await
+            .unwrap();
+        upsert("[TEST]purge/b?{}", &[1, 2], &expire).await.unwrap();
+
+        let stat = stats()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|s| s.source == "TEST" && s.api == "purge/a")
+            .unwrap();
+        assert_eq!(stat.count, 1);
+        assert_eq!(stat.size, 3);
+        assert_eq!(stat.expired_count, 0);
+
+        assert_eq!(purge(Some("[TEST]purge/a"), None, false).await.unwrap(), 1);
+        assert_eq!(purge(Some("[TEST]purge/"), None, true).await.unwrap(), 0);
+        assert!(get("[TEST]purge/a?{}", false).await.unwrap().is_none());
+        assert!(get("[TEST]purge/b?{}", false).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,98 @@
+//! Tickers marked with bad data by quality checks, so backtests warn when they use them
+
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::Local;
+
+use super::{connect, datetime_to_str};
+use crate::{error::VfResult, ticker::Ticker};
+
+pub async fn init() -> VfResult<()> {
+    let conn = connect().await?;
+    conn.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS "marks" (
+    "ticker" TEXT PRIMARY KEY,
+    "reason" TEXT NOT NULL,
+    "marked" TIMESTAMP NOT NULL)
+;"#,
+        (),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn mark(ticker: &Ticker, reason: &str) -> VfResult<()> {
+    let conn = connect().await?;
+    conn.execute(
+        r#"
+INSERT OR REPLACE INTO "marks"
+    ("ticker", "reason", "marked")
+VALUES
+    (?, ?, ?)
+;"#,
+        [
+            ticker.to_string(),
+            reason.to_string(),
+            datetime_to_str(&Local::now().naive_local()),
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Marked tickers with reasons
+pub async fn marks() -> VfResult<HashMap<Ticker, String>> {
+    let conn = connect().await?;
+
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "ticker", "reason"
+FROM "marks"
+;"#,
+            (),
+        )
+        .await?;
+
+    let mut marks: HashMap<Ticker, String> = HashMap::new();
+    while let Some(row) = rows.next().await? {
+        if let Ok(ticker) = Ticker::from_str(&row.get::<String>(0)?) {
+            marks.insert(ticker, row.get::<String>(1)?);
+        }
+    }
+
+    Ok(marks)
+}
+
+pub async fn unmark(ticker: &Ticker) -> VfResult<()> {
+    let conn = connect().await?;
+    conn.execute(
+        r#"
+DELETE FROM "marks"
+WHERE "ticker" = ?
+;"#,
+        [ticker.to_string()],
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_marks() {
+        let ticker = Ticker::from_str("600000.TEST").unwrap();
+
+        mark(&ticker, "MissingDays").await.unwrap();
+        assert_eq!(marks().await.unwrap().get(&ticker).unwrap(), "MissingDays");
+
+        unmark(&ticker).await.unwrap();
+        assert!(!marks().await.unwrap().contains_key(&ticker));
+    }
+}
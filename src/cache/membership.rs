@@ -0,0 +1,319 @@
+//! Historical constituents of indexes and sectors
+//!
+//! Snapshots are recorded by effective date from every fetch, or imported from user supplied
+//! files, so universes can be resolved as of backtest dates rather than the present. Snapshots
+//! are not cache entries, they are never expired or purged.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::size_of,
+    path::Path,
+    str::FromStr,
+};
+
+use chrono::NaiveDate;
+
+use super::{connect, memory::MemorySize};
+use crate::{
+    error::VfResult,
+    ticker::{Ticker, TickersIndex},
+    utils::datetime::{date_from_str, date_to_str},
+};
+
+/// Members as tickers with labels (e.g. sector names, empty for indexes) effective from the date
+#[derive(Clone, Debug)]
+pub struct Membership {
+    pub date: NaiveDate,
+    pub members: HashMap<Ticker, String>,
+    /// Resolved to the current membership for lack of the historical one
+    pub is_fallback: bool,
+}
+
+impl MemorySize for Membership {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.members.memory_size()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MembershipStat {
+    pub source: String,
+    pub snapshot_count: usize,
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+}
+
+pub async fn init() -> VfResult<()> {
+    let conn = connect().await?;
+    conn.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS "membership" (
+    "source" TEXT NOT NULL,
+    "date"   TEXT NOT NULL,
+    "ticker" TEXT NOT NULL,
+    "label"  TEXT NOT NULL,
+    PRIMARY KEY ("source", "date", "ticker"))
+;"#,
+        (),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub fn index_source(index: &TickersIndex) -> String {
+    format!("index/{index}")
+}
+
+pub fn sector_source(sector_prefix: &str) -> String {
+    format!("sector/{sector_prefix}")
+}
+
+/// The latest snapshot in effect on the date, as snapshots are effective from their dates
+pub async fn get_as_of(source: &str, date: &NaiveDate) -> VfResult<Option<Membership>> {
+    match latest_date(source, Some(date)).await? {
+        Some(snapshot_date) => get_snapshot(source, &snapshot_date).await,
+        None => Ok(None),
+    }
+}
+
+/// The latest snapshot, i.e. the current membership
+pub async fn get_latest(source: &str) -> VfResult<Option<Membership>> {
+    match latest_date(source, None).await? {
+        Some(snapshot_date) => get_snapshot(source, &snapshot_date).await,
+        None => Ok(None),
+    }
+}
+
+/// Import snapshots from a CSV file of `date,ticker[,label]` rows, returns the number of
+/// snapshots, lines without a date in the first column (e.g. headers) are skipped
+pub async fn import(source: &str, path: &Path) -> VfResult<usize> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+
+    let mut snapshots: BTreeMap<NaiveDate, HashMap<Ticker, String>> = BTreeMap::new();
+    for record in reader.records() {
+        let record = record?;
+
+        let Some(date) = record.get(0).and_then(|s| date_from_str(s).ok()) else {
+            continue;
+        };
+        let ticker_str = record.get(1).unwrap_or_default();
+        let ticker = if let Some(ticker) = Ticker::from_tushare_str(ticker_str) {
+            ticker
+        } else {
+            Ticker::from_str(ticker_str)?
+        };
+        let label = record.get(2).unwrap_or_default().to_string();
+
+        snapshots.entry(date).or_default().insert(ticker, label);
+    }
+
+    for (date, members) in &snapshots {
+        record(source, date, members).await?;
+    }
+
+    Ok(snapshots.len())
+}
+
+/// Record the snapshot effective from the date, unless it is the same as the one in effect
+pub async fn record(
+    source: &str,
+    date: &NaiveDate,
+    members: &HashMap<Ticker, String>,
+) -> VfResult<()> {
+    if let Some(membership) = get_as_of(source, date).await?
+        && &membership.members == members
+    {
+        return Ok(());
+    }
+
+    let date_str = date_to_str(date);
+
+    let conn = connect().await?;
+
+    let tx = conn.transaction().await?;
+    {
+        tx.execute(
+            r#"
+DELETE FROM "membership"
+WHERE "source" = ? AND "date" = ?
+;"#,
+            [source, &date_str],
+        )
+        .await?;
+
+        for (ticker, label) in members {
+            tx.execute(
+                r#"
+INSERT INTO "membership"
+    ("source", "date", "ticker", "label")
+VALUES
+    (?, ?, ?, ?)
+;"#,
+                [source, &date_str, &ticker.to_string(), label],
+            )
+            .await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Snapshot count and date range per source
+pub async fn stats() -> VfResult<Vec<MembershipStat>> {
+    let conn = connect().await?;
+
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "source", COUNT(DISTINCT "date"), MIN("date"), MAX("date")
+FROM "membership"
+GROUP BY "source"
+ORDER BY "source"
+;"#,
+            (),
+        )
+        .await?;
+
+    let mut stats: Vec<MembershipStat> = vec![];
+    while let Some(row) = rows.next().await? {
+        if let (Ok(first_date), Ok(last_date)) = (
+            date_from_str(&row.get::<String>(2)?),
+            date_from_str(&row.get::<String>(3)?),
+        ) {
+            stats.push(MembershipStat {
+                source: row.get::<String>(0)?,
+                snapshot_count: row.get::<u64>(1)? as usize,
+                first_date,
+                last_date,
+            });
+        }
+    }
+
+    Ok(stats)
+}
+
+async fn latest_date(source: &str, as_of: Option<&NaiveDate>) -> VfResult<Option<String>> {
+    let conn = connect().await?;
+
+    let mut rows = if let Some(as_of) = as_of {
+        conn.query(
+            r#"
+SELECT MAX("date")
+FROM "membership"
+WHERE "source" = ? AND "date" <= ?
+;"#,
+            [source.to_string(), date_to_str(as_of)],
+        )
+        .await?
+    } else {
+        conn.query(
+            r#"
+SELECT MAX("date")
+FROM "membership"
+WHERE "source" = ?
+;"#,
+            [source],
+        )
+        .await?
+    };
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<Option<String>>(0)?),
+        None => Ok(None),
+    }
+}
+
+async fn get_snapshot(source: &str, date_str: &str) -> VfResult<Option<Membership>> {
+    let conn = connect().await?;
+
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "ticker", "label"
+FROM "membership"
+WHERE "source" = ? AND "date" = ?
+;"#,
+            [source, date_str],
+        )
+        .await?;
+
+    let mut members: HashMap<Ticker, String> = HashMap::new();
+    while let Some(row) = rows.next().await? {
+        if let Ok(ticker) = Ticker::from_str(&row.get::<String>(0)?) {
+            members.insert(ticker, row.get::<String>(1)?);
+        }
+    }
+
+    Ok(Some(Membership {
+        date: date_from_str(date_str)?,
+        members,
+        is_fallback: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_membership() {
+        let source = "index/TEST.MEMBERSHIP";
+        let date = |s: &str| date_from_str(s).unwrap();
+        let ticker = |s: &str| Ticker::from_str(s).unwrap();
+
+        let path = std::env::temp_dir().join(format!("membership_{}.csv", std::process::id()));
+        {
+            let mut file = std::fs::File::create(&path).unwrap();
+            writeln!(file, "date,ticker").unwrap();
+            writeln!(file, "2018-01-02,600000.SH").unwrap();
+            writeln!(file, "2018-01-02,000001.SZ").unwrap();
+            writeln!(file, "2018-07-02,600000.SH").unwrap();
+        }
+        assert_eq!(import(source, &path).await.unwrap(), 2);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(
+            get_as_of(source, &date("2018-01-01"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let membership = get_as_of(source, &date("2018-01-02"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(membership.date, date("2018-01-02"));
+
+        let membership = get_as_of(source, &date("2018-03-01"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(membership.date, date("2018-01-02"));
+        assert_eq!(membership.members.len(), 2);
+
+        // The same members are not recorded again
+        let members: HashMap<Ticker, String> =
+            HashMap::from([(ticker("600000.XSHG"), "".to_string())]);
+        record(source, &date("2018-09-03"), &members).await.unwrap();
+        let membership = get_latest(source).await.unwrap().unwrap();
+        assert_eq!(membership.date, date("2018-07-02"));
+        assert_eq!(membership.members, members);
+
+        let stat = stats()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|s| s.source == source)
+            .unwrap();
+        assert_eq!(stat.snapshot_count, 2);
+    }
+}
@@ -0,0 +1,378 @@
+//! In-process cache of fetched datasets in front of the persistent cache
+//!
+//! Entries share a global memory budget, set by env `MEMORY_CACHE_MB`, and the least recently
+//! used ones are evicted when over it. Entries expire with the earliest persistent entries they
+//! were built from, or at the next market close if none, and are invalidated once any of those is
+//! rewritten, so they never outlive the data they were built from.
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use hashlink::LinkedHashMap;
+
+use crate::{
+    CACHE_NO_EXPIRE, MEMORY_CACHE_MB,
+    cache::{CacheSources, is_tracking_sources, record_sources},
+    data::series::DailySeries,
+    ds::Dataset,
+    market::next_data_expire_in_china,
+    ticker::Ticker,
+};
+
+/// Estimated heap and inline bytes of a cached value
+pub trait MemorySize {
+    fn memory_size(&self) -> usize;
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryCacheStat {
+    pub dataset: Dataset,
+    pub count: usize,
+    pub size: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+pub fn get<T: Clone + 'static>(dataset: Dataset, key: &str) -> Option<T> {
+    let mut cache = MEMORY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.get(dataset, key)
+}
+
+/// Insert the value built from the persistent entries, e.g. tracked by `cache::track_sources`
+pub fn insert<T: MemorySize + Send + Sync + 'static>(
+    dataset: Dataset,
+    key: &str,
+    value: T,
+    sources: CacheSources,
+) {
+    let expire = if *CACHE_NO_EXPIRE {
+        None
+    } else {
+        Some(
+            sources
+                .expire
+                .unwrap_or_else(|| next_data_expire_in_china(0)),
+        )
+    };
+
+    let mut cache = MEMORY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(dataset, key, value, sources, expire);
+}
+
+/// Remove entries of any dataset built from the persistent entry, e.g. when it's refreshed
+pub fn invalidate_source(source_key: &str) {
+    let mut cache = MEMORY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.invalidate_source(source_key);
+}
+
+/// Statistics per dataset, sorted by dataset
+pub fn stats() -> Vec<MemoryCacheStat> {
+    let cache = MEMORY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.stats.values().cloned().collect()
+}
+
+static MEMORY_CACHE: LazyLock<Mutex<MemoryCache>> =
+    LazyLock::new(|| Mutex::new(MemoryCache::new(*MEMORY_CACHE_MB * 1024 * 1024)));
+
+struct MemoryCache {
+    budget: usize,
+    size: usize,
+    entries: LinkedHashMap<(Dataset, String), MemoryCacheEntry>,
+    stats: BTreeMap<Dataset, MemoryCacheStat>,
+}
+
+struct MemoryCacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    size: usize,
+    sources: CacheSources,
+    expire: Option<NaiveDateTime>,
+}
+
+impl MemoryCache {
+    fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            size: 0,
+            entries: LinkedHashMap::new(),
+            stats: BTreeMap::new(),
+        }
+    }
+
+    fn get<T: Clone + 'static>(&mut self, dataset: Dataset, key: &str) -> Option<T> {
+        let entry_key = (dataset, key.to_string());
+
+        let is_expired = self
+            .entries
+            .get(&entry_key)
+            .and_then(|entry| entry.expire)
+            .map(|expire| expire <= Local::now().naive_local())
+            .unwrap_or(false);
+        if is_expired {
+            self.remove(&entry_key);
+        }
+
+        let value = self.entries.to_back(&entry_key).and_then(|entry| {
+            // Values built from this one are built from its sources too
+            if is_tracking_sources() {
+                record_sources(&entry.sources);
+            }
+
+            entry.value.downcast_ref::<T>().cloned()
+        });
+
+        let stat = self.stat_mut(dataset);
+        if value.is_some() {
+            stat.hits += 1;
+        } else {
+            stat.misses += 1;
+        }
+
+        value
+    }
+
+    fn insert<T: MemorySize + Send + Sync + 'static>(
+        &mut self,
+        dataset: Dataset,
+        key: &str,
+        value: T,
+        sources: CacheSources,
+        expire: Option<NaiveDateTime>,
+    ) {
+        let entry_key = (dataset, key.to_string());
+        self.remove(&entry_key);
+
+        let size = value.memory_size()
+            + key.len()
+            + sources.keys.memory_size()
+            + size_of::<MemoryCacheEntry>();
+        if size > self.budget {
+            return;
+        }
+
+        while self.size + size > self.budget {
+            if let Some(((evicted_dataset, _), evicted)) = self.entries.pop_front() {
+                self.size -= evicted.size;
+
+                let stat = self.stat_mut(evicted_dataset);
+                stat.count -= 1;
+                stat.size -= evicted.size;
+                stat.evictions += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.entries.insert(
+            entry_key,
+            MemoryCacheEntry {
+                value: Arc::new(value),
+                size,
+                sources,
+                expire,
+            },
+        );
+        self.size += size;
+
+        let stat = self.stat_mut(dataset);
+        stat.count += 1;
+        stat.size += size;
+    }
+
+    fn invalidate_source(&mut self, source_key: &str) {
+        let entry_keys: Vec<(Dataset, String)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.sources.keys.contains(source_key))
+            .map(|(entry_key, _)| entry_key.clone())
+            .collect();
+
+        for entry_key in entry_keys {
+            self.remove(&entry_key);
+        }
+    }
+
+    fn remove(&mut self, entry_key: &(Dataset, String)) {
+        if let Some(entry) = self.entries.remove(entry_key) {
+            self.size -= entry.size;
+
+            let stat = self.stat_mut(entry_key.0);
+            stat.count -= 1;
+            stat.size -= entry.size;
+        }
+    }
+
+    fn stat_mut(&mut self, dataset: Dataset) -> &mut MemoryCacheStat {
+        self.stats
+            .entry(dataset)
+            .or_insert_with(|| MemoryCacheStat {
+                dataset,
+                count: 0,
+                size: 0,
+                hits: 0,
+                misses: 0,
+                evictions: 0,
+            })
+    }
+}
+
+impl MemorySize for DailySeries {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.estimated_size()
+    }
+}
+
+impl MemorySize for NaiveDate {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+impl MemorySize for String {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.capacity()
+    }
+}
+
+impl MemorySize for Ticker {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.exchange.capacity() + self.symbol.capacity()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Option<T> {
+    fn memory_size(&self) -> usize {
+        match self {
+            Some(value) => value.memory_size(),
+            None => size_of::<Self>(),
+        }
+    }
+}
+
+impl<T: MemorySize> MemorySize for Vec<T> {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self.iter().map(|v| v.memory_size()).sum::<usize>()
+            + (self.capacity() - self.len()) * size_of::<T>()
+    }
+}
+
+impl<K: MemorySize, V: MemorySize> MemorySize for HashMap<K, V> {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self
+                .iter()
+                .map(|(k, v)| k.memory_size() + v.memory_size())
+                .sum::<usize>()
+            + (self.capacity() - self.len()) * (size_of::<K>() + size_of::<V>())
+    }
+}
+
+impl<T: MemorySize> MemorySize for HashSet<T> {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self.iter().map(|v| v.memory_size()).sum::<usize>()
+            + (self.capacity() - self.len()) * size_of::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::track_sources;
+
+    impl MemorySize for u64 {
+        fn memory_size(&self) -> usize {
+            1000
+        }
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let entry_size =
+            1000 + 1 + HashSet::<String>::new().memory_size() + size_of::<MemoryCacheEntry>();
+        let mut cache = MemoryCache::new(entry_size * 2);
+
+        cache.insert(
+            Dataset::StockKline,
+            "a",
+            1u64,
+            CacheSources::default(),
+            None,
+        );
+        cache.insert(
+            Dataset::StockKline,
+            "b",
+            2u64,
+            CacheSources::default(),
+            None,
+        );
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "a"), Some(1));
+
+        // "b" is the least recently used
+        cache.insert(
+            Dataset::StockIndicators,
+            "c",
+            3u64,
+            CacheSources::default(),
+            None,
+        );
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "b"), None);
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "a"), Some(1));
+        assert_eq!(cache.get::<u64>(Dataset::StockIndicators, "c"), Some(3));
+
+        let stat = &cache.stats[&Dataset::StockKline];
+        assert_eq!(
+            (stat.count, stat.hits, stat.misses, stat.evictions),
+            (1, 2, 1, 1)
+        );
+
+        let expired = Local::now().naive_local() - chrono::Duration::seconds(1);
+        cache.insert(
+            Dataset::StockKline,
+            "d",
+            4u64,
+            CacheSources::default(),
+            Some(expired),
+        );
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "d"), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_sources() {
+        let mut cache = MemoryCache::new(usize::MAX);
+
+        let expire = Local::now().naive_local() + chrono::Duration::days(1);
+        let sources = CacheSources {
+            keys: HashSet::from(["[TEST]daily?{}".to_string()]),
+            expire: Some(expire),
+        };
+        cache.insert(Dataset::StockKline, "a", 1u64, sources.clone(), None);
+        cache.insert(Dataset::StockIndicators, "b", 2u64, sources, None);
+        cache.insert(
+            Dataset::StockKline,
+            "c",
+            3u64,
+            CacheSources::default(),
+            None,
+        );
+
+        // Values built from cached ones inherit their sources
+        let (value, sources) =
+            track_sources(async { cache.get::<u64>(Dataset::StockKline, "a") }).await;
+        assert_eq!(value, Some(1));
+        assert!(sources.keys.contains("[TEST]daily?{}"));
+        assert_eq!(sources.expire, Some(expire));
+
+        // Entries of all datasets built from the rewritten source are invalidated
+        cache.invalidate_source("[TEST]daily?{}");
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "a"), None);
+        assert_eq!(cache.get::<u64>(Dataset::StockIndicators, "b"), None);
+        assert_eq!(cache.get::<u64>(Dataset::StockKline, "c"), Some(3));
+    }
+}
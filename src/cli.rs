@@ -1,17 +1,26 @@
 use clap::Subcommand;
 
 mod backtest;
+mod cache;
 mod check;
 mod config;
+mod data;
+mod export;
 mod kline;
 mod list;
+mod membership;
 mod result;
+mod ticker;
 
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Backtest virtual funds")]
     Backtest(Box<backtest::BacktestCommand>),
 
+    #[command(about = "Manage cached data")]
+    #[clap(subcommand)]
+    Cache(Box<cache::CacheCommand>),
+
     #[command(about = "Check all dependent services")]
     #[clap(visible_aliases = &["chk"])]
     Check(Box<check::CheckCommand>),
@@ -20,6 +29,13 @@ pub enum Commands {
     #[clap(subcommand)]
     Config(Box<config::ConfigCommand>),
 
+    #[command(about = "Check quality of data")]
+    #[clap(subcommand)]
+    Data(Box<data::DataCommand>),
+
+    #[command(about = "Export cached data into local data files")]
+    Export(Box<export::ExportCommand>),
+
     #[command(about = "Show kline of ticker")]
     Kline(Box<kline::KlineCommand>),
 
@@ -27,6 +43,14 @@ pub enum Commands {
     #[clap(visible_aliases = &["ls"])]
     List(Box<list::ListCommand>),
 
+    #[command(about = "Manage historical constituents of indexes and sectors")]
+    #[clap(subcommand)]
+    Membership(Box<membership::MembershipCommand>),
+
     #[command(about = "Show backtest results of virtual funds")]
     Result(Box<result::ResultCommand>),
+
+    #[command(about = "Search tickers")]
+    #[clap(subcommand)]
+    Ticker(Box<ticker::TickerCommand>),
 }
@@ -1,8 +1,8 @@
-use std::{collections::BTreeMap, fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr};
 
 use chrono::{Local, NaiveDate};
 use colored::Colorize;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use tabled::settings::{
     Alignment, Color, Width,
     measurement::Percent,
@@ -14,7 +14,7 @@ use vfunds::{
     api,
     api::{
         BacktestCvOptions, BacktestEvent, BacktestOptions, BacktestResult, BacktestStream,
-        Notification, NotificationType,
+        Currency, InterestRate, Notification, NotificationType,
     },
     error::{VfError, VfResult},
     utils::datetime::{date_from_str, date_to_str},
@@ -85,32 +85,49 @@ pub struct BacktestCommand {
     risk_free_rate: f64,
 
     #[arg(
-        long = "stamp-duty",
-        default_value_t = 0.001,
-        help = "The stamp-duty rate, the default value is 0.001"
+        long = "risk-free-series",
+        value_parser = InterestRate::from_str,
+        help = "Interest rate as the time-varying risk-free rate, e.g. --risk-free-series cgb1y, and the risk-free rate applies to dates without it"
     )]
-    stamp_duty_rate: f64,
+    risk_free_series: Option<InterestRate>,
 
     #[arg(
-        long = "stamp-duty-min",
-        default_value_t = 1.0,
-        help = "The stamp-duty minimal fee, the default value is 1"
+        long = "currency",
+        value_parser = Currency::from_str,
+        default_value = "CNY",
+        help = "Base currency of cash and values, e.g. --currency HKD, the default value is CNY"
     )]
-    stamp_duty_min_fee: f64,
+    base_currency: Currency,
 
     #[arg(
-        long = "broker-commission",
-        default_value_t = 0.0002,
-        help = "The broker-commission rate, the default value is 0.0002"
+        long = "fees",
+        help = "Fee schedule file in TOML, the default is fees.toml of the workspace if exists"
     )]
-    broker_commission_rate: f64,
+    fees_path: Option<PathBuf>,
 
     #[arg(
-        long = "broker-commission-min",
-        default_value_t = 5.0,
-        help = "The broker-commission minimal fee, the default value is 5"
+        long = "fee",
+        help = "Override a rule of the fee schedule, e.g. --fee stock.stamp_duty.rate=0.0005 --fee stock.XSHG.transfer_fee.rate=0.00001"
     )]
-    broker_commission_min_fee: f64,
+    fees: Vec<String>,
+
+    #[arg(long = "stamp-duty", hide = true)]
+    stamp_duty_rate: Option<f64>,
+
+    #[arg(long = "stamp-duty-min", hide = true)]
+    stamp_duty_min_fee: Option<f64>,
+
+    #[arg(long = "broker-commission", hide = true)]
+    broker_commission_rate: Option<f64>,
+
+    #[arg(long = "broker-commission-min", hide = true)]
+    broker_commission_min_fee: Option<f64>,
+
+    #[arg(long = "fund-broker-commission", hide = true)]
+    fund_broker_commission_rate: Option<f64>,
+
+    #[arg(long = "fund-broker-commission-min", hide = true)]
+    fund_broker_commission_min_fee: Option<f64>,
 
     #[arg(
         short = 'o',
@@ -129,6 +146,9 @@ pub struct BacktestCommand {
     #[arg(short = 'N', long = "notify", help = "Notify external systems")]
     notify: bool,
 
+    #[arg(long = "cache-stats", help = "Show statistics of the in-process cache")]
+    cache_stats: bool,
+
     #[arg(
         short = 'S',
         long = "cv-search",
@@ -163,7 +183,75 @@ pub struct BacktestCommand {
 }
 
 impl BacktestCommand {
+    /// Overrides of the fee schedule, with those of the deprecated fee options first so that
+    /// `--fee` takes precedence
+    fn fee_overrides(&self) -> Vec<String> {
+        let deprecated_options = [
+            (self.stamp_duty_rate, &["stock.stamp_duty.rate"][..]),
+            (self.stamp_duty_min_fee, &["stock.stamp_duty.min"][..]),
+            (
+                self.broker_commission_rate,
+                &[
+                    "stock.broker_commission.rate",
+                    "conv_bond.broker_commission.rate",
+                ][..],
+            ),
+            (
+                self.broker_commission_min_fee,
+                &[
+                    "stock.broker_commission.min",
+                    "conv_bond.broker_commission.min",
+                ][..],
+            ),
+            (
+                self.fund_broker_commission_rate,
+                &[
+                    "etf.broker_commission.rate",
+                    "lof.broker_commission.rate",
+                    "reit.broker_commission.rate",
+                ][..],
+            ),
+            (
+                self.fund_broker_commission_min_fee,
+                &[
+                    "etf.broker_commission.min",
+                    "lof.broker_commission.min",
+                    "reit.broker_commission.min",
+                ][..],
+            ),
+        ];
+
+        let mut overrides: Vec<String> = vec![];
+        for (value, keys) in deprecated_options {
+            if let Some(value) = value {
+                let replacement = keys
+                    .iter()
+                    .map(|key| format!("--fee {key}={value}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!(
+                    "[!] {}",
+                    format!("Fee option is deprecated, use '{replacement}' instead").yellow()
+                );
+
+                overrides.extend(keys.iter().map(|key| format!("{key}={value}")));
+            }
+        }
+        overrides.extend(self.fees.iter().cloned());
+
+        overrides
+    }
+
     pub async fn exec(&self) {
+        let fees =
+            match api::load_fee_schedule(self.fees_path.as_deref(), &self.fee_overrides()).await {
+                Ok(fees) => fees,
+                Err(err) => {
+                    println!("[!] {}", err.to_string().red());
+                    return;
+                }
+            };
+
         let multi_progress = MultiProgress::new();
 
         let logger = multi_progress.add(ProgressBar::new_spinner());
@@ -191,6 +279,7 @@ impl BacktestCommand {
             "Sharpe".to_string(),
             "Calmar".to_string(),
             "Sortino".to_string(),
+            "Fees".to_string(),
         ]];
 
         let base_options = BacktestOptions {
@@ -205,10 +294,9 @@ impl BacktestCommand {
             buffer_ratio: self.buffer_ratio,
             position_tolerance: self.position_tolerance,
             risk_free_rate: self.risk_free_rate,
-            stamp_duty_rate: self.stamp_duty_rate,
-            stamp_duty_min_fee: self.stamp_duty_min_fee,
-            broker_commission_rate: self.broker_commission_rate,
-            broker_commission_min_fee: self.broker_commission_min_fee,
+            risk_free_series: self.risk_free_series,
+            fees,
+            base_currency: self.base_currency,
         };
 
         let mut process_streams =
@@ -293,7 +381,10 @@ impl BacktestCommand {
                                         }
 
                                         let BacktestResult {
-                                            options, metrics, ..
+                                            options,
+                                            metrics,
+                                            fees,
+                                            ..
                                         } = *backtest_result;
                                         table_data.push(vec![
                                             vfund_tranche.to_string(),
@@ -362,6 +453,11 @@ impl BacktestCommand {
                                                 .sortino_ratio
                                                 .map(|v| format!("{v:.3}"))
                                                 .unwrap_or("-".to_string()),
+                                            format!(
+                                                "{:.2}%",
+                                                fees.values().sum::<f64>() / options.init_cash
+                                                    * 100.0
+                                            ),
                                         ]);
                                     }
                                     BacktestEvent::Error(err) => {
@@ -438,6 +534,33 @@ impl BacktestCommand {
             logger.println(format!("\n{table}"));
         }
 
+        if self.cache_stats {
+            let mut table_data: Vec<Vec<String>> = vec![vec![
+                "Dataset".to_string(),
+                "Entries".to_string(),
+                "Size".to_string(),
+                "Hits".to_string(),
+                "Misses".to_string(),
+                "Evictions".to_string(),
+            ]];
+            for stat in api::memory_cache_stats() {
+                table_data.push(vec![
+                    stat.dataset.to_string(),
+                    stat.count.to_string(),
+                    HumanBytes(stat.size as u64).to_string(),
+                    stat.hits.to_string(),
+                    stat.misses.to_string(),
+                    stat.evictions.to_string(),
+                ]);
+            }
+
+            let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+            table.modify(Rows::first(), Color::FG_BRIGHT_BLACK);
+            table.modify(Columns::first().not(Rows::first()), Color::FG_CYAN);
+            table.modify(Columns::new(1..), Alignment::right());
+            logger.println(format!("\n{table}"));
+        }
+
         if self.notify {
             let funds_title = self.funds.join("|");
 
@@ -0,0 +1,47 @@
+use clap::Subcommand;
+
+mod expired;
+mod prefetch;
+mod purge;
+mod stats;
+mod vacuum;
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    #[command(about = "List expired cache entries")]
+    Expired(Box<expired::CacheExpiredCommand>),
+
+    #[command(about = "Prefetch data needed by backtesting virtual funds")]
+    Prefetch(Box<prefetch::CachePrefetchCommand>),
+
+    #[command(about = "Delete cache entries")]
+    Purge(Box<purge::CachePurgeCommand>),
+
+    #[command(about = "Show cache size per source and API")]
+    Stats(Box<stats::CacheStatsCommand>),
+
+    #[command(about = "Reclaim unused space of cache database")]
+    Vacuum(Box<vacuum::CacheVacuumCommand>),
+}
+
+impl CacheCommand {
+    pub async fn exec(&self) {
+        match self {
+            CacheCommand::Expired(cmd) => {
+                cmd.exec().await;
+            }
+            CacheCommand::Prefetch(cmd) => {
+                cmd.exec().await;
+            }
+            CacheCommand::Purge(cmd) => {
+                cmd.exec().await;
+            }
+            CacheCommand::Stats(cmd) => {
+                cmd.exec().await;
+            }
+            CacheCommand::Vacuum(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
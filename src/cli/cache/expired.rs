@@ -0,0 +1,43 @@
+use colored::Colorize;
+use indicatif::HumanBytes;
+use tabled::settings::{
+    Alignment, Color,
+    object::{Columns, Rows},
+};
+use vfunds::api;
+
+#[derive(clap::Args)]
+pub struct CacheExpiredCommand;
+
+impl CacheExpiredCommand {
+    pub async fn exec(&self) {
+        match api::cache_entries(true).await {
+            Ok(entries) => {
+                let mut table_data: Vec<Vec<String>> = vec![vec![
+                    "Key".to_string(),
+                    "Expire".to_string(),
+                    "Size".to_string(),
+                ]];
+
+                for entry in &entries {
+                    table_data.push(vec![
+                        entry.key.to_string(),
+                        entry
+                            .expire
+                            .map(|expire| expire.to_string())
+                            .unwrap_or_default(),
+                        HumanBytes(entry.size).to_string(),
+                    ]);
+                }
+
+                let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+                table.modify(Rows::first(), Color::FG_CYAN);
+                table.modify(Columns::last(), Alignment::right());
+                println!("{table}");
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use futures::{StreamExt, stream};
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::time::Duration;
+use vfunds::{api, utils::datetime::date_from_str};
+
+#[derive(clap::Args)]
+pub struct CachePrefetchCommand {
+    #[arg(
+        short = 's',
+        long = "start",
+        value_parser = date_from_str,
+        help = "Start date of backtest, e.g. -s 2018-01-01"
+    )]
+    start_date: NaiveDate,
+
+    #[arg(
+        short = 'e',
+        long = "end",
+        value_parser = date_from_str,
+        help = "End date of backtest, the default value is today, e.g. -e 2025-08-08"
+    )]
+    end_date: Option<NaiveDate>,
+
+    #[arg(
+        short = 'f',
+        long = "fund",
+        help = "Virtual fund requires prefetching, all by default, e.g. -f index_fund -f hedge_fund"
+    )]
+    funds: Vec<String>,
+}
+
+impl CachePrefetchCommand {
+    pub async fn exec(&self) {
+        let end_date = self.end_date.unwrap_or(Local::now().date_naive());
+
+        let spinner = ProgressBar::new_spinner();
+        spinner
+            .set_style(ProgressStyle::with_template("{msg}[{elapsed}] {spinner:.cyan}").unwrap());
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner.set_message("Resolving tickers ");
+
+        let tickers = match api::prefetch_tickers(&self.funds, &self.start_date, &end_date).await {
+            Ok(tickers) => {
+                spinner.finish_with_message(format!("Resolved {} tickers ", tickers.len()));
+                tickers
+            }
+            Err(err) => {
+                spinner.finish_with_message(format!("{} ", err.to_string().red()));
+                return;
+            }
+        };
+
+        let progress = ProgressBar::new(tickers.len() as u64);
+        progress.set_style(
+            ProgressStyle::with_template("{msg}[{elapsed}] {wide_bar:.cyan/blue} {pos}/{len}")
+                .unwrap(),
+        );
+
+        let mut errors: Vec<String> = vec![];
+        let mut results = stream::iter(&tickers)
+            .map(|ticker| async move { (ticker, api::prefetch_ticker(ticker).await) })
+            .buffer_unordered(api::MAX_FETCH_CONCURRENCY);
+        while let Some((ticker, result)) = results.next().await {
+            progress.set_message(format!("{ticker} "));
+
+            if let Err(err) = result {
+                errors.push(format!("{ticker}: {err}"));
+            }
+
+            progress.inc(1);
+        }
+        progress.finish_with_message(format!(
+            "Prefetched {} tickers ",
+            tickers.len() - errors.len()
+        ));
+
+        for error in errors {
+            println!("[!] {}", error.red());
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use colored::Colorize;
+use vfunds::api;
+
+#[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("conditions").required(true).multiple(true).args(&["prefix", "age_days", "expired", "all"]))]
+pub struct CachePurgeCommand {
+    #[arg(
+        short = 'p',
+        long = "prefix",
+        help = "Purge entries whose key starts with the prefix, e.g. -p [TUSHARE]daily_basic"
+    )]
+    prefix: Option<String>,
+
+    #[arg(
+        short = 'a',
+        long = "age",
+        help = "Purge entries cached more than the days ago, e.g. -a 30"
+    )]
+    age_days: Option<u64>,
+
+    #[arg(short = 'e', long = "expired", help = "Purge expired entries only")]
+    expired: bool,
+
+    #[arg(long = "all", help = "Purge all entries")]
+    all: bool,
+}
+
+impl CachePurgeCommand {
+    pub async fn exec(&self) {
+        match api::purge_cache(self.prefix.as_deref(), self.age_days, self.expired).await {
+            Ok(count) => {
+                println!("Purged {count} entries");
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use colored::Colorize;
+use indicatif::HumanBytes;
+use tabled::settings::{
+    Alignment, Color,
+    object::{Columns, Rows},
+};
+use vfunds::api;
+
+#[derive(clap::Args)]
+pub struct CacheStatsCommand;
+
+impl CacheStatsCommand {
+    pub async fn exec(&self) {
+        match api::cache_stats().await {
+            Ok(stats) => {
+                let mut table_data: Vec<Vec<String>> = vec![vec![
+                    "Source".to_string(),
+                    "API".to_string(),
+                    "Entries".to_string(),
+                    "Expired".to_string(),
+                    "Size".to_string(),
+                ]];
+
+                for stat in &stats {
+                    table_data.push(vec![
+                        stat.source.to_string(),
+                        stat.api.to_string(),
+                        stat.count.to_string(),
+                        stat.expired_count.to_string(),
+                        HumanBytes(stat.size).to_string(),
+                    ]);
+                }
+
+                table_data.push(vec![
+                    "Total".to_string(),
+                    "".to_string(),
+                    stats.iter().map(|s| s.count).sum::<usize>().to_string(),
+                    stats
+                        .iter()
+                        .map(|s| s.expired_count)
+                        .sum::<usize>()
+                        .to_string(),
+                    HumanBytes(stats.iter().map(|s| s.size).sum()).to_string(),
+                ]);
+
+                let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+                table.modify(Rows::first(), Color::FG_CYAN);
+                table.modify(Rows::last(), Color::FG_CYAN);
+                table.modify(Columns::new(2..), Alignment::right());
+                println!("{table}");
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::time::Duration;
+use vfunds::api;
+
+#[derive(clap::Args)]
+pub struct CacheVacuumCommand;
+
+impl CacheVacuumCommand {
+    pub async fn exec(&self) {
+        let spinner = ProgressBar::new_spinner();
+        spinner
+            .set_style(ProgressStyle::with_template("{msg}[{elapsed}] {spinner:.cyan}").unwrap());
+        spinner.enable_steady_tick(Duration::from_millis(100));
+
+        match api::vacuum_cache().await {
+            Ok(_) => {
+                spinner.finish_with_message("Vacuumed ");
+            }
+            Err(err) => {
+                spinner.finish_with_message(format!("{} ", err.to_string().red()));
+            }
+        }
+    }
+}
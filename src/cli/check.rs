@@ -8,7 +8,15 @@ use tokio::time::Duration;
 use vfunds::api;
 
 #[derive(clap::Args)]
-pub struct CheckCommand;
+pub struct CheckCommand {
+    #[cfg(feature = "mock")]
+    #[arg(
+        short = 'm',
+        long = "mock",
+        help = "Check against the mock server on fixtures directory instead of the configured services"
+    )]
+    mock_fixtures_dir: Option<std::path::PathBuf>,
+}
 
 impl CheckCommand {
     pub async fn exec(&self) {
@@ -17,6 +25,17 @@ impl CheckCommand {
             .set_style(ProgressStyle::with_template("{msg}[{elapsed}] {spinner:.cyan}").unwrap());
         spinner.enable_steady_tick(Duration::from_millis(100));
 
+        #[cfg(feature = "mock")]
+        if let Some(fixtures_dir) = &self.mock_fixtures_dir {
+            match api::use_mock_server(fixtures_dir, 0).await {
+                Ok(addr) => spinner.set_message(format!("Mock server on {addr} ")),
+                Err(err) => {
+                    spinner.finish_with_message(format!("{} ", err.to_string().red()));
+                    return;
+                }
+            }
+        }
+
         match api::check().await {
             Ok(status) => {
                 let mut table_data: Vec<Vec<String>> = vec![];
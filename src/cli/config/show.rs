@@ -9,19 +9,41 @@ impl ConfigShowCommand {
     pub async fn exec(&self) {
         match api::get_config().await {
             Ok(config) => {
-                let table_data: Vec<Vec<String>> = vec![
+                let mut table_data: Vec<Vec<String>> = vec![
                     vec!["qmt_api".to_string(), config.qmt_api.to_string()],
                     vec!["tushare_api".to_string(), config.tushare_api.to_string()],
                     vec![
                         "tushare_token".to_string(),
                         config.tushare_token.to_string(),
                     ],
+                    vec![
+                        "tushare_points".to_string(),
+                        config.tushare_points.to_string(),
+                    ],
+                    vec![
+                        "qmt_rate_limit".to_string(),
+                        config.qmt_rate_limit.to_string(),
+                    ],
                     vec![
                         "wecom_webhook".to_string(),
                         config.wecom_webhook.to_string(),
                     ],
+                    vec![
+                        "local_data_dir".to_string(),
+                        config.local_data_dir.to_string(),
+                    ],
+                    vec!["data_sources".to_string(), config.data_sources.join(",")],
                 ];
 
+                let mut dataset_sources: Vec<_> = config.dataset_sources.iter().collect();
+                dataset_sources.sort_by_key(|(dataset, _)| *dataset);
+                for (dataset, data_sources) in dataset_sources {
+                    table_data.push(vec![
+                        format!("data_sources.{dataset}"),
+                        data_sources.join(","),
+                    ]);
+                }
+
                 let mut table = tabled::builder::Builder::from_iter(&table_data).build();
                 table.modify(Columns::first(), Color::FG_CYAN);
                 println!("{table}");
@@ -0,0 +1,19 @@
+use clap::Subcommand;
+
+mod check;
+
+#[derive(Subcommand)]
+pub enum DataCommand {
+    #[command(about = "Check quality of klines, e.g. missing days and divergence between sources")]
+    Check(Box<check::DataCheckCommand>),
+}
+
+impl DataCommand {
+    pub async fn exec(&self) {
+        match self {
+            DataCommand::Check(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use tabled::settings::{Color, object::Rows};
+use tokio::time::Duration;
+use vfunds::{
+    api,
+    utils::datetime::{date_from_str, date_to_str},
+};
+
+#[derive(clap::Args)]
+pub struct DataCheckCommand {
+    #[arg(
+        short = 's',
+        long = "start",
+        value_parser = date_from_str,
+        help = "Start date of checking, e.g. -s 2018-01-01"
+    )]
+    start_date: NaiveDate,
+
+    #[arg(
+        short = 'e',
+        long = "end",
+        value_parser = date_from_str,
+        help = "End date of checking, the default value is today, e.g. -e 2025-08-08"
+    )]
+    end_date: Option<NaiveDate>,
+
+    #[arg(
+        short = 't',
+        long = "ticker",
+        help = "Ticker to check, e.g. -t 600000 -t 000001"
+    )]
+    tickers: Vec<String>,
+
+    #[arg(
+        short = 'f',
+        long = "fund",
+        help = "Virtual fund whose tickers to check if no ticker specified, all by default, e.g. -f index_fund"
+    )]
+    funds: Vec<String>,
+
+    #[arg(
+        long = "mark",
+        help = "Mark tickers with bad klines, and unmark good ones, so backtests warn when they use them"
+    )]
+    mark: bool,
+}
+
+impl DataCheckCommand {
+    pub async fn exec(&self) {
+        let end_date = self.end_date.unwrap_or(Local::now().date_naive());
+
+        let tickers = if self.tickers.is_empty() {
+            let spinner = ProgressBar::new_spinner();
+            spinner.set_style(
+                ProgressStyle::with_template("{msg}[{elapsed}] {spinner:.cyan}").unwrap(),
+            );
+            spinner.enable_steady_tick(Duration::from_millis(100));
+            spinner.set_message("Resolving tickers ");
+
+            match api::prefetch_tickers(&self.funds, &self.start_date, &end_date).await {
+                Ok(tickers) => {
+                    spinner.finish_with_message(format!("Resolved {} tickers ", tickers.len()));
+                    tickers
+                }
+                Err(err) => {
+                    spinner.finish_with_message(format!("{} ", err.to_string().red()));
+                    return;
+                }
+            }
+        } else {
+            let mut tickers = vec![];
+            for ticker_str in &self.tickers {
+                match api::parse_ticker_title(ticker_str).await {
+                    Ok((ticker, _)) => tickers.push(ticker),
+                    Err(err) => {
+                        println!("[!] {}", err.to_string().red());
+                        return;
+                    }
+                }
+            }
+            tickers
+        };
+
+        let progress = ProgressBar::new(tickers.len() as u64);
+        progress.set_style(
+            ProgressStyle::with_template("{msg}[{elapsed}] {wide_bar:.cyan/blue} {pos}/{len}")
+                .unwrap(),
+        );
+
+        let mut issues: Vec<api::KlineIssue> = vec![];
+        let mut errors: Vec<String> = vec![];
+        for ticker in &tickers {
+            progress.set_message(format!("{ticker} "));
+
+            match api::check_kline(ticker, &self.start_date, &end_date, self.mark).await {
+                Ok(ticker_issues) => issues.extend(ticker_issues),
+                Err(err) => errors.push(format!("{ticker}: {err}")),
+            }
+
+            progress.inc(1);
+        }
+        progress.finish_with_message(format!(
+            "Checked {} tickers, {} with bad klines ",
+            tickers.len() - errors.len(),
+            issues
+                .iter()
+                .filter(|issue| issue.is_bad())
+                .map(|issue| &issue.ticker)
+                .unique()
+                .count()
+        ));
+
+        if !issues.is_empty() {
+            let mut table_data: Vec<Vec<String>> = vec![vec![
+                "Ticker".to_string(),
+                "Issue".to_string(),
+                "Days".to_string(),
+                "First Dates".to_string(),
+                "Detail".to_string(),
+            ]];
+
+            for issue in &issues {
+                let kind_str = if issue.is_bad() {
+                    issue.kind.to_string().red().to_string()
+                } else {
+                    issue.kind.to_string()
+                };
+
+                table_data.push(vec![
+                    issue.ticker.to_string(),
+                    kind_str,
+                    issue.dates.len().to_string(),
+                    issue.dates.iter().take(3).map(date_to_str).join(" "),
+                    issue.message.to_string(),
+                ]);
+            }
+
+            let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+            table.modify(Rows::first(), Color::FG_CYAN);
+            println!("{table}");
+        }
+
+        for error in errors {
+            println!("[!] {}", error.red());
+        }
+    }
+}
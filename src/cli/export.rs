@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::time::Duration;
+use vfunds::{api, api::LocalFileFormat};
+
+#[derive(clap::Args)]
+pub struct ExportCommand {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "Output directory, the default value is the local data directory in config"
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[arg(
+        short = 'f',
+        long = "format",
+        default_value = "csv",
+        help = "File format, csv or parquet"
+    )]
+    format: LocalFileFormat,
+}
+
+impl ExportCommand {
+    pub async fn exec(&self) {
+        let spinner = ProgressBar::new_spinner();
+        spinner
+            .set_style(ProgressStyle::with_template("{msg}[{elapsed}] {spinner:.cyan}").unwrap());
+        spinner.enable_steady_tick(Duration::from_millis(100));
+
+        match api::export_local_data(self.output_dir.as_deref(), self.format).await {
+            Ok((count, errors)) => {
+                spinner.finish_with_message(format!("Exported {count} files "));
+
+                for err in errors {
+                    println!("[!] {}", err.to_string().red());
+                }
+            }
+            Err(err) => {
+                spinner.finish_with_message(format!("{} ", err.to_string().red()));
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use clap::Subcommand;
+
+mod import;
+mod stats;
+
+#[derive(Subcommand)]
+pub enum MembershipCommand {
+    #[command(about = "Import historical constituents of an index or sectors from a CSV file")]
+    Import(Box<import::MembershipImportCommand>),
+
+    #[command(about = "Show snapshots of historical constituents per index and sectors")]
+    Stats(Box<stats::MembershipStatsCommand>),
+}
+
+impl MembershipCommand {
+    pub async fn exec(&self) {
+        match self {
+            MembershipCommand::Import(cmd) => {
+                cmd.exec().await;
+            }
+            MembershipCommand::Stats(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
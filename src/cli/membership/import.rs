@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use colored::Colorize;
+use vfunds::api;
+
+#[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("source").required(true).args(&["index", "sector_prefix"]))]
+pub struct MembershipImportCommand {
+    #[arg(
+        short = 'i',
+        long = "index",
+        help = "Index of the constituents, the file has rows of date,ticker, e.g. -i 000300.SH"
+    )]
+    index: Option<String>,
+
+    #[arg(
+        short = 's',
+        long = "sector",
+        help = "Sector prefix of the constituents, the file has rows of date,ticker,sector, e.g. -s SW1"
+    )]
+    sector_prefix: Option<String>,
+
+    #[arg(help = "CSV file, each date is a snapshot effective from the date")]
+    file: PathBuf,
+}
+
+impl MembershipImportCommand {
+    pub async fn exec(&self) {
+        let result = if let Some(index) = &self.index {
+            api::import_index_membership(index, &self.file).await
+        } else if let Some(sector_prefix) = &self.sector_prefix {
+            api::import_sector_membership(sector_prefix, &self.file).await
+        } else {
+            return;
+        };
+
+        match result {
+            Ok(count) => {
+                println!("Imported {count} snapshots");
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
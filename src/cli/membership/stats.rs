@@ -0,0 +1,41 @@
+use colored::Colorize;
+use tabled::settings::{
+    Alignment, Color,
+    object::{Columns, Rows},
+};
+use vfunds::{api, utils::datetime::date_to_str};
+
+#[derive(clap::Args)]
+pub struct MembershipStatsCommand;
+
+impl MembershipStatsCommand {
+    pub async fn exec(&self) {
+        match api::membership_stats().await {
+            Ok(stats) => {
+                let mut table_data: Vec<Vec<String>> = vec![vec![
+                    "Source".to_string(),
+                    "Snapshots".to_string(),
+                    "First".to_string(),
+                    "Last".to_string(),
+                ]];
+
+                for stat in &stats {
+                    table_data.push(vec![
+                        stat.source.to_string(),
+                        stat.snapshot_count.to_string(),
+                        date_to_str(&stat.first_date),
+                        date_to_str(&stat.last_date),
+                    ]);
+                }
+
+                let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+                table.modify(Rows::first(), Color::FG_CYAN);
+                table.modify(Columns::new(1..), Alignment::right());
+                println!("{table}");
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
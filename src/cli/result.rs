@@ -125,11 +125,15 @@ async fn load_backtest_results_as_table(
         "Sharpe".to_string(),
         "Calmar".to_string(),
         "Sortino".to_string(),
+        "Fees".to_string(),
     ]];
 
     for (fund_name, fund_result) in &results {
         let api::BacktestOutputResult {
-            options, metrics, ..
+            options,
+            metrics,
+            fees,
+            ..
         } = fund_result;
         table_data.push(vec![
             fund_name.to_string(),
@@ -191,6 +195,10 @@ async fn load_backtest_results_as_table(
                 .sortino_ratio
                 .map(|v| format!("{v:.3}"))
                 .unwrap_or("-".to_string()),
+            format!(
+                "{:.2}%",
+                fees.values().sum::<f64>() / options.init_cash * 100.0
+            ),
         ]);
     }
 
@@ -0,0 +1,19 @@
+use clap::Subcommand;
+
+mod search;
+
+#[derive(Subcommand)]
+pub enum TickerCommand {
+    #[command(about = "Search tickers by names, partial names, pinyin initials or symbols")]
+    Search(Box<search::TickerSearchCommand>),
+}
+
+impl TickerCommand {
+    pub async fn exec(&self) {
+        match self {
+            TickerCommand::Search(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use colored::Colorize;
+use tabled::settings::{Color, object::Rows};
+use vfunds::api;
+
+#[derive(clap::Args)]
+pub struct TickerSearchCommand {
+    #[arg(
+        short = 'n',
+        default_value_t = 20,
+        help = "Max number of tickers to show"
+    )]
+    limit: usize,
+
+    #[arg(help = "Name, partial name, pinyin initials or symbol prefix, e.g. hldb")]
+    query: String,
+}
+
+impl TickerSearchCommand {
+    pub async fn exec(&self) {
+        match api::search_tickers(&self.query).await {
+            Ok(matches) => {
+                if matches.is_empty() {
+                    println!("[!] {}", format!("No ticker of '{}'", self.query).yellow());
+                    return;
+                }
+
+                let mut table_data: Vec<Vec<String>> = vec![vec![
+                    "Ticker".to_string(),
+                    "Name".to_string(),
+                    "Match".to_string(),
+                ]];
+
+                for m in matches.iter().take(self.limit) {
+                    table_data.push(vec![
+                        m.ticker.to_string(),
+                        m.name.to_string(),
+                        m.kind.to_string(),
+                    ]);
+                }
+
+                let mut table = tabled::builder::Builder::from_iter(&table_data).build();
+                table.modify(Rows::first(), Color::FG_CYAN);
+                println!("{table}");
+
+                if matches.len() > self.limit {
+                    println!("... {} more", matches.len() - self.limit);
+                }
+            }
+            Err(err) => {
+                println!("[!] {}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use chrono::{Duration, NaiveDate};
 use num_traits::NumCast;
@@ -17,6 +23,9 @@ pub struct DailySeries {
 
     date_field_name: String,
     value_field_names: HashMap<String, String>,
+
+    #[serde(skip)]
+    index: Arc<OnceLock<DateIndex>>,
 }
 
 impl DailySeries {
@@ -35,6 +44,33 @@ impl DailySeries {
         }
     }
 
+    /// Value fields are named as the columns except the date column
+    pub fn from_dataframe(df: DataFrame, date_field_name: &str) -> VfResult<Self> {
+        match df.column(date_field_name).map(|col| col.dtype()) {
+            Ok(DataType::Date) => {}
+            _ => {
+                return Err(VfError::Invalid {
+                    code: "INVALID_DATAFRAME",
+                    message: format!("No date column '{date_field_name}'"),
+                });
+            }
+        }
+
+        let value_field_names: HashMap<String, String> = df
+            .get_column_names()
+            .into_iter()
+            .filter(|name| name.as_str() != date_field_name)
+            .map(|name| (name.to_string(), name.to_string()))
+            .collect();
+
+        Ok(Self {
+            df,
+            date_field_name: date_field_name.to_string(),
+            value_field_names,
+            index: Default::default(),
+        })
+    }
+
     pub fn from_tushare_json(
         json: &Value,
         date_field_name: &str,
@@ -136,6 +172,8 @@ impl DailySeries {
         rows
     }
 
+    /// Value of the field at the latest date before the date, or on it if `include_today`,
+    /// within `max_stale_days`
     pub fn get_latest_value<T: NumCast + IsFloat>(
         &self,
         date: &NaiveDate,
@@ -143,52 +181,26 @@ impl DailySeries {
         include_today: bool,
         field_name: &str,
     ) -> Option<(NaiveDate, T)> {
-        if let Some(origin_field_name) = self.value_field_names.get(field_name) {
-            let filter = if include_today {
-                col(&self.date_field_name)
-                    .lt_eq(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            } else {
-                col(&self.date_field_name)
-                    .lt(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            };
+        let index = self.index();
+        let values = index.values(self.value_field_names.get(field_name)?)?;
 
-            if let Ok(df) = self
-                .df
-                .clone()
-                .lazy()
-                .filter(filter)
-                .sort(
-                    [&self.date_field_name],
-                    SortMultipleOptions::default().with_order_descending(true),
-                )
-                .collect()
-            {
-                if let (Ok(col_date), Ok(col_val)) = (
-                    df.column(&self.date_field_name),
-                    df.column(origin_field_name),
-                ) {
-                    if let (Ok(cell_date), Ok(cell_val)) = (col_date.get(0), col_val.get(0)) {
-                        if let (Some(date_days_after_epoch), Some(val)) =
-                            (cell_date.extract::<i32>(), cell_val.extract::<T>())
-                        {
-                            if let Some(val_date) =
-                                NaiveDate::from_epoch_days(date_days_after_epoch)
-                            {
-                                if *date - val_date <= Duration::days(max_stale_days as i64) {
-                                    return Some((val_date, val));
-                                }
-                            }
-                        }
-                    }
-                }
+        let end = index.position(date, include_today);
+        for i in (0..end).rev() {
+            let val_date = index.dates[i];
+            if *date - val_date > Duration::days(max_stale_days as i64) {
+                break;
+            }
+
+            if values[i].is_finite() {
+                return T::from(values[i]).map(|val| (val_date, val));
             }
         }
 
         None
     }
 
+    /// Values of the field at the latest `count` dates before the date, or on it if
+    /// `include_today`, sorted by date
     pub fn get_latest_values<T: NumCast + IsFloat>(
         &self,
         date: &NaiveDate,
@@ -196,57 +208,23 @@ impl DailySeries {
         field_name: &str,
         count: u32,
     ) -> Vec<(NaiveDate, T)> {
-        if let Some(origin_field_name) = self.value_field_names.get(field_name) {
-            let filter = if include_today {
-                col(&self.date_field_name)
-                    .lt_eq(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            } else {
-                col(&self.date_field_name)
-                    .lt(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            };
-
-            if let Ok(df) = self
-                .df
-                .clone()
-                .lazy()
-                .filter(filter)
-                .sort(
-                    [&self.date_field_name],
-                    SortMultipleOptions::default().with_order_descending(false),
-                )
-                .tail(count)
-                .collect()
-            {
-                if let (Ok(col_date), Ok(col_val)) = (
-                    df.column(&self.date_field_name),
-                    df.column(origin_field_name),
-                ) {
-                    let mut vals = vec![];
-
-                    for i in 0..col_date.len() {
-                        if let (Ok(cell_date), Ok(cell_val)) = (col_date.get(i), col_val.get(i)) {
-                            if let (Some(date_days_after_epoch), Some(val)) =
-                                (cell_date.extract::<i32>(), cell_val.extract::<T>())
-                            {
-                                if let Some(val_date) =
-                                    NaiveDate::from_epoch_days(date_days_after_epoch)
-                                {
-                                    vals.push((val_date, val));
-                                }
-                            }
-                        }
-                    }
+        let index = self.index();
+        let Some(values) = self
+            .value_field_names
+            .get(field_name)
+            .and_then(|origin_field_name| index.values(origin_field_name))
+        else {
+            return vec![];
+        };
 
-                    return vals;
-                }
-            }
-        }
-
-        vec![]
+        index
+            .latest_positions(values, date, include_today, count)
+            .into_iter()
+            .filter_map(|i| T::from(values[i]).map(|val| (index.dates[i], val)))
+            .collect()
     }
 
+    /// Same as `get_latest_values` with the label of each date
     pub fn get_latest_values_with_label<T: NumCast + IsFloat>(
         &self,
         date: &NaiveDate,
@@ -255,63 +233,29 @@ impl DailySeries {
         label_field_name: &str,
         count: u32,
     ) -> Vec<(NaiveDate, T, Option<String>)> {
-        if let (Some(origin_field_name), Some(origin_label_field_name)) = (
-            self.value_field_names.get(field_name),
-            self.value_field_names.get(label_field_name),
-        ) {
-            let filter = if include_today {
-                col(&self.date_field_name)
-                    .lt_eq(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            } else {
-                col(&self.date_field_name)
-                    .lt(lit(*date))
-                    .and(col(origin_field_name).is_finite())
-            };
+        let index = self.index();
+        let (Some(values), Some(col_label)) = (
+            self.value_field_names
+                .get(field_name)
+                .and_then(|origin_field_name| index.values(origin_field_name)),
+            self.value_field_names
+                .get(label_field_name)
+                .and_then(|origin_label_field_name| self.df.column(origin_label_field_name).ok()),
+        ) else {
+            return vec![];
+        };
 
-            if let Ok(df) = self
-                .df
-                .clone()
-                .lazy()
-                .filter(filter)
-                .sort(
-                    [&self.date_field_name],
-                    SortMultipleOptions::default().with_order_descending(false),
-                )
-                .tail(count)
-                .collect()
-            {
-                let mut vals = vec![];
-
-                if let (Ok(col_date), Ok(col_val), Ok(col_label)) = (
-                    df.column(&self.date_field_name),
-                    df.column(origin_field_name),
-                    df.column(origin_label_field_name),
-                ) {
-                    for i in 0..col_date.len() {
-                        if let (Ok(cell_date), Ok(cell_val), Ok(cell_label)) =
-                            (col_date.get(i), col_val.get(i), col_label.get(i))
-                        {
-                            if let (Some(date_days_after_epoch), Some(val), label) = (
-                                cell_date.extract::<i32>(),
-                                cell_val.extract::<T>(),
-                                cell_label.get_str(),
-                            ) {
-                                if let Some(val_date) =
-                                    NaiveDate::from_epoch_days(date_days_after_epoch)
-                                {
-                                    vals.push((val_date, val, label.map(|s| s.to_string())));
-                                }
-                            }
-                        }
-                    }
-                }
-
-                return vals;
-            }
-        }
-
-        vec![]
+        index
+            .latest_positions(values, date, include_today, count)
+            .into_iter()
+            .filter_map(|i| {
+                let label = col_label
+                    .get(index.rows[i])
+                    .ok()
+                    .and_then(|cell| cell.get_str().map(|s| s.to_string()));
+                T::from(values[i]).map(|val| (index.dates[i], val, label))
+            })
+            .collect()
     }
 
     pub fn get_value<T: NumCast + IsFloat>(
@@ -319,40 +263,14 @@ impl DailySeries {
         date: &NaiveDate,
         field_name: &str,
     ) -> Option<(NaiveDate, T)> {
-        if let Some(origin_field_name) = self.value_field_names.get(field_name) {
-            if let Ok(df) = self
-                .df
-                .clone()
-                .lazy()
-                .filter(
-                    col(&self.date_field_name)
-                        .eq(lit(*date))
-                        .and(col(origin_field_name).is_finite()),
-                )
-                .collect()
-            {
-                if let (Ok(col_date), Ok(col_val)) = (
-                    df.column(&self.date_field_name),
-                    df.column(origin_field_name),
-                ) {
-                    for i in 0..col_date.len() {
-                        if let (Ok(cell_date), Ok(cell_val)) = (col_date.get(i), col_val.get(i)) {
-                            if let (Some(date_days_after_epoch), Some(val)) =
-                                (cell_date.extract::<i32>(), cell_val.extract::<T>())
-                            {
-                                if let Some(date) =
-                                    NaiveDate::from_epoch_days(date_days_after_epoch)
-                                {
-                                    return Some((date, val));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let index = self.index();
+        let values = index.values(self.value_field_names.get(field_name)?)?;
 
-        None
+        let start = index.position(date, false);
+        let end = index.position(date, true);
+        (start..end)
+            .find(|i| values[*i].is_finite())
+            .and_then(|i| T::from(values[i]).map(|val| (index.dates[i], val)))
     }
 
     pub fn get_values<T: NumCast + IsFloat>(&self, field_name: &str) -> Vec<(NaiveDate, T)> {
@@ -398,6 +316,18 @@ impl DailySeries {
         vec![]
     }
 
+    /// Estimated heap bytes of the data, including the date index which is built lazily
+    pub fn estimated_size(&self) -> usize {
+        self.df.estimated_size()
+            + DateIndex::estimated_size(&self.df, &self.date_field_name)
+            + self.date_field_name.capacity()
+            + self
+                .value_field_names
+                .iter()
+                .map(|(k, v)| k.capacity() + v.capacity())
+                .sum::<usize>()
+    }
+
     pub fn len(&self) -> usize {
         self.df.height()
     }
@@ -423,9 +353,108 @@ impl DailySeries {
 
             date_field_name: self.date_field_name.clone(),
             value_field_names: self.value_field_names.clone(),
+            index: Default::default(),
         })
     }
 
+    /// Columns are renamed to the value field names, and rows are sorted by date
+    pub fn to_dataframe(&self, date_column_name: &str) -> VfResult<DataFrame> {
+        let mut field_names: Vec<(&String, &String)> = self.value_field_names.iter().collect();
+        field_names.sort();
+
+        let mut columns: Vec<Expr> = vec![col(&self.date_field_name).alias(date_column_name)];
+        columns.extend(
+            field_names
+                .into_iter()
+                .map(|(field_name, column_name)| col(column_name).alias(field_name)),
+        );
+
+        let df = self
+            .df
+            .clone()
+            .lazy()
+            .select(columns)
+            .sort([date_column_name], Default::default())
+            .collect()?;
+
+        Ok(df)
+    }
+
+    /// Point-in-time series of reports, with one row for each announcement of a period, dated
+    /// by the announcement, or by the statutory deadline if its announcement date is missing or
+    /// not after the period ends. Restatements are kept as later rows of the period, and reports
+    /// as known on a date are looked up by `as_of`
+    pub fn to_point_in_time(&self, period_field_name: &str) -> VfResult<Self> {
+        let mut rows: Vec<(usize, NaiveDate, NaiveDate)> = self
+            .report_rows(period_field_name)?
+            .into_iter()
+            .map(|(i, date, period)| {
+                let date = date
+                    .filter(|date| date > &period)
+                    .unwrap_or_else(|| datetime::report_deadline(&period));
+                (i, date, period)
+            })
+            .collect();
+        // Stable, so the last of duplicate announcements is kept
+        rows.sort_by_key(|(_, date, period)| (*date, *period));
+
+        let mut pit_rows: Vec<(usize, NaiveDate)> = vec![];
+        let mut last_key: Option<(NaiveDate, NaiveDate)> = None;
+        for (i, date, period) in rows {
+            if last_key == Some((date, period)) {
+                pit_rows.pop();
+            }
+            last_key = Some((date, period));
+            pit_rows.push((i, date));
+        }
+
+        self.take_rows_with_dates(&pit_rows)
+    }
+
+    /// Reports as known on the date from a point-in-time series, i.e. the latest announcement
+    /// on or before the date of each period, dated by the first announcement of the period.
+    /// Periods first announced after a later one are dropped, so lookups of the latest reports
+    /// never go back to older periods
+    pub fn as_of(&self, period_field_name: &str, date: &NaiveDate) -> VfResult<Self> {
+        // Period to its first announcement date, and the row and date of the latest one
+        let mut periods: HashMap<NaiveDate, (NaiveDate, usize, NaiveDate)> = HashMap::new();
+        for (i, announce_date, period) in self.report_rows(period_field_name)? {
+            let Some(announce_date) = announce_date.filter(|d| d <= date) else {
+                continue;
+            };
+
+            periods
+                .entry(period)
+                .and_modify(|(first_date, latest_row, latest_date)| {
+                    *first_date = (*first_date).min(announce_date);
+                    if announce_date >= *latest_date {
+                        *latest_row = i;
+                        *latest_date = announce_date;
+                    }
+                })
+                .or_insert((announce_date, i, announce_date));
+        }
+
+        let mut rows: Vec<(NaiveDate, NaiveDate, usize)> = periods
+            .into_iter()
+            .map(|(period, (first_date, latest_row, _))| (first_date, period, latest_row))
+            .collect();
+        rows.sort_unstable();
+
+        let mut as_of_rows: Vec<(usize, NaiveDate)> = vec![];
+        let mut latest_period: Option<NaiveDate> = None;
+        for (first_date, period, i) in rows {
+            if latest_period.is_some_and(|latest_period| period < latest_period) {
+                continue;
+            }
+            latest_period = Some(period);
+
+            as_of_rows.push((i, first_date));
+        }
+
+        self.take_rows_with_dates(&as_of_rows)
+    }
+
     pub fn subset_by_columns(
         &self,
         select_field_names: &HashMap<String, String>,
@@ -440,9 +469,78 @@ impl DailySeries {
 
             date_field_name: self.date_field_name.clone(),
             value_field_names: select_field_names.clone(),
+            index: Default::default(),
         })
     }
 
+    /// Rows in the order, with their dates replaced
+    fn take_rows_with_dates(&self, rows: &[(usize, NaiveDate)]) -> VfResult<Self> {
+        let idx = IdxCa::from_vec(
+            "idx".into(),
+            rows.iter().map(|(i, _)| *i as IdxSize).collect(),
+        );
+        let mut df = self.df.take(&idx)?;
+        df.with_column(
+            Column::new(
+                self.date_field_name.as_str().into(),
+                rows.iter()
+                    .map(|(_, date)| date.to_epoch_days())
+                    .collect::<Vec<i32>>(),
+            )
+            .cast(&DataType::Date)?,
+        )?;
+
+        Ok(Self {
+            df,
+
+            date_field_name: self.date_field_name.clone(),
+            value_field_names: self.value_field_names.clone(),
+            index: Default::default(),
+        })
+    }
+
+    fn index(&self) -> &DateIndex {
+        self.index
+            .get_or_init(|| DateIndex::new(&self.df, &self.date_field_name))
+    }
+
+    /// Rows of reports as (row, date, period), rows without a valid period are skipped
+    fn report_rows(
+        &self,
+        period_field_name: &str,
+    ) -> VfResult<Vec<(usize, Option<NaiveDate>, NaiveDate)>> {
+        let origin_period_field_name =
+            self.value_field_names
+                .get(period_field_name)
+                .ok_or_else(|| VfError::Invalid {
+                    code: "INVALID_FIELD",
+                    message: format!("No period field '{period_field_name}'"),
+                })?;
+
+        let col_date = self
+            .df
+            .column(&self.date_field_name)?
+            .cast(&DataType::Int32)?;
+        let col_period = self
+            .df
+            .column(origin_period_field_name)?
+            .cast(&DataType::String)?;
+
+        let rows = col_date
+            .i32()?
+            .into_iter()
+            .zip(col_period.str()?)
+            .enumerate()
+            .filter_map(|(i, (days, period))| {
+                let period = datetime::date_from_str(period?).ok()?;
+                let date = days.and_then(NaiveDate::from_epoch_days);
+                Some((i, date, period))
+            })
+            .collect();
+
+        Ok(rows)
+    }
+
     fn from_json_items(
         json_items: &[Value],
         date_field_name: &str,
@@ -515,6 +613,425 @@ impl DailySeries {
             df,
             date_field_name: date_field_name.to_string(),
             value_field_names: value_field_names.clone(),
+            index: Default::default(),
         })
     }
 }
+
+/// Harness to detect look-ahead of report series
+#[cfg(test)]
+impl DailySeries {
+    /// First announcement date of each report period, ignoring those not after the period ends
+    pub fn announce_dates(&self, period_field_name: &str) -> HashMap<NaiveDate, NaiveDate> {
+        let mut announce_dates: HashMap<NaiveDate, NaiveDate> = HashMap::new();
+
+        for (_, date, period) in self.report_rows(period_field_name).unwrap_or_default() {
+            if let Some(date) = date.filter(|date| date > &period) {
+                announce_dates
+                    .entry(period)
+                    .and_modify(|d| *d = (*d).min(date))
+                    .or_insert(date);
+            }
+        }
+
+        announce_dates
+    }
+
+    /// Rows of reports as (date, period) known before they could be, i.e. dated not after the
+    /// period ends, or before the first announcement of the period by `announce_dates`, which
+    /// may come from another data source
+    pub fn look_ahead_rows(
+        &self,
+        period_field_name: &str,
+        announce_dates: &HashMap<NaiveDate, NaiveDate>,
+    ) -> Vec<(NaiveDate, NaiveDate)> {
+        self.report_rows(period_field_name)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(_, date, period)| {
+                let date = date?;
+                let known_date = announce_dates.get(&period).unwrap_or(&period);
+                (date <= period || &date < known_date).then_some((date, period))
+            })
+            .collect()
+    }
+
+    /// Periods of the latest reports by as-of lookups on each day of the date range, which must
+    /// be non-decreasing as reports are never unknown again
+    pub fn latest_periods(
+        &self,
+        period_field_name: &str,
+        field_name: &str,
+        date_from: &NaiveDate,
+        date_to: &NaiveDate,
+    ) -> Vec<(NaiveDate, NaiveDate)> {
+        date_from
+            .iter_days()
+            .take_while(|date| date <= date_to)
+            .filter_map(|date| {
+                let (_, _, label) = self
+                    .as_of(period_field_name, &date)
+                    .ok()?
+                    .get_latest_values_with_label::<f64>(
+                        &date,
+                        true,
+                        field_name,
+                        period_field_name,
+                        1,
+                    )
+                    .pop()?;
+                let period = datetime::date_from_str(&label?).ok()?;
+                Some((date, period))
+            })
+            .collect()
+    }
+
+    /// Values of the period's report by as-of lookups on each day of the date range, whose
+    /// changes must be on announcement dates of the period
+    pub fn period_values(
+        &self,
+        period_field_name: &str,
+        field_name: &str,
+        period: &NaiveDate,
+        date_from: &NaiveDate,
+        date_to: &NaiveDate,
+    ) -> Vec<(NaiveDate, f64)> {
+        date_from
+            .iter_days()
+            .take_while(|date| date <= date_to)
+            .filter_map(|date| {
+                let value = self
+                    .as_of(period_field_name, &date)
+                    .ok()?
+                    .get_latest_values_with_label::<f64>(
+                        &date,
+                        true,
+                        field_name,
+                        period_field_name,
+                        u32::MAX,
+                    )
+                    .into_iter()
+                    .find(|(_, _, label)| {
+                        label
+                            .as_deref()
+                            .and_then(|label| datetime::date_from_str(label).ok())
+                            == Some(*period)
+                    })
+                    .map(|(_, value, _)| value)?;
+                Some((date, value))
+            })
+            .collect()
+    }
+}
+
+/// Rows sorted by date with numeric columns as vectors, for lookups by binary search, or in O(1)
+/// when dates are looked up in increasing order as in backtests
+#[derive(Debug, Default)]
+struct DateIndex {
+    dates: Vec<NaiveDate>,
+    /// Rows in the dataframe of the dates
+    rows: Vec<usize>,
+    /// Values of numeric columns, NaN if null
+    columns: HashMap<String, Vec<f64>>,
+    /// Position of the last lookup
+    cursor: AtomicUsize,
+}
+
+impl DateIndex {
+    fn new(df: &DataFrame, date_field_name: &str) -> Self {
+        let Ok(col_date) = df
+            .column(date_field_name)
+            .and_then(|col| col.cast(&DataType::Int32))
+        else {
+            return Self::default();
+        };
+        let Ok(days) = col_date.i32() else {
+            return Self::default();
+        };
+
+        let mut rows: Vec<(usize, NaiveDate)> = days
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, d)| d.and_then(NaiveDate::from_epoch_days).map(|date| (i, date)))
+            .collect();
+        rows.sort_by_key(|(_, date)| *date);
+
+        let mut columns: HashMap<String, Vec<f64>> = HashMap::new();
+        for col in df.get_columns() {
+            if col.name().as_str() == date_field_name || !col.dtype().is_primitive_numeric() {
+                continue;
+            }
+
+            if let Ok(col_f64) = col.cast(&DataType::Float64)
+                && let Ok(values) = col_f64.f64()
+            {
+                let values: Vec<f64> = rows
+                    .iter()
+                    .map(|(i, _)| values.get(*i).unwrap_or(f64::NAN))
+                    .collect();
+                columns.insert(col.name().to_string(), values);
+            }
+        }
+
+        Self {
+            dates: rows.iter().map(|(_, date)| *date).collect(),
+            rows: rows.into_iter().map(|(i, _)| i).collect(),
+            columns,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// Estimated heap bytes of the index of the dataframe, whether built or not
+    fn estimated_size(df: &DataFrame, date_field_name: &str) -> usize {
+        let numeric_columns: Vec<&Column> = df
+            .get_columns()
+            .iter()
+            .filter(|col| {
+                col.name().as_str() != date_field_name && col.dtype().is_primitive_numeric()
+            })
+            .collect();
+
+        df.height()
+            * (size_of::<NaiveDate>()
+                + size_of::<usize>()
+                + numeric_columns.len() * size_of::<f64>())
+            + numeric_columns
+                .iter()
+                .map(|col| size_of::<String>() + col.name().len())
+                .sum::<usize>()
+    }
+
+    /// Number of rows before the date, or on it if `include_today`
+    fn position(&self, date: &NaiveDate, include_today: bool) -> usize {
+        let is_before = |d: &NaiveDate| if include_today { d <= date } else { d < date };
+        let is_position = |p: usize| {
+            (p == 0 || is_before(&self.dates[p - 1]))
+                && (p == self.dates.len() || !is_before(&self.dates[p]))
+        };
+
+        let cursor = self.cursor.load(Ordering::Relaxed);
+        let position = if cursor <= self.dates.len() && is_position(cursor) {
+            cursor
+        } else if cursor < self.dates.len() && is_position(cursor + 1) {
+            cursor + 1
+        } else {
+            self.dates.partition_point(is_before)
+        };
+        self.cursor.store(position, Ordering::Relaxed);
+
+        position
+    }
+
+    fn values(&self, column_name: &str) -> Option<&Vec<f64>> {
+        self.columns.get(column_name)
+    }
+
+    /// Positions of the latest `count` finite values before the date, or on it if
+    /// `include_today`, sorted by date
+    fn latest_positions(
+        &self,
+        values: &[f64],
+        date: &NaiveDate,
+        include_today: bool,
+        count: u32,
+    ) -> Vec<usize> {
+        let end = self.position(date, include_today);
+        let mut positions: Vec<usize> = (0..end)
+            .rev()
+            .filter(|i| values[*i].is_finite())
+            .take(count as usize)
+            .collect();
+        positions.reverse();
+
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_index() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates: Vec<NaiveDate> = (0..100)
+            .rev()
+            .filter(|i| i % 7 != 3)
+            .map(|i| start + Duration::days(i))
+            .collect();
+        let values: Vec<Option<f64>> = (0..dates.len())
+            .map(|i| match i % 5 {
+                0 => None,
+                1 => Some(f64::NAN),
+                _ => Some(i as f64),
+            })
+            .collect();
+        let labels: Vec<String> = (0..dates.len()).map(|i| format!("#{i}")).collect();
+
+        // Rows of finite values sorted by date, as the reference of lookups
+        let mut rows: Vec<(NaiveDate, f64, Option<String>)> = dates
+            .iter()
+            .zip(&values)
+            .zip(&labels)
+            .filter_map(|((date, value), label)| {
+                value
+                    .filter(|v| v.is_finite())
+                    .map(|v| (*date, v, Some(label.clone())))
+            })
+            .collect();
+        rows.sort_by_key(|(date, _, _)| *date);
+        let latest_rows = |date: &NaiveDate, include_today: bool, count: usize| {
+            let mut latest: Vec<(NaiveDate, f64, Option<String>)> = rows
+                .iter()
+                .rev()
+                .filter(|(d, _, _)| if include_today { d <= date } else { d < date })
+                .take(count)
+                .cloned()
+                .collect();
+            latest.reverse();
+            latest
+        };
+        let latest_value = |date: &NaiveDate, max_stale_days: i64, include_today: bool| {
+            latest_rows(date, include_today, 1)
+                .into_iter()
+                .map(|(d, v, _)| (d, v))
+                .find(|(d, _)| *date - *d <= Duration::days(max_stale_days))
+        };
+
+        let df = df!(
+            "date" => dates,
+            "close" => values,
+            "label" => labels,
+        )
+        .unwrap();
+        let series = DailySeries::from_dataframe(df, "date").unwrap();
+
+        for i in -5..110 {
+            let date = start + Duration::days(i);
+            for include_today in [false, true] {
+                assert_eq!(
+                    series.get_latest_value::<f64>(&date, 3, include_today, "close"),
+                    latest_value(&date, 3, include_today),
+                );
+                assert_eq!(
+                    series.get_latest_values::<f64>(&date, include_today, "close", 4),
+                    latest_rows(&date, include_today, 4)
+                        .into_iter()
+                        .map(|(d, v, _)| (d, v))
+                        .collect::<Vec<_>>(),
+                );
+                assert_eq!(
+                    series.get_latest_values_with_label::<f64>(
+                        &date,
+                        include_today,
+                        "close",
+                        "label",
+                        4
+                    ),
+                    latest_rows(&date, include_today, 4),
+                );
+            }
+            assert_eq!(
+                series.get_value::<f64>(&date, "close"),
+                rows.iter()
+                    .find(|(d, _, _)| *d == date)
+                    .map(|(d, v, _)| (*d, *v)),
+            );
+        }
+
+        // Lookups out of order fall back to binary search
+        for i in (-5..110).rev().step_by(3) {
+            let date = start + Duration::days(i);
+            assert_eq!(
+                series.get_latest_value::<f64>(&date, 10, true, "close"),
+                latest_value(&date, 10, true),
+            );
+        }
+
+        assert_eq!(series.get_value::<f64>(&start, "open"), None);
+        assert!(
+            series.estimated_size() >= series.len() * (size_of::<NaiveDate>() + size_of::<f64>())
+        );
+    }
+
+    #[test]
+    fn test_to_point_in_time() {
+        let date = |s: &str| datetime::date_from_str(s).unwrap();
+        let df = df!(
+            "date" => [
+                Some(date("2023-03-20")),
+                Some(date("2023-03-31")), // Keyed on the period
+                Some(date("2023-08-20")),
+                Some(date("2023-07-01")), // Restatement of an older period
+                Some(date("2023-09-15")), // Restatement of the latest period
+                None,
+                Some(date("1970-01-01")), // Unparsable
+            ],
+            "period" => [
+                "20221231", "20230331", "20230630", "20221231", "20230630", "20230930", "20231231",
+            ],
+            "eps" => [1.0, 0.3, 0.6, 1.1, 0.65, 0.9, 1.2],
+        )
+        .unwrap();
+        let raw = DailySeries::from_dataframe(df, "date").unwrap();
+        let pit = raw.to_point_in_time("period").unwrap();
+
+        // Restatements are kept as later announcements of their periods
+        assert_eq!(
+            pit.get_values::<f64>("eps"),
+            vec![
+                (date("2023-03-20"), 1.0),
+                (date("2023-04-30"), 0.3),
+                (date("2023-07-01"), 1.1),
+                (date("2023-08-20"), 0.6),
+                (date("2023-09-15"), 0.65),
+                (date("2023-10-31"), 0.9),
+                (date("2024-04-30"), 1.2),
+            ]
+        );
+
+        // Restated figures replace the original ones as of the restatement dates, dated by the
+        // first announcements of their periods
+        assert_eq!(
+            pit.as_of("period", &date("2023-09-30"))
+                .unwrap()
+                .get_values::<f64>("eps"),
+            vec![
+                (date("2023-03-20"), 1.1),
+                (date("2023-04-30"), 0.3),
+                (date("2023-08-20"), 0.65),
+            ]
+        );
+
+        let announce_dates = raw.announce_dates("period");
+        assert_eq!(
+            raw.look_ahead_rows("period", &announce_dates),
+            vec![
+                (date("2023-03-31"), date("2023-03-31")),
+                (date("1970-01-01"), date("2023-12-31")),
+            ]
+        );
+        assert!(pit.look_ahead_rows("period", &announce_dates).is_empty());
+
+        let is_non_decreasing =
+            |periods: &[(NaiveDate, NaiveDate)]| periods.windows(2).all(|w| w[0].1 <= w[1].1);
+        let is_known =
+            |periods: &[(NaiveDate, NaiveDate)]| periods.iter().all(|(date, period)| period < date);
+        let (date_from, date_to) = (date("2023-01-01"), date("2023-12-31"));
+        let raw_periods = raw.latest_periods("period", "eps", &date_from, &date_to);
+        assert!(!is_known(&raw_periods));
+        let pit_periods = pit.latest_periods("period", "eps", &date_from, &date_to);
+        assert!(is_non_decreasing(&pit_periods) && is_known(&pit_periods));
+
+        // The original figure until the restatement, and the restated one since then
+        let period_values =
+            pit.period_values("period", "eps", &date("2022-12-31"), &date_from, &date_to);
+        assert_eq!(period_values.first(), Some(&(date("2023-03-20"), 1.0)));
+        assert!(
+            period_values
+                .iter()
+                .all(|(d, eps)| *eps == if *d < date("2023-07-01") { 1.0 } else { 1.1 })
+        );
+        assert_eq!(period_values.last(), Some(&(date("2023-12-31"), 1.1)));
+    }
+}
@@ -1,2 +1,487 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock},
+};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use dashmap::DashMap;
+
+use crate::{
+    CONFIG,
+    data::series::DailySeries,
+    error::{VfError, VfResult},
+    financial::{
+        bond::{ConvBondBasic, ConvBondCall, ConvBondClauses, ConvBondIssue},
+        economy::{InterestRate, MacroIndicator},
+        fund::FundBasic,
+        fx::Currency,
+        stock::{StockBasic, StockDetail, StockDividendAdjust},
+    },
+    ticker::{Ticker, TickersIndex},
+};
+
+pub mod limiter;
+pub mod local;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod qmt;
 pub mod tushare;
+
+pub const DEFAULT_DATA_SOURCES: [&str; 2] = ["tushare", "qmt"];
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Dataset {
+    ConvBondBasic,
+    ConvBondCalls,
+    ConvBondClauses,
+    ConvBondDaily,
+    ConvBondIssues,
+    ConvBondShares,
+    ConvBondsBasic,
+    DelistedStocks,
+    Etfs,
+    FundKline,
+    FundNav,
+    FundShare,
+    FxDaily,
+    HkStocksBasic,
+    IndexIndicators,
+    IndexKline,
+    IndexTickers,
+    InterestRates,
+    MacroIndicators,
+    SectorTickers,
+    StStocks,
+    StockBasic,
+    StockDetail,
+    StockDividends,
+    StockIndicators,
+    StockKline,
+    StockReportBalance,
+    StockReportCapital,
+    StockReportCashFlow,
+    StockReportIncome,
+    StockReportPershare,
+    StocksBasic,
+    TradeDates,
+}
+
+/// Provider of datasets, a dataset not supported by the provider is skipped when falling back
+#[async_trait]
+pub trait DataSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn conv_bond_basic(&self, _ticker: &Ticker) -> VfResult<ConvBondBasic> {
+        Err(unsupported(self.name(), Dataset::ConvBondBasic))
+    }
+
+    /// Announcements of redemptions, both forced and at maturity
+    async fn conv_bond_calls(&self, _ticker: &Ticker) -> VfResult<Vec<ConvBondCall>> {
+        Err(unsupported(self.name(), Dataset::ConvBondCalls))
+    }
+
+    async fn conv_bond_clauses(&self, _ticker: &Ticker) -> VfResult<ConvBondClauses> {
+        Err(unsupported(self.name(), Dataset::ConvBondClauses))
+    }
+
+    async fn conv_bond_daily(
+        &self,
+        _ticker: &Ticker,
+        _ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::ConvBondDaily))
+    }
+
+    async fn conv_bond_issues(
+        &self,
+        _date: &NaiveDate,
+        _lookback_months: u32,
+    ) -> VfResult<Vec<ConvBondIssue>> {
+        Err(unsupported(self.name(), Dataset::ConvBondIssues))
+    }
+
+    /// Remaining sizes by announcement dates of conversions
+    async fn conv_bond_shares(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::ConvBondShares))
+    }
+
+    async fn conv_bonds_basic(&self) -> VfResult<Vec<ConvBondBasic>> {
+        Err(unsupported(self.name(), Dataset::ConvBondsBasic))
+    }
+
+    async fn delisted_stocks(&self) -> VfResult<HashMap<Ticker, NaiveDate>> {
+        Err(unsupported(self.name(), Dataset::DelistedStocks))
+    }
+
+    async fn etfs(&self) -> VfResult<Vec<FundBasic>> {
+        Err(unsupported(self.name(), Dataset::Etfs))
+    }
+
+    /// Kline of the fund, adjusted backward for dividends and splits
+    async fn fund_kline(&self, _ticker: &Ticker, _ignore_cache: bool) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::FundKline))
+    }
+
+    async fn fund_nav(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::FundNav))
+    }
+
+    async fn fund_share(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::FundShare))
+    }
+
+    /// Daily rates of the currency per USD
+    async fn fx_daily(&self, _currency: &Currency) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::FxDaily))
+    }
+
+    async fn hk_stocks_basic(&self) -> VfResult<Vec<StockBasic>> {
+        Err(unsupported(self.name(), Dataset::HkStocksBasic))
+    }
+
+    async fn index_indicators(&self, _index: &TickersIndex) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::IndexIndicators))
+    }
+
+    /// Prices of the index itself, not adjusted as indexes have no dividends
+    async fn index_kline(
+        &self,
+        _index: &TickersIndex,
+        _ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::IndexKline))
+    }
+
+    async fn index_tickers(
+        &self,
+        _index: &TickersIndex,
+        _date: &NaiveDate,
+    ) -> VfResult<Vec<Ticker>> {
+        Err(unsupported(self.name(), Dataset::IndexTickers))
+    }
+
+    async fn interest_rate(&self, _rate: &InterestRate) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::InterestRates))
+    }
+
+    async fn macro_indicator(&self, _indicator: &MacroIndicator) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::MacroIndicators))
+    }
+
+    async fn sector_tickers(&self, _sector_prefix: &str) -> VfResult<HashMap<Ticker, String>> {
+        Err(unsupported(self.name(), Dataset::SectorTickers))
+    }
+
+    async fn st_stocks(&self, _date: &NaiveDate, _lookback_days: u64) -> VfResult<Vec<Ticker>> {
+        Err(unsupported(self.name(), Dataset::StStocks))
+    }
+
+    async fn stock_basic(&self, _ticker: &Ticker) -> VfResult<StockBasic> {
+        Err(unsupported(self.name(), Dataset::StockBasic))
+    }
+
+    async fn stock_detail(&self, _ticker: &Ticker) -> VfResult<StockDetail> {
+        Err(unsupported(self.name(), Dataset::StockDetail))
+    }
+
+    async fn stock_dividends(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockDividends))
+    }
+
+    async fn stock_indicators(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockIndicators))
+    }
+
+    async fn stock_kline(
+        &self,
+        _ticker: &Ticker,
+        _adjust: StockDividendAdjust,
+        _ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockKline))
+    }
+
+    async fn stock_report_balance(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockReportBalance))
+    }
+
+    async fn stock_report_capital(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockReportCapital))
+    }
+
+    async fn stock_report_cash_flow(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockReportCashFlow))
+    }
+
+    async fn stock_report_income(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockReportIncome))
+    }
+
+    async fn stock_report_pershare(&self, _ticker: &Ticker) -> VfResult<DailySeries> {
+        Err(unsupported(self.name(), Dataset::StockReportPershare))
+    }
+
+    async fn stocks_basic(&self) -> VfResult<Vec<StockBasic>> {
+        Err(unsupported(self.name(), Dataset::StocksBasic))
+    }
+
+    /// Trading days of the exchange in MIC, e.g. `XSHG`
+    async fn trade_dates(&self, _exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+        Err(unsupported(self.name(), Dataset::TradeDates))
+    }
+}
+
+/// Data that can be empty, an empty result falls back to the next data source
+pub trait DatasetValue {
+    fn is_empty_data(&self) -> bool {
+        false
+    }
+}
+
+impl DatasetValue for ConvBondBasic {}
+impl DatasetValue for ConvBondClauses {}
+impl DatasetValue for FundBasic {}
+impl DatasetValue for StockBasic {}
+impl DatasetValue for StockDetail {}
+
+impl DatasetValue for DailySeries {
+    fn is_empty_data(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> DatasetValue for HashMap<K, V> {
+    fn is_empty_data(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<T> DatasetValue for HashSet<T> {
+    fn is_empty_data(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<T> DatasetValue for Vec<T> {
+    fn is_empty_data(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+pub fn get_data_source(name: &str) -> VfResult<Arc<dyn DataSource>> {
+    DATA_SOURCES
+        .get(&name.to_lowercase())
+        .map(|ds| ds.clone())
+        .ok_or(VfError::Invalid {
+            code: "INVALID_DATA_SOURCE",
+            message: format!("No data source '{name}'"),
+        })
+}
+
+/// Data sources of the dataset in priority order, configured per dataset or globally
+pub async fn get_data_sources(dataset: Dataset) -> VfResult<Vec<Arc<dyn DataSource>>> {
+    let names: Vec<String> = {
+        let config = CONFIG.read().await;
+        config
+            .dataset_sources
+            .get(&dataset.to_string())
+            .filter(|names| !names.is_empty())
+            .unwrap_or(&config.data_sources)
+            .clone()
+    };
+
+    names.iter().map(|name| get_data_source(name)).collect()
+}
+
+/// Try each data source of the dataset in priority order until one returns non-empty data,
+/// empty data is returned only if no data source has any
+pub async fn fetch_with_fallback<T, F, Fut>(dataset: Dataset, fetch: F) -> VfResult<T>
+where
+    T: DatasetValue,
+    F: Fn(Arc<dyn DataSource>) -> Fut,
+    Fut: Future<Output = VfResult<T>>,
+{
+    fetch_with_data_sources(dataset, get_data_sources(dataset).await?, fetch).await
+}
+
+/// Try each of the data sources in order as `fetch_with_fallback` does
+pub async fn fetch_with_data_sources<T, F, Fut>(
+    dataset: Dataset,
+    data_sources: Vec<Arc<dyn DataSource>>,
+    fetch: F,
+) -> VfResult<T>
+where
+    T: DatasetValue,
+    F: Fn(Arc<dyn DataSource>) -> Fut,
+    Fut: Future<Output = VfResult<T>>,
+{
+    let mut empty_result: Option<T> = None;
+    let mut errors: Vec<VfError> = vec![];
+
+    for ds in data_sources {
+        match fetch(ds).await {
+            Ok(result) => {
+                if !result.is_empty_data() {
+                    return Ok(result);
+                }
+
+                if empty_result.is_none() {
+                    empty_result = Some(result);
+                }
+            }
+            Err(VfError::Invalid {
+                code: "UNSUPPORTED_DATASET",
+                ..
+            }) => {}
+            Err(err) => {
+                errors.push(err);
+            }
+        }
+    }
+
+    if let Some(result) = empty_result {
+        Ok(result)
+    } else if errors.len() == 1
+        && let Some(err) = errors.pop()
+    {
+        Err(err)
+    } else {
+        Err(VfError::NoData {
+            code: "NO_DATA",
+            message: format!(
+                "No {dataset} data from any data source{}",
+                errors
+                    .iter()
+                    .map(|err| format!(" {err}"))
+                    .collect::<Vec<_>>()
+                    .join(";")
+            ),
+        })
+    }
+}
+
+/// Register a data source, which replaces the one with the same name
+#[allow(dead_code)]
+pub fn register_data_source(ds: Arc<dyn DataSource>) {
+    DATA_SOURCES.insert(ds.name().to_string(), ds);
+}
+
+static DATA_SOURCES: LazyLock<DashMap<String, Arc<dyn DataSource>>> = LazyLock::new(|| {
+    let data_sources: DashMap<String, Arc<dyn DataSource>> = DashMap::new();
+    data_sources.insert("local".to_string(), Arc::new(local::Local::default()));
+    data_sources.insert("qmt".to_string(), Arc::new(qmt::Qmt));
+    data_sources.insert("tushare".to_string(), Arc::new(tushare::Tushare));
+    data_sources
+});
+
+fn unsupported(ds_name: &str, dataset: Dataset) -> VfError {
+    VfError::Invalid {
+        code: "UNSUPPORTED_DATASET",
+        message: format!("Dataset {dataset} is not supported by {ds_name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubDataSource {
+        name: &'static str,
+        tickers: Vec<Ticker>,
+    }
+
+    #[async_trait]
+    impl DataSource for StubDataSource {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn conv_bond_issues(
+            &self,
+            _date: &NaiveDate,
+            _lookback_months: u32,
+        ) -> VfResult<Vec<ConvBondIssue>> {
+            Ok(self
+                .tickers
+                .iter()
+                .map(|ticker| ConvBondIssue {
+                    ticker: ticker.clone(),
+                    name: self.name.to_string(),
+                    issue_size: None,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_fallback() {
+        let ticker = Ticker::from_tushare_str("113050.SH").unwrap();
+
+        register_data_source(Arc::new(StubDataSource {
+            name: "stub_empty",
+            tickers: vec![],
+        }));
+        register_data_source(Arc::new(StubDataSource {
+            name: "stub_full",
+            tickers: vec![ticker.clone()],
+        }));
+
+        let dataset = Dataset::ConvBondIssues;
+        let fetch =
+            |ds: Arc<dyn DataSource>| async move { ds.conv_bond_issues(&NaiveDate::MIN, 1).await };
+        let data_sources = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| get_data_source(name).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let result = fetch_with_data_sources(
+            dataset,
+            data_sources(&["stub_empty", "qmt", "stub_full"]),
+            fetch,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "stub_full");
+        assert_eq!(result[0].ticker, ticker);
+
+        let result = fetch_with_data_sources(dataset, data_sources(&["stub_empty"]), fetch)
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+
+        let result = fetch_with_data_sources(dataset, data_sources(&["qmt"]), fetch).await;
+        assert!(matches!(result, Err(VfError::NoData { .. })));
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let mut config = crate::Config::default();
+        assert!(config.validate().is_ok());
+
+        config.data_sources.push("tushere".to_string());
+        assert!(config.validate().is_err());
+
+        config.data_sources.pop();
+        config
+            .dataset_sources
+            .insert("conv_bond_issues".to_string(), vec!["qtm".to_string()]);
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Rate limiting of data source APIs
+//!
+//! Each source has a token bucket refilled at its quota per minute, with a burst of a few
+//! seconds of quota, and a semaphore bounding requests in flight. Duplicate requests in flight
+//! (i.e. of the same cache key) are coalesced so only the first one calls the API.
+
+use std::{
+    future::Future,
+    sync::{Arc, LazyLock},
+};
+
+use dashmap::DashMap;
+use tokio::{
+    sync::{Mutex, OnceCell, Semaphore, SemaphorePermit},
+    time::{Duration, Instant, sleep},
+};
+
+use crate::error::VfResult;
+
+/// Seconds of quota that can be requested at once
+const BURST_SECS: f64 = 5.0;
+
+/// Max requests in flight per data source, also how many tickers callers fetch at once
+pub const MAX_CONCURRENCY: usize = 4;
+
+pub static QMT_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(MAX_CONCURRENCY));
+
+pub static TUSHARE_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(MAX_CONCURRENCY));
+
+/// Requests per minute of Tushare by points, see https://tushare.pro/document/1?doc_id=290,
+/// no limit for 0 points as it's not a tier but e.g. a private proxy
+pub fn tushare_rate_limit(points: u32) -> u32 {
+    match points {
+        0 => 0,
+        1..2000 => 50,
+        2000..5000 => 200,
+        5000..10000 => 500,
+        _ => 1000,
+    }
+}
+
+pub struct RateLimiter {
+    semaphore: Semaphore,
+    bucket: Mutex<Option<(f64, Instant)>>,
+    in_flight: DashMap<String, Arc<OnceCell<Vec<u8>>>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(max_concurrency.max(1)),
+            bucket: Mutex::new(None),
+            in_flight: DashMap::new(),
+        }
+    }
+
+    /// Wait for a slot of requests in flight and a token of the rate per minute, 0 for no rate
+    /// limit, the slot is released when the permit is dropped
+    pub async fn acquire(&self, rate_per_minute: u32) -> SemaphorePermit<'_> {
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("Semaphore of rate limiter is never closed");
+
+        if rate_per_minute > 0 {
+            let rate_per_sec = rate_per_minute as f64 / 60.0;
+            let capacity = (rate_per_sec * BURST_SECS).max(1.0);
+
+            let wait_secs = {
+                let mut bucket = self.bucket.lock().await;
+
+                let now = Instant::now();
+                let (tokens, last_time) = bucket.unwrap_or((capacity, now));
+                let tokens = (tokens
+                    + now.saturating_duration_since(last_time).as_secs_f64() * rate_per_sec)
+                    .min(capacity)
+                    - 1.0;
+                // Tokens can go below zero, as reserved by requests waiting for them
+                *bucket = Some((tokens, now));
+
+                if tokens < 0.0 {
+                    -tokens / rate_per_sec
+                } else {
+                    0.0
+                }
+            };
+
+            if wait_secs > 0.0 {
+                sleep(Duration::from_secs_f64(wait_secs)).await;
+            }
+        }
+
+        permit
+    }
+
+    /// Run the request unless the same key is in flight, whose response is shared instead
+    pub async fn coalesce<F, Fut>(&self, key: &str, request: F) -> VfResult<Vec<u8>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = VfResult<Vec<u8>>>,
+    {
+        let (cell, is_first) = match self.in_flight.entry(key.to_string()) {
+            dashmap::Entry::Occupied(entry) => (entry.get().clone(), false),
+            dashmap::Entry::Vacant(entry) => {
+                (entry.insert(Arc::new(OnceCell::new())).clone(), true)
+            }
+        };
+
+        // Removes the entry even if the first caller is cancelled, waiters still hold the cell
+        let _guard = is_first.then(|| InFlightGuard {
+            in_flight: &self.in_flight,
+            key,
+            cell: &cell,
+        });
+
+        // A failed request is not shared, the next one waiting on the cell runs its own request
+        cell.get_or_try_init(request).await.cloned()
+    }
+}
+
+struct InFlightGuard<'a> {
+    in_flight: &'a DashMap<String, Arc<OnceCell<Vec<u8>>>>,
+    key: &'a str,
+    cell: &'a Arc<OnceCell<Vec<u8>>>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight
+            .remove_if(self.key, |_, cell| Arc::ptr_eq(cell, self.cell));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_tushare_rate_limit() {
+        assert_eq!(tushare_rate_limit(0), 0);
+        assert_eq!(tushare_rate_limit(120), 50);
+        assert_eq!(tushare_rate_limit(2000), 200);
+        assert_eq!(tushare_rate_limit(5000), 500);
+        assert_eq!(tushare_rate_limit(15000), 1000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(2);
+
+        // A burst of 5 seconds at 60 per minute, then 1 per second
+        let start = Instant::now();
+        for _ in 0..5 {
+            let _permit = limiter.acquire(60).await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        for _ in 0..3 {
+            let _permit = limiter.acquire(60).await;
+        }
+        assert!(start.elapsed() >= Duration::from_secs(3));
+
+        let _permit1 = limiter.acquire(0).await;
+        let _permit2 = limiter.acquire(0).await;
+        assert!(limiter.semaphore.try_acquire().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_coalesce() {
+        let limiter = RateLimiter::new(2);
+        let request_count = AtomicUsize::new(0);
+
+        let request = || async {
+            request_count.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(50)).await;
+            Ok(vec![1, 2, 3])
+        };
+        let (r1, r2, r3) = tokio::join!(
+            limiter.coalesce("a", request),
+            limiter.coalesce("a", request),
+            limiter.coalesce("b", request),
+        );
+
+        assert_eq!(r1.unwrap(), vec![1, 2, 3]);
+        assert_eq!(r2.unwrap(), vec![1, 2, 3]);
+        assert_eq!(r3.unwrap(), vec![1, 2, 3]);
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+        assert!(limiter.in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_cancelled() {
+        let limiter = RateLimiter::new(2);
+
+        let request = || async {
+            sleep(Duration::from_secs(60)).await;
+            Ok(vec![])
+        };
+        let result =
+            tokio::time::timeout(Duration::from_millis(10), limiter.coalesce("a", request)).await;
+        assert!(result.is_err());
+        assert!(limiter.in_flight.is_empty());
+
+        let result = limiter.coalesce("a", || async { Ok(vec![1]) }).await;
+        assert_eq!(result.unwrap(), vec![1]);
+    }
+}
@@ -0,0 +1,658 @@
+//! Data files in the local data directory, so backtests fetch less from QMT and Tushare, other
+//! datasets like stock basics, ST, sectors and conv bond issues are not supported
+//!
+//! Each file is either `<name>.parquet` or `<name>.csv` (parquet is preferred when both exist),
+//! with a `date` column in `YYYY-MM-DD` and value columns named as the dataset fields:
+//!
+//! ```text
+//! trade_dates/<exchange>                     date
+//! conv_bond_daily/<ticker>                   date, Open, Close, ..., ConversionPremium
+//! fund_kline/<ticker>                        date, Open, Close, High, Low, Volume
+//! fund_nav/<ticker>                          date, Unit, Accumulated, Adjusted
+//! fund_share/<ticker>                        date, Share
+//! index_indicators/<index>                   date, Pb, Pe, PeTtm, TurnoverRate
+//! index_kline/<index>                        date, Open, Close, High, Low, Volume
+//! index_tickers/<index>                      date, ticker
+//! stock_dividends/<ticker>                   date, <StockDividendField>...
+//! stock_indicators/<ticker>                  date, <StockIndicatorField>...
+//! stock_kline/<no|forward|backward>/<ticker> date, Open, Close, High, Low, Volume
+//! stock_report_balance/<ticker>              date, <StockReportBalanceField>...
+//! stock_report_capital/<ticker>              date, <StockReportCapitalField>...
+//! stock_report_cash_flow/<ticker>            date, <StockReportCashFlowField>...
+//! stock_report_income/<ticker>               date, <StockReportIncomeField>...
+//! stock_report_pershare/<ticker>             date, <StockReportPershareField>...
+//! ```
+//!
+//! Tickers are like `600000.XSHG` and indexes are Tushare codes like `000300.SH`. Index tickers
+//! of a date are the latest ones listed before the date. Exchanges of trade dates are in MIC like
+//! `XSHG`, and a single `trade_dates` file of older layouts is read as the one of `XSHG`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs::{File, create_dir_all, remove_file},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate};
+use polars::prelude::*;
+use serde_json::Value;
+
+use crate::{
+    CONFIG, cache,
+    data::series::DailySeries,
+    ds::{DataSource, Dataset},
+    error::{VfError, VfResult},
+    financial::{
+        bond::fetch_conv_bond_daily,
+        fund::{fetch_fund_kline, fetch_fund_nav, fetch_fund_share},
+        index::{fetch_index_indicators, fetch_index_kline, fetch_index_tickers},
+        market::fetch_trade_dates,
+        stock::{
+            StockDividendAdjust, fetch_stock_dividends, fetch_stock_indicators, fetch_stock_kline,
+            fetch_stock_report_balance, fetch_stock_report_capital, fetch_stock_report_cash_flow,
+            fetch_stock_report_income, fetch_stock_report_pershare,
+        },
+    },
+    ticker::{Ticker, TickerType, TickersIndex},
+};
+
+/// Data files in the configured local data directory, unless the directory is given
+#[derive(Default)]
+pub struct Local {
+    data_dir: Option<PathBuf>,
+}
+
+impl Local {
+    async fn data_dir(&self) -> PathBuf {
+        match &self.data_dir {
+            Some(data_dir) => data_dir.clone(),
+            None => local_data_dir().await,
+        }
+    }
+
+    async fn read_dataframe(&self, name: &str) -> VfResult<DataFrame> {
+        let dir = self.data_dir().await;
+
+        let parquet_path = dir.join(format!("{name}.{}", LocalFileFormat::Parquet));
+        if parquet_path.exists() {
+            let df = ParquetReader::new(File::open(parquet_path)?).finish()?;
+            return Ok(df);
+        }
+
+        let csv_path = dir.join(format!("{name}.{}", LocalFileFormat::Csv));
+        if csv_path.exists() {
+            let df = CsvReadOptions::default()
+                .with_has_header(true)
+                .map_parse_options(|options| options.with_try_parse_dates(true))
+                .try_into_reader_with_file_path(Some(csv_path))?
+                .finish()?;
+            return Ok(df);
+        }
+
+        Err(VfError::NoData {
+            code: "NO_DATA",
+            message: format!("No local data '{name}' in '{}'", dir.to_string_lossy()),
+        })
+    }
+
+    async fn read_series(&self, name: &str) -> VfResult<DailySeries> {
+        let df = self.read_dataframe(name).await?;
+        DailySeries::from_dataframe(df, DATE_COLUMN)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+pub enum LocalFileFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+#[async_trait]
+impl DataSource for Local {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn conv_bond_daily(&self, ticker: &Ticker, _ignore_cache: bool) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::ConvBondDaily))
+            .await
+    }
+
+    async fn fund_kline(&self, ticker: &Ticker, _ignore_cache: bool) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::FundKline))
+            .await
+    }
+
+    async fn fund_nav(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::FundNav))
+            .await
+    }
+
+    async fn fund_share(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::FundShare))
+            .await
+    }
+
+    async fn index_indicators(&self, index: &TickersIndex) -> VfResult<DailySeries> {
+        self.read_series(&format!(
+            "{}/{}",
+            Dataset::IndexIndicators,
+            index.to_tushare_code()
+        ))
+        .await
+    }
+
+    async fn index_kline(
+        &self,
+        index: &TickersIndex,
+        _ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        self.read_series(&format!(
+            "{}/{}",
+            Dataset::IndexKline,
+            index.to_tushare_code()
+        ))
+        .await
+    }
+
+    async fn index_tickers(&self, index: &TickersIndex, date: &NaiveDate) -> VfResult<Vec<Ticker>> {
+        let df = self
+            .read_dataframe(&format!(
+                "{}/{}",
+                Dataset::IndexTickers,
+                index.to_tushare_code()
+            ))
+            .await?;
+
+        let mut hist_tickers: BTreeMap<NaiveDate, Vec<Ticker>> = BTreeMap::new();
+
+        let (col_date, col_ticker) = (df.column(DATE_COLUMN)?, df.column(TICKER_COLUMN)?);
+        for i in 0..df.height() {
+            if let (Ok(cell_date), Ok(cell_ticker)) = (col_date.get(i), col_ticker.get(i)) {
+                if let (Some(ticker_date), Some(ticker)) = (
+                    cell_date
+                        .extract::<i32>()
+                        .and_then(NaiveDate::from_epoch_days),
+                    cell_ticker.get_str().and_then(|s| Ticker::from_str(s).ok()),
+                ) {
+                    if ticker_date < *date {
+                        hist_tickers.entry(ticker_date).or_default().push(ticker);
+                    }
+                }
+            }
+        }
+
+        Ok(hist_tickers
+            .pop_last()
+            .map(|(_, tickers)| tickers)
+            .unwrap_or_default())
+    }
+
+    async fn stock_dividends(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockDividends))
+            .await
+    }
+
+    async fn stock_indicators(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockIndicators))
+            .await
+    }
+
+    async fn stock_kline(
+        &self,
+        ticker: &Ticker,
+        adjust: StockDividendAdjust,
+        _ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        self.read_series(&stock_kline_file_name(ticker, &adjust))
+            .await
+    }
+
+    async fn stock_report_balance(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockReportBalance))
+            .await
+    }
+
+    async fn stock_report_capital(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockReportCapital))
+            .await
+    }
+
+    async fn stock_report_cash_flow(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockReportCashFlow))
+            .await
+    }
+
+    async fn stock_report_income(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockReportIncome))
+            .await
+    }
+
+    async fn stock_report_pershare(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        self.read_series(&format!("{}/{ticker}", Dataset::StockReportPershare))
+            .await
+    }
+
+    async fn trade_dates(&self, exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+        let series = match self
+            .read_series(&format!("{}/{exchange}", Dataset::TradeDates))
+            .await
+        {
+            Ok(series) => series,
+            Err(_) if exchange == "XSHG" => {
+                self.read_series(&Dataset::TradeDates.to_string()).await?
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(series.all_dates().into_iter().collect())
+    }
+}
+
+/// Export data of all cached QMT and Tushare requests into the local data layout,
+/// returns the number of exported files and the errors of data failed to export
+///
+/// Data are read from the cache only regardless of expiration, so nothing is fetched
+pub async fn export(output_dir: &Path, format: LocalFileFormat) -> VfResult<(usize, Vec<VfError>)> {
+    cache::cache_only(export_cached(output_dir, format)).await
+}
+
+async fn export_cached(
+    output_dir: &Path,
+    format: LocalFileFormat,
+) -> VfResult<(usize, Vec<VfError>)> {
+    let mut items: BTreeSet<(Dataset, String)> = BTreeSet::new();
+    let mut index_dates: BTreeMap<String, BTreeSet<NaiveDate>> = BTreeMap::new();
+
+    for key in cache::keys().await? {
+        if let Some((dataset, code, date)) = parse_cache_key(&key) {
+            if let (Dataset::IndexTickers, Some(date)) = (dataset, date) {
+                index_dates.entry(code).or_default().insert(date);
+            } else {
+                items.insert((dataset, code));
+            }
+        }
+    }
+
+    let mut dfs: Vec<(String, VfResult<DataFrame>)> = vec![];
+
+    for (dataset, code) in items {
+        let name = format!("{dataset}/{code}");
+
+        if dataset == Dataset::TradeDates {
+            let df = fetch_trade_dates(&code).await.and_then(|dates| {
+                let mut dates: Vec<NaiveDate> = dates.into_iter().collect();
+                dates.sort();
+
+                Ok(DataFrame::new(vec![date_column(&dates)])?)
+            });
+            dfs.push((name, df));
+        } else if matches!(dataset, Dataset::IndexIndicators | Dataset::IndexKline) {
+            if let Ok(index) = TickersIndex::from_str(&code) {
+                let series = if dataset == Dataset::IndexKline {
+                    fetch_index_kline(&index).await
+                } else {
+                    fetch_index_indicators(&index).await
+                };
+                dfs.push((name, series.and_then(|s| s.to_dataframe(DATE_COLUMN))));
+            }
+        } else if let Ok(ticker) = Ticker::from_str(&code) {
+            let series = match dataset {
+                Dataset::ConvBondDaily => fetch_conv_bond_daily(&ticker).await,
+                Dataset::FundKline => fetch_fund_kline(&ticker).await,
+                Dataset::FundNav => fetch_fund_nav(&ticker).await,
+                Dataset::FundShare => fetch_fund_share(&ticker).await,
+                Dataset::StockDividends => fetch_stock_dividends(&ticker).await,
+                Dataset::StockIndicators => fetch_stock_indicators(&ticker).await,
+                Dataset::StockReportBalance => fetch_stock_report_balance(&ticker).await,
+                Dataset::StockReportCapital => fetch_stock_report_capital(&ticker).await,
+                Dataset::StockReportCashFlow => fetch_stock_report_cash_flow(&ticker).await,
+                Dataset::StockReportIncome => fetch_stock_report_income(&ticker).await,
+                Dataset::StockReportPershare => fetch_stock_report_pershare(&ticker).await,
+                Dataset::StockKline => {
+                    for adjust in [
+                        StockDividendAdjust::No,
+                        StockDividendAdjust::Forward,
+                        StockDividendAdjust::Backward,
+                    ] {
+                        let name = stock_kline_file_name(&ticker, &adjust);
+                        let series = fetch_stock_kline(&ticker, adjust).await;
+                        dfs.push((name, series.and_then(|s| s.to_dataframe(DATE_COLUMN))));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            dfs.push((name, series.and_then(|s| s.to_dataframe(DATE_COLUMN))));
+        }
+    }
+
+    for (code, dates) in index_dates {
+        let df = async {
+            let index = TickersIndex::from_str(&code)?;
+
+            let mut rows: Vec<(NaiveDate, Ticker)> = vec![];
+            for date in dates {
+                for ticker in fetch_index_tickers(&index, &(date + Days::new(1))).await? {
+                    rows.push((date, ticker));
+                }
+            }
+
+            let row_dates: Vec<NaiveDate> = rows.iter().map(|(date, _)| *date).collect();
+            let row_tickers: Vec<String> = rows.iter().map(|(_, t)| t.to_string()).collect();
+            Ok(DataFrame::new(vec![
+                date_column(&row_dates),
+                Column::new(TICKER_COLUMN.into(), row_tickers),
+            ])?)
+        }
+        .await;
+        dfs.push((format!("{}/{code}", Dataset::IndexTickers), df));
+    }
+
+    let mut count: usize = 0;
+    let mut errors: Vec<VfError> = vec![];
+
+    for (name, df) in dfs {
+        match df.and_then(|mut df| write_dataframe(output_dir, &name, &mut df, format)) {
+            Ok(_) => count += 1,
+            Err(err) => errors.push(VfError::NoData {
+                code: "EXPORT_FAILED",
+                message: format!("Failed to export '{name}': {err}"),
+            }),
+        }
+    }
+
+    Ok((count, errors))
+}
+
+pub async fn local_data_dir() -> PathBuf {
+    let config = CONFIG.read().await;
+    PathBuf::from(&config.local_data_dir)
+}
+
+static DATE_COLUMN: &str = "date";
+static TICKER_COLUMN: &str = "ticker";
+
+fn date_column(dates: &[NaiveDate]) -> Column {
+    let values: Vec<AnyValue> = dates
+        .iter()
+        .map(|date| AnyValue::Date(date.to_epoch_days()))
+        .collect();
+    Column::new(DATE_COLUMN.into(), values)
+}
+
+/// Cache keys are in format `[QMT]<path>?<params>` or `[TUSHARE]<api_name>?<params>`,
+/// returns the dataset, the ticker or index code, and the date of index tickers
+fn parse_cache_key(key: &str) -> Option<(Dataset, String, Option<NaiveDate>)> {
+    let (api, params) = key.split_once('?')?;
+    let params: Value = serde_json::from_str(params).ok()?;
+
+    if let Some(path) = api.strip_prefix("[QMT]") {
+        let (route, code) = path.trim_start_matches('/').split_once('/')?;
+        let ticker = Ticker::from_qmt_str(code)?;
+
+        let dataset = match route {
+            "stock_kline" if ticker.r#type == TickerType::Index => {
+                return Some((Dataset::IndexKline, ticker.to_tushare_code(), None));
+            }
+            "stock_dividend" => Dataset::StockDividends,
+            "stock_kline" if ticker.r#type.is_fund() => Dataset::FundKline,
+            "stock_kline" => Dataset::StockKline,
+            "stock_report" => match params["table"].as_str()? {
+                "Balance" => Dataset::StockReportBalance,
+                "Capital" => Dataset::StockReportCapital,
+                "CashFlow" => Dataset::StockReportCashFlow,
+                "Income" => Dataset::StockReportIncome,
+                "PershareIndex" => Dataset::StockReportPershare,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some((dataset, ticker.to_string(), None))
+    } else if let Some(api_name) = api.strip_prefix("[TUSHARE]") {
+        match api_name {
+            "index_daily" => Some((
+                Dataset::IndexKline,
+                params["ts_code"].as_str()?.to_string(),
+                None,
+            )),
+            "index_dailybasic" => Some((
+                Dataset::IndexIndicators,
+                params["ts_code"].as_str()?.to_string(),
+                None,
+            )),
+            "index_weight" => Some((
+                Dataset::IndexTickers,
+                params["index_code"].as_str()?.to_string(),
+                params["end_date"]
+                    .as_str()
+                    .and_then(|s| NaiveDate::parse_from_str(s, "%Y%m%d").ok()),
+            )),
+            "stock_hsgt" => Some((
+                Dataset::IndexTickers,
+                format!("{}.HSGT", params["type"].as_str()?),
+                params["end_date"]
+                    .as_str()
+                    .and_then(|s| NaiveDate::parse_from_str(s, "%Y%m%d").ok()),
+            )),
+            "hk_tradecal" => Some((Dataset::TradeDates, "XHKG".to_string(), None)),
+            "trade_cal" => {
+                let exchange = match params["exchange"].as_str() {
+                    Some("BSE") => "XBSE",
+                    Some("SZSE") => "XSHE",
+                    Some("SSE") | None => "XSHG",
+                    Some(_) => return None,
+                };
+                Some((Dataset::TradeDates, exchange.to_string(), None))
+            }
+            _ => {
+                let dataset = match api_name {
+                    "adj_factor" | "daily" | "hk_daily" | "hk_daily_adj" => Dataset::StockKline,
+                    "balancesheet" => Dataset::StockReportBalance,
+                    "cashflow" => Dataset::StockReportCashFlow,
+                    "cb_daily" => Dataset::ConvBondDaily,
+                    "daily_basic" => Dataset::StockIndicators,
+                    "fund_adj" | "fund_daily" => Dataset::FundKline,
+                    "fund_nav" => Dataset::FundNav,
+                    "fund_share" => Dataset::FundShare,
+                    "fina_indicator" => Dataset::StockReportPershare,
+                    "income" => Dataset::StockReportIncome,
+                    _ => return None,
+                };
+                let ticker = Ticker::from_tushare_str(params["ts_code"].as_str()?)?;
+
+                Some((dataset, ticker.to_string(), None))
+            }
+        }
+    } else {
+        None
+    }
+}
+
+fn stock_kline_file_name(ticker: &Ticker, adjust: &StockDividendAdjust) -> String {
+    format!(
+        "{}/{}/{ticker}",
+        Dataset::StockKline,
+        adjust.to_string().to_lowercase()
+    )
+}
+
+/// Write the dataframe and remove the file of other format, which would shadow it
+fn write_dataframe(
+    dir: &Path,
+    name: &str,
+    df: &mut DataFrame,
+    format: LocalFileFormat,
+) -> VfResult<()> {
+    let path = dir.join(format!("{name}.{format}"));
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut file = File::create(&path)?;
+    match format {
+        LocalFileFormat::Csv => {
+            CsvWriter::new(&mut file).finish(df)?;
+            let _ = remove_file(path.with_extension(LocalFileFormat::Parquet.to_string()));
+        }
+        LocalFileFormat::Parquet => {
+            ParquetWriter::new(&mut file).finish(df)?;
+            let _ = remove_file(path.with_extension(LocalFileFormat::Csv.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::financial::KlineField;
+
+    #[test]
+    fn test_parse_cache_key() {
+        assert_eq!(
+            parse_cache_key(r#"[QMT]/stock_report/600000.SH?{"table":"CashFlow"}"#),
+            Some((
+                Dataset::StockReportCashFlow,
+                "600000.XSHG".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            parse_cache_key(r#"[TUSHARE]daily?{"ts_code":"000001.SZ"}"#),
+            Some((Dataset::StockKline, "000001.XSHE".to_string(), None))
+        );
+        assert_eq!(
+            parse_cache_key(
+                r#"[TUSHARE]index_weight?{"end_date":"20240630","index_code":"000300.SH"}"#
+            ),
+            Some((
+                Dataset::IndexTickers,
+                "000300.SH".to_string(),
+                NaiveDate::from_ymd_opt(2024, 6, 30)
+            ))
+        );
+        assert_eq!(
+            parse_cache_key(r#"[TUSHARE]index_daily?{"ts_code":"H30269.CSI"}"#),
+            Some((Dataset::IndexKline, "H30269.CSI".to_string(), None))
+        );
+        assert_eq!(
+            parse_cache_key(r#"[QMT]/stock_kline/000300.SH?{"dividend_type":"none"}"#),
+            Some((Dataset::IndexKline, "000300.SH".to_string(), None))
+        );
+        assert_eq!(
+            parse_cache_key(r#"[QMT]/stock_kline/510300.SH?{"dividend_type":"back"}"#),
+            Some((Dataset::FundKline, "510300.XSHG".to_string(), None))
+        );
+        assert_eq!(
+            parse_cache_key(r#"[TUSHARE]trade_cal?{"exchange":"SZSE","is_open":"1"}"#),
+            Some((Dataset::TradeDates, "XSHE".to_string(), None))
+        );
+        assert_eq!(parse_cache_key(r#"[TUSHARE]stock_basic?{}"#), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_only() {
+        let result = cache::cache_only(crate::ds::tushare::call_api(
+            "daily",
+            &serde_json::json!({"ts_code": "TEST_NOT_CACHED"}),
+            None,
+            1,
+            false,
+        ))
+        .await;
+        assert!(matches!(
+            result,
+            Err(VfError::NoData {
+                code: "NOT_CACHED",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_data_source() {
+        let data_dir = std::env::temp_dir().join("vfunds_test_local_data_source");
+        let _ = std::fs::remove_dir_all(&data_dir);
+        std::fs::create_dir_all(data_dir.join("stock_kline/backward")).unwrap();
+        std::fs::create_dir_all(data_dir.join("index_tickers")).unwrap();
+        std::fs::write(
+            data_dir.join("stock_kline/backward/600000.XSHG.csv"),
+            "date,Open,Close,High,Low,Volume\n\
+             2024-01-02,10.0,10.5,10.8,9.9,1000\n\
+             2024-01-03,10.5,10.2,10.6,10.1,1200\n",
+        )
+        .unwrap();
+        std::fs::write(
+            data_dir.join("index_tickers/000300.SH.csv"),
+            "date,ticker\n\
+             2024-01-01,600000.XSHG\n\
+             2024-01-01,000001.XSHE\n\
+             2024-02-01,600036.XSHG\n",
+        )
+        .unwrap();
+        let local = Local {
+            data_dir: Some(data_dir.clone()),
+        };
+
+        let ticker = Ticker::from_str("600000").unwrap();
+        let kline = local
+            .stock_kline(&ticker, StockDividendAdjust::Backward, false)
+            .await
+            .unwrap();
+        assert_eq!(kline.len(), 2);
+        assert_eq!(
+            kline
+                .get_latest_value::<f64>(
+                    &NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                    30,
+                    true,
+                    &KlineField::Close.to_string()
+                )
+                .map(|(_, v)| v),
+            Some(10.2)
+        );
+        assert!(
+            local
+                .stock_kline(&ticker, StockDividendAdjust::No, false)
+                .await
+                .is_err()
+        );
+
+        let mut df = kline.to_dataframe(DATE_COLUMN).unwrap();
+        write_dataframe(
+            &data_dir,
+            &stock_kline_file_name(&ticker, &StockDividendAdjust::No),
+            &mut df,
+            LocalFileFormat::Parquet,
+        )
+        .unwrap();
+        let kline_parquet = local
+            .stock_kline(&ticker, StockDividendAdjust::No, false)
+            .await
+            .unwrap();
+        assert_eq!(kline_parquet.all_dates(), kline.all_dates());
+
+        let index = TickersIndex::from_str("000300.CSI").unwrap();
+        let tickers = local
+            .index_tickers(&index, &NaiveDate::from_ymd_opt(2024, 1, 15).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(tickers.len(), 2);
+        let tickers = local
+            .index_tickers(&index, &NaiveDate::from_ymd_opt(2024, 2, 1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(tickers.len(), 2);
+        let tickers = local
+            .index_tickers(&index, &NaiveDate::from_ymd_opt(2024, 2, 2).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(tickers, vec![Ticker::from_str("600036").unwrap()]);
+    }
+}
@@ -0,0 +1,183 @@
+//! In-process fake server of the QMT proxy and Tushare APIs, which responds from fixture files
+//!
+//! Fixtures are JSON files of synthetic responses in the shape of the real APIs:
+//!
+//! ```text
+//! qmt/<path>[@<query>].json            e.g. qmt/stock_kline/600000.SH@dividend_type=back.json
+//! tushare/<api_name>[@<params>].json   e.g. tushare/daily@ts_code=600000.SH.json
+//! ```
+//!
+//! Query and params are `<key>=<value>` sorted by key and joined by `&`, and the fixture without
+//! them is used if no exact one. Tushare `limit` and `offset` are applied to the fixture items
+//! instead of being matched, so paginated requests end as with the real API.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+
+use crate::{
+    CONFIG,
+    error::{VfError, VfResult},
+};
+
+pub const TUSHARE_PATH: &str = "/tushare";
+
+/// Start the server in background on the port, a random one if 0, and returns its address
+pub async fn start(fixtures_dir: &Path, port: u16) -> VfResult<SocketAddr> {
+    if !fixtures_dir.is_dir() {
+        return Err(VfError::NotExists {
+            code: "FIXTURES_NOT_EXISTS",
+            message: format!(
+                "Fixtures directory '{}' not exists",
+                fixtures_dir.to_string_lossy()
+            ),
+        });
+    }
+
+    let app = Router::new()
+        .route(TUSHARE_PATH, post(handle_tushare))
+        .fallback(handle_qmt)
+        .with_state(Arc::new(fixtures_dir.to_path_buf()));
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    Ok(addr)
+}
+
+/// Point QMT and Tushare APIs to the server without rate limits or saving the config
+pub async fn use_server(addr: &SocketAddr) {
+    let mut config = CONFIG.write().await;
+    config.qmt_api = format!("http://{addr}");
+    config.qmt_rate_limit = 0;
+    config.tushare_api = format!("http://{addr}{TUSHARE_PATH}");
+    config.tushare_token = "mock".to_string();
+    config.tushare_points = 0;
+}
+
+async fn handle_qmt(
+    State(fixtures_dir): State<Arc<PathBuf>>,
+    uri: Uri,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let params: BTreeMap<String, String> = query.into_iter().collect();
+
+    match read_fixture(&fixtures_dir, &format!("qmt{}", uri.path()), &params) {
+        Some(json) => Json(json).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("No fixture of {}", uri.path()),
+        )
+            .into_response(),
+    }
+}
+
+async fn handle_tushare(State(fixtures_dir): State<Arc<PathBuf>>, body: Bytes) -> Json<Value> {
+    // The client does not always send a JSON content type, so the body is parsed as is
+    let body: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let api_name = body["api_name"].as_str().unwrap_or_default();
+
+    let mut params: BTreeMap<String, String> = BTreeMap::new();
+    let (mut limit, mut offset): (Option<usize>, usize) = (None, 0);
+    if let Some(obj) = body["params"].as_object() {
+        for (k, v) in obj {
+            match k.as_str() {
+                "limit" => limit = value_to_string(v).parse().ok(),
+                "offset" => offset = value_to_string(v).parse().unwrap_or(0),
+                _ => {
+                    params.insert(k.to_string(), value_to_string(v));
+                }
+            }
+        }
+    }
+
+    match read_fixture(&fixtures_dir, &format!("tushare/{api_name}"), &params) {
+        Some(mut json) => {
+            if let Some(items) = json["data"]["items"].as_array_mut() {
+                let page: Vec<Value> = items
+                    .iter()
+                    .skip(offset)
+                    .take(limit.unwrap_or(usize::MAX))
+                    .cloned()
+                    .collect();
+                *items = page;
+            }
+
+            Json(json)
+        }
+        None => Json(json!({
+            "code": 40404,
+            "msg": format!("No fixture of {api_name}"),
+            "data": null,
+        })),
+    }
+}
+
+fn read_fixture(
+    fixtures_dir: &Path,
+    name: &str,
+    params: &BTreeMap<String, String>,
+) -> Option<Value> {
+    let mut names = vec![];
+    if !params.is_empty() {
+        let params_str = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        names.push(format!("{name}@{params_str}"));
+    }
+    names.push(name.to_string());
+
+    names.iter().find_map(|name| {
+        let path = fixtures_dir.join(format!("{}.json", name.trim_start_matches('/')));
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    })
+}
+
+fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.to_string(),
+        Value::Null => "".to_string(),
+        _ => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_fixture() {
+        let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+        let mut params = BTreeMap::new();
+        params.insert("table".to_string(), "PershareIndex".to_string());
+        assert!(read_fixture(&fixtures_dir, "qmt/stock_report/600595.SH", &params).is_some());
+
+        params.insert("unknown".to_string(), "1".to_string());
+        assert!(read_fixture(&fixtures_dir, "qmt/stock_report/600595.SH", &params).is_none());
+        assert!(read_fixture(&fixtures_dir, "/qmt/stock_kline/000001.SH", &params).is_some());
+    }
+}
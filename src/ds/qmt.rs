@@ -1,18 +1,376 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
-use serde_json::Value;
-use tokio::time::sleep;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde_json::{Value, json};
 
 use crate::{
     CACHE_NO_EXPIRE, CONFIG, cache,
+    data::series::DailySeries,
+    ds::{DataSource, Dataset, limiter::QMT_LIMITER},
     error::{VfError, VfResult},
+    financial::{
+        KlineField,
+        sector::fetch_sector_tickers,
+        stock::{
+            StockDetail, StockDividendAdjust, StockDividendField, StockReportBalanceField,
+            StockReportCapitalField, StockReportCashFlowField, StockReportIncomeField,
+            StockReportPershareField,
+        },
+    },
     market::next_data_expire_in_china,
+    ticker::{Ticker, TickersIndex},
     utils::{
         compress,
+        datetime::date_from_str,
         net::{http_get, join_url},
     },
 };
 
+pub struct Qmt;
+
+#[async_trait]
+impl DataSource for Qmt {
+    fn name(&self) -> &'static str {
+        "qmt"
+    }
+
+    /// QMT quotes funds the same as stocks
+    async fn fund_kline(&self, ticker: &Ticker, ignore_cache: bool) -> VfResult<DailySeries> {
+        self.stock_kline(ticker, StockDividendAdjust::Backward, ignore_cache)
+            .await
+    }
+
+    /// QMT quotes indexes of exchanges the same as stocks
+    async fn index_kline(&self, index: &TickersIndex, ignore_cache: bool) -> VfResult<DailySeries> {
+        match Ticker::from_qmt_str(&index.to_tushare_code()) {
+            Some(ticker) if matches!(ticker.exchange.as_str(), "XSHG" | "XSHE") => {
+                self.stock_kline(&ticker, StockDividendAdjust::No, ignore_cache)
+                    .await
+            }
+            _ => Err(super::unsupported(self.name(), Dataset::IndexKline)),
+        }
+    }
+
+    async fn sector_tickers(&self, sector_prefix: &str) -> VfResult<HashMap<Ticker, String>> {
+        let json = call_api(
+            "/stocks_sector",
+            &json!({"sector_prefix": sector_prefix}),
+            30,
+            false,
+        )
+        .await?;
+
+        let mut tickers_sector: HashMap<Ticker, String> = HashMap::new();
+
+        if let Some(obj) = json.as_object() {
+            for (ticker_str, sector) in obj {
+                if let Some(ticker) = Ticker::from_qmt_str(ticker_str) {
+                    tickers_sector.insert(ticker, sector.to_string());
+                }
+            }
+        }
+
+        Ok(tickers_sector)
+    }
+
+    async fn stock_detail(&self, ticker: &Ticker) -> VfResult<StockDetail> {
+        let json = call_api(
+            &format!("/stock_detail/{}", ticker.to_qmt_code()),
+            &json!({}),
+            30,
+            false,
+        )
+        .await?;
+
+        let tickers_sector_map = fetch_sector_tickers("SW1").await?;
+
+        Ok(StockDetail {
+            name: json["InstrumentName"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            sector: tickers_sector_map.get(ticker).map(|s| s.replace("\"", "")),
+            trading_date: json["TradingDay"]
+                .as_str()
+                .and_then(|s| date_from_str(s).ok()),
+            expire_date: json["ExpireDate"]
+                .as_str()
+                .and_then(|s| date_from_str(s).ok()),
+            pre_close_price: json["PreClose"].as_f64(),
+            float_volume: json["FloatVolume"].as_u64(),
+            total_volume: json["TotalVolume"].as_u64(),
+        })
+    }
+
+    async fn stock_dividends(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_dividend/{}", ticker.to_qmt_code()),
+            &json!({}),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockDividendField::Interest.to_string(),
+            "interest".to_string(),
+        );
+        fields.insert(
+            StockDividendField::StockBonus.to_string(),
+            "stockBonus".to_string(),
+        );
+        fields.insert(
+            StockDividendField::StockGift.to_string(),
+            "stockGift".to_string(),
+        );
+        fields.insert(
+            StockDividendField::AllotNum.to_string(),
+            "allotNum".to_string(),
+        );
+        fields.insert(
+            StockDividendField::AllotPrice.to_string(),
+            "allotPrice".to_string(),
+        );
+        fields.insert(
+            StockDividendField::PriceAdjustmentFactor.to_string(),
+            "dr".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    /// QMT does not support delisted stocks, consider use tushare first
+    async fn stock_kline(
+        &self,
+        ticker: &Ticker,
+        adjust: StockDividendAdjust,
+        ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        let param_dividend_type = match adjust {
+            StockDividendAdjust::Backward => "back",
+            StockDividendAdjust::Forward => "front",
+            StockDividendAdjust::No => "none",
+        };
+
+        let json = call_api(
+            &format!("/stock_kline/{}", ticker.to_qmt_code()),
+            &json!({
+                "dividend_type": param_dividend_type,
+            }),
+            0,
+            ignore_cache,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(KlineField::Open.to_string(), "open".to_string());
+        fields.insert(KlineField::Close.to_string(), "close".to_string());
+        fields.insert(KlineField::High.to_string(), "high".to_string());
+        fields.insert(KlineField::Low.to_string(), "low".to_string());
+        fields.insert(KlineField::Volume.to_string(), "volume".to_string());
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    async fn stock_report_balance(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_report/{}", ticker.to_qmt_code()),
+            &json!({
+                "table": "Balance",
+            }),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportBalanceField::ReportDate.to_string(),
+            "m_timetag".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::CashEquivalents.to_string(),
+            "cash_equivalents".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalAssets.to_string(),
+            "tot_assets".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalCurrentAssets.to_string(),
+            "total_current_assets".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalLiability.to_string(),
+            "tot_liab".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalCurrentLiability.to_string(),
+            "total_current_liability".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    async fn stock_report_capital(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_report/{}", ticker.to_qmt_code()),
+            &json!({
+                "table": "Capital",
+            }),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportCapitalField::ReportDate.to_string(),
+            "m_timetag".to_string(),
+        );
+        fields.insert(
+            StockReportCapitalField::Total.to_string(),
+            "total_capital".to_string(),
+        );
+        fields.insert(
+            StockReportCapitalField::Circulating.to_string(),
+            "circulating_capital".to_string(),
+        );
+        fields.insert(
+            StockReportCapitalField::FreeFloat.to_string(),
+            "freeFloatCapital".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    async fn stock_report_cash_flow(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_report/{}", ticker.to_qmt_code()),
+            &json!({
+                "table": "CashFlow",
+            }),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportCashFlowField::ReportDate.to_string(),
+            "m_timetag".to_string(),
+        );
+        fields.insert(
+            StockReportCashFlowField::NetCashByOperatingActivities.to_string(),
+            "net_cash_flows_oper_act".to_string(),
+        );
+        fields.insert(
+            StockReportCashFlowField::CapitalExpenditures.to_string(),
+            "cash_pay_acq_const_fiolta".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    async fn stock_report_income(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_report/{}", ticker.to_qmt_code()),
+            &json!({
+                "table": "Income",
+            }),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportIncomeField::ReportDate.to_string(),
+            "m_timetag".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::Revenue.to_string(),
+            "revenue".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::OperatingProfit.to_string(),
+            "oper_profit".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::TotalProfit.to_string(),
+            "tot_profit".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    async fn stock_report_pershare(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            &format!("/stock_report/{}", ticker.to_qmt_code()),
+            &json!({
+                "table": "PershareIndex",
+            }),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportPershareField::ReportDate.to_string(),
+            "m_timetag".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::Bps.to_string(),
+            "s_fa_bps".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::Eps.to_string(),
+            "s_fa_eps_basic".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::EquityRoe.to_string(),
+            "equity_roe".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::GrossProfit.to_string(),
+            "gross_profit".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::NetProfit.to_string(),
+            "net_profit".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::Ocfps.to_string(),
+            "s_fa_ocfps".to_string(),
+        );
+
+        DailySeries::from_qmt_json(&json, "date", &fields)
+    }
+
+    /// Dates of the benchmark kline, as QMT has no trade calendar
+    async fn trade_dates(&self, exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+        // Composite indexes are quoted on all trading days of their exchanges
+        let bench_code = match exchange {
+            "XBSE" => "899050.BJ",
+            "XSHE" => "399001.SZ",
+            "XSHG" => "000001.SH",
+            _ => return Err(super::unsupported(self.name(), Dataset::TradeDates)),
+        };
+        let bench_ticker = Ticker::from_str(bench_code)?;
+        let bench_kline = self
+            .stock_kline(&bench_ticker, StockDividendAdjust::No, false)
+            .await?;
+
+        Ok(bench_kline.all_dates().into_iter().collect())
+    }
+}
+
 pub async fn call_api(
     path: &str,
     params: &serde_json::Value,
@@ -20,49 +378,59 @@ pub async fn call_api(
     ignore_cache: bool,
 ) -> VfResult<serde_json::Value> {
     let cache_key = format!("[QMT]{path}?{params}");
+    let cache_only = cache::is_cache_only();
 
-    let bytes: VfResult<Vec<u8>> =
-        if !ignore_cache && let Some(data) = cache::get(&cache_key, *CACHE_NO_EXPIRE).await? {
-            Ok(compress::decode(&data)?)
-        } else {
-            let mut query = HashMap::new();
-            if let Some(params) = params.as_object() {
-                for (k, v) in params.iter() {
-                    let s = match v {
-                        Value::Bool(b) => {
-                            if *b {
-                                "true".to_string()
-                            } else {
-                                "false".to_string()
-                            }
+    let bytes: VfResult<Vec<u8>> = if !ignore_cache
+        && let Some(data) = cache::get(&cache_key, *CACHE_NO_EXPIRE || cache_only).await?
+    {
+        Ok(compress::decode(&data)?)
+    } else if cache_only {
+        Err(VfError::NoData {
+            code: "NOT_CACHED",
+            message: format!("No cache of '{cache_key}'"),
+        })
+    } else {
+        let mut query = HashMap::new();
+        if let Some(params) = params.as_object() {
+            for (k, v) in params.iter() {
+                let s = match v {
+                    Value::Bool(b) => {
+                        if *b {
+                            "true".to_string()
+                        } else {
+                            "false".to_string()
                         }
-                        Value::Number(n) => n.to_string(),
-                        Value::String(s) => s.to_string(),
-                        _ => "".to_string(),
-                    };
-                    query.insert(k.to_string(), s);
-                }
+                    }
+                    Value::Number(n) => n.to_string(),
+                    Value::String(s) => s.to_string(),
+                    _ => "".to_string(),
+                };
+                query.insert(k.to_string(), s);
             }
+        }
 
-            let request_delay_secs: f64 = std::env::var("QMT_DELAY")
-                .ok()
-                .and_then(|s| s.parse::<f64>().ok())
-                .unwrap_or(2.0);
-            if request_delay_secs > 0.0 {
-                sleep(tokio::time::Duration::from_secs(request_delay_secs as u64)).await;
-            }
+        let cache_key: &str = &cache_key;
+        QMT_LIMITER
+            .coalesce(cache_key, || async move {
+                let rate_limit = { CONFIG.read().await.qmt_rate_limit };
+                let _permit = QMT_LIMITER.acquire(rate_limit).await;
 
-            let qmt_api = { &CONFIG.read().await.qmt_api };
+                let qmt_api = { &CONFIG.read().await.qmt_api };
 
-            let bytes = http_get(qmt_api, Some(path), Some(query), None, 30, 3).await?;
+                let bytes = http_get(qmt_api, Some(path), Some(query), None, 30, 3).await?;
 
-            if let Ok(data) = compress::encode(&bytes) {
-                let expire = next_data_expire_in_china(expire_days);
-                let _ = cache::upsert(&cache_key, &data, &expire).await;
-            }
+                if let Ok(data) = compress::encode(&bytes) {
+                    let expire = next_data_expire_in_china(expire_days);
+                    let _ = cache::upsert(cache_key, &data, &expire).await;
+                }
 
-            Ok(bytes)
-        };
+                Ok(bytes)
+            })
+            .await
+            .inspect(|_| {
+                cache::record_source(cache_key, Some(next_data_expire_in_china(expire_days)))
+            })
+    };
 
     let json: serde_json::Value = serde_json::from_slice(&bytes?)?;
 
@@ -1,13 +1,1626 @@
-use serde_json::json;
-use tokio::time::sleep;
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::{Days, Duration, Months, NaiveDate};
+use serde_json::{Value, json};
 
 use crate::{
     CACHE_NO_EXPIRE, CONFIG, cache,
+    data::series::DailySeries,
+    ds::{
+        DataSource, Dataset,
+        limiter::{TUSHARE_LIMITER, tushare_rate_limit},
+    },
     error::{VfError, VfResult},
+    financial::{
+        KlineField,
+        bond::{
+            ConvBondBasic, ConvBondCall, ConvBondClauses, ConvBondCoupon, ConvBondDailyField,
+            ConvBondIssue, ConvBondShareField, ConvBondTrigger,
+        },
+        economy::{
+            InterestRate, InterestRateField, MacroIndicator, MacroIndicatorField,
+            macro_indicator_publish_date,
+        },
+        fund::{FundBasic, FundNavField, FundShareField},
+        fx::{Currency, FxDailyField},
+        index::IndexIndicatorField,
+        stock::{
+            StockBasic, StockDividendAdjust, StockIndicatorField, StockReportBalanceField,
+            StockReportCapitalField, StockReportCashFlowField, StockReportIncomeField,
+            StockReportPershareField,
+        },
+    },
     market::next_data_expire_in_china,
-    utils::{compress, net::http_post},
+    ticker::{Ticker, TickersIndex},
+    utils::{compress, datetime::date_from_str, net::http_post},
 };
 
+pub struct Tushare;
+
+#[async_trait]
+impl DataSource for Tushare {
+    fn name(&self) -> &'static str {
+        "tushare"
+    }
+
+    async fn conv_bond_basic(&self, ticker: &Ticker) -> VfResult<ConvBondBasic> {
+        let json_item = conv_bond_basic_item(ticker).await?;
+
+        if let Some(ticker) = json_item["ts_code"]
+            .as_str()
+            .and_then(Ticker::from_tushare_str)
+        {
+            let result = ConvBondBasic {
+                ticker,
+                name: json_item["bond_short_name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                issue_size: json_item["issue_size"].as_f64(),
+                remain_size: json_item["remain_size"].as_f64(),
+                par_value: json_item["par"].as_f64(),
+                list_date: json_item["list_date"]
+                    .as_str()
+                    .and_then(|s| date_from_str(s).ok()),
+                expire_date: json_item["delist_date"]
+                    .as_str()
+                    .and_then(|s| date_from_str(s).ok()),
+            };
+
+            return Ok(result);
+        }
+
+        Err(VfError::Invalid {
+            code: "NO_DATA",
+            message: format!("No data from Tushare cb_basic of {ticker}"),
+        })
+    }
+
+    async fn conv_bond_calls(&self, ticker: &Ticker) -> VfResult<Vec<ConvBondCall>> {
+        let json = call_api(
+            "cb_call",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            1,
+            false,
+        )
+        .await?;
+
+        let mut result = vec![];
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            for item in items {
+                if let Some(values) = item.as_array() {
+                    let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                    for (i, field) in fields.iter().enumerate() {
+                        if let Some(field_name) = field.as_str() {
+                            if let Some(value) = values.get(i) {
+                                json_item.insert(field_name.to_string(), value.clone());
+                            }
+                        }
+                    }
+
+                    if json_item
+                        .get("call_type")
+                        .and_then(Value::as_str)
+                        .is_some_and(|t| t != "赎回")
+                    {
+                        continue;
+                    }
+
+                    if let Some(announce_date) = json_item["ann_date"]
+                        .as_str()
+                        .and_then(|s| date_from_str(s).ok())
+                    {
+                        result.push(ConvBondCall {
+                            ticker: ticker.clone(),
+                            announce_date,
+                            is_call: json_item["is_call"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                            call_date: json_item["call_date"]
+                                .as_str()
+                                .and_then(|s| date_from_str(s).ok()),
+                            call_price: json_item["call_price"].as_f64(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn conv_bond_clauses(&self, ticker: &Ticker) -> VfResult<ConvBondClauses> {
+        let json_item = conv_bond_basic_item(ticker).await?;
+
+        let json = call_api(
+            "cb_rate",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            Some("ts_code,rate_start_date,rate_end_date,coupon_rate"),
+            30,
+            false,
+        )
+        .await?;
+
+        let mut coupons = vec![];
+        if let Some(items) = json["data"]["items"].as_array() {
+            for item in items {
+                if let (Some(start_date), Some(end_date), Some(rate)) = (
+                    item[1].as_str().and_then(|s| date_from_str(s).ok()),
+                    item[2].as_str().and_then(|s| date_from_str(s).ok()),
+                    item[3].as_f64(),
+                ) {
+                    coupons.push(ConvBondCoupon {
+                        start_date,
+                        end_date,
+                        rate,
+                    });
+                }
+            }
+        }
+        coupons.sort_by_key(|c| c.start_date);
+
+        let clause = |field: &str, default: ConvBondTrigger| {
+            json_item
+                .get(field)
+                .and_then(Value::as_str)
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| ConvBondTrigger::from_clause(s, default))
+        };
+        let date = |field: &str| {
+            json_item
+                .get(field)
+                .and_then(Value::as_str)
+                .and_then(|s| date_from_str(s).ok())
+        };
+
+        Ok(ConvBondClauses {
+            ticker: ticker.clone(),
+            par_value: json_item
+                .get("par")
+                .and_then(Value::as_f64)
+                .unwrap_or(100.0),
+            value_date: date("value_date"),
+            maturity_date: date("maturity_date").or(date("delist_date")),
+            conversion_start_date: date("conv_start_date"),
+            coupons,
+            maturity_redemption_price: json_item.get("maturity_put_price").and_then(Value::as_f64),
+            call: clause("call_clause", ConvBondTrigger::DEFAULT_CALL),
+            put: clause("put_clause", ConvBondTrigger::DEFAULT_PUT),
+            reset: clause("reset_clause", ConvBondTrigger::DEFAULT_RESET),
+        })
+    }
+
+    async fn conv_bond_daily(&self, ticker: &Ticker, ignore_cache: bool) -> VfResult<DailySeries> {
+        let mut json = call_api(
+            "cb_daily",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            Some("trade_date,open,close,high,low,vol,bond_value,cb_value,bond_over_rate,cb_over_rate"),
+            0,
+            ignore_cache,
+        )
+        .await?;
+
+        let check_field_idxs = {
+            let mut idxs = vec![];
+            if let Some(fields) = json["data"]["fields"].as_array() {
+                for (i, name) in fields.iter().enumerate() {
+                    if name == "open" || name == "close" || name == "high" || name == "low" {
+                        idxs.push(i);
+                    }
+                }
+            }
+            idxs
+        };
+
+        if let Some(data) = json.get_mut("data") {
+            if let Some(items) = data.get_mut("items").and_then(Value::as_array_mut) {
+                for item in items.iter_mut() {
+                    for field_idx in &check_field_idxs {
+                        if let Some(v) = item[field_idx].as_f64() {
+                            if v == 0.0 {
+                                item[field_idx] = Value::Null;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(ConvBondDailyField::Open.to_string(), "open".to_string());
+        fields.insert(ConvBondDailyField::Close.to_string(), "close".to_string());
+        fields.insert(ConvBondDailyField::High.to_string(), "high".to_string());
+        fields.insert(ConvBondDailyField::Low.to_string(), "low".to_string());
+        fields.insert(ConvBondDailyField::Volume.to_string(), "vol".to_string());
+        fields.insert(
+            ConvBondDailyField::StraightValue.to_string(),
+            "bond_value".to_string(),
+        );
+        fields.insert(
+            ConvBondDailyField::ConversionValue.to_string(),
+            "cb_value".to_string(),
+        );
+        fields.insert(
+            ConvBondDailyField::StraightPremium.to_string(),
+            "bond_over_rate".to_string(),
+        );
+        fields.insert(
+            ConvBondDailyField::ConversionPremium.to_string(),
+            "cb_over_rate".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn conv_bond_issues(
+        &self,
+        date: &NaiveDate,
+        lookback_months: u32,
+    ) -> VfResult<Vec<ConvBondIssue>> {
+        let json = call_api(
+            "cb_issue",
+            &json!({
+                "start_date": (*date - Months::new(lookback_months)).format("%Y%m%d").to_string(),
+                "end_date": date.format("%Y%m%d").to_string(),
+            }),
+            None,
+            30,
+            false,
+        )
+        .await?;
+
+        let mut result = vec![];
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            for item in items {
+                if let Some(values) = item.as_array() {
+                    let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                    for (i, field) in fields.iter().enumerate() {
+                        if let Some(field_name) = field.as_str() {
+                            if let Some(value) = values.get(i) {
+                                json_item.insert(field_name.to_string(), value.clone());
+                            }
+                        }
+                    }
+
+                    if let Some(ticker) = json_item["ts_code"]
+                        .as_str()
+                        .and_then(Ticker::from_tushare_str)
+                    {
+                        let cb = ConvBondIssue {
+                            ticker,
+                            name: json_item["onl_name"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                            issue_size: json_item["issue_size"].as_f64(),
+                        };
+
+                        result.push(cb);
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn conv_bond_shares(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "cb_share",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            Some("ts_code,publish_date,convert_price,remain_size"),
+            1,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            ConvBondShareField::ConversionPrice.to_string(),
+            "convert_price".to_string(),
+        );
+        fields.insert(
+            ConvBondShareField::RemainSize.to_string(),
+            "remain_size".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "publish_date", &fields)
+    }
+
+    async fn conv_bonds_basic(&self) -> VfResult<Vec<ConvBondBasic>> {
+        static PAGE_SIZE: usize = 2000;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "cb_basic",
+                &json!({
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                None,
+                30,
+                false,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let mut result = vec![];
+
+        for item in &items {
+            if let Some(values) = item.as_array() {
+                let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                for (i, field) in fields.iter().enumerate() {
+                    if let Some(field_name) = field.as_str() {
+                        if let Some(value) = values.get(i) {
+                            json_item.insert(field_name.to_string(), value.clone());
+                        }
+                    }
+                }
+
+                if let Some(ticker) = json_item["ts_code"]
+                    .as_str()
+                    .and_then(Ticker::from_tushare_str)
+                {
+                    result.push(ConvBondBasic {
+                        ticker,
+                        name: json_item["bond_short_name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        issue_size: json_item["issue_size"].as_f64(),
+                        remain_size: json_item["remain_size"].as_f64(),
+                        par_value: json_item["par"].as_f64(),
+                        list_date: json_item["list_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                        expire_date: json_item["delist_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                    });
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn delisted_stocks(&self) -> VfResult<HashMap<Ticker, NaiveDate>> {
+        let json = call_api(
+            "stock_basic",
+            &json!({
+                "list_status": "D".to_string(),
+            }),
+            Some("ts_code,delist_date"),
+            0,
+            false,
+        )
+        .await?;
+
+        let mut result = HashMap::new();
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            if let Some(ts_code_idx) = fields.iter().position(|f| f == "ts_code")
+                && let Some(delist_date_idx) = fields.iter().position(|f| f == "delist_date")
+            {
+                for item in items {
+                    if let Some(values) = item.as_array() {
+                        if let Some(ticker) = values[ts_code_idx]
+                            .as_str()
+                            .and_then(Ticker::from_tushare_str)
+                            && let Some(delist_date) = values[delist_date_idx]
+                                .as_str()
+                                .and_then(|s| date_from_str(s).ok())
+                        {
+                            result.insert(ticker, delist_date);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn etfs(&self) -> VfResult<Vec<FundBasic>> {
+        static PAGE_SIZE: usize = 5000;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "fund_basic",
+                &json!({
+                    "market": "E",
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                Some("ts_code,name,fund_type,invest_type,benchmark,list_date,delist_date"),
+                30,
+                false,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let mut result = vec![];
+
+        for item in &items {
+            if let Some(values) = item.as_array() {
+                let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                for (i, field) in fields.iter().enumerate() {
+                    if let Some(field_name) = field.as_str() {
+                        if let Some(value) = values.get(i) {
+                            json_item.insert(field_name.to_string(), value.clone());
+                        }
+                    }
+                }
+
+                if let Some(ticker) = json_item["ts_code"]
+                    .as_str()
+                    .and_then(Ticker::from_tushare_str)
+                {
+                    result.push(FundBasic {
+                        ticker,
+                        name: json_item["name"].as_str().unwrap_or_default().to_string(),
+                        fund_type: json_item["fund_type"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        invest_type: json_item["invest_type"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        benchmark: json_item["benchmark"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        list_date: json_item["list_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                        delist_date: json_item["delist_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                    });
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn fund_kline(&self, ticker: &Ticker, ignore_cache: bool) -> VfResult<DailySeries> {
+        adjusted_kline(
+            ticker,
+            "fund_daily",
+            "fund_adj",
+            StockDividendAdjust::Backward,
+            ignore_cache,
+        )
+        .await
+    }
+
+    async fn fund_nav(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "fund_nav",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(FundNavField::Unit.to_string(), "unit_nav".to_string());
+        fields.insert(
+            FundNavField::Accumulated.to_string(),
+            "accum_nav".to_string(),
+        );
+        fields.insert(FundNavField::Adjusted.to_string(), "adj_nav".to_string());
+
+        DailySeries::from_tushare_json(&json, "nav_date", &fields)
+    }
+
+    async fn fund_share(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "fund_share",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(FundShareField::Share.to_string(), "fd_share".to_string());
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn fx_daily(&self, currency: &Currency) -> VfResult<DailySeries> {
+        // FXCM quotes offshore CNH for CNY
+        let fx_code = match currency {
+            Currency::Cny => "USDCNH.FXCM",
+            Currency::Hkd => "USDHKD.FXCM",
+            Currency::Usd => return Err(super::unsupported(self.name(), Dataset::FxDaily)),
+        };
+
+        let json = call_api(
+            "fx_daily",
+            &json!({
+                "ts_code": fx_code,
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(FxDailyField::Open.to_string(), "bid_open".to_string());
+        fields.insert(FxDailyField::Close.to_string(), "bid_close".to_string());
+        fields.insert(FxDailyField::High.to_string(), "bid_high".to_string());
+        fields.insert(FxDailyField::Low.to_string(), "bid_low".to_string());
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn hk_stocks_basic(&self) -> VfResult<Vec<StockBasic>> {
+        let mut result = vec![];
+        for list_status in ["L", "D"] {
+            result.extend(hk_stocks_basic(&json!({"list_status": list_status})).await?);
+        }
+
+        Ok(result)
+    }
+
+    async fn index_indicators(&self, index: &TickersIndex) -> VfResult<DailySeries> {
+        static PAGE_SIZE: usize = 2000;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "index_dailybasic",
+                &json!({
+                    "ts_code": index.to_tushare_code(),
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                None,
+                0,
+                false,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let json = json!({
+            "data": {
+                "fields": fields,
+                "items": items,
+            }
+        });
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(IndexIndicatorField::Pb.to_string(), "pb".to_string());
+        fields.insert(IndexIndicatorField::Pe.to_string(), "pe".to_string());
+        fields.insert(IndexIndicatorField::PeTtm.to_string(), "pe_ttm".to_string());
+        fields.insert(
+            IndexIndicatorField::TurnoverRate.to_string(),
+            "turnover_rate".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn index_kline(&self, index: &TickersIndex, ignore_cache: bool) -> VfResult<DailySeries> {
+        static PAGE_SIZE: usize = 8000;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "index_daily",
+                &json!({
+                    "ts_code": index.to_tushare_code(),
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                None,
+                0,
+                ignore_cache,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let json = json!({
+            "data": {
+                "fields": fields,
+                "items": items,
+            }
+        });
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(KlineField::Open.to_string(), "open".to_string());
+        fields.insert(KlineField::Close.to_string(), "close".to_string());
+        fields.insert(KlineField::High.to_string(), "high".to_string());
+        fields.insert(KlineField::Low.to_string(), "low".to_string());
+        fields.insert(KlineField::Volume.to_string(), "vol".to_string());
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn index_tickers(&self, index: &TickersIndex, date: &NaiveDate) -> VfResult<Vec<Ticker>> {
+        let prev_date = *date - Duration::days(1);
+
+        // Southbound Connect lists are like `SH_HK.HSGT` of Shanghai and `SZ_HK.HSGT` of Shenzhen
+        let (json, code_field) = if index.provider == "HSGT" {
+            let json = call_api(
+                "stock_hsgt",
+                &json!({
+                    "type": index.symbol,
+                    "start_date": (prev_date - Duration::days(14)).format("%Y%m%d").to_string(),
+                    "end_date": prev_date.format("%Y%m%d").to_string(),
+                }),
+                None,
+                30,
+                false,
+            )
+            .await?;
+            (json, "ts_code")
+        } else {
+            let json = call_api(
+                "index_weight",
+                &json!({
+                    "index_code": index.to_tushare_code(),
+                    "end_date": prev_date.format("%Y%m%d").to_string(),
+                }),
+                None,
+                30,
+                false,
+            )
+            .await?;
+            (json, "con_code")
+        };
+
+        let mut hist_tickers: HashMap<NaiveDate, Vec<Ticker>> = HashMap::new();
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            if let (Some(idx_con_code), Some(idx_trade_date)) = (
+                fields.iter().position(|f| f == code_field),
+                fields.iter().position(|f| f == "trade_date"),
+            ) {
+                for item in items {
+                    if let Some(values) = item.as_array() {
+                        if let (Some(con_code_str), Some(trade_date_str)) = (
+                            values[idx_con_code].as_str(),
+                            values[idx_trade_date].as_str(),
+                        ) {
+                            if let (Ok(date), Some(ticker)) = (
+                                date_from_str(trade_date_str),
+                                Ticker::from_tushare_str(con_code_str),
+                            ) {
+                                hist_tickers.entry(date).or_default().push(ticker);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let tickers = if let Some(latest_date) = hist_tickers.keys().max() {
+            hist_tickers.get(latest_date).unwrap_or(&vec![]).clone()
+        } else {
+            vec![]
+        };
+
+        Ok(tickers)
+    }
+
+    async fn interest_rate(&self, rate: &InterestRate) -> VfResult<DailySeries> {
+        static PAGE_SIZE: usize = 2000;
+
+        // Yields of China government bonds are from the ChinaBond yield curve by maturities
+        let (api, params, date_field, value_field) = match rate {
+            InterestRate::Cgb1y | InterestRate::Cgb10y => (
+                "yc_cb",
+                json!({
+                    "ts_code": "1001.CB",
+                    "curve_type": "0",
+                    "curve_term": if *rate == InterestRate::Cgb1y { 1 } else { 10 },
+                }),
+                "trade_date",
+                "yield",
+            ),
+            InterestRate::Lpr1y => ("shibor_lpr", json!({}), "date", "1y"),
+            InterestRate::Lpr5y => ("shibor_lpr", json!({}), "date", "5y"),
+            InterestRate::ShiborOn => ("shibor", json!({}), "date", "on"),
+            InterestRate::Shibor1w => ("shibor", json!({}), "date", "1w"),
+            InterestRate::Shibor1m => ("shibor", json!({}), "date", "1m"),
+            InterestRate::Shibor3m => ("shibor", json!({}), "date", "3m"),
+            InterestRate::Shibor1y => ("shibor", json!({}), "date", "1y"),
+        };
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let mut page_params = params.clone();
+            page_params["limit"] = json!(PAGE_SIZE);
+            page_params["offset"] = json!(offset);
+
+            let json = call_api(api, &page_params, None, 0, false).await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let json = json!({
+            "data": {
+                "fields": fields,
+                "items": items,
+            }
+        });
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(InterestRateField::Rate.to_string(), value_field.to_string());
+
+        DailySeries::from_tushare_json(&json, date_field, &fields)
+    }
+
+    async fn macro_indicator(&self, indicator: &MacroIndicator) -> VfResult<DailySeries> {
+        let (api, value_field) = match indicator {
+            MacroIndicator::CpiYoy => ("cn_cpi", "nt_yoy"),
+            MacroIndicator::PpiYoy => ("cn_ppi", "ppi_yoy"),
+            MacroIndicator::M1 => ("cn_m", "m1"),
+            MacroIndicator::M1Yoy => ("cn_m", "m1_yoy"),
+            MacroIndicator::M2 => ("cn_m", "m2"),
+            MacroIndicator::M2Yoy => ("cn_m", "m2_yoy"),
+        };
+
+        let mut json = call_api(api, &json!({}), None, 0, false).await?;
+
+        // Months like `202401` are replaced with the publish dates
+        let month_idx = json["data"]["fields"]
+            .as_array()
+            .and_then(|fields| fields.iter().position(|f| f == "month"));
+        if let (Some(month_idx), Some(items)) = (month_idx, json["data"]["items"].as_array_mut()) {
+            for item in items.iter_mut() {
+                let publish_date = item[month_idx].as_str().and_then(|month| {
+                    let year = month.get(0..4)?.parse::<i32>().ok()?;
+                    let month = month.get(4..6)?.parse::<u32>().ok()?;
+                    macro_indicator_publish_date(year, month)
+                });
+                item[month_idx] = match publish_date {
+                    Some(date) => json!(date.format("%Y%m%d").to_string()),
+                    None => Value::Null,
+                };
+            }
+            items.retain(|item| !item[month_idx].is_null());
+        }
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            MacroIndicatorField::Value.to_string(),
+            value_field.to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "month", &fields)
+    }
+
+    async fn st_stocks(&self, date: &NaiveDate, lookback_days: u64) -> VfResult<Vec<Ticker>> {
+        let json = call_api(
+            "stock_st",
+            &json!({
+                "start_date": (*date - Days::new(lookback_days)).format("%Y%m%d").to_string(),
+                "end_date": date.format("%Y%m%d").to_string(),
+            }),
+            None,
+            30,
+            false,
+        )
+        .await?;
+
+        let mut result = vec![];
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            if let Some(ts_code_idx) = fields.iter().position(|f| f == "ts_code") {
+                for item in items {
+                    if let Some(values) = item.as_array() {
+                        if let Some(ticker) = values[ts_code_idx]
+                            .as_str()
+                            .and_then(Ticker::from_tushare_str)
+                        {
+                            result.push(ticker);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn stock_basic(&self, ticker: &Ticker) -> VfResult<StockBasic> {
+        if ticker.exchange_mic() == "XHKG" {
+            return hk_stocks_basic(&json!({"ts_code": ticker.to_tushare_code()}))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(VfError::NoData {
+                    code: "NO_DATA",
+                    message: format!("No data from Tushare hk_basic of {ticker}"),
+                });
+        }
+
+        let json = call_api(
+            "stock_basic",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            Some("ts_code,name,industry,list_date,delist_date"),
+            30,
+            false,
+        )
+        .await?;
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            if let Some(item) = items.first() {
+                if let Some(values) = item.as_array() {
+                    let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                    for (i, field) in fields.iter().enumerate() {
+                        if let Some(field_name) = field.as_str() {
+                            if let Some(value) = values.get(i) {
+                                json_item.insert(field_name.to_string(), value.clone());
+                            }
+                        }
+                    }
+
+                    if let Some(ticker) = json_item["ts_code"]
+                        .as_str()
+                        .and_then(Ticker::from_tushare_str)
+                    {
+                        let result = StockBasic {
+                            ticker,
+                            name: json_item["name"].as_str().unwrap_or_default().to_string(),
+                            industry: json_item["industry"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                            list_date: json_item["list_date"]
+                                .as_str()
+                                .and_then(|s| date_from_str(s).ok()),
+                            delist_date: json_item["delist_date"]
+                                .as_str()
+                                .and_then(|s| date_from_str(s).ok()),
+                            lot_size: None,
+                        };
+
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+
+        Err(VfError::NoData {
+            code: "NO_DATA",
+            message: format!("No data from Tushare stock_basic of {ticker}"),
+        })
+    }
+
+    async fn stock_indicators(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        static PAGE_SIZE: usize = 5000;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "daily_basic",
+                &json!({
+                    "ts_code": ticker.to_tushare_code(),
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                None,
+                0,
+                false,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let json = json!({
+            "data": {
+                "fields": fields,
+                "items": items,
+            }
+        });
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockIndicatorField::DividendRatio.to_string(),
+            "dv_ratio".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::DividendRatioTtm.to_string(),
+            "dv_ttm".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::MarketValueCirculating.to_string(),
+            "circ_mv".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::MarketValueTotal.to_string(),
+            "total_mv".to_string(),
+        );
+        fields.insert(StockIndicatorField::Pb.to_string(), "pb".to_string());
+        fields.insert(StockIndicatorField::Pe.to_string(), "pe".to_string());
+        fields.insert(StockIndicatorField::PeTtm.to_string(), "pe_ttm".to_string());
+        fields.insert(StockIndicatorField::Ps.to_string(), "ps".to_string());
+        fields.insert(StockIndicatorField::PsTtm.to_string(), "ps_ttm".to_string());
+        fields.insert(
+            StockIndicatorField::SharesCirculating.to_string(),
+            "float_share".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::SharesTotal.to_string(),
+            "total_share".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::TurnoverRate.to_string(),
+            "turnover_rate".to_string(),
+        );
+        fields.insert(
+            StockIndicatorField::VolumeRatio.to_string(),
+            "volume_ratio".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "trade_date", &fields)
+    }
+
+    async fn stock_kline(
+        &self,
+        ticker: &Ticker,
+        adjust: StockDividendAdjust,
+        ignore_cache: bool,
+    ) -> VfResult<DailySeries> {
+        if ticker.exchange_mic() == "XHKG" {
+            adjusted_kline(ticker, "hk_daily", "hk_daily_adj", adjust, ignore_cache).await
+        } else {
+            adjusted_kline(ticker, "daily", "adj_factor", adjust, ignore_cache).await
+        }
+    }
+
+    async fn stock_report_balance(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "balancesheet",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportBalanceField::ReportDate.to_string(),
+            "end_date".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::CashEquivalents.to_string(),
+            "money_cap".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalAssets.to_string(),
+            "total_assets".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalCurrentAssets.to_string(),
+            "total_cur_assets".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalLiability.to_string(),
+            "total_liab".to_string(),
+        );
+        fields.insert(
+            StockReportBalanceField::TotalCurrentLiability.to_string(),
+            "total_cur_liab".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "f_ann_date", &fields)
+    }
+
+    async fn stock_report_cash_flow(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "cashflow",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportCashFlowField::ReportDate.to_string(),
+            "end_date".to_string(),
+        );
+        fields.insert(
+            StockReportCashFlowField::NetCashByOperatingActivities.to_string(),
+            "n_cashflow_act".to_string(),
+        );
+        fields.insert(
+            StockReportCashFlowField::CapitalExpenditures.to_string(),
+            "c_pay_acq_const_fiolta".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "f_ann_date", &fields)
+    }
+
+    async fn stock_report_income(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        let json = call_api(
+            "income",
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            false,
+        )
+        .await?;
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportCapitalField::ReportDate.to_string(),
+            "end_date".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::Revenue.to_string(),
+            "total_revenue".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::OperatingProfit.to_string(),
+            "operate_profit".to_string(),
+        );
+        fields.insert(
+            StockReportIncomeField::TotalProfit.to_string(),
+            "total_profit".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "f_ann_date", &fields)
+    }
+
+    async fn stock_report_pershare(&self, ticker: &Ticker) -> VfResult<DailySeries> {
+        static PAGE_SIZE: usize = 100;
+
+        let mut fields: Vec<Value> = vec![];
+        let mut items: Vec<Value> = vec![];
+
+        let mut offset: usize = 0;
+        while items.len() == offset {
+            let json = call_api(
+                "fina_indicator",
+                &json!({
+                    "ts_code": ticker.to_tushare_code(),
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                }),
+                None,
+                0,
+                false,
+            )
+            .await?;
+
+            if let Some(page_fields) = json["data"]["fields"].as_array() {
+                fields = page_fields.clone();
+            }
+
+            if let Some(page_items) = json["data"]["items"].as_array() {
+                items.extend_from_slice(page_items);
+            }
+
+            offset += PAGE_SIZE;
+        }
+
+        let json = json!({
+            "data": {
+                "fields": fields,
+                "items": items,
+            }
+        });
+
+        let mut fields: HashMap<String, String> = HashMap::new();
+        fields.insert(
+            StockReportPershareField::ReportDate.to_string(),
+            "end_date".to_string(),
+        );
+        fields.insert(StockReportPershareField::Bps.to_string(), "bps".to_string());
+        fields.insert(StockReportPershareField::Eps.to_string(), "eps".to_string());
+        fields.insert(
+            StockReportPershareField::EquityRoe.to_string(),
+            "roe_waa".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::GrossProfit.to_string(),
+            "gross_margin".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::NetProfit.to_string(),
+            "netprofit_margin".to_string(),
+        );
+        fields.insert(
+            StockReportPershareField::Ocfps.to_string(),
+            "ocfps".to_string(),
+        );
+
+        DailySeries::from_tushare_json(&json, "ann_date", &fields)
+    }
+
+    async fn stocks_basic(&self) -> VfResult<Vec<StockBasic>> {
+        let mut result = vec![];
+
+        for list_status in ["L", "D", "P"] {
+            let json = call_api(
+                "stock_basic",
+                &json!({
+                    "list_status": list_status,
+                }),
+                Some("ts_code,name,industry,list_date,delist_date"),
+                30,
+                false,
+            )
+            .await?;
+
+            if let (Some(fields), Some(items)) = (
+                json["data"]["fields"].as_array(),
+                json["data"]["items"].as_array(),
+            ) {
+                for item in items {
+                    if let Some(values) = item.as_array() {
+                        let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                        for (i, field) in fields.iter().enumerate() {
+                            if let Some(field_name) = field.as_str() {
+                                if let Some(value) = values.get(i) {
+                                    json_item.insert(field_name.to_string(), value.clone());
+                                }
+                            }
+                        }
+
+                        if let Some(ticker) = json_item["ts_code"]
+                            .as_str()
+                            .and_then(Ticker::from_tushare_str)
+                        {
+                            result.push(StockBasic {
+                                ticker,
+                                name: json_item["name"].as_str().unwrap_or_default().to_string(),
+                                industry: json_item["industry"]
+                                    .as_str()
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                list_date: json_item["list_date"]
+                                    .as_str()
+                                    .and_then(|s| date_from_str(s).ok()),
+                                delist_date: json_item["delist_date"]
+                                    .as_str()
+                                    .and_then(|s| date_from_str(s).ok()),
+                                lot_size: None,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn trade_dates(&self, exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+        let json = if exchange == "XHKG" {
+            call_api("hk_tradecal", &json!({"is_open": "1"}), None, 0, false).await?
+        } else {
+            let cal_exchange = match exchange {
+                "XBSE" => "BSE",
+                "XSHE" => "SZSE",
+                "XSHG" => "SSE",
+                _ => return Err(super::unsupported(self.name(), Dataset::TradeDates)),
+            };
+            call_api(
+                "trade_cal",
+                &json!({"exchange": cal_exchange, "is_open": "1"}),
+                None,
+                0,
+                false,
+            )
+            .await?
+        };
+
+        let mut dates: HashSet<NaiveDate> = HashSet::new();
+
+        if let (Some(fields), Some(items)) = (
+            json["data"]["fields"].as_array(),
+            json["data"]["items"].as_array(),
+        ) {
+            if let Some(idx_cal_date) = fields.iter().position(|f| f == "cal_date") {
+                for item in items {
+                    if let Some(values) = item.as_array() {
+                        if let Some(trade_date_str) = values[idx_cal_date].as_str() {
+                            if let Ok(date) = date_from_str(trade_date_str) {
+                                dates.insert(date);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(dates)
+    }
+}
+
+/// Kline of daily prices adjusted by factors, which are of different APIs for stocks and funds
+async fn adjusted_kline(
+    ticker: &Ticker,
+    daily_api_name: &str,
+    adj_api_name: &str,
+    adjust: StockDividendAdjust,
+    ignore_cache: bool,
+) -> VfResult<DailySeries> {
+    static PAGE_SIZE: usize = 5000;
+
+    let mut fields: Vec<Value> = vec![];
+    let mut items: Vec<Value> = vec![];
+
+    let mut trade_date_idx: Option<usize> = None;
+    let mut open_idx: Option<usize> = None;
+    let mut close_idx: Option<usize> = None;
+    let mut high_idx: Option<usize> = None;
+    let mut low_idx: Option<usize> = None;
+
+    let mut offset: usize = 0;
+    while items.len() == offset {
+        let json = call_api(
+            daily_api_name,
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+                "limit": PAGE_SIZE,
+                "offset": offset,
+            }),
+            None,
+            0,
+            ignore_cache,
+        )
+        .await?;
+
+        if let Some(page_fields) = json["data"]["fields"].as_array() {
+            trade_date_idx = page_fields.iter().position(|f| f == "trade_date");
+            open_idx = page_fields.iter().position(|f| f == "open");
+            close_idx = page_fields.iter().position(|f| f == "close");
+            high_idx = page_fields.iter().position(|f| f == "high");
+            low_idx = page_fields.iter().position(|f| f == "low");
+
+            fields = page_fields.clone();
+        }
+
+        if let Some(page_items) = json["data"]["items"].as_array() {
+            items.extend_from_slice(page_items);
+        }
+
+        offset += PAGE_SIZE;
+    }
+
+    // Adjustment
+    if let Some(trade_date_idx) = trade_date_idx
+        && let Some(open_idx) = open_idx
+        && let Some(close_idx) = close_idx
+        && let Some(high_idx) = high_idx
+        && let Some(low_idx) = low_idx
+    {
+        let adjust_json = call_api(
+            adj_api_name,
+            &json!({
+                "ts_code": ticker.to_tushare_code(),
+            }),
+            None,
+            0,
+            ignore_cache,
+        )
+        .await?;
+
+        if let Some(adjust_fields) = adjust_json["data"]["fields"].as_array()
+            && let Some(adjust_items) = adjust_json["data"]["items"].as_array()
+        {
+            if let Some(adjust_trade_date_idx) =
+                adjust_fields.iter().position(|f| f == "trade_date")
+                && let Some(adjust_adj_factor_idx) =
+                    adjust_fields.iter().position(|f| f == "adj_factor")
+            {
+                let mut adjust_factors: HashMap<NaiveDate, f64> = HashMap::new();
+                for adjust_item in adjust_items {
+                    if let Some(date) = adjust_item[adjust_trade_date_idx]
+                        .as_str()
+                        .and_then(|s| date_from_str(s).ok())
+                        && let Some(factor) = adjust_item[adjust_adj_factor_idx].as_f64()
+                    {
+                        adjust_factors.insert(date, factor);
+                    }
+                }
+
+                match adjust {
+                    StockDividendAdjust::Backward => {
+                        for item_idx in (0..items.len()).rev() {
+                            if let Some(trade_date) = items[item_idx][trade_date_idx]
+                                .as_str()
+                                .and_then(|s| date_from_str(s).ok())
+                                && let Some(open) = items[item_idx][open_idx].as_f64()
+                                && let Some(close) = items[item_idx][close_idx].as_f64()
+                                && let Some(high) = items[item_idx][high_idx].as_f64()
+                                && let Some(low) = items[item_idx][low_idx].as_f64()
+                            {
+                                if let Some(adjust_factor) = adjust_factors.get(&trade_date) {
+                                    items[item_idx][open_idx] = json!(open * adjust_factor);
+                                    items[item_idx][close_idx] = json!(close * adjust_factor);
+                                    items[item_idx][high_idx] = json!(high * adjust_factor);
+                                    items[item_idx][low_idx] = json!(low * adjust_factor);
+                                } else {
+                                    items.remove(item_idx);
+                                }
+                            }
+                        }
+                    }
+                    StockDividendAdjust::Forward => {
+                        if let Some(latest_adjust_factor) = adjust_factors
+                            .iter()
+                            .max_by_key(|(date, _)| *date)
+                            .map(|(_, factor)| *factor)
+                        {
+                            for item_idx in (0..items.len()).rev() {
+                                if let Some(trade_date) = items[item_idx][trade_date_idx]
+                                    .as_str()
+                                    .and_then(|s| date_from_str(s).ok())
+                                    && let Some(open) = items[item_idx][open_idx].as_f64()
+                                    && let Some(close) = items[item_idx][close_idx].as_f64()
+                                    && let Some(high) = items[item_idx][high_idx].as_f64()
+                                    && let Some(low) = items[item_idx][low_idx].as_f64()
+                                {
+                                    if let Some(adjust_factor) = adjust_factors.get(&trade_date) {
+                                        let factor = latest_adjust_factor / adjust_factor;
+                                        items[item_idx][open_idx] = json!(open * factor);
+                                        items[item_idx][close_idx] = json!(close * factor);
+                                        items[item_idx][high_idx] = json!(high * factor);
+                                        items[item_idx][low_idx] = json!(low * factor);
+                                    } else {
+                                        items.remove(item_idx);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let json = json!({
+        "data": {
+            "fields": fields,
+            "items": items,
+        }
+    });
+
+    let mut fields: HashMap<String, String> = HashMap::new();
+    fields.insert(KlineField::Open.to_string(), "open".to_string());
+    fields.insert(KlineField::Close.to_string(), "close".to_string());
+    fields.insert(KlineField::High.to_string(), "high".to_string());
+    fields.insert(KlineField::Low.to_string(), "low".to_string());
+    fields.insert(KlineField::Volume.to_string(), "vol".to_string());
+
+    DailySeries::from_tushare_json(&json, "trade_date", &fields)
+}
+
+/// HK stocks basic of Tushare `hk_basic`, with board lots of `trade_unit`
+async fn conv_bond_basic_item(ticker: &Ticker) -> VfResult<HashMap<String, Value>> {
+    let json = call_api(
+        "cb_basic",
+        &json!({
+            "ts_code": ticker.to_tushare_code(),
+        }),
+        None,
+        30,
+        false,
+    )
+    .await?;
+
+    if let (Some(fields), Some(values)) = (
+        json["data"]["fields"].as_array(),
+        json["data"]["items"]
+            .as_array()
+            .and_then(|items| items.first())
+            .and_then(Value::as_array),
+    ) {
+        let mut json_item: HashMap<String, Value> = HashMap::new();
+
+        for (i, field) in fields.iter().enumerate() {
+            if let Some(field_name) = field.as_str() {
+                if let Some(value) = values.get(i) {
+                    json_item.insert(field_name.to_string(), value.clone());
+                }
+            }
+        }
+
+        return Ok(json_item);
+    }
+
+    Err(VfError::Invalid {
+        code: "NO_DATA",
+        message: format!("No data from Tushare cb_basic of {ticker}"),
+    })
+}
+
+async fn hk_stocks_basic(params: &Value) -> VfResult<Vec<StockBasic>> {
+    let json = call_api(
+        "hk_basic",
+        params,
+        Some("ts_code,name,list_date,delist_date,trade_unit"),
+        30,
+        false,
+    )
+    .await?;
+
+    let mut result = vec![];
+
+    if let (Some(fields), Some(items)) = (
+        json["data"]["fields"].as_array(),
+        json["data"]["items"].as_array(),
+    ) {
+        for item in items {
+            if let Some(values) = item.as_array() {
+                let mut json_item: HashMap<String, Value> = HashMap::new();
+
+                for (i, field) in fields.iter().enumerate() {
+                    if let Some(field_name) = field.as_str() {
+                        if let Some(value) = values.get(i) {
+                            json_item.insert(field_name.to_string(), value.clone());
+                        }
+                    }
+                }
+
+                if let Some(ticker) = json_item["ts_code"]
+                    .as_str()
+                    .and_then(Ticker::from_tushare_str)
+                {
+                    result.push(StockBasic {
+                        ticker,
+                        name: json_item["name"].as_str().unwrap_or_default().to_string(),
+                        industry: "".to_string(),
+                        list_date: json_item["list_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                        delist_date: json_item["delist_date"]
+                            .as_str()
+                            .and_then(|s| date_from_str(s).ok()),
+                        lot_size: json_item["trade_unit"].as_f64().map(|v| v as u64),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
 pub async fn call_api(
     api_name: &str,
     params: &serde_json::Value,
@@ -16,53 +1629,65 @@ pub async fn call_api(
     ignore_cache: bool,
 ) -> VfResult<serde_json::Value> {
     let cache_key = format!("[TUSHARE]{api_name}?{params}");
+    let cache_only = cache::is_cache_only();
 
-    if !ignore_cache && let Some(data) = cache::get(&cache_key, *CACHE_NO_EXPIRE).await? {
+    if !ignore_cache
+        && let Some(data) = cache::get(&cache_key, *CACHE_NO_EXPIRE || cache_only).await?
+    {
         let bytes: Vec<u8> = compress::decode(&data)?;
         let json: serde_json::Value = serde_json::from_slice(&bytes)?;
 
         Ok(json)
+    } else if cache_only {
+        Err(VfError::NoData {
+            code: "NOT_CACHED",
+            message: format!("No cache of '{cache_key}'"),
+        })
     } else {
-        let request_delay_secs: f64 = std::env::var("TUSHARE_DELAY")
-            .ok()
-            .and_then(|s| s.parse::<f64>().ok())
-            .unwrap_or(1.0);
-        if request_delay_secs > 0.0 {
-            sleep(tokio::time::Duration::from_secs(request_delay_secs as u64)).await;
-        }
+        let cache_key: &str = &cache_key;
+        let bytes = TUSHARE_LIMITER
+            .coalesce(cache_key, || async move {
+                let rate_limit = { tushare_rate_limit(CONFIG.read().await.tushare_points) };
+                let _permit = TUSHARE_LIMITER.acquire(rate_limit).await;
 
-        let tushare_api = { &CONFIG.read().await.tushare_api };
-        let tushare_token = { &CONFIG.read().await.tushare_token };
+                let tushare_api = { &CONFIG.read().await.tushare_api };
+                let tushare_token = { &CONFIG.read().await.tushare_token };
 
-        let bytes = http_post(
-            tushare_api,
-            None,
-            &json!({
-                "api_name": api_name,
-                "token": tushare_token,
-                "params": params,
-                "fields": fields.unwrap_or_default(),
-            }),
-            None,
-            30,
-            3,
-        )
-        .await?;
-        let json: serde_json::Value = serde_json::from_slice(&bytes)?;
+                let bytes = http_post(
+                    tushare_api,
+                    None,
+                    &json!({
+                        "api_name": api_name,
+                        "token": tushare_token,
+                        "params": params,
+                        "fields": fields.unwrap_or_default(),
+                    }),
+                    None,
+                    30,
+                    3,
+                )
+                .await?;
+                let json: serde_json::Value = serde_json::from_slice(&bytes)?;
 
-        if json["code"].as_i64() == Some(0) {
-            if let Ok(data) = compress::encode(&bytes) {
-                let expire = next_data_expire_in_china(expire_days);
-                let _ = cache::upsert(&cache_key, &data, &expire).await;
-            }
+                if json["code"].as_i64() == Some(0) {
+                    if let Ok(data) = compress::encode(&bytes) {
+                        let expire = next_data_expire_in_china(expire_days);
+                        let _ = cache::upsert(cache_key, &data, &expire).await;
+                    }
 
-            Ok(json)
-        } else {
-            Err(VfError::Invalid {
-                code: "INVALID_RESPONSE",
-                message: format!("[{}]{}", json["code"], json["msg"]),
+                    Ok(bytes)
+                } else {
+                    Err(VfError::Invalid {
+                        code: "INVALID_RESPONSE",
+                        message: format!("[{}]{}", json["code"], json["msg"]),
+                    })
+                }
             })
-        }
+            .await?;
+        cache::record_source(cache_key, Some(next_data_expire_in_china(expire_days)));
+        let json: serde_json::Value = serde_json::from_slice(&bytes)?;
+
+        Ok(json)
     }
 }
 
@@ -49,6 +49,9 @@ pub enum VfError {
     #[error("[Parse URL Error] {0}")]
     ParseUrlError(#[from] url::ParseError),
 
+    #[error("[Script Error] {0}")]
+    ScriptError(String),
+
     #[error("[Serde JSON Error] {0}")]
     SerdeJsonError(#[from] ::serde_json::Error),
 
@@ -1,4 +1,167 @@
+use chrono::NaiveDate;
+
+use crate::{
+    STALE_DAYS_LONG,
+    error::{VfError, VfResult},
+    filter::{
+        filter_delisted::is_delisted,
+        filter_invalid::has_invalid_price,
+        filter_liquidity::is_turnover_rate_low,
+        filter_listing::is_listed_days_less,
+        filter_market_cap::{is_circulating_ratio_low, is_market_cap_out_of_range},
+        filter_price::is_price_low,
+        filter_sector::{get_sector, sector_fallback_date},
+        filter_st::is_st,
+    },
+    spec::FilterDefinition,
+    ticker::{Ticker, TickerType},
+};
+
 pub mod filter_delisted;
 pub mod filter_invalid;
+pub mod filter_liquidity;
+pub mod filter_listing;
 pub mod filter_market_cap;
+pub mod filter_price;
+pub mod filter_sector;
 pub mod filter_st;
+
+const FILTER_NAMES: [&str; 9] = [
+    "circulating_ratio",
+    "exclude_delisted",
+    "exclude_invalid_price",
+    "exclude_st",
+    "listing_age",
+    "liquidity",
+    "market_cap",
+    "price",
+    "sector",
+];
+
+pub fn check_filter(filter: &FilterDefinition) -> VfResult<()> {
+    if FILTER_NAMES.contains(&filter.name.as_str()) {
+        Ok(())
+    } else {
+        Err(VfError::Invalid {
+            code: "INVALID_FILTER",
+            message: format!("Unsupported filter '{}'", filter.name),
+        })
+    }
+}
+
+/// Check if the ticker should be removed from the universe, tickers without the required data are removed too
+pub async fn is_filtered_out(
+    filter: &FilterDefinition,
+    ticker: &Ticker,
+    date: &NaiveDate,
+) -> VfResult<bool> {
+    let options = &filter.options;
+
+    // Filters based on stock data do not apply to other types of tickers
+    let is_stock = ticker.r#type == TickerType::Stock;
+
+    match filter.name.as_str() {
+        "circulating_ratio" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            let lower = options.read_f64_in_range("lower", 0.0, 0.0..=1.0);
+            is_circulating_ratio_low(ticker, date, lower).await
+        }
+        "exclude_delisted" => is_delisted(ticker, date).await,
+        "exclude_invalid_price" => has_invalid_price(ticker, date).await,
+        "exclude_st" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            let lookback_days = options.read_u64("lookback_days", STALE_DAYS_LONG as u64);
+            is_st(ticker, date, lookback_days).await
+        }
+        "listing_age" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            let min_days = options.read_u64("min_days", 365);
+            is_listed_days_less(ticker, date, min_days).await
+        }
+        "liquidity" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            let lookback_trade_days = options.read_u64_no_zero("lookback_trade_days", 21);
+            let turnover_rate_lower = options.read_f64_gte("turnover_rate_lower", 0.0, 0.0);
+            is_turnover_rate_low(
+                ticker,
+                date,
+                lookback_trade_days as u32,
+                turnover_rate_lower,
+            )
+            .await
+        }
+        "market_cap" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            // Thresholds are in 100 million
+            let circulating = options.read_bool("circulating", false);
+            let lower = options.read_f64_gte("lower", 0.0, 0.0);
+            let upper = options.read_f64_gt("upper", f64::MAX / 1e8, 0.0);
+            is_market_cap_out_of_range(ticker, date, circulating, lower * 1e8, upper * 1e8).await
+        }
+        "price" => {
+            let lower = options.read_f64_gte("lower", 0.0, 0.0);
+            is_price_low(ticker, date, lower).await
+        }
+        "sector" => {
+            if !is_stock {
+                return Ok(false);
+            }
+
+            let sector_prefix = options.read_str("sector_prefix", "SW1");
+            let read_patterns = |key: &str| -> Vec<String> {
+                options
+                    .read_array(key)
+                    .map(|array| {
+                        array
+                            .iter()
+                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let include = read_patterns("include");
+            let exclude = read_patterns("exclude");
+
+            get_sector(ticker, sector_prefix, date).await.map(|sector| {
+                if let Some(sector) = sector {
+                    (!include.is_empty() && !include.iter().any(|s| sector.contains(s)))
+                        || exclude.iter().any(|s| sector.contains(s))
+                } else {
+                    !include.is_empty()
+                }
+            })
+        }
+        // Unsupported filters are rejected when the fund definition is loaded
+        _ => check_filter(filter).map(|_| false),
+    }
+}
+
+/// Date of the current membership (e.g. sectors) if the filter falls back to it for lack of the
+/// historical one on the date
+pub async fn membership_fallback_date(
+    filter: &FilterDefinition,
+    date: &NaiveDate,
+) -> Option<NaiveDate> {
+    match filter.name.as_str() {
+        "sector" => {
+            let sector_prefix = filter.options.read_str("sector_prefix", "SW1");
+            sector_fallback_date(sector_prefix, date).await
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDate;
+
+use crate::{
+    error::VfResult,
+    financial::stock::{StockIndicatorField, fetch_stock_indicators},
+    ticker::Ticker,
+    utils::stats::mean,
+};
+
+pub async fn is_turnover_rate_low(
+    ticker: &Ticker,
+    date: &NaiveDate,
+    lookback_trade_days: u32,
+    threshold: f64,
+) -> VfResult<bool> {
+    let stock_indicators = fetch_stock_indicators(ticker).await?;
+    let turnover_rates: Vec<f64> = stock_indicators
+        .get_latest_values::<f64>(
+            date,
+            false,
+            &StockIndicatorField::TurnoverRate.to_string(),
+            lookback_trade_days,
+        )
+        .into_iter()
+        .map(|(_, v)| v)
+        .collect();
+
+    if let Some(turnover_rate) = mean(&turnover_rates) {
+        Ok(turnover_rate < threshold)
+    } else {
+        Ok(true)
+    }
+}
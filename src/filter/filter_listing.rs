@@ -0,0 +1,17 @@
+use chrono::NaiveDate;
+
+use crate::{error::VfResult, financial::stock::fetch_stock_basic, ticker::Ticker};
+
+pub async fn is_listed_days_less(
+    ticker: &Ticker,
+    date: &NaiveDate,
+    min_days: u64,
+) -> VfResult<bool> {
+    let stock_basic = fetch_stock_basic(ticker).await?;
+
+    if let Some(list_date) = stock_basic.list_date {
+        Ok((*date - list_date).num_days() < min_days as i64)
+    } else {
+        Ok(true)
+    }
+}
@@ -12,3 +12,15 @@ pub async fn is_circulating_ratio_low(
 
     Ok(circulating < total * threshold)
 }
+
+pub async fn is_market_cap_out_of_range(
+    ticker: &Ticker,
+    date: &NaiveDate,
+    circulating: bool,
+    lower: f64,
+    upper: f64,
+) -> VfResult<bool> {
+    let market_cap = calc_stock_market_cap(ticker, date, circulating).await?;
+
+    Ok(market_cap < lower || market_cap > upper)
+}
@@ -0,0 +1,53 @@
+use chrono::NaiveDate;
+
+use crate::{
+    STALE_DAYS_SHORT,
+    error::VfResult,
+    financial::{
+        KlineField, PriceType, get_ticker_price,
+        stock::{StockDividendAdjust, fetch_stock_kline},
+    },
+    ticker::{Ticker, TickerType},
+};
+
+/// Compare stocks with the unadjusted close price, as a price floor is about the quoted price,
+/// other tickers with the close price of their own kline
+pub async fn is_price_low(ticker: &Ticker, date: &NaiveDate, threshold: f64) -> VfResult<bool> {
+    let price = match ticker.r#type {
+        TickerType::Stock => {
+            let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
+            kline
+                .get_latest_value::<f64>(
+                    date,
+                    STALE_DAYS_SHORT,
+                    false,
+                    &KlineField::Close.to_string(),
+                )
+                .map(|(_, v)| v)
+        }
+        _ => get_ticker_price(ticker, date, false, &PriceType::Close).await?,
+    };
+
+    if let Some(price) = price {
+        Ok(price < threshold)
+    } else {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::utils::datetime::date_from_str;
+
+    #[tokio::test]
+    async fn test_is_price_low() {
+        let etf = Ticker::from_str("510300.SH").unwrap();
+        let date = date_from_str("2024-12-31").unwrap();
+
+        assert!(is_price_low(&etf, &date, 4.0).await.unwrap());
+        assert!(!is_price_low(&etf, &date, 3.9).await.unwrap());
+    }
+}
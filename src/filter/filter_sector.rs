@@ -0,0 +1,21 @@
+use chrono::NaiveDate;
+
+use crate::{error::VfResult, financial::sector::fetch_sector_membership, ticker::Ticker};
+
+pub async fn get_sector(
+    ticker: &Ticker,
+    sector_prefix: &str,
+    date: &NaiveDate,
+) -> VfResult<Option<String>> {
+    let membership = fetch_sector_membership(sector_prefix, date).await?;
+
+    Ok(membership.members.get(ticker).map(|s| s.replace("\"", "")))
+}
+
+/// Date of the current sectors if sectors of the date fall back to them
+pub async fn sector_fallback_date(sector_prefix: &str, date: &NaiveDate) -> Option<NaiveDate> {
+    fetch_sector_membership(sector_prefix, date)
+        .await
+        .ok()
+        .and_then(|membership| membership.is_fallback.then_some(membership.date))
+}
@@ -11,6 +11,15 @@ use crate::{
             ConvBondDailyField, fetch_conv_bond_basic, fetch_conv_bond_daily,
             fetch_conv_bond_kline, fetch_conv_bond_kline_ignore_cache,
         },
+        fund::{
+            fetch_fund_basic, fetch_fund_kline, fetch_fund_kline_ignore_cache,
+            fetch_fund_kline_ignore_cache_with_ds, fetch_fund_kline_with_ds,
+        },
+        fx::Currency,
+        index::{
+            fetch_index_kline, fetch_index_kline_ignore_cache,
+            fetch_index_kline_ignore_cache_with_ds, fetch_index_kline_with_ds,
+        },
         stock::{
             StockDividendAdjust, fetch_stock_basic, fetch_stock_detail, fetch_stock_kline,
             fetch_stock_kline_ignore_cache, fetch_stock_kline_ignore_cache_with_ds,
@@ -21,9 +30,13 @@ use crate::{
 };
 
 pub mod bond;
+pub mod economy;
+pub mod fund;
+pub mod fx;
 pub mod helper;
 pub mod index;
 pub mod market;
+pub mod search;
 pub mod sector;
 pub mod stock;
 
@@ -37,10 +50,13 @@ pub enum KlineField {
     Volume,
 }
 
+/// Free and reserved cash are of the base currency, while cash of other currencies is kept
+/// apart from selling tickers quoted in them until spent or exchanged
 #[derive(Debug, Clone)]
 pub struct Portfolio {
     pub free_cash: f64,
     pub reserved_cash: HashMap<Ticker, (f64, NaiveDate)>,
+    pub currency_cash: HashMap<Currency, f64>,
     pub positions: HashMap<Ticker, u64>,
 }
 
@@ -49,6 +65,7 @@ impl Portfolio {
         Self {
             free_cash: cash,
             reserved_cash: HashMap::new(),
+            currency_cash: HashMap::new(),
             positions: HashMap::new(),
         }
     }
@@ -63,10 +80,15 @@ pub enum PriceType {
     Open,
 }
 
+/// Kline adjusted backward, indexes have no dividends so they are not adjusted
 pub async fn get_ticker_kline(ticker: &Ticker, ignore_cache: bool) -> VfResult<DailySeries> {
     if ignore_cache {
         match ticker.r#type {
             TickerType::ConvBond => fetch_conv_bond_kline_ignore_cache(ticker).await,
+            TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit => {
+                fetch_fund_kline_ignore_cache(ticker).await
+            }
+            TickerType::Index => fetch_index_kline_ignore_cache(&ticker.to_tickers_index()).await,
             TickerType::Stock => {
                 fetch_stock_kline_ignore_cache(ticker, StockDividendAdjust::Backward).await
             }
@@ -74,6 +96,10 @@ pub async fn get_ticker_kline(ticker: &Ticker, ignore_cache: bool) -> VfResult<D
     } else {
         match ticker.r#type {
             TickerType::ConvBond => fetch_conv_bond_kline(ticker).await,
+            TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit => {
+                fetch_fund_kline(ticker).await
+            }
+            TickerType::Index => fetch_index_kline(&ticker.to_tickers_index()).await,
             TickerType::Stock => fetch_stock_kline(ticker, StockDividendAdjust::Backward).await,
         }
     }
@@ -87,6 +113,12 @@ pub async fn get_ticker_kline_with_ds(
     if ignore_cache {
         match ticker.r#type {
             TickerType::ConvBond => fetch_conv_bond_kline_ignore_cache(ticker).await,
+            TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit => {
+                fetch_fund_kline_ignore_cache_with_ds(ticker, ds_name).await
+            }
+            TickerType::Index => {
+                fetch_index_kline_ignore_cache_with_ds(&ticker.to_tickers_index(), ds_name).await
+            }
             TickerType::Stock => {
                 fetch_stock_kline_ignore_cache_with_ds(
                     ticker,
@@ -99,6 +131,12 @@ pub async fn get_ticker_kline_with_ds(
     } else {
         match ticker.r#type {
             TickerType::ConvBond => fetch_conv_bond_kline(ticker).await,
+            TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit => {
+                fetch_fund_kline_with_ds(ticker, ds_name).await
+            }
+            TickerType::Index => {
+                fetch_index_kline_with_ds(&ticker.to_tickers_index(), ds_name).await
+            }
             TickerType::Stock => {
                 fetch_stock_kline_with_ds(ticker, StockDividendAdjust::Backward, ds_name).await
             }
@@ -154,8 +192,8 @@ pub async fn get_ticker_price(
                     .map(|(_, price)| price))
             }
         }
-        TickerType::Stock => {
-            let kline = fetch_stock_kline(ticker, StockDividendAdjust::Backward).await?;
+        _ => {
+            let kline = get_ticker_kline(ticker, false).await?;
             if *price_type == PriceType::Mid {
                 if let Some((date_high, high)) = kline.get_latest_value::<f64>(
                     date,
@@ -201,6 +239,10 @@ pub async fn get_ticker_price(
 pub async fn get_ticker_title(ticker: &Ticker) -> String {
     if let Ok(name) = match ticker.r#type {
         TickerType::ConvBond => fetch_conv_bond_basic(ticker).await.map(|d| d.name),
+        TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit => {
+            fetch_fund_basic(ticker).await.map(|d| d.name)
+        }
+        TickerType::Index => Ok("".to_string()),
         TickerType::Stock => fetch_stock_basic(ticker).await.map(|d| d.name),
     } {
         if !name.is_empty() {
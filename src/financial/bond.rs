@@ -1,18 +1,23 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::{collections::HashMap, mem::size_of, sync::LazyLock};
 
-use chrono::{Months, NaiveDate};
-use dashmap::DashMap;
-use serde_json::{Value, json};
+use chrono::NaiveDate;
+use regex::Regex;
 
 use crate::{
+    cache::{
+        memory::{self, MemorySize},
+        track_sources,
+    },
     data::series::*,
-    ds::tushare,
+    ds::{Dataset, fetch_with_fallback},
     error::*,
     financial::KlineField,
     ticker::Ticker,
-    utils::datetime::{date_from_str, date_to_str},
+    utils::{datetime::date_to_str, text::parse_chinese_number},
 };
 
+pub mod analytics;
+
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
 pub struct ConvBondBasic {
@@ -21,9 +26,46 @@ pub struct ConvBondBasic {
     pub issue_size: Option<f64>,
     pub remain_size: Option<f64>,
     pub par_value: Option<f64>,
+    pub list_date: Option<NaiveDate>,
     pub expire_date: Option<NaiveDate>,
 }
 
+/// Redemption announcement, `is_call` is the status text like `公告实施强赎` or `公告不强赎`
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct ConvBondCall {
+    pub ticker: Ticker,
+    pub announce_date: NaiveDate,
+    pub is_call: String,
+    pub call_date: Option<NaiveDate>,
+    pub call_price: Option<f64>,
+}
+
+/// Clauses of a conv bond from its prospectus, prices are per `par_value`
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct ConvBondClauses {
+    pub ticker: Ticker,
+    pub par_value: f64,
+    pub value_date: Option<NaiveDate>,
+    pub maturity_date: Option<NaiveDate>,
+    pub conversion_start_date: Option<NaiveDate>,
+    pub coupons: Vec<ConvBondCoupon>,
+    /// Redemption price at maturity including the last coupon
+    pub maturity_redemption_price: Option<f64>,
+    pub call: Option<ConvBondTrigger>,
+    pub put: Option<ConvBondTrigger>,
+    pub reset: Option<ConvBondTrigger>,
+}
+
+/// Coupon rate in percent of an interest year, paid at the end date
+#[derive(Clone, Debug)]
+pub struct ConvBondCoupon {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub rate: f64,
+}
+
 #[derive(strum::Display, strum::EnumString)]
 #[strum(ascii_case_insensitive)]
 pub enum ConvBondDailyField {
@@ -46,69 +88,179 @@ pub struct ConvBondIssue {
     pub issue_size: Option<f64>,
 }
 
-pub async fn fetch_conv_bond_basic(ticker: &Ticker) -> VfResult<ConvBondBasic> {
-    let cache_key = format!("tushare:{ticker}");
-    if let Some(result) = CONV_BOND_BASIC_CACHE.get(&cache_key) {
-        return Ok(result.clone());
-    }
+#[derive(strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum ConvBondShareField {
+    ConversionPrice,
+    RemainSize,
+}
 
-    let json = tushare::call_api(
-        "cb_basic",
-        &json!({
-            "ts_code": ticker.to_tushare_code(),
-        }),
-        None,
-        30,
-        false,
-    )
-    .await?;
-
-    if let (Some(fields), Some(items)) = (
-        json["data"]["fields"].as_array(),
-        json["data"]["items"].as_array(),
-    ) {
-        if let Some(item) = items.first() {
-            if let Some(values) = item.as_array() {
-                let mut json_item: HashMap<String, Value> = HashMap::new();
-
-                for (i, field) in fields.iter().enumerate() {
-                    if let Some(field_name) = field.as_str() {
-                        if let Some(value) = values.get(i) {
-                            json_item.insert(field_name.to_string(), value.clone());
-                        }
-                    }
-                }
+/// Clause triggered when at least `required_days` of `window_days` consecutive trading days close
+/// at `price_ratio` of the conversion price, above it for calls and below it for puts and resets
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvBondTrigger {
+    pub window_days: usize,
+    pub required_days: usize,
+    pub price_ratio: f64,
+}
+
+impl ConvBondTrigger {
+    pub const DEFAULT_CALL: Self = Self {
+        window_days: 30,
+        required_days: 15,
+        price_ratio: 1.3,
+    };
 
-                if let Some(ticker) = json_item["ts_code"]
-                    .as_str()
-                    .and_then(Ticker::from_tushare_str)
-                {
-                    let result = ConvBondBasic {
-                        ticker,
-                        name: json_item["bond_short_name"]
-                            .as_str()
-                            .unwrap_or_default()
-                            .to_string(),
-                        issue_size: json_item["issue_size"].as_f64(),
-                        remain_size: json_item["remain_size"].as_f64(),
-                        par_value: json_item["par"].as_f64(),
-                        expire_date: json_item["delist_date"]
-                            .as_str()
-                            .and_then(|s| date_from_str(s).ok()),
-                    };
-
-                    CONV_BOND_BASIC_CACHE.insert(cache_key, result.clone());
-
-                    return Ok(result);
+    pub const DEFAULT_PUT: Self = Self {
+        window_days: 30,
+        required_days: 30,
+        price_ratio: 0.7,
+    };
+
+    pub const DEFAULT_RESET: Self = Self {
+        window_days: 30,
+        required_days: 15,
+        price_ratio: 0.85,
+    };
+
+    /// Trigger of clause text like `连续三十个交易日中至少有十五个交易日的收盘价格不低于当期转股价格的
+    /// 130%`, or the default if the text is not in such a form
+    pub fn from_clause(clause: &str, default: Self) -> Self {
+        static WINDOW_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"连续([零〇一二两三四五六七八九十百\d]+)个交易日").unwrap()
+        });
+        static REQUIRED_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?:至少|不少于)有?([零〇一二两三四五六七八九十百\d]+)个交易日").unwrap()
+        });
+        static RATIO_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"转股价格?的(\d+(?:\.\d+)?)[%％]").unwrap());
+
+        let window_days = WINDOW_REGEX
+            .captures(clause)
+            .and_then(|c| parse_chinese_number(&c[1]));
+        let required_days = REQUIRED_REGEX
+            .captures(clause)
+            .and_then(|c| parse_chinese_number(&c[1]))
+            .or(window_days);
+        let price_ratio = RATIO_REGEX
+            .captures(clause)
+            .and_then(|c| c[1].parse::<f64>().ok());
+
+        match (window_days, required_days, price_ratio) {
+            (Some(window_days), Some(required_days), Some(price_ratio))
+                if required_days > 0 && required_days <= window_days =>
+            {
+                Self {
+                    window_days: window_days as usize,
+                    required_days: required_days as usize,
+                    price_ratio: price_ratio / 100.0,
                 }
             }
+            _ => default,
         }
     }
+}
 
-    Err(VfError::Invalid {
-        code: "NO_DATA",
-        message: format!("No data from Tushare cb_basic of {ticker}"),
-    })
+impl MemorySize for ConvBondBasic {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.ticker.memory_size() + self.name.capacity()
+    }
+}
+
+impl MemorySize for ConvBondCall {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.ticker.memory_size() + self.is_call.capacity()
+    }
+}
+
+impl MemorySize for ConvBondClauses {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self.ticker.memory_size()
+            + self.coupons.capacity() * size_of::<ConvBondCoupon>()
+    }
+}
+
+impl MemorySize for ConvBondIssue {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.ticker.memory_size() + self.name.capacity()
+    }
+}
+
+pub async fn fetch_conv_bond_basic(ticker: &Ticker) -> VfResult<ConvBondBasic> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::ConvBondBasic, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondBasic,
+        |ds| async move { ds.conv_bond_basic(ticker).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::ConvBondBasic, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+/// All conv bonds ever listed
+pub async fn fetch_conv_bonds_basic() -> VfResult<Vec<ConvBondBasic>> {
+    let cache_key = "conv_bonds_basic".to_string();
+    if let Some(result) = memory::get(Dataset::ConvBondsBasic, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondsBasic,
+        |ds| async move { ds.conv_bonds_basic().await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::ConvBondsBasic, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+/// Redemption announcements sorted by announce dates
+pub async fn fetch_conv_bond_calls(ticker: &Ticker) -> VfResult<Vec<ConvBondCall>> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::ConvBondCalls, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondCalls,
+        |ds| async move { ds.conv_bond_calls(ticker).await },
+    ))
+    .await;
+    let mut result = result?;
+    result.sort_by_key(|c| c.announce_date);
+    memory::insert(Dataset::ConvBondCalls, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+pub async fn fetch_conv_bond_clauses(ticker: &Ticker) -> VfResult<ConvBondClauses> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::ConvBondClauses, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondClauses,
+        |ds| async move { ds.conv_bond_clauses(ticker).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(
+        Dataset::ConvBondClauses,
+        &cache_key,
+        result.clone(),
+        sources,
+    );
+
+    Ok(result)
 }
 
 pub async fn fetch_conv_bond_daily(ticker: &Ticker) -> VfResult<DailySeries> {
@@ -141,146 +293,59 @@ pub async fn fetch_conv_bond_kline_ignore_cache(ticker: &Ticker) -> VfResult<Dai
     daily.subset_by_columns(&fields)
 }
 
+pub async fn fetch_conv_bond_shares(ticker: &Ticker) -> VfResult<DailySeries> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::ConvBondShares, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondShares,
+        |ds| async move { ds.conv_bond_shares(ticker).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::ConvBondShares, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
 pub async fn fetch_conv_bonds(
     date: &NaiveDate,
     lookback_months: u32,
 ) -> VfResult<Vec<ConvBondIssue>> {
-    let cache_key = format!("tushare:{}/{lookback_months}", date_to_str(date));
-    if let Some(result) = CONV_BONDS_CACHE.get(&cache_key) {
-        return Ok(result.clone());
-    }
-
-    let json = tushare::call_api(
-        "cb_issue",
-        &json!({
-            "start_date": (*date - Months::new(lookback_months)).format("%Y%m%d").to_string(),
-            "end_date": date.format("%Y%m%d").to_string(),
-        }),
-        None,
-        30,
-        false,
-    )
-    .await?;
-
-    let mut result = vec![];
-
-    if let (Some(fields), Some(items)) = (
-        json["data"]["fields"].as_array(),
-        json["data"]["items"].as_array(),
-    ) {
-        for item in items {
-            if let Some(values) = item.as_array() {
-                let mut json_item: HashMap<String, Value> = HashMap::new();
-
-                for (i, field) in fields.iter().enumerate() {
-                    if let Some(field_name) = field.as_str() {
-                        if let Some(value) = values.get(i) {
-                            json_item.insert(field_name.to_string(), value.clone());
-                        }
-                    }
-                }
-
-                if let Some(ticker) = json_item["ts_code"]
-                    .as_str()
-                    .and_then(Ticker::from_tushare_str)
-                {
-                    let cb = ConvBondIssue {
-                        ticker,
-                        name: json_item["onl_name"]
-                            .as_str()
-                            .unwrap_or_default()
-                            .to_string(),
-                        issue_size: json_item["issue_size"].as_f64(),
-                    };
-
-                    result.push(cb);
-                }
-            }
-        }
+    let cache_key = format!("{}/{lookback_months}", date_to_str(date));
+    if let Some(result) = memory::get(Dataset::ConvBondIssues, &cache_key) {
+        return Ok(result);
     }
 
-    CONV_BONDS_CACHE.insert(cache_key, result.clone());
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondIssues,
+        |ds| async move { ds.conv_bond_issues(date, lookback_months).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::ConvBondIssues, &cache_key, result.clone(), sources);
 
     Ok(result)
 }
 
-static CONV_BOND_BASIC_CACHE: LazyLock<DashMap<String, ConvBondBasic>> =
-    LazyLock::new(DashMap::new);
-static CONV_BOND_DAILY_CACHE: LazyLock<DashMap<String, DailySeries>> = LazyLock::new(DashMap::new);
-static CONV_BONDS_CACHE: LazyLock<DashMap<String, Vec<ConvBondIssue>>> =
-    LazyLock::new(DashMap::new);
-
 async fn fetch_conv_bond_daily_with_ignore_cache(
     ticker: &Ticker,
     ignore_cache: bool,
 ) -> VfResult<DailySeries> {
-    let cache_key = format!("tushare:{ticker}");
-    if !ignore_cache && let Some(result) = CONV_BOND_DAILY_CACHE.get(&cache_key) {
-        return Ok(result.clone());
+    let cache_key = ticker.to_string();
+    if !ignore_cache && let Some(result) = memory::get(Dataset::ConvBondDaily, &cache_key) {
+        return Ok(result);
     }
 
-    let mut json = tushare::call_api(
-        "cb_daily",
-        &json!({
-            "ts_code": ticker.to_tushare_code(),
-        }),
-        Some("trade_date,open,close,high,low,vol,bond_value,cb_value,bond_over_rate,cb_over_rate"),
-        0,
-        ignore_cache,
-    )
-    .await?;
-
-    let check_field_idxs = {
-        let mut idxs = vec![];
-        if let Some(fields) = json["data"]["fields"].as_array() {
-            for (i, name) in fields.iter().enumerate() {
-                if name == "open" || name == "close" || name == "high" || name == "low" {
-                    idxs.push(i);
-                }
-            }
-        }
-        idxs
-    };
-
-    if let Some(data) = json.get_mut("data") {
-        if let Some(items) = data.get_mut("items").and_then(Value::as_array_mut) {
-            for item in items.iter_mut() {
-                for field_idx in &check_field_idxs {
-                    if let Some(v) = item[field_idx].as_f64() {
-                        if v == 0.0 {
-                            item[field_idx] = Value::Null;
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    let mut fields: HashMap<String, String> = HashMap::new();
-    fields.insert(ConvBondDailyField::Open.to_string(), "open".to_string());
-    fields.insert(ConvBondDailyField::Close.to_string(), "close".to_string());
-    fields.insert(ConvBondDailyField::High.to_string(), "high".to_string());
-    fields.insert(ConvBondDailyField::Low.to_string(), "low".to_string());
-    fields.insert(ConvBondDailyField::Volume.to_string(), "vol".to_string());
-    fields.insert(
-        ConvBondDailyField::StraightValue.to_string(),
-        "bond_value".to_string(),
-    );
-    fields.insert(
-        ConvBondDailyField::ConversionValue.to_string(),
-        "cb_value".to_string(),
-    );
-    fields.insert(
-        ConvBondDailyField::StraightPremium.to_string(),
-        "bond_over_rate".to_string(),
-    );
-    fields.insert(
-        ConvBondDailyField::ConversionPremium.to_string(),
-        "cb_over_rate".to_string(),
-    );
-
-    let result = DailySeries::from_tushare_json(&json, "trade_date", &fields)?;
-    CONV_BOND_DAILY_CACHE.insert(cache_key, result.clone());
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::ConvBondDaily,
+        |ds| async move { ds.conv_bond_daily(ticker, ignore_cache).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::ConvBondDaily, &cache_key, result.clone(), sources);
 
     Ok(result)
 }
@@ -318,11 +383,55 @@ mod tests {
         assert_eq!(detail.name, "南药转债");
     }
 
+    #[tokio::test]
+    async fn test_fetch_conv_bond_clauses() {
+        let ticker = Ticker::from_str("110098").unwrap();
+        let clauses = fetch_conv_bond_clauses(&ticker).await.unwrap();
+
+        assert_eq!(clauses.coupons.len(), 6);
+        assert_eq!(clauses.maturity_redemption_price, Some(110.0));
+        assert_eq!(clauses.call, Some(ConvBondTrigger::DEFAULT_CALL));
+        assert_eq!(
+            clauses.reset,
+            Some(ConvBondTrigger {
+                window_days: 20,
+                required_days: 10,
+                price_ratio: 0.9,
+            })
+        );
+    }
+
+    #[test]
+    fn test_conv_bond_trigger_from_clause() {
+        assert_eq!(
+            ConvBondTrigger::from_clause(
+                "在本次发行的可转债最后两个计息年度，如果公司股票在任何连续三十个交易日的收盘价格低于当期转股价的70%时，可转债持有人有权将其持有的可转债全部或部分按面值加上当期应计利息的价格回售给公司。",
+                ConvBondTrigger::DEFAULT_PUT,
+            ),
+            ConvBondTrigger::DEFAULT_PUT
+        );
+        assert_eq!(
+            ConvBondTrigger::from_clause(
+                "如果公司股票连续20个交易日中至少有15个交易日的收盘价不低于当期转股价格的125%",
+                ConvBondTrigger::DEFAULT_CALL,
+            ),
+            ConvBondTrigger {
+                window_days: 20,
+                required_days: 15,
+                price_ratio: 1.25,
+            }
+        );
+        assert_eq!(
+            ConvBondTrigger::from_clause("未约定", ConvBondTrigger::DEFAULT_RESET),
+            ConvBondTrigger::DEFAULT_RESET
+        );
+    }
+
     #[tokio::test]
     async fn test_fetch_conv_bonds() {
         let date = date_from_str("2025-08-08").unwrap();
         let bonds = fetch_conv_bonds(&date, 12).await.unwrap();
 
-        assert!(bonds.len() > 0);
+        assert!(!bonds.is_empty());
     }
 }
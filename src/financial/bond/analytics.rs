@@ -0,0 +1,315 @@
+use chrono::{Days, NaiveDate};
+
+use crate::{
+    DAYS_PER_YEAR, STALE_DAYS_LONG, STALE_DAYS_SHORT,
+    error::VfResult,
+    financial::bond::{
+        ConvBondClauses, ConvBondDailyField, ConvBondShareField, ConvBondTrigger,
+        fetch_conv_bond_calls, fetch_conv_bond_clauses, fetch_conv_bond_daily,
+        fetch_conv_bond_shares,
+    },
+    ticker::Ticker,
+};
+
+/// Point-in-time analytics of a conv bond, premiums and YTM are in percent
+#[derive(Clone, Debug)]
+pub struct ConvBondAnalysis {
+    pub ticker: Ticker,
+    /// Date of the latest daily data
+    pub date: NaiveDate,
+    pub price: f64,
+    pub double_low: f64,
+    pub ytm: Option<f64>,
+    pub remaining_days: Option<i64>,
+    /// Change ratio of the remain size in the last 90 days
+    pub remain_size_change: Option<f64>,
+    /// Forced redemption or redemption at maturity has been announced
+    pub call_announced: bool,
+    pub call_progress: Option<ConvBondTriggerProgress>,
+}
+
+impl ConvBondAnalysis {
+    /// Whether the bond is announced to be redeemed, or its call clause is to be triggered within
+    /// the margin days
+    pub fn is_call_imminent(&self, margin_days: usize) -> bool {
+        self.call_announced
+            || self
+                .call_progress
+                .is_some_and(|p| p.days_to_trigger() <= margin_days)
+    }
+}
+
+/// Days hitting the trigger price in the latest window of trading days
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvBondTriggerProgress {
+    pub hit_days: usize,
+    pub required_days: usize,
+    pub window_days: usize,
+}
+
+impl ConvBondTriggerProgress {
+    #[allow(dead_code)]
+    pub fn is_triggered(&self) -> bool {
+        self.hit_days >= self.required_days
+    }
+
+    /// Least trading days to trigger if all following days hit
+    pub fn days_to_trigger(&self) -> usize {
+        self.required_days.saturating_sub(self.hit_days)
+    }
+}
+
+/// Analytics of the conv bond before the date, `None` if there is no recent daily data
+pub async fn analyze_conv_bond(
+    ticker: &Ticker,
+    date: &NaiveDate,
+) -> VfResult<Option<ConvBondAnalysis>> {
+    let daily = fetch_conv_bond_daily(ticker).await?;
+    let (Some((latest_date, price)), Some((_, conversion_premium))) = (
+        daily.get_latest_value::<f64>(
+            date,
+            STALE_DAYS_SHORT,
+            false,
+            &ConvBondDailyField::Close.to_string(),
+        ),
+        daily.get_latest_value::<f64>(
+            date,
+            STALE_DAYS_SHORT,
+            false,
+            &ConvBondDailyField::ConversionPremium.to_string(),
+        ),
+    ) else {
+        return Ok(None);
+    };
+
+    let clauses = fetch_conv_bond_clauses(ticker).await?;
+
+    let progress = |trigger: Option<ConvBondTrigger>, above: bool| {
+        trigger.map(|trigger| {
+            let values: Vec<f64> = daily
+                .get_latest_values::<f64>(
+                    date,
+                    false,
+                    &ConvBondDailyField::ConversionValue.to_string(),
+                    trigger.window_days as u32,
+                )
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect();
+            calc_trigger_progress(&values, &trigger, clauses.par_value, above)
+        })
+    };
+    let is_convertible = clauses.conversion_start_date.is_none_or(|d| d <= *date);
+
+    let call_announced = fetch_conv_bond_calls(ticker)
+        .await?
+        .iter()
+        .rfind(|c| c.announce_date <= *date)
+        .is_some_and(|c| c.is_call.contains("实施") || c.is_call.contains("到期赎回"));
+
+    let shares = fetch_conv_bond_shares(ticker).await?;
+    let remain_size_at = |date: &NaiveDate| {
+        shares
+            .get_latest_value::<f64>(
+                date,
+                STALE_DAYS_LONG,
+                false,
+                &ConvBondShareField::RemainSize.to_string(),
+            )
+            .map(|(_, v)| v)
+    };
+    let remain_size = remain_size_at(date);
+    let remain_size_change = remain_size
+        .zip(remain_size_at(&(*date - Days::new(90))))
+        .filter(|&(_, prev)| prev > 0.0)
+        .map(|(size, prev)| size / prev - 1.0);
+
+    Ok(Some(ConvBondAnalysis {
+        ticker: ticker.clone(),
+        date: latest_date,
+        price,
+        double_low: calc_double_low(price, conversion_premium),
+        ytm: calc_ytm(&clauses, date, price).map(|y| y * 100.0),
+        remaining_days: clauses.maturity_date.map(|d| (d - *date).num_days()),
+        remain_size_change,
+        call_announced,
+        call_progress: progress(clauses.call.filter(|_| is_convertible), true),
+    }))
+}
+
+/// Sum of the price and the conversion premium in percent
+pub fn calc_double_low(price: f64, conversion_premium: f64) -> f64 {
+    price + conversion_premium
+}
+
+/// Annually compounded yield to maturity of the price on the date, by the remaining coupons and
+/// the redemption price at maturity which replaces the last coupon
+pub fn calc_ytm(clauses: &ConvBondClauses, date: &NaiveDate, price: f64) -> Option<f64> {
+    let maturity_date = clauses.maturity_date.filter(|d| d > date)?;
+    if price <= 0.0 {
+        return None;
+    }
+
+    let par_value = clauses.par_value;
+    let mut cash_flows: Vec<(NaiveDate, f64)> = vec![];
+    if let Some((last, coupons)) = clauses.coupons.split_last() {
+        for coupon in coupons.iter().filter(|c| c.end_date > *date) {
+            cash_flows.push((coupon.end_date, par_value * coupon.rate / 100.0));
+        }
+        cash_flows.push((
+            maturity_date,
+            clauses
+                .maturity_redemption_price
+                .unwrap_or(par_value * (1.0 + last.rate / 100.0)),
+        ));
+    } else {
+        cash_flows.push((
+            maturity_date,
+            clauses.maturity_redemption_price.unwrap_or(par_value),
+        ));
+    }
+
+    let pv = |ytm: f64| -> f64 {
+        cash_flows
+            .iter()
+            .map(|(d, v)| {
+                let years = (*d - *date).num_days() as f64 / DAYS_PER_YEAR;
+                v / (1.0 + ytm).powf(years)
+            })
+            .sum()
+    };
+
+    let (mut lower, mut upper) = (-0.99, 10.0);
+    if pv(lower) < price || pv(upper) > price {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (lower + upper) / 2.0;
+        if pv(mid) > price {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+
+    Some((lower + upper) / 2.0)
+}
+
+/// Progress of the trigger on the latest conversion values per 100 of par value, which are at
+/// or above the trigger price for calls, or below it for puts and resets
+fn calc_trigger_progress(
+    conversion_values: &[f64],
+    trigger: &ConvBondTrigger,
+    par_value: f64,
+    above: bool,
+) -> ConvBondTriggerProgress {
+    let trigger_value = trigger.price_ratio * par_value;
+    let hit_days = conversion_values
+        .iter()
+        .rev()
+        .take(trigger.window_days)
+        .filter(|&&v| {
+            if above {
+                v >= trigger_value
+            } else {
+                v < trigger_value
+            }
+        })
+        .count();
+
+    ConvBondTriggerProgress {
+        hit_days,
+        required_days: trigger.required_days,
+        window_days: trigger.window_days,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{financial::bond::ConvBondCoupon, utils::datetime::date_from_str};
+
+    #[test]
+    fn test_calc_ytm() {
+        let date = date_from_str("2025-01-01").unwrap();
+        let clauses = ConvBondClauses {
+            ticker: Ticker::from_str("110098").unwrap(),
+            par_value: 100.0,
+            value_date: None,
+            maturity_date: Some(date_from_str("2027-01-01").unwrap()),
+            conversion_start_date: None,
+            coupons: vec![
+                ConvBondCoupon {
+                    start_date: date_from_str("2025-01-01").unwrap(),
+                    end_date: date_from_str("2026-01-01").unwrap(),
+                    rate: 5.0,
+                },
+                ConvBondCoupon {
+                    start_date: date_from_str("2026-01-01").unwrap(),
+                    end_date: date_from_str("2027-01-01").unwrap(),
+                    rate: 5.0,
+                },
+            ],
+            maturity_redemption_price: None,
+            call: None,
+            put: None,
+            reset: None,
+        };
+
+        let ytm = calc_ytm(&clauses, &date, 100.0).unwrap();
+        assert!((ytm - 0.05).abs() < 1e-3);
+
+        let ytm = calc_ytm(&clauses, &date, 120.0).unwrap();
+        assert!(ytm < 0.0);
+
+        assert!(calc_ytm(&clauses, &date_from_str("2027-01-02").unwrap(), 100.0).is_none());
+    }
+
+    #[test]
+    fn test_calc_trigger_progress() {
+        let values = [120.0, 131.0, 135.0, 129.0, 130.0];
+
+        let progress = calc_trigger_progress(&values, &ConvBondTrigger::DEFAULT_CALL, 100.0, true);
+        assert_eq!(progress.hit_days, 3);
+        assert_eq!(progress.days_to_trigger(), 12);
+        assert!(!progress.is_triggered());
+
+        let trigger = ConvBondTrigger {
+            window_days: 3,
+            required_days: 2,
+            price_ratio: 1.3,
+        };
+        let progress = calc_trigger_progress(&values, &trigger, 100.0, true);
+        assert_eq!(progress.hit_days, 2);
+        assert!(progress.is_triggered());
+
+        let progress = calc_trigger_progress(&values, &trigger, 100.0, false);
+        assert_eq!(progress.hit_days, 1);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_conv_bond() {
+        let ticker = Ticker::from_str("110098").unwrap();
+
+        let analysis = analyze_conv_bond(&ticker, &date_from_str("2025-08-08").unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(analysis.date, date_from_str("2025-08-07").unwrap());
+        assert_eq!(analysis.double_low, 130.2 + 31.5);
+        assert!(analysis.ytm.is_some_and(|y| y < 0.0 && y > -5.0));
+        assert!((analysis.remain_size_change.unwrap() + 0.0303).abs() < 1e-3);
+        assert!(!analysis.call_announced);
+        assert_eq!(analysis.call_progress.unwrap().hit_days, 0);
+        assert!(!analysis.is_call_imminent(5));
+
+        let analysis = analyze_conv_bond(&ticker, &date_from_str("2025-08-20").unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(analysis.call_announced);
+        assert!(analysis.is_call_imminent(0));
+    }
+}
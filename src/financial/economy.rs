@@ -0,0 +1,307 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    STALE_DAYS_LONG, STALE_DAYS_SHORT,
+    cache::{memory, track_sources},
+    data::series::DailySeries,
+    ds::{Dataset, fetch_with_fallback},
+    error::VfResult,
+    financial::index::{IndexIndicatorField, fetch_index_valuation},
+    ticker::TickersIndex,
+};
+
+/// Interest rates in percent per annum
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum InterestRate {
+    /// Yield of 1-year China government bonds
+    Cgb1y,
+    /// Yield of 10-year China government bonds
+    Cgb10y,
+    Lpr1y,
+    Lpr5y,
+    ShiborOn,
+    Shibor1w,
+    Shibor1m,
+    Shibor3m,
+    Shibor1y,
+}
+
+#[derive(strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum InterestRateField {
+    Rate,
+}
+
+/// Monthly macro indicators of China, YoY rates are in percent and money supplies are in 100
+/// million yuan
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum MacroIndicator {
+    CpiYoy,
+    PpiYoy,
+    M1,
+    M1Yoy,
+    M2,
+    M2Yoy,
+}
+
+/// Values are dated when they are published, see `macro_indicator_publish_date`
+#[derive(strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum MacroIndicatorField {
+    Value,
+}
+
+pub async fn fetch_interest_rate(rate: &InterestRate) -> VfResult<DailySeries> {
+    let cache_key = rate.to_string();
+    if let Some(result) = memory::get(Dataset::InterestRates, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::InterestRates,
+        |ds| async move { ds.interest_rate(rate).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::InterestRates, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+pub async fn fetch_macro_indicator(indicator: &MacroIndicator) -> VfResult<DailySeries> {
+    let cache_key = indicator.to_string();
+    if let Some(result) = memory::get(Dataset::MacroIndicators, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::MacroIndicators,
+        |ds| async move { ds.macro_indicator(indicator).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(
+        Dataset::MacroIndicators,
+        &cache_key,
+        result.clone(),
+        sources,
+    );
+
+    Ok(result)
+}
+
+/// Latest interest rate before the date
+pub async fn get_interest_rate(rate: &InterestRate, date: &NaiveDate) -> VfResult<Option<f64>> {
+    Ok(fetch_interest_rate(rate)
+        .await?
+        .get_latest_value::<f64>(
+            date,
+            STALE_DAYS_LONG,
+            false,
+            &InterestRateField::Rate.to_string(),
+        )
+        .map(|(_, v)| v))
+}
+
+/// Mean of the interest rates on the dates, the default rate applies to dates without data
+pub async fn get_mean_interest_rate(
+    rate: &InterestRate,
+    dates: &[NaiveDate],
+    default_rate: f64,
+) -> VfResult<Option<f64>> {
+    if dates.is_empty() {
+        return Ok(None);
+    }
+
+    let series = fetch_interest_rate(rate).await?;
+    let sum: f64 = dates
+        .iter()
+        .map(|date| {
+            series
+                .get_latest_value::<f64>(
+                    date,
+                    STALE_DAYS_LONG,
+                    true,
+                    &InterestRateField::Rate.to_string(),
+                )
+                .map(|(_, v)| v)
+                .unwrap_or(default_rate)
+        })
+        .sum();
+
+    Ok(Some(sum / dates.len() as f64))
+}
+
+/// Latest published value of the macro indicator before the date
+pub async fn get_macro_indicator(
+    indicator: &MacroIndicator,
+    date: &NaiveDate,
+) -> VfResult<Option<f64>> {
+    Ok(fetch_macro_indicator(indicator)
+        .await?
+        .get_latest_value::<f64>(
+            date,
+            STALE_DAYS_LONG,
+            false,
+            &MacroIndicatorField::Value.to_string(),
+        )
+        .map(|(_, v)| v))
+}
+
+/// Equity risk premium of the index before the date in percent, which is the earnings yield by PE
+/// TTM, or PE if unavailable, minus the yield of 10-year China government bonds
+pub async fn get_equity_risk_premium(
+    index: &TickersIndex,
+    date: &NaiveDate,
+) -> VfResult<Option<f64>> {
+    let date_to = *date - Duration::days(1);
+    let date_from = date_to - Duration::days(STALE_DAYS_SHORT as i64);
+    let valuation = fetch_index_valuation(index, &date_from, &date_to).await?;
+    let pe = [IndexIndicatorField::PeTtm, IndexIndicatorField::Pe]
+        .iter()
+        .find_map(|field| {
+            valuation
+                .get_latest_value::<f64>(date, STALE_DAYS_SHORT, false, &field.to_string())
+                .map(|(_, v)| v)
+                .filter(|v| *v > 0.0)
+        });
+
+    let Some(pe) = pe else {
+        return Ok(None);
+    };
+    let Some(yield_10y) = get_interest_rate(&InterestRate::Cgb10y, date).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(100.0 / pe - yield_10y))
+}
+
+/// Monthly indicators are published around the middle of the next month, so they are dated on
+/// the 15th of the next month to avoid look-ahead
+pub fn macro_indicator_publish_date(year: i32, month: u32) -> Option<NaiveDate> {
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 15)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::utils::datetime::date_from_str;
+
+    #[tokio::test]
+    async fn test_get_interest_rate() {
+        let date = date_from_str("2024-12-31").unwrap();
+
+        let rate = get_interest_rate(&InterestRate::Cgb10y, &date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rate > 1.0 && rate < 3.0);
+
+        let rate = get_interest_rate(&InterestRate::Shibor3m, &date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rate > 1.0 && rate < 3.0);
+
+        let rate = get_interest_rate(&InterestRate::Lpr1y, &date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rate, 3.1);
+
+        let dates = [
+            date_from_str("2024-12-02").unwrap(),
+            date_from_str("2024-12-31").unwrap(),
+        ];
+        let mean_rate = get_mean_interest_rate(&InterestRate::Cgb1y, &dates, 2.0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(mean_rate > 1.0 && mean_rate < 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_get_macro_indicator() {
+        let cpi = get_macro_indicator(
+            &MacroIndicator::CpiYoy,
+            &date_from_str("2024-12-16").unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cpi, Some(0.2));
+
+        let m2 = get_macro_indicator(
+            &MacroIndicator::M2Yoy,
+            &date_from_str("2024-12-14").unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(m2, Some(7.5));
+
+        assert_eq!(
+            macro_indicator_publish_date(2024, 12),
+            NaiveDate::from_ymd_opt(2025, 1, 15)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_equity_risk_premium() {
+        let index = TickersIndex::from_str("000922.CSI").unwrap();
+        let date = date_from_str("2020-12-31").unwrap();
+        let erp = get_equity_risk_premium(&index, &date)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (_, pe_ttm) = fetch_index_valuation(
+            &index,
+            &date_from_str("2020-12-01").unwrap(),
+            &date_from_str("2020-12-30").unwrap(),
+        )
+        .await
+        .unwrap()
+        .get_latest_value::<f64>(
+            &date,
+            STALE_DAYS_SHORT,
+            false,
+            &IndexIndicatorField::PeTtm.to_string(),
+        )
+        .unwrap();
+        assert!((erp - (100.0 / pe_ttm - 3.2)).abs() < 1e-9);
+    }
+}
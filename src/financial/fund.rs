@@ -0,0 +1,233 @@
+use std::mem::size_of;
+
+use chrono::NaiveDate;
+
+use crate::{
+    cache::{
+        memory::{self, MemorySize},
+        track_sources,
+    },
+    data::series::DailySeries,
+    ds::{Dataset, fetch_with_fallback, get_data_source},
+    error::{VfError, VfResult},
+    ticker::Ticker,
+};
+
+#[derive(Clone, Debug, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum FundNavField {
+    Unit,
+    Accumulated,
+    Adjusted,
+}
+
+/// Shares outstanding in 10 thousands
+#[derive(Clone, Debug, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum FundShareField {
+    Share,
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct FundBasic {
+    pub ticker: Ticker,
+    pub name: String,
+    pub fund_type: String,
+    pub invest_type: String,
+    pub benchmark: String,
+    pub list_date: Option<NaiveDate>,
+    pub delist_date: Option<NaiveDate>,
+}
+
+impl FundBasic {
+    /// Match the category against fund type, invest type, benchmark or name
+    pub fn is_category(&self, category: &str) -> bool {
+        category.is_empty()
+            || self.fund_type == category
+            || self.invest_type.contains(category)
+            || self.benchmark.contains(category)
+            || self.name.contains(category)
+    }
+
+    pub fn is_listed(&self, date: &NaiveDate) -> bool {
+        self.list_date.is_some_and(|d| d <= *date) && self.delist_date.is_none_or(|d| d > *date)
+    }
+}
+
+impl MemorySize for FundBasic {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self.ticker.memory_size()
+            + self.name.capacity()
+            + self.fund_type.capacity()
+            + self.invest_type.capacity()
+            + self.benchmark.capacity()
+    }
+}
+
+/// All exchange traded funds ever listed
+pub async fn fetch_etfs() -> VfResult<Vec<FundBasic>> {
+    let cache_key = "etfs".to_string();
+    if let Some(result) = memory::get(Dataset::Etfs, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(Dataset::Etfs, |ds| async move {
+        ds.etfs().await
+    }))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::Etfs, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+/// Basic of the exchange traded fund
+pub async fn fetch_fund_basic(ticker: &Ticker) -> VfResult<FundBasic> {
+    fetch_etfs()
+        .await?
+        .into_iter()
+        .find(|fund| fund.ticker == *ticker)
+        .ok_or(VfError::NoData {
+            code: "NO_FUND_BASIC",
+            message: format!("No basic of fund '{ticker}'"),
+        })
+}
+
+pub async fn fetch_fund_kline(ticker: &Ticker) -> VfResult<DailySeries> {
+    fetch_fund_kline_with_ignore_cache(ticker, false).await
+}
+
+pub async fn fetch_fund_kline_with_ds(ticker: &Ticker, ds_name: &str) -> VfResult<DailySeries> {
+    fetch_fund_kline_with_ds_and_ignore_cache(ticker, ds_name, false).await
+}
+
+pub async fn fetch_fund_kline_ignore_cache(ticker: &Ticker) -> VfResult<DailySeries> {
+    fetch_fund_kline_with_ignore_cache(ticker, true).await
+}
+
+pub async fn fetch_fund_kline_ignore_cache_with_ds(
+    ticker: &Ticker,
+    ds_name: &str,
+) -> VfResult<DailySeries> {
+    fetch_fund_kline_with_ds_and_ignore_cache(ticker, ds_name, true).await
+}
+
+/// Net asset values per unit, as announced by the fund
+pub async fn fetch_fund_nav(ticker: &Ticker) -> VfResult<DailySeries> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::FundNav, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(Dataset::FundNav, |ds| async move {
+        ds.fund_nav(ticker).await
+    }))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::FundNav, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+pub async fn fetch_fund_share(ticker: &Ticker) -> VfResult<DailySeries> {
+    let cache_key = ticker.to_string();
+    if let Some(result) = memory::get(Dataset::FundShare, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) =
+        track_sources(fetch_with_fallback(Dataset::FundShare, |ds| async move {
+            ds.fund_share(ticker).await
+        }))
+        .await;
+    let result = result?;
+    memory::insert(Dataset::FundShare, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+async fn fetch_fund_kline_with_ignore_cache(
+    ticker: &Ticker,
+    ignore_cache: bool,
+) -> VfResult<DailySeries> {
+    let cache_key = ticker.to_string();
+    if !ignore_cache && let Some(result) = memory::get(Dataset::FundKline, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) =
+        track_sources(fetch_with_fallback(Dataset::FundKline, |ds| async move {
+            ds.fund_kline(ticker, ignore_cache).await
+        }))
+        .await;
+    let result = result?;
+    memory::insert(Dataset::FundKline, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+async fn fetch_fund_kline_with_ds_and_ignore_cache(
+    ticker: &Ticker,
+    ds_name: &str,
+    ignore_cache: bool,
+) -> VfResult<DailySeries> {
+    let ds = get_data_source(ds_name)?;
+
+    let cache_key = format!("{}:{ticker}", ds.name());
+    if !ignore_cache && let Some(result) = memory::get(Dataset::FundKline, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(ds.fund_kline(ticker, ignore_cache)).await;
+    let result = result?;
+    memory::insert(Dataset::FundKline, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{financial::KlineField, ticker::TickerType, utils::datetime::date_from_str};
+
+    #[tokio::test]
+    async fn test_fetch_fund() {
+        let ticker = Ticker::from_str("510300").unwrap();
+        assert_eq!(ticker.r#type, TickerType::Etf);
+
+        let date = date_from_str("2024-12-31").unwrap();
+
+        let kline = fetch_fund_kline(&ticker).await.unwrap();
+        assert!(
+            kline
+                .get_latest_value::<f64>(&date, 7, true, &KlineField::Close.to_string())
+                .is_some()
+        );
+
+        let nav = fetch_fund_nav(&ticker).await.unwrap();
+        assert!(
+            nav.get_latest_value::<f64>(&date, 7, true, &FundNavField::Unit.to_string())
+                .is_some()
+        );
+
+        let share = fetch_fund_share(&ticker).await.unwrap();
+        assert!(
+            share
+                .get_latest_value::<f64>(&date, 7, true, &FundShareField::Share.to_string())
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_etfs() {
+        let etfs = fetch_etfs().await.unwrap();
+        assert!(
+            etfs.iter()
+                .any(|etf| etf.ticker.to_string() == "510300.XSHG")
+        );
+    }
+}
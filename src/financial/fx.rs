@@ -0,0 +1,164 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    STALE_DAYS_SHORT,
+    cache::{memory, track_sources},
+    data::series::DailySeries,
+    ds::{Dataset, fetch_with_fallback},
+    error::VfResult,
+};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "UPPERCASE")]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum Currency {
+    #[default]
+    Cny,
+    Hkd,
+    Usd,
+}
+
+/// Units of the currency per USD
+#[derive(strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum FxDailyField {
+    Open,
+    Close,
+    High,
+    Low,
+}
+
+/// Daily rates of the currency per USD, not available for USD itself
+pub async fn fetch_fx_daily(currency: &Currency) -> VfResult<DailySeries> {
+    let cache_key = currency.to_string();
+    if let Some(result) = memory::get(Dataset::FxDaily, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(fetch_with_fallback(Dataset::FxDaily, |ds| async move {
+        ds.fx_daily(currency).await
+    }))
+    .await;
+    let result = result?;
+    memory::insert(Dataset::FxDaily, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
+
+/// Units of the quote currency per unit of the base one by close rates crossed via USD, e.g.
+/// HKD/CNY is USD/CNY divided by USD/HKD
+pub async fn get_fx_rate(
+    base: &Currency,
+    quote: &Currency,
+    date: &NaiveDate,
+) -> VfResult<Option<f64>> {
+    if base == quote {
+        return Ok(Some(1.0));
+    }
+
+    let Some((_, base_per_usd)) = get_usd_rate(base, date, STALE_DAYS_SHORT).await? else {
+        return Ok(None);
+    };
+    let Some((_, quote_per_usd)) = get_usd_rate(quote, date, STALE_DAYS_SHORT).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(quote_per_usd / base_per_usd))
+}
+
+/// Same as `get_fx_rate` but carried forward from the last known rates however stale, with the
+/// date of the staler one
+pub async fn get_last_fx_rate(
+    base: &Currency,
+    quote: &Currency,
+    date: &NaiveDate,
+) -> VfResult<Option<(NaiveDate, f64)>> {
+    if base == quote {
+        return Ok(Some((*date, 1.0)));
+    }
+
+    let Some((base_date, base_per_usd)) = get_usd_rate(base, date, u32::MAX).await? else {
+        return Ok(None);
+    };
+    let Some((quote_date, quote_per_usd)) = get_usd_rate(quote, date, u32::MAX).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some((
+        base_date.min(quote_date),
+        quote_per_usd / base_per_usd,
+    )))
+}
+
+async fn get_usd_rate(
+    currency: &Currency,
+    date: &NaiveDate,
+    max_stale_days: u32,
+) -> VfResult<Option<(NaiveDate, f64)>> {
+    if *currency == Currency::Usd {
+        return Ok(Some((*date, 1.0)));
+    }
+
+    let fx_daily = fetch_fx_daily(currency).await?;
+    Ok(fx_daily
+        .get_latest_value::<f64>(date, max_stale_days, true, &FxDailyField::Close.to_string())
+        .filter(|(_, rate)| *rate > 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::datetime::date_from_str;
+
+    #[tokio::test]
+    async fn test_get_fx_rate() {
+        let date = date_from_str("2024-12-31").unwrap();
+
+        let rate = get_fx_rate(&Currency::Hkd, &Currency::Cny, &date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rate > 0.9 && rate < 1.0);
+
+        let inverse_rate = get_fx_rate(&Currency::Cny, &Currency::Hkd, &date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((rate * inverse_rate - 1.0).abs() < 1e-9);
+
+        assert_eq!(
+            get_fx_rate(&Currency::Cny, &Currency::Cny, &date)
+                .await
+                .unwrap(),
+            Some(1.0)
+        );
+
+        // Carried forward from the last rates long after them
+        let later_date = date_from_str("2030-12-31").unwrap();
+        assert_eq!(
+            get_fx_rate(&Currency::Hkd, &Currency::Cny, &later_date)
+                .await
+                .unwrap(),
+            None
+        );
+        let (rate_date, last_rate) = get_last_fx_rate(&Currency::Hkd, &Currency::Cny, &later_date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rate_date < later_date);
+        assert!(last_rate > 0.0);
+    }
+}
@@ -23,7 +23,9 @@ use crate::{
 };
 
 pub async fn calc_stock_cash_ratio(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
-    let report_balance = fetch_stock_report_balance(ticker).await?;
+    let report_balance = fetch_stock_report_balance(ticker)
+        .await?
+        .as_of(&StockReportBalanceField::ReportDate.to_string(), date)?;
 
     if let Some((_, cash)) = report_balance.get_latest_value::<f64>(
         date,
@@ -46,7 +48,9 @@ pub async fn calc_stock_cash_ratio(ticker: &Ticker, date: &NaiveDate) -> VfResul
 }
 
 pub async fn calc_stock_current_ratio(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
-    let report_balance = fetch_stock_report_balance(ticker).await?;
+    let report_balance = fetch_stock_report_balance(ticker)
+        .await?
+        .as_of(&StockReportBalanceField::ReportDate.to_string(), date)?;
 
     if let Some((_, current_assets)) = report_balance.get_latest_value::<f64>(
         date,
@@ -69,7 +73,9 @@ pub async fn calc_stock_current_ratio(ticker: &Ticker, date: &NaiveDate) -> VfRe
 }
 
 pub async fn calc_stock_debt_ratio(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
-    let report_balance = fetch_stock_report_balance(ticker).await?;
+    let report_balance = fetch_stock_report_balance(ticker)
+        .await?
+        .as_of(&StockReportBalanceField::ReportDate.to_string(), date)?;
 
     if let Some((_, liability)) = report_balance.get_latest_value::<f64>(
         date,
@@ -126,7 +132,9 @@ pub async fn calc_stock_free_cash_ratio_of_years(
     date: &NaiveDate,
     lookback_years: u32,
 ) -> VfResult<f64> {
-    let report_cash_flow = fetch_stock_report_cash_flow(ticker).await?;
+    let report_cash_flow = fetch_stock_report_cash_flow(ticker)
+        .await?
+        .as_of(&StockReportCashFlowField::ReportDate.to_string(), date)?;
 
     let lookback_start_date = date
         .with_year(date.year() - lookback_years as i32)
@@ -187,7 +195,9 @@ pub async fn calc_stock_market_cap(
         };
 
         let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
-        let report_capital = fetch_stock_report_capital(ticker).await?;
+        let report_capital = fetch_stock_report_capital(ticker)
+            .await?
+            .as_of(&StockReportCapitalField::ReportDate.to_string(), date)?;
 
         if let (Some((_, price)), Some((_, capital))) = (
             kline.get_latest_value::<f64>(
@@ -226,7 +236,9 @@ pub async fn calc_stock_pb(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
     // Try calculate from stock report
     {
         let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
-        let report_pershare = fetch_stock_report_pershare(ticker).await?;
+        let report_pershare = fetch_stock_report_pershare(ticker)
+            .await?
+            .as_of(&StockReportPershareField::ReportDate.to_string(), date)?;
 
         if let (Some((_, price)), Some((_, bps))) = (
             kline.get_latest_value::<f64>(
@@ -255,7 +267,9 @@ pub async fn calc_stock_pb(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
 
 pub async fn calc_stock_pe_ttm(ticker: &Ticker, date: &NaiveDate) -> VfResult<f64> {
     let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
-    let report_pershare = fetch_stock_report_pershare(ticker).await?;
+    let report_pershare = fetch_stock_report_pershare(ticker)
+        .await?
+        .as_of(&StockReportPershareField::ReportDate.to_string(), date)?;
 
     if let (Some((_, price)), eps_values) = (
         kline.get_latest_value::<f64>(
@@ -337,8 +351,12 @@ pub async fn calc_stock_ps_ttm(ticker: &Ticker, date: &NaiveDate) -> VfResult<f6
     // Try calculate from stock report
     {
         let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
-        let report_capital = fetch_stock_report_capital(ticker).await?;
-        let report_income = fetch_stock_report_income(ticker).await?;
+        let report_capital = fetch_stock_report_capital(ticker)
+            .await?
+            .as_of(&StockReportCapitalField::ReportDate.to_string(), date)?;
+        let report_income = fetch_stock_report_income(ticker)
+            .await?
+            .as_of(&StockReportIncomeField::ReportDate.to_string(), date)?;
 
         if let (Some((_, price)), Some((_, total_captical)), revenues) = (
             kline.get_latest_value::<f64>(
@@ -415,7 +433,9 @@ pub async fn calc_stock_roe_of_years(
     date: &NaiveDate,
     lookback_years: u32,
 ) -> VfResult<Option<f64>> {
-    let report_pershare = fetch_stock_report_pershare(ticker).await?;
+    let report_pershare = fetch_stock_report_pershare(ticker)
+        .await?
+        .as_of(&StockReportPershareField::ReportDate.to_string(), date)?;
 
     let lookback_start_date = date
         .with_year(date.year() - lookback_years as i32)
@@ -468,7 +488,9 @@ pub async fn calc_stock_shares(
             StockReportCapitalField::Total.to_string()
         };
 
-        let report_capital = fetch_stock_report_capital(ticker).await?;
+        let report_capital = fetch_stock_report_capital(ticker)
+            .await?
+            .as_of(&StockReportCapitalField::ReportDate.to_string(), date)?;
 
         if let Some((_, capital)) =
             report_capital.get_latest_value::<f64>(date, STALE_DAYS_LONG, false, &report_field)
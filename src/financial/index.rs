@@ -1,15 +1,26 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::collections::HashMap;
 
-use chrono::{Duration, NaiveDate};
-use dashmap::DashMap;
-use serde_json::{Value, json};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use futures::{StreamExt, stream};
+use log::warn;
+use polars::prelude::{AnyValue, Column, DataFrame};
 
 use crate::{
+    STALE_DAYS_SHORT,
+    cache::{
+        membership::{self, Membership},
+        memory::{self, MemorySize},
+        track_sources,
+    },
     data::series::DailySeries,
-    ds::tushare,
-    error::VfResult,
+    ds::{Dataset, fetch_with_fallback, get_data_source, limiter::MAX_CONCURRENCY},
+    error::{VfError, VfResult},
+    financial::{
+        market::fetch_trade_dates,
+        stock::{StockIndicatorField, fetch_stock_indicators},
+    },
     ticker::{Ticker, TickersIndex},
-    utils::datetime::{date_from_str, date_to_str},
+    utils::datetime::date_to_str,
 };
 
 #[derive(strum::Display, strum::EnumString)]
@@ -21,6 +32,22 @@ pub enum IndexIndicatorField {
     TurnoverRate,
 }
 
+/// Valuation of an index on a trading day aggregated from its constituents
+#[derive(Clone, Debug)]
+pub struct IndexValuation {
+    pub date: NaiveDate,
+    pub pb: Option<f64>,
+    pub pe: Option<f64>,
+    pub pe_ttm: Option<f64>,
+    pub turnover_rate: Option<f64>,
+}
+
+impl MemorySize for IndexValuation {
+    fn memory_size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
 /// Currently only supports:
 /// - 000001.SH 上证综指
 /// - 399001.SZ 深证成指
@@ -28,132 +55,324 @@ pub enum IndexIndicatorField {
 /// - 000905.SH 中证500
 /// - 399005.SZ 中小板指
 /// - 399006.SZ 创业板指
+///
+/// Valuations of other indexes are aggregated from constituents by `fetch_index_valuation`
 pub async fn fetch_index_indicators(index: &TickersIndex) -> VfResult<DailySeries> {
-    let cache_key = format!("tushare:{index}");
-    if let Some(result) = INDEX_INDICATORS_CACHE.get(&cache_key) {
-        return Ok(result.clone());
+    let cache_key = index.to_string();
+    if let Some(result) = memory::get(Dataset::IndexIndicators, &cache_key) {
+        return Ok(result);
     }
 
-    static PAGE_SIZE: usize = 2000;
-
-    let mut fields: Vec<Value> = vec![];
-    let mut items: Vec<Value> = vec![];
-
-    let mut offset: usize = 0;
-    while items.len() == offset {
-        let json = tushare::call_api(
-            "index_dailybasic",
-            &json!({
-                "ts_code": index.to_tushare_code(),
-                "limit": PAGE_SIZE,
-                "offset": offset,
-            }),
-            None,
-            0,
-            false,
-        )
-        .await?;
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::IndexIndicators,
+        |ds| async move { ds.index_indicators(index).await },
+    ))
+    .await;
+    let result = result?;
+    memory::insert(
+        Dataset::IndexIndicators,
+        &cache_key,
+        result.clone(),
+        sources,
+    );
 
-        if let Some(page_fields) = json["data"]["fields"].as_array() {
-            fields = page_fields.clone();
-        }
+    Ok(result)
+}
 
-        if let Some(page_items) = json["data"]["items"].as_array() {
-            items.extend_from_slice(page_items);
+pub async fn fetch_index_kline(index: &TickersIndex) -> VfResult<DailySeries> {
+    fetch_index_kline_with_ignore_cache(index, false).await
+}
+
+pub async fn fetch_index_kline_with_ds(
+    index: &TickersIndex,
+    ds_name: &str,
+) -> VfResult<DailySeries> {
+    fetch_index_kline_with_ds_and_ignore_cache(index, ds_name, false).await
+}
+
+pub async fn fetch_index_kline_ignore_cache(index: &TickersIndex) -> VfResult<DailySeries> {
+    fetch_index_kline_with_ignore_cache(index, true).await
+}
+
+pub async fn fetch_index_kline_ignore_cache_with_ds(
+    index: &TickersIndex,
+    ds_name: &str,
+) -> VfResult<DailySeries> {
+    fetch_index_kline_with_ds_and_ignore_cache(index, ds_name, true).await
+}
+
+/// Valuation of the index during the date range by data sources, or aggregated from its
+/// constituents if the data sources have none of it, with fields of `IndexIndicatorField`
+pub async fn fetch_index_valuation(
+    index: &TickersIndex,
+    date_from: &NaiveDate,
+    date_to: &NaiveDate,
+) -> VfResult<DailySeries> {
+    if let Ok(index_indicators) = fetch_index_indicators(index).await {
+        let index_indicators = index_indicators.slice_by_date_range(date_from, date_to)?;
+        if index_indicators.len() > 0 {
+            return Ok(index_indicators);
         }
+    }
+
+    let mut valuations: Vec<IndexValuation> = vec![];
+
+    let mut month_start = date_from.with_day(1).unwrap_or(*date_from);
+    while month_start <= *date_to {
+        valuations.extend(
+            fetch_index_month_valuation(index, &month_start)
+                .await?
+                .into_iter()
+                .filter(|v| v.date >= *date_from && v.date <= *date_to),
+        );
 
-        offset += PAGE_SIZE;
+        month_start = month_start + Months::new(1);
     }
 
-    let json = json!({
-        "data": {
-            "fields": fields,
-            "items": items,
+    let dates: Vec<AnyValue> = valuations
+        .iter()
+        .map(|v| AnyValue::Date(v.date.to_epoch_days()))
+        .collect();
+    let column = |field: IndexIndicatorField, value: fn(&IndexValuation) -> Option<f64>| {
+        Column::new(
+            field.to_string().into(),
+            valuations.iter().map(value).collect::<Vec<Option<f64>>>(),
+        )
+    };
+    let df = DataFrame::new(vec![
+        Column::new("date".into(), dates),
+        column(IndexIndicatorField::Pb, |v| v.pb),
+        column(IndexIndicatorField::Pe, |v| v.pe),
+        column(IndexIndicatorField::PeTtm, |v| v.pe_ttm),
+        column(IndexIndicatorField::TurnoverRate, |v| v.turnover_rate),
+    ])?;
+
+    DailySeries::from_dataframe(df, "date")
+}
+
+/// Index tickers as of the date by data sources, or the membership store for indexes without
+/// historical weights, falling back to the current membership if neither has them
+pub async fn fetch_index_membership(
+    index: &TickersIndex,
+    date: &NaiveDate,
+) -> VfResult<Membership> {
+    let prev_date = *date - Duration::days(1);
+
+    let cache_key = format!("{index}/{}", date_to_str(&prev_date));
+    if let Some(result) = memory::get(Dataset::IndexTickers, &cache_key) {
+        return Ok(result);
+    }
+
+    let source = membership::index_source(index);
+    let (result, sources) = track_sources(fetch_with_fallback(
+        Dataset::IndexTickers,
+        |ds| async move { ds.index_tickers(index, date).await },
+    ))
+    .await;
+    let membership = match result {
+        Ok(tickers) if !tickers.is_empty() => {
+            let members: HashMap<Ticker, String> = tickers
+                .into_iter()
+                .map(|ticker| (ticker, "".to_string()))
+                .collect();
+            membership::record(&source, &prev_date, &members).await?;
+
+            Membership {
+                date: prev_date,
+                members,
+                is_fallback: false,
+            }
         }
-    });
-
-    let mut fields: HashMap<String, String> = HashMap::new();
-    fields.insert(IndexIndicatorField::Pb.to_string(), "pb".to_string());
-    fields.insert(IndexIndicatorField::Pe.to_string(), "pe".to_string());
-    fields.insert(IndexIndicatorField::PeTtm.to_string(), "pe_ttm".to_string());
-    fields.insert(
-        IndexIndicatorField::TurnoverRate.to_string(),
-        "turnover_rate".to_string(),
+        result => {
+            if let Some(membership) = membership::get_as_of(&source, date).await? {
+                membership
+            } else if let Some(membership) = membership::get_latest(&source).await? {
+                Membership {
+                    is_fallback: true,
+                    ..membership
+                }
+            } else {
+                result?;
+                return Err(VfError::NoData {
+                    code: "NO_INDEX_TICKERS",
+                    message: format!(
+                        "Tickers of index '{index}' @{} not exists",
+                        date_to_str(date)
+                    ),
+                });
+            }
+        }
+    };
+    memory::insert(
+        Dataset::IndexTickers,
+        &cache_key,
+        membership.clone(),
+        sources,
     );
 
-    let result = DailySeries::from_tushare_json(&json, "trade_date", &fields)?;
-    INDEX_INDICATORS_CACHE.insert(cache_key, result.clone());
+    Ok(membership)
+}
+
+pub async fn fetch_index_tickers(index: &TickersIndex, date: &NaiveDate) -> VfResult<Vec<Ticker>> {
+    let membership = fetch_index_membership(index, date).await?;
+    Ok(membership.members.into_keys().collect())
+}
+
+/// Valuations of trading days in the month by constituents as of the month start point-in-time,
+/// with the fields of `index_dailybasic`: PE and PB are harmonic means weighted by total market
+/// values, i.e. total market value over total earnings or book values of constituents with
+/// positive ratios, and turnover rate is the mean weighted by total market values, in percent.
+/// Constituents failing to fetch are skipped with a warning
+async fn fetch_index_month_valuation(
+    index: &TickersIndex,
+    month_start: &NaiveDate,
+) -> VfResult<Vec<IndexValuation>> {
+    let cache_key = format!("{index}/{}", month_start.format("%Y-%m"));
+    if let Some(result) = memory::get(Dataset::IndexIndicators, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(calc_index_month_valuation(index, month_start)).await;
+    let result = result?;
+    memory::insert(
+        Dataset::IndexIndicators,
+        &cache_key,
+        result.clone(),
+        sources,
+    );
 
     Ok(result)
 }
 
-pub async fn fetch_index_tickers(index: &TickersIndex, date: &NaiveDate) -> VfResult<Vec<Ticker>> {
-    let prev_date = *date - Duration::days(1);
+async fn calc_index_month_valuation(
+    index: &TickersIndex,
+    month_start: &NaiveDate,
+) -> VfResult<Vec<IndexValuation>> {
+    let month_end = *month_start + Months::new(1) - Duration::days(1);
+    let mut dates: Vec<NaiveDate> = fetch_trade_dates("XSHG")
+        .await?
+        .into_iter()
+        .filter(|date| date >= month_start && *date <= month_end)
+        .collect();
+    dates.sort();
 
-    let cache_key = format!("tushare:{index}/{}", date_to_str(&prev_date));
-    if let Some(result) = INDEX_TICKERS_CACHE.get(&cache_key) {
-        return Ok(result.clone());
+    let tickers = fetch_index_tickers(index, month_start).await?;
+
+    // Constituents are fetched concurrently, as it's bound by fetching their data
+    let mut indicators_stream = stream::iter(tickers)
+        .map(|ticker| async move {
+            let indicators = fetch_stock_indicators(&ticker).await;
+            (ticker, indicators)
+        })
+        .buffer_unordered(MAX_CONCURRENCY);
+    let mut tickers_indicators: Vec<DailySeries> = vec![];
+    while let Some((ticker, indicators)) = indicators_stream.next().await {
+        match indicators {
+            Ok(indicators) => tickers_indicators.push(indicators),
+            Err(err) => warn!("[Index Valuation] Skipped constituent {ticker} of {index}: {err}"),
+        }
     }
 
-    let json = tushare::call_api(
-        "index_weight",
-        &json!({
-            "index_code": index.to_tushare_code(),
-            "end_date": prev_date.format("%Y%m%d").to_string(),
-        }),
-        None,
-        30,
-        false,
-    )
-    .await?;
-
-    let mut hist_tickers: HashMap<NaiveDate, Vec<Ticker>> = HashMap::new();
-
-    if let (Some(fields), Some(items)) = (
-        json["data"]["fields"].as_array(),
-        json["data"]["items"].as_array(),
-    ) {
-        if let (Some(idx_con_code), Some(idx_trade_date)) = (
-            fields.iter().position(|f| f == "con_code"),
-            fields.iter().position(|f| f == "trade_date"),
-        ) {
-            for item in items {
-                if let Some(values) = item.as_array() {
-                    if let (Some(con_code_str), Some(trade_date_str)) = (
-                        values[idx_con_code].as_str(),
-                        values[idx_trade_date].as_str(),
-                    ) {
-                        if let (Ok(date), Some(ticker)) = (
-                            date_from_str(trade_date_str),
-                            Ticker::from_tushare_str(con_code_str),
-                        ) {
-                            hist_tickers.entry(date).or_default().push(ticker);
-                        }
-                    }
+    let ratio_fields = [
+        StockIndicatorField::Pb,
+        StockIndicatorField::Pe,
+        StockIndicatorField::PeTtm,
+    ];
+
+    let mut result: Vec<IndexValuation> = vec![];
+    for date in dates {
+        // Sums of market values and of market values over the ratio, per ratio field
+        let mut ratio_sums: [(f64, f64); 3] = [(0.0, 0.0); 3];
+        let mut market_value_sum = 0.0;
+        let mut turnover_sum = 0.0;
+
+        for indicators in &tickers_indicators {
+            let value = |field: &StockIndicatorField| {
+                indicators
+                    .get_latest_value::<f64>(&date, STALE_DAYS_SHORT, true, &field.to_string())
+                    .map(|(_, v)| v)
+            };
+
+            let Some(market_value) =
+                value(&StockIndicatorField::MarketValueTotal).filter(|v| *v > 0.0)
+            else {
+                continue;
+            };
+
+            market_value_sum += market_value;
+            turnover_sum += market_value * value(&StockIndicatorField::TurnoverRate).unwrap_or(0.0);
+
+            for (field, sums) in ratio_fields.iter().zip(ratio_sums.iter_mut()) {
+                if let Some(ratio) = value(field).filter(|v| *v > 0.0) {
+                    sums.0 += market_value;
+                    sums.1 += market_value / ratio;
                 }
             }
         }
+
+        if market_value_sum > 0.0 {
+            let [pb, pe, pe_ttm] = ratio_sums.map(|(numerator, denominator)| {
+                (denominator > 0.0).then(|| numerator / denominator)
+            });
+
+            result.push(IndexValuation {
+                date,
+                pb,
+                pe,
+                pe_ttm,
+                turnover_rate: Some(turnover_sum / market_value_sum),
+            });
+        }
     }
 
-    let tickers = if let Some(latest_date) = hist_tickers.keys().max() {
-        hist_tickers.get(latest_date).unwrap_or(&vec![]).clone()
-    } else {
-        vec![]
-    };
+    Ok(result)
+}
+
+async fn fetch_index_kline_with_ignore_cache(
+    index: &TickersIndex,
+    ignore_cache: bool,
+) -> VfResult<DailySeries> {
+    let cache_key = index.to_string();
+    if !ignore_cache && let Some(result) = memory::get(Dataset::IndexKline, &cache_key) {
+        return Ok(result);
+    }
 
-    INDEX_TICKERS_CACHE.insert(cache_key, tickers.clone());
+    let (result, sources) =
+        track_sources(fetch_with_fallback(Dataset::IndexKline, |ds| async move {
+            ds.index_kline(index, ignore_cache).await
+        }))
+        .await;
+    let result = result?;
+    memory::insert(Dataset::IndexKline, &cache_key, result.clone(), sources);
 
-    Ok(tickers)
+    Ok(result)
 }
 
-static INDEX_INDICATORS_CACHE: LazyLock<DashMap<String, DailySeries>> = LazyLock::new(DashMap::new);
-static INDEX_TICKERS_CACHE: LazyLock<DashMap<String, Vec<Ticker>>> = LazyLock::new(DashMap::new);
+async fn fetch_index_kline_with_ds_and_ignore_cache(
+    index: &TickersIndex,
+    ds_name: &str,
+    ignore_cache: bool,
+) -> VfResult<DailySeries> {
+    let ds = get_data_source(ds_name)?;
+
+    let cache_key = format!("{}:{index}", ds.name());
+    if !ignore_cache && let Some(result) = memory::get(Dataset::IndexKline, &cache_key) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(ds.index_kline(index, ignore_cache)).await;
+    let result = result?;
+    memory::insert(Dataset::IndexKline, &cache_key, result.clone(), sources);
+
+    Ok(result)
+}
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::financial::KlineField;
 
     #[tokio::test]
     async fn test_fetch_index_indicators() {
@@ -162,4 +381,58 @@ mod tests {
 
         assert!(series.all_dates().len() > 3000);
     }
+
+    #[tokio::test]
+    async fn test_fetch_index_valuation() {
+        let index = TickersIndex::from_str("000922.CSI").unwrap();
+        let date_from = NaiveDate::from_ymd_opt(2020, 12, 1).unwrap();
+        let date_to = NaiveDate::from_ymd_opt(2020, 12, 31).unwrap();
+        let series = fetch_index_valuation(&index, &date_from, &date_to)
+            .await
+            .unwrap();
+        assert_eq!(series.all_dates().len(), 23);
+
+        let value = |field: IndexIndicatorField| {
+            series
+                .get_latest_value::<f64>(&date_to, 7, true, &field.to_string())
+                .unwrap()
+                .1
+        };
+
+        // Market values of 5.6M and 11M with PE of 9.5 and 10.0, and the constituent without
+        // data is skipped
+        let pe = (5_600_000.0 + 11_000_000.0) / (5_600_000.0 / 9.5 + 11_000_000.0 / 10.0);
+        assert!((value(IndexIndicatorField::Pe) - pe).abs() < 1e-9);
+        assert!((value(IndexIndicatorField::TurnoverRate) - 0.72).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_hsgt_tickers() {
+        let index = TickersIndex::from_str("SH_HK.HSGT").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let mut tickers: Vec<String> = fetch_index_tickers(&index, &date)
+            .await
+            .unwrap()
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        tickers.sort();
+
+        // Only the latest list in the window
+        assert_eq!(tickers, vec!["00700.XHKG", "09988.XHKG"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_index_kline() {
+        let ticker = Ticker::from_str("000300.SH").unwrap();
+        assert_eq!(ticker.to_tickers_index().to_tushare_code(), "000300.SH");
+
+        let kline = fetch_index_kline(&ticker.to_tickers_index()).await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        assert!(
+            kline
+                .get_latest_value::<f64>(&date, 7, true, &KlineField::Close.to_string())
+                .is_some()
+        );
+    }
 }
@@ -1,55 +1,105 @@
 use std::{collections::HashSet, str::FromStr};
 
 use chrono::NaiveDate;
-use serde_json::json;
 
 use crate::{
-    ds::tushare,
-    error::VfResult,
-    financial::stock::{StockDividendAdjust, fetch_stock_kline},
-    ticker::Ticker,
-    utils::datetime::date_from_str,
+    cache::{memory, track_sources},
+    ds::{Dataset, fetch_with_fallback},
+    error::{VfError, VfResult},
+    financial::{
+        index::fetch_index_kline,
+        stock::{StockDividendAdjust, fetch_stock_kline},
+    },
+    ticker::{Ticker, TickersIndex},
 };
 
-pub async fn fetch_trade_dates() -> VfResult<HashSet<NaiveDate>> {
-    let mut dates: HashSet<NaiveDate> = HashSet::new();
+/// Trading days of the exchange in MIC, e.g. `XSHG` or `XHKG`
+pub async fn fetch_trade_dates(exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+    if let Some(result) = memory::get(Dataset::TradeDates, exchange) {
+        return Ok(result);
+    }
+
+    let (result, sources) = track_sources(async {
+        match fetch_with_fallback(Dataset::TradeDates, |ds| async move {
+            ds.trade_dates(exchange).await
+        })
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(err) => fetch_benchmark_trade_dates(exchange).await.map_err(|_| err),
+        }
+    })
+    .await;
+    let result: HashSet<NaiveDate> = result?;
 
-    if let Ok(json) = tushare::call_api("trade_cal", &json!({"is_open": "1"}), None, 0, false).await
-    {
-        if let (Some(fields), Some(items)) = (
-            json["data"]["fields"].as_array(),
-            json["data"]["items"].as_array(),
-        ) {
-            if let Some(idx_cal_date) = fields.iter().position(|f| f == "cal_date") {
-                for item in items {
-                    if let Some(values) = item.as_array() {
-                        if let Some(trade_date_str) = values[idx_cal_date].as_str() {
-                            if let Ok(date) = date_from_str(trade_date_str) {
-                                dates.insert(date);
-                            }
-                        }
-                    }
+    memory::insert(Dataset::TradeDates, exchange, result.clone(), sources);
+    Ok(result)
+}
+
+/// Dates of the benchmark kline of the exchange, as the last resort if no trade calendar is
+/// available, HK indexes have no klines so the Tracker Fund of the Hang Seng Index stands for it
+async fn fetch_benchmark_trade_dates(exchange: &str) -> VfResult<HashSet<NaiveDate>> {
+    let kline = match exchange {
+        "XHKG" => {
+            fetch_stock_kline(&Ticker::from_str("02800.HK")?, StockDividendAdjust::No).await?
+        }
+        _ => {
+            let index_code = match exchange {
+                "XBSE" => "899050.BJ",
+                "XSHE" => "399001.SZ",
+                "XSHG" => "000001.SH",
+                _ => {
+                    return Err(VfError::NoData {
+                        code: "NO_TRADE_DATES",
+                        message: format!("No trade dates of exchange '{exchange}'"),
+                    });
                 }
-            }
+            };
+            fetch_index_kline(&TickersIndex::from_str(index_code)?).await?
         }
-    } else {
-        let bench_ticker = Ticker::from_str("000001.SH")?;
-        let bench_kline = fetch_stock_kline(&bench_ticker, StockDividendAdjust::No).await?;
-        let bench_dates = bench_kline.all_dates();
-        dates.extend(bench_dates);
+    };
+
+    Ok(kline.all_dates().into_iter().collect())
+}
+
+/// Trading days of any of the exchanges, on which positions across markets are valued
+pub async fn fetch_trade_dates_union(exchanges: &HashSet<String>) -> VfResult<HashSet<NaiveDate>> {
+    let mut dates: HashSet<NaiveDate> = HashSet::new();
+    for exchange in exchanges {
+        dates.extend(fetch_trade_dates(exchange).await?);
     }
 
     Ok(dates)
 }
 
+/// Tickers are traded only on trading days of their own exchanges
+pub async fn is_trade_date(ticker: &Ticker, date: &NaiveDate) -> VfResult<bool> {
+    Ok(fetch_trade_dates(ticker.exchange_mic())
+        .await?
+        .contains(date))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[tokio::test]
     async fn test_fetch_trade_dates() {
-        let trade_dates = fetch_trade_dates().await.unwrap();
+        let trade_dates = fetch_trade_dates("XSHG").await.unwrap();
+        assert!(!trade_dates.is_empty());
+
+        let boxing_day = NaiveDate::from_ymd_opt(2024, 12, 26).unwrap();
+        let hk_ticker = Ticker::from_str("00700").unwrap();
+        let sh_ticker = Ticker::from_str("600000").unwrap();
+        assert!(!is_trade_date(&hk_ticker, &boxing_day).await.unwrap());
+        assert!(is_trade_date(&sh_ticker, &boxing_day).await.unwrap());
+
+        let exchanges: HashSet<String> = ["XSHG".to_string(), "XHKG".to_string()].into();
+        let union_dates = fetch_trade_dates_union(&exchanges).await.unwrap();
+        assert!(union_dates.contains(&boxing_day));
 
-        assert!(trade_dates.len() > 0);
+        let bench_dates = fetch_benchmark_trade_dates("XSHG").await.unwrap();
+        assert!(bench_dates.contains(&NaiveDate::from_ymd_opt(2026, 1, 16).unwrap()));
+        assert!(fetch_benchmark_trade_dates("XNYS").await.is_err());
     }
 }
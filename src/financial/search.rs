@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{
+    cache::{memory, track_sources},
+    ds::Dataset,
+    error::{VfError, VfResult},
+    financial::{bond::fetch_conv_bonds_basic, fund::fetch_etfs, stock::fetch_stocks_basic},
+    ticker::Ticker,
+    utils::text::{compare_phonetic, is_ascii_digits, match_pinyin_initials},
+};
+
+/// How a ticker matches the query, better matches first
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, strum::Display)]
+pub enum TickerMatchKind {
+    Name,
+    Symbol,
+    NamePrefix,
+    PartialName,
+    PinyinInitials,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TickerMatch {
+    pub ticker: Ticker,
+    pub name: String,
+    pub kind: TickerMatchKind,
+}
+
+/// Tickers of stocks, conv bonds and funds whose names or symbols match the query, by names,
+/// partial names, pinyin initials of names or symbol prefixes, sorted by the best matches first
+pub async fn search_tickers(query: &str) -> VfResult<Vec<TickerMatch>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut candidates: Vec<(Ticker, String)> = vec![];
+    candidates.extend(
+        fetch_stocks_basic()
+            .await?
+            .into_iter()
+            .map(|d| (d.ticker, d.name)),
+    );
+    candidates.extend(
+        fetch_conv_bonds_basic()
+            .await?
+            .into_iter()
+            .map(|d| (d.ticker, d.name)),
+    );
+    candidates.extend(fetch_etfs().await?.into_iter().map(|d| (d.ticker, d.name)));
+
+    let query_lowercase = query.to_lowercase();
+    let mut matches: Vec<TickerMatch> = candidates
+        .into_iter()
+        .filter_map(|(ticker, name)| {
+            let name_lowercase = name.to_lowercase();
+            let kind = if name_lowercase == query_lowercase {
+                TickerMatchKind::Name
+            } else if is_ascii_digits(query) && ticker.symbol.starts_with(query) {
+                TickerMatchKind::Symbol
+            } else if name_lowercase.starts_with(&query_lowercase) {
+                TickerMatchKind::NamePrefix
+            } else if name_lowercase.contains(&query_lowercase) {
+                TickerMatchKind::PartialName
+            } else if match_pinyin_initials(&name, query) {
+                TickerMatchKind::PinyinInitials
+            } else {
+                return None;
+            };
+
+            Some(TickerMatch { ticker, name, kind })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then_with(|| compare_phonetic(&a.name, &b.name))
+            .then_with(|| a.ticker.to_string().cmp(&b.ticker.to_string()))
+    });
+    matches.dedup_by(|a, b| a.ticker == b.ticker);
+
+    Ok(matches)
+}
+
+/// Ticker of a code as `Ticker::from_str` does, or else of a name, partial name or pinyin
+/// initials which must match exactly one ticker unless one's name is the same as it, symbol
+/// prefixes are not resolved as they are more likely malformed codes
+pub async fn resolve_ticker(ticker_str: &str) -> VfResult<Ticker> {
+    if let Ok(ticker) = Ticker::from_str(ticker_str) {
+        return Ok(ticker);
+    }
+
+    // Resolutions are derived from the basics, so cached along with them
+    let cache_key = format!("resolve/{}", ticker_str.trim());
+    if let Some(result) = memory::get(Dataset::StocksBasic, &cache_key) {
+        return Ok(result);
+    }
+
+    let (matches, sources) = track_sources(search_tickers(ticker_str)).await;
+    let matches = matches?;
+    let name_matches: Vec<&TickerMatch> = matches
+        .iter()
+        .filter(|m| m.kind == TickerMatchKind::Name)
+        .collect();
+    let candidates: Vec<&TickerMatch> = if name_matches.is_empty() {
+        matches
+            .iter()
+            .filter(|m| m.kind != TickerMatchKind::Symbol)
+            .collect()
+    } else {
+        name_matches
+    };
+
+    match candidates.as_slice() {
+        [] => Err(VfError::Invalid {
+            code: "INVALID_TICKER",
+            message: format!("Invalid ticker '{}'", ticker_str.trim()),
+        }),
+        [candidate] => {
+            memory::insert(
+                Dataset::StocksBasic,
+                &cache_key,
+                candidate.ticker.clone(),
+                sources,
+            );
+            Ok(candidate.ticker.clone())
+        }
+        _ => Err(VfError::Invalid {
+            code: "AMBIGUOUS_TICKER",
+            message: format!(
+                "Ambiguous ticker '{}' of {}",
+                ticker_str.trim(),
+                candidates
+                    .iter()
+                    .take(5)
+                    .map(|m| format!("{}({})", m.ticker, m.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    + if candidates.len() > 5 { ", ..." } else { "" }
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_tickers() {
+        let matches = search_tickers("hldb").await.unwrap();
+        assert_eq!(matches[0].ticker.to_string(), "512890.XSHG");
+        assert_eq!(matches[0].kind, TickerMatchKind::PinyinInitials);
+
+        let matches = search_tickers("银行").await.unwrap();
+        assert!(matches.len() > 1);
+        assert!(
+            matches
+                .iter()
+                .all(|m| m.kind == TickerMatchKind::PartialName)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_ticker() {
+        assert_eq!(
+            resolve_ticker("600000").await.unwrap().to_string(),
+            "600000.XSHG"
+        );
+        assert_eq!(
+            resolve_ticker("红利低波").await.unwrap().to_string(),
+            "512890.XSHG"
+        );
+        assert_eq!(
+            resolve_ticker("平安银行").await.unwrap().to_string(),
+            "000001.XSHE"
+        );
+
+        assert!(matches!(
+            resolve_ticker("银行").await,
+            Err(VfError::Invalid {
+                code: "AMBIGUOUS_TICKER",
+                ..
+            })
+        ));
+        // A symbol prefix is a malformed code rather than a ticker
+        assert!(matches!(
+            resolve_ticker("6000").await,
+            Err(VfError::Invalid {
+                code: "INVALID_TICKER",
+                ..
+            })
+        ));
+        assert!(matches!(
+            resolve_ticker("不存在").await,
+            Err(VfError::Invalid {
+                code: "INVALID_TICKER",
+                ..
+            })
+        ));
+    }
+}
@@ -1,38 +1,77 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::collections::HashMap;
 
-use dashmap::DashMap;
-use serde_json::json;
+use chrono::{Local, NaiveDate};
 
-use crate::{ds::qmt, error::VfResult, ticker::Ticker};
+use crate::{
+    cache::{
+        membership::{self, Membership},
+        memory, track_sources,
+    },
+    ds::{Dataset, fetch_with_fallback},
+    error::VfResult,
+    ticker::Ticker,
+    utils::datetime::date_to_str,
+};
 
-pub async fn fetch_sector_tickers(sector_prefix: &str) -> VfResult<HashMap<Ticker, String>> {
-    let cache_key = format!("qmt:{sector_prefix}");
-    if let Some(result) = TICKERS_SECTOR_CACHE.get(&cache_key) {
-        return Ok(result.clone());
+/// Tickers with sectors as of the date by the membership store, falling back to the current
+/// ones if the store has none before the date
+pub async fn fetch_sector_membership(
+    sector_prefix: &str,
+    date: &NaiveDate,
+) -> VfResult<Membership> {
+    let cache_key = format!("{sector_prefix}/{}", date_to_str(date));
+    if let Some(result) = memory::get(Dataset::SectorTickers, &cache_key) {
+        return Ok(result);
     }
 
-    let json = qmt::call_api(
-        "/stocks_sector",
-        &json!({"sector_prefix": sector_prefix}),
-        30,
-        false,
-    )
-    .await?;
+    let source = membership::sector_source(sector_prefix);
+    let (membership, sources): (VfResult<Membership>, _) = track_sources(async {
+        if let Some(membership) = membership::get_as_of(&source, date).await? {
+            Ok(membership)
+        } else {
+            Ok(Membership {
+                date: Local::now().date_naive(),
+                members: fetch_sector_tickers(sector_prefix).await?,
+                is_fallback: true,
+            })
+        }
+    })
+    .await;
+    let membership = membership?;
+    memory::insert(
+        Dataset::SectorTickers,
+        &cache_key,
+        membership.clone(),
+        sources,
+    );
 
-    let mut tickers_sector: HashMap<Ticker, String> = HashMap::new();
+    Ok(membership)
+}
 
-    if let Some(obj) = json.as_object() {
-        for (ticker_str, sector) in obj {
-            if let Some(ticker) = Ticker::from_qmt_str(ticker_str) {
-                tickers_sector.insert(ticker, sector.to_string());
-            }
-        }
+pub async fn fetch_sector_tickers(sector_prefix: &str) -> VfResult<HashMap<Ticker, String>> {
+    let cache_key = sector_prefix.to_string();
+    if let Some(result) = memory::get(Dataset::SectorTickers, &cache_key) {
+        return Ok(result);
     }
 
-    TICKERS_SECTOR_CACHE.insert(cache_key, tickers_sector.clone());
+    let (tickers_sector, sources) = track_sources(fetch_with_fallback(
+        Dataset::SectorTickers,
+        |ds| async move { ds.sector_tickers(sector_prefix).await },
+    ))
+    .await;
+    let tickers_sector = tickers_sector?;
+    membership::record(
+        &membership::sector_source(sector_prefix),
+        &Local::now().date_naive(),
+        &tickers_sector,
+    )
+    .await?;
+    memory::insert(
+        Dataset::SectorTickers,
+        &cache_key,
+        tickers_sector.clone(),
+        sources,
+    );
 
     Ok(tickers_sector)
 }
-
-static TICKERS_SECTOR_CACHE: LazyLock<DashMap<String, HashMap<Ticker, String>>> =
-    LazyLock::new(DashMap::new);
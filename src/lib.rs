@@ -1,6 +1,6 @@
 //! # vfunds lib
 
-use std::{collections::HashMap, env, path::PathBuf, sync::LazyLock};
+use std::{collections::HashMap, env, path::PathBuf, str::FromStr, sync::LazyLock};
 
 use directories::ProjectDirs;
 use rayon::iter::*;
@@ -18,11 +18,22 @@ pub mod utils;
 pub static CHANNEL_BUFFER_DEFAULT: usize = 64;
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub qmt_api: String,
     pub tushare_api: String,
     pub tushare_token: String,
+    /// Points of the Tushare account, which tier the requests per minute, 0 for no limit
+    pub tushare_points: u32,
+    /// Requests per minute of QMT, 0 for no limit
+    pub qmt_rate_limit: u32,
     pub wecom_webhook: String,
+    /// Data sources in priority order
+    pub data_sources: Vec<String>,
+    /// Data sources in priority order of specific datasets, overriding `data_sources`
+    pub dataset_sources: HashMap<String, Vec<String>>,
+    /// Directory of data files of the local data source
+    pub local_data_dir: String,
 }
 
 impl Default for Config {
@@ -31,11 +42,43 @@ impl Default for Config {
             qmt_api: "http://127.0.0.1:9000".to_string(),
             tushare_api: "http://api.tushare.pro".to_string(),
             tushare_token: "".to_string(),
+            tushare_points: 2000,
+            qmt_rate_limit: 60,
             wecom_webhook: "".to_string(),
+            data_sources: ds::DEFAULT_DATA_SOURCES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            dataset_sources: HashMap::new(),
+            local_data_dir: DATA_PATH.join("local").to_string_lossy().to_string(),
         }
     }
 }
 
+impl Config {
+    /// Data sources and datasets must be known, so a typo doesn't fall back to other sources
+    pub fn validate(&self) -> error::VfResult<()> {
+        for name in &self.data_sources {
+            ds::get_data_source(name)?;
+        }
+
+        for (dataset, names) in &self.dataset_sources {
+            if ds::Dataset::from_str(dataset).is_err() {
+                return Err(error::VfError::Invalid {
+                    code: "INVALID_DATASET",
+                    message: format!("Invalid dataset '{dataset}'"),
+                });
+            }
+
+            for name in names {
+                ds::get_data_source(name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Options that each item is String in <key>:<value> format
 pub struct VecOptions<'a>(pub &'a [String]);
 
@@ -89,6 +132,10 @@ pub async fn init(workspace: Option<PathBuf>) {
     }
 
     if let Ok(config) = confy::load_path::<Config>(&*CONFIG_PATH) {
+        if let Err(err) = config.validate() {
+            panic!("Invalid config '{}': {err}", CONFIG_PATH.to_string_lossy());
+        }
+
         *CONFIG.write().await = config;
     }
 
@@ -125,6 +172,8 @@ mod filter;
 mod financial;
 mod market;
 mod notifier;
+mod prefetch;
+mod quality;
 mod rule;
 mod ticker;
 
@@ -136,23 +185,48 @@ static CACHE_NO_EXPIRE: LazyLock<bool> = LazyLock::new(|| {
     v == "true" || v == "t" || v == "yes" || v == "y" || v == "1"
 });
 
+/// Memory budget of the in-process cache in MB
+static MEMORY_CACHE_MB: LazyLock<usize> = LazyLock::new(|| {
+    env::var("MEMORY_CACHE_MB")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(2048)
+});
+
 static CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| DATA_PATH.join("cache.db"));
 
 static CONFIG_PATH: LazyLock<PathBuf> = LazyLock::new(|| DATA_PATH.join("config.toml"));
 
 static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(|| RwLock::new(Config::default()));
 
-static DATA_PATH: LazyLock<PathBuf> =
-    LazyLock::new(|| match ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
+static DATA_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    #[cfg(test)]
+    if !*TEST_LIVE {
+        return env::temp_dir().join(format!("vfunds_test_{}", std::process::id()));
+    }
+
+    match ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
         Some(proj_dirs) => proj_dirs.data_dir().to_path_buf(),
         None => env::current_dir().expect("Unable to get current directory!"),
-    });
+    }
+});
 
 static PROGRESS_INTERVAL_SECS: u64 = 1;
 
 static WORKSPACE: LazyLock<RwLock<PathBuf>> =
     LazyLock::new(|| RwLock::new(env::current_dir().expect("Unable to get current directory!")));
 
+/// Tests run against the mock server with fixtures in `tests/fixtures` and a temporary data
+/// directory, unless `TEST_LIVE` is set to run against the configured services
+#[cfg(test)]
+static TEST_LIVE: LazyLock<bool> = LazyLock::new(|| {
+    let v = env::var("TEST_LIVE")
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    v == "true" || v == "t" || v == "yes" || v == "y" || v == "1"
+});
+
 #[cfg(test)]
 use ctor::ctor;
 
@@ -161,4 +235,21 @@ use ctor::ctor;
 fn global_test_setup() {
     let rt = tokio::runtime::Runtime::new().unwrap();
     rt.block_on(init(None));
+
+    if !*TEST_LIVE {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+                let addr = ds::mock::start(&fixtures_dir, 0).await.unwrap();
+                sender.send(addr).unwrap();
+
+                std::future::pending::<()>().await;
+            });
+        });
+
+        let addr = receiver.recv().unwrap();
+        rt.block_on(ds::mock::use_server(&addr));
+    }
 }
@@ -53,20 +53,35 @@ async fn main() {
         Commands::Backtest(cmd) => {
             cmd.exec().await;
         }
+        Commands::Cache(cmd) => {
+            cmd.exec().await;
+        }
         Commands::Check(cmd) => {
             cmd.exec().await;
         }
         Commands::Config(cmd) => {
             cmd.exec().await;
         }
+        Commands::Data(cmd) => {
+            cmd.exec().await;
+        }
+        Commands::Export(cmd) => {
+            cmd.exec().await;
+        }
         Commands::Kline(cmd) => {
             cmd.exec().await;
         }
         Commands::List(cmd) => {
             cmd.exec().await;
         }
+        Commands::Membership(cmd) => {
+            cmd.exec().await;
+        }
         Commands::Result(cmd) => {
             cmd.exec().await;
         }
+        Commands::Ticker(cmd) => {
+            cmd.exec().await;
+        }
     }
 }
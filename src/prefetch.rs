@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{
+    error::VfResult,
+    financial::{
+        fund::{fetch_fund_nav, fetch_fund_share},
+        get_ticker_kline, get_ticker_title,
+        market::fetch_trade_dates_union,
+        stock::*,
+    },
+    spec::FundDefinition,
+    ticker::{Ticker, TickerType},
+};
+
+/// Fetch data of the ticker into cache, fundamentals of stocks and NAVs of funds are best effort
+/// since not all of them have
+pub async fn prefetch_ticker(ticker: &Ticker) -> VfResult<()> {
+    let (kline_result, _) = tokio::join!(get_ticker_kline(ticker, false), get_ticker_title(ticker));
+    kline_result?;
+
+    if ticker.r#type == TickerType::Stock {
+        let _ = tokio::join!(
+            fetch_stock_indicators(ticker),
+            fetch_stock_dividends(ticker),
+            fetch_stock_report_balance(ticker),
+            fetch_stock_report_capital(ticker),
+            fetch_stock_report_cash_flow(ticker),
+            fetch_stock_report_income(ticker),
+            fetch_stock_report_pershare(ticker),
+        );
+    } else if ticker.r#type.is_fund() {
+        let _ = tokio::join!(fetch_fund_nav(ticker), fetch_fund_share(ticker));
+    }
+
+    Ok(())
+}
+
+/// All tickers the funds may hold during the date range, the universe is resolved on the first
+/// trade date of each month
+pub async fn prefetch_tickers(
+    fund_definitions: &[FundDefinition],
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+) -> VfResult<Vec<Ticker>> {
+    let mut exchanges: HashSet<String> = HashSet::new();
+    for fund_definition in fund_definitions {
+        exchanges.extend(fund_definition.exchanges().await?);
+    }
+    let trade_dates = fetch_trade_dates_union(&exchanges).await?;
+
+    let mut sample_dates: Vec<NaiveDate> = vec![];
+    for date in start_date.iter_days().take_while(|date| date <= end_date) {
+        if trade_dates.contains(&date)
+            && sample_dates
+                .last()
+                .map(|last| (last.year(), last.month()) != (date.year(), date.month()))
+                .unwrap_or(true)
+        {
+            sample_dates.push(date);
+        }
+    }
+
+    let mut tickers: HashSet<Ticker> = HashSet::new();
+    for fund_definition in fund_definitions {
+        for date in &sample_dates {
+            tickers.extend(fund_definition.all_tickers_map(date).await?.into_keys());
+        }
+    }
+
+    let mut tickers: Vec<Ticker> = tickers.into_iter().collect();
+    tickers.sort_by_key(|ticker| ticker.to_string());
+
+    Ok(tickers)
+}
@@ -0,0 +1,418 @@
+//! Data quality checks of klines, within a date range and across data sources
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::NaiveDate;
+
+use crate::{
+    data::series::DailySeries,
+    error::VfResult,
+    financial::{
+        KlineField, get_ticker_kline,
+        market::fetch_trade_dates,
+        stock::{StockDividendAdjust, fetch_stock_kline_with_ds},
+    },
+    ticker::{Ticker, TickerType},
+    utils::datetime::date_to_str,
+};
+
+/// Max difference of daily returns between data sources
+const MAX_RETURN_DIVERGENCE: f64 = 0.005;
+
+/// Tolerance over the daily price limit, for rounding of limit prices
+const PRICE_LIMIT_TOLERANCE: f64 = 0.01;
+
+/// Trade days after listing without price limits
+const UNLIMITED_DAYS_AFTER_LISTING: usize = 5;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, strum::Display)]
+pub enum KlineIssueKind {
+    NoData,
+    MissingDays,
+    NonPositivePrice,
+    InconsistentOhlc,
+    AbnormalJump,
+    SourceDivergence,
+}
+
+#[derive(Clone, Debug)]
+pub struct KlineIssue {
+    pub ticker: Ticker,
+    pub kind: KlineIssueKind,
+    pub dates: Vec<NaiveDate>,
+    pub message: String,
+}
+
+impl KlineIssue {
+    /// If the kline is bad for backtests, missing days are not as they are mostly suspensions,
+    /// nor failures to compare data sources
+    pub fn is_bad(&self) -> bool {
+        match self.kind {
+            KlineIssueKind::MissingDays => false,
+            KlineIssueKind::SourceDivergence => !self.dates.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+/// Issue found on a date, or of the whole kline without a date
+type Finding = (KlineIssueKind, Option<NaiveDate>, String);
+
+/// Rows of [open, close, high, low] sorted by date
+type OhlcRow = (NaiveDate, [f64; 4]);
+
+/// Check the kline of the ticker within the date range, stocks are also checked for abnormal
+/// jumps of adjusted prices (i.e. not explained by dividends) and divergence between QMT and
+/// Tushare
+pub async fn check_kline(
+    ticker: &Ticker,
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+) -> VfResult<Vec<KlineIssue>> {
+    let mut findings: Vec<Finding> = vec![];
+
+    let kline = get_ticker_kline(ticker, false).await?;
+    let rows = ohlc_rows(&kline, start_date, end_date);
+
+    if rows.is_empty() {
+        findings.push((
+            KlineIssueKind::NoData,
+            None,
+            format!(
+                "No kline from {} to {}",
+                date_to_str(start_date),
+                date_to_str(end_date)
+            ),
+        ));
+    } else {
+        let trade_dates = fetch_trade_dates(ticker.exchange_mic()).await?;
+        findings.extend(check_missing_days(&rows, &trade_dates));
+    }
+
+    findings.extend(check_prices(&rows));
+
+    if ticker.r#type == TickerType::Stock {
+        let is_listed_in_range =
+            kline.all_dates().into_iter().min() == rows.first().map(|(date, _)| *date);
+        findings.extend(check_jumps(&rows, price_limit(ticker), is_listed_in_range));
+
+        match tokio::try_join!(
+            fetch_stock_kline_with_ds(ticker, StockDividendAdjust::Backward, "qmt"),
+            fetch_stock_kline_with_ds(ticker, StockDividendAdjust::Backward, "tushare"),
+        ) {
+            Ok((kline_qmt, kline_tushare)) => {
+                findings.extend(check_divergence(
+                    &close_values(&kline_qmt, start_date, end_date),
+                    &close_values(&kline_tushare, start_date, end_date),
+                ));
+            }
+            Err(err) => {
+                findings.push((
+                    KlineIssueKind::SourceDivergence,
+                    None,
+                    format!("Unable to compare data sources: {err}"),
+                ));
+            }
+        }
+    }
+
+    Ok(merge_findings(ticker, findings))
+}
+
+/// Trade days without kline between the first and last days, as before or after are not listed
+fn check_missing_days(rows: &[OhlcRow], trade_dates: &HashSet<NaiveDate>) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    if let (Some((first_date, _)), Some((last_date, _))) = (rows.first(), rows.last()) {
+        for date in first_date.iter_days().take_while(|date| date <= last_date) {
+            if trade_dates.contains(&date) && rows.binary_search_by_key(&date, |(d, _)| *d).is_err()
+            {
+                findings.push((
+                    KlineIssueKind::MissingDays,
+                    Some(date),
+                    "Trade days without kline, e.g. suspended".to_string(),
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+fn check_prices(rows: &[OhlcRow]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    for (date, [open, close, high, low]) in rows {
+        if [open, close, high, low].iter().any(|price| **price <= 0.0) {
+            findings.push((
+                KlineIssueKind::NonPositivePrice,
+                Some(*date),
+                format!("Prices not positive, e.g. O={open} C={close} H={high} L={low}"),
+            ));
+        }
+
+        if *high < open.max(*close).max(*low) || *low > open.min(*close).min(*high) {
+            findings.push((
+                KlineIssueKind::InconsistentOhlc,
+                Some(*date),
+                format!("High or low out of range, e.g. O={open} C={close} H={high} L={low}"),
+            ));
+        }
+    }
+
+    findings
+}
+
+/// Changes of adjusted close over the daily price limit, which dividends do not explain
+fn check_jumps(rows: &[OhlcRow], price_limit: f64, is_listed_in_range: bool) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    let mut prev_close: Option<f64> = None;
+    for (i, (date, [_, close, _, _])) in rows.iter().enumerate() {
+        let is_unlimited = is_listed_in_range && i < UNLIMITED_DAYS_AFTER_LISTING;
+
+        if let Some(prev_close) = prev_close
+            && prev_close > 0.0
+            && !is_unlimited
+        {
+            let change = close / prev_close - 1.0;
+            if change.abs() > price_limit + PRICE_LIMIT_TOLERANCE {
+                findings.push((
+                    KlineIssueKind::AbnormalJump,
+                    Some(*date),
+                    format!(
+                        "Adjusted close changed over the limit {:.0}%, e.g. {:.2}%",
+                        price_limit * 100.0,
+                        change * 100.0
+                    ),
+                ));
+            }
+        }
+
+        prev_close = Some(*close);
+    }
+
+    findings
+}
+
+/// Differences of daily returns of adjusted close, which are comparable even if data sources
+/// adjust prices from different bases
+fn check_divergence(
+    closes_qmt: &HashMap<NaiveDate, f64>,
+    closes_tushare: &HashMap<NaiveDate, f64>,
+) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    let mut dates: Vec<NaiveDate> = closes_qmt
+        .keys()
+        .filter(|date| closes_tushare.contains_key(date))
+        .copied()
+        .collect();
+    dates.sort_unstable();
+
+    for window in dates.windows(2) {
+        let (prev_date, date) = (&window[0], &window[1]);
+        let return_qmt = closes_qmt[date] / closes_qmt[prev_date] - 1.0;
+        let return_tushare = closes_tushare[date] / closes_tushare[prev_date] - 1.0;
+
+        if (return_qmt - return_tushare).abs() > MAX_RETURN_DIVERGENCE {
+            findings.push((
+                KlineIssueKind::SourceDivergence,
+                Some(*date),
+                format!(
+                    "Daily returns of adjusted close differ, e.g. QMT={:.2}% Tushare={:.2}%",
+                    return_qmt * 100.0,
+                    return_tushare * 100.0
+                ),
+            ));
+        }
+    }
+
+    findings
+}
+
+/// Findings of a kind are merged into an issue, with the message of the first one
+fn merge_findings(ticker: &Ticker, findings: Vec<Finding>) -> Vec<KlineIssue> {
+    let mut issues: BTreeMap<KlineIssueKind, (Vec<NaiveDate>, String)> = BTreeMap::new();
+    for (kind, date, message) in findings {
+        issues
+            .entry(kind)
+            .or_insert((vec![], message))
+            .0
+            .extend(date);
+    }
+
+    issues
+        .into_iter()
+        .map(|(kind, (dates, message))| KlineIssue {
+            ticker: ticker.clone(),
+            kind,
+            dates,
+            message,
+        })
+        .collect()
+}
+
+/// Daily price limit by board, ST stocks have lower limits so they are covered too
+fn price_limit(ticker: &Ticker) -> f64 {
+    if ticker.exchange_mic() == "XBSE" {
+        0.3
+    } else if ["300", "301", "688", "689"]
+        .iter()
+        .any(|prefix| ticker.symbol.starts_with(prefix))
+    {
+        0.2
+    } else {
+        0.1
+    }
+}
+
+fn close_values(
+    kline: &DailySeries,
+    start_date: &NaiveDate,
+    end_date: &NaiveDate,
+) -> HashMap<NaiveDate, f64> {
+    kline
+        .get_values::<f64>(&KlineField::Close.to_string())
+        .into_iter()
+        .filter(|(date, _)| date >= start_date && date <= end_date)
+        .collect()
+}
+
+fn ohlc_rows(kline: &DailySeries, start_date: &NaiveDate, end_date: &NaiveDate) -> Vec<OhlcRow> {
+    let mut rows: Vec<OhlcRow> = kline
+        .all_values::<f64>()
+        .into_iter()
+        .filter(|(date, _)| date >= start_date && date <= end_date)
+        .filter_map(|(date, values)| {
+            let value = |field: KlineField| values.get(&field.to_string()).copied();
+            Some((
+                date,
+                [
+                    value(KlineField::Open)?,
+                    value(KlineField::Close)?,
+                    value(KlineField::High)?,
+                    value(KlineField::Low)?,
+                ],
+            ))
+        })
+        .collect();
+    rows.sort_by_key(|(date, _)| *date);
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::utils::datetime::date_from_str;
+
+    #[tokio::test]
+    async fn test_check_kline() {
+        let ticker = Ticker::from_str("600595").unwrap();
+        let issues = check_kline(
+            &ticker,
+            &date_from_str("2019-02-01").unwrap(),
+            &date_from_str("2019-12-31").unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            !issues
+                .iter()
+                .any(|issue| issue.kind == KlineIssueKind::NoData)
+        );
+        assert!(
+            !issues
+                .iter()
+                .any(|issue| issue.kind == KlineIssueKind::NonPositivePrice)
+        );
+    }
+
+    #[test]
+    fn test_check_bad_rows() {
+        let date = |s: &str| date_from_str(s).unwrap();
+        let dated = |findings: Vec<Finding>| -> Vec<(KlineIssueKind, NaiveDate)> {
+            findings
+                .into_iter()
+                .filter_map(|(kind, date, _)| date.map(|date| (kind, date)))
+                .collect()
+        };
+
+        let rows: Vec<OhlcRow> = vec![
+            (date("2024-01-02"), [10.0, 10.0, 10.5, 9.5]),
+            (date("2024-01-03"), [10.0, 10.5, 10.2, 9.8]),
+            (date("2024-01-05"), [10.0, 12.0, 12.0, 10.0]),
+            (date("2024-01-08"), [0.0, 12.0, 12.0, 0.0]),
+        ];
+        let trade_dates: HashSet<NaiveDate> = [
+            "2024-01-02",
+            "2024-01-03",
+            "2024-01-04",
+            "2024-01-05",
+            "2024-01-08",
+            "2024-01-09",
+        ]
+        .into_iter()
+        .map(date)
+        .collect();
+
+        assert_eq!(
+            dated(check_missing_days(&rows, &trade_dates)),
+            vec![(KlineIssueKind::MissingDays, date("2024-01-04"))]
+        );
+        assert_eq!(
+            dated(check_prices(&rows)),
+            vec![
+                (KlineIssueKind::InconsistentOhlc, date("2024-01-03")),
+                (KlineIssueKind::NonPositivePrice, date("2024-01-08")),
+            ]
+        );
+        assert_eq!(
+            dated(check_jumps(&rows, 0.1, false)),
+            vec![(KlineIssueKind::AbnormalJump, date("2024-01-05"))]
+        );
+        assert!(check_jumps(&rows, 0.1, true).is_empty());
+
+        // Adjusted from different bases but with the same returns
+        let closes_qmt: HashMap<NaiveDate, f64> = rows
+            .iter()
+            .map(|(date, [_, close, _, _])| (*date, *close))
+            .collect();
+        let mut closes_tushare: HashMap<NaiveDate, f64> = closes_qmt
+            .iter()
+            .map(|(date, close)| (*date, close * 2.0))
+            .collect();
+        assert!(check_divergence(&closes_qmt, &closes_tushare).is_empty());
+
+        closes_tushare.insert(date("2024-01-05"), 23.0);
+        assert_eq!(
+            dated(check_divergence(&closes_qmt, &closes_tushare)),
+            vec![
+                (KlineIssueKind::SourceDivergence, date("2024-01-05")),
+                (KlineIssueKind::SourceDivergence, date("2024-01-08")),
+            ]
+        );
+
+        let issues = merge_findings(
+            &Ticker::from_str("600000").unwrap(),
+            check_prices(&rows)
+                .into_iter()
+                .chain(check_missing_days(&rows, &trade_dates))
+                .collect(),
+        );
+        assert_eq!(
+            issues.iter().map(|issue| issue.kind).collect::<Vec<_>>(),
+            vec![
+                KlineIssueKind::MissingDays,
+                KlineIssueKind::NonPositivePrice,
+                KlineIssueKind::InconsistentOhlc,
+            ]
+        );
+        assert!(!issues[0].is_bad());
+        assert!(issues[1].is_bad());
+    }
+}
@@ -30,9 +30,13 @@ impl Rule {
         let executor: Box<dyn RuleExecutor> = match definition.name.as_str() {
             "hold" => Box::new(hold::Executor::new(definition)),
             "hold_by_cluster_pb" => Box::new(hold_by_cluster_pb::Executor::new(definition)),
+            "hold_by_conv_bond_double_low" => {
+                Box::new(hold_by_conv_bond_double_low::Executor::new(definition))
+            }
             "hold_by_conv_bond_premium" => {
                 Box::new(hold_by_conv_bond_premium::Executor::new(definition))
             }
+            "hold_by_expression" => Box::new(hold_by_expression::Executor::new(definition)),
             "hold_by_factors_boosting" => {
                 Box::new(hold_by_factors_boosting::Executor::new(definition))
             }
@@ -58,6 +62,7 @@ impl Rule {
             }
             "resize_by_spike" => Box::new(resize_by_spike::Executor::new(definition)),
             "resize_by_valuation" => Box::new(resize_by_valuation::Executor::new(definition)),
+            "script" => Box::new(script::Executor::new(definition)),
             _ => panic!("Unsupported rule: {}", definition.name),
         };
 
@@ -93,7 +98,9 @@ pub trait RuleExecutor: Send {
 
 mod hold;
 mod hold_by_cluster_pb;
+mod hold_by_conv_bond_double_low;
 mod hold_by_conv_bond_premium;
+mod hold_by_expression;
 mod hold_by_factors_boosting;
 mod hold_by_factors_knn;
 mod hold_by_momentum;
@@ -109,6 +116,7 @@ mod resize_by_index_valuation;
 mod resize_by_macd_crossover;
 mod resize_by_spike;
 mod resize_by_valuation;
+mod script;
 
 enum WeightMethod {
     Equal,          // Equal weight
@@ -82,7 +82,7 @@ impl RuleExecutor for Executor {
             }
         }
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut clusters_tickers: HashMap<String, Vec<Ticker>> = HashMap::new();
             for (ticker, (_, optional_ticker_source)) in &tickers_map {
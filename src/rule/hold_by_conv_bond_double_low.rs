@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate};
+use tokio::{sync::mpsc::Sender, time::Instant};
+
+use crate::{
+    PROGRESS_INTERVAL_SECS,
+    error::VfResult,
+    financial::bond::{analytics::analyze_conv_bond, fetch_conv_bonds},
+    rule::{
+        BacktestEvent, FundBacktestContext, RuleDefinition, RuleExecutor, calc_weights,
+        rule_notify_calc_progress, rule_notify_indicators, rule_send_info, rule_send_warning,
+        select_by_indicators,
+    },
+    spec::{Frequency, RuleOptions},
+    ticker::Ticker,
+};
+
+pub struct Executor {
+    frequency: Frequency,
+
+    #[allow(dead_code)]
+    options: RuleOptions,
+}
+
+impl Executor {
+    pub fn new(definition: &RuleDefinition) -> Self {
+        Self {
+            frequency: definition.frequency.clone(),
+            options: definition.options.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl RuleExecutor for Executor {
+    async fn exec(
+        &mut self,
+        context: &mut FundBacktestContext,
+        date: &NaiveDate,
+        event_sender: &Sender<BacktestEvent>,
+    ) -> VfResult<()> {
+        let rule_name = mod_name!();
+
+        let call_margin_days = self.options.read_u64("call_margin_days", 5);
+        let limit = self.options.read_u64_no_zero("limit", 5);
+        let max_tenor_months = self.options.read_u64_no_zero("max_tenor_months", 72);
+        let min_remaining_days = self.options.read_u64_no_zero("min_remaining_days", 60);
+        let remain_size_change_lower = self.options.read_f64("remain_size_change_lower", -1.0);
+        let weight_method = self.options.read_str("weight_method", "equal");
+        let ytm_lower = self.options.read_f64("ytm_lower", -100.0);
+
+        let mut conv_bond_issues = fetch_conv_bonds(date, max_tenor_months as u32).await?;
+
+        // Restrict to the fund universe when tickers or ticker sources are defined
+        let tickers_map = context.all_tickers_map(date).await?;
+        if !tickers_map.is_empty() {
+            conv_bond_issues.retain(|b| tickers_map.contains_key(&b.ticker));
+        }
+
+        if !conv_bond_issues.is_empty() {
+            let filter_analysis_date = *date - Days::new(7);
+            let min_remaining_days = min_remaining_days.max(self.frequency.to_days()) as i64;
+
+            let mut indicators: Vec<(Ticker, f64)> = vec![];
+            {
+                let mut last_time = Instant::now();
+                let mut calc_count: usize = 0;
+
+                for conv_bond_issue in &conv_bond_issues {
+                    calc_count += 1;
+
+                    let analysis = match analyze_conv_bond(&conv_bond_issue.ticker, date).await {
+                        Ok(analysis) => analysis,
+                        Err(err) => {
+                            rule_send_warning(
+                                rule_name,
+                                &format!("[Analysis Failed] {}: {err}", conv_bond_issue.ticker),
+                                date,
+                                event_sender,
+                            )
+                            .await;
+                            None
+                        }
+                    };
+                    if let Some(analysis) = analysis
+                        && analysis.price > 0.0
+                        && analysis.date >= filter_analysis_date
+                        && analysis
+                            .remaining_days
+                            .is_none_or(|d| d >= min_remaining_days)
+                        && analysis.ytm.is_none_or(|y| y >= ytm_lower)
+                        && analysis
+                            .remain_size_change
+                            .is_none_or(|c| c >= remain_size_change_lower)
+                        && !analysis.is_call_imminent(call_margin_days as usize)
+                    {
+                        indicators.push((analysis.ticker, analysis.double_low));
+                    }
+
+                    if last_time.elapsed().as_secs() > PROGRESS_INTERVAL_SECS {
+                        rule_notify_calc_progress(
+                            rule_name,
+                            calc_count as f64 / conv_bond_issues.len() as f64 * 100.0,
+                            date,
+                            event_sender,
+                        )
+                        .await;
+
+                        last_time = Instant::now();
+                    }
+                }
+
+                rule_notify_calc_progress(rule_name, 100.0, date, event_sender).await;
+            }
+            indicators.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            rule_send_info(
+                rule_name,
+                &format!(
+                    "[Universe] {}({})",
+                    conv_bond_issues.len(),
+                    indicators.len()
+                ),
+                date,
+                event_sender,
+            )
+            .await;
+
+            let (targets_indicators, candidates_indicators) =
+                select_by_indicators(&indicators, limit as usize, false).await?;
+
+            rule_notify_indicators(
+                rule_name,
+                &targets_indicators
+                    .iter()
+                    .map(|&(ref t, v)| (t.clone(), format!("{v:.4}")))
+                    .collect::<Vec<_>>(),
+                &candidates_indicators
+                    .iter()
+                    .map(|&(ref t, v)| (t.clone(), format!("{v:.4}")))
+                    .collect::<Vec<_>>(),
+                date,
+                event_sender,
+            )
+            .await;
+
+            let weights = calc_weights(&targets_indicators, weight_method)?;
+            context.rebalance(&weights, date, event_sender).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,11 +6,13 @@ use crate::{
     PROGRESS_INTERVAL_SECS, STALE_DAYS_SHORT,
     error::VfResult,
     financial::bond::{
-        ConvBondDailyField, fetch_conv_bond_basic, fetch_conv_bond_daily, fetch_conv_bonds,
+        ConvBondDailyField, analytics::analyze_conv_bond, fetch_conv_bond_basic,
+        fetch_conv_bond_daily, fetch_conv_bonds,
     },
     rule::{
         BacktestEvent, FundBacktestContext, RuleDefinition, RuleExecutor, calc_weights,
-        rule_notify_calc_progress, rule_notify_indicators, rule_send_info, select_by_indicators,
+        rule_notify_calc_progress, rule_notify_indicators, rule_send_info, rule_send_warning,
+        select_by_indicators,
     },
     spec::{Frequency, RuleOptions},
     ticker::Ticker,
@@ -43,6 +45,8 @@ impl RuleExecutor for Executor {
     ) -> VfResult<()> {
         let rule_name = mod_name!();
 
+        let avoid_call = self.options.read_bool("avoid_call", false);
+        let call_margin_days = self.options.read_u64("call_margin_days", 5);
         let issue_size_quantile_lower =
             self.options
                 .read_f64_in_range("issue_size_quantile_lower", 0.0, 0.0..=1.0);
@@ -55,7 +59,14 @@ impl RuleExecutor for Executor {
                 .read_f64_in_range("straight_premium_quantile_upper", 1.0, 0.0..=1.0);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let conv_bond_issues = fetch_conv_bonds(date, max_tenor_months as u32).await?;
+        let mut conv_bond_issues = fetch_conv_bonds(date, max_tenor_months as u32).await?;
+
+        // Restrict to the fund universe when tickers or ticker sources are defined
+        let tickers_map = context.all_tickers_map(date).await?;
+        if !tickers_map.is_empty() {
+            conv_bond_issues.retain(|b| tickers_map.contains_key(&b.ticker));
+        }
+
         if !conv_bond_issues.is_empty() {
             let filter_analysis_date = *date - Days::new(7);
             let filter_expire_date =
@@ -98,6 +109,28 @@ impl RuleExecutor for Executor {
                         }
                     }
 
+                    if avoid_call {
+                        match analyze_conv_bond(ticker, date).await {
+                            Ok(analysis) => {
+                                if analysis
+                                    .is_some_and(|a| a.is_call_imminent(call_margin_days as usize))
+                                {
+                                    continue;
+                                }
+                            }
+                            Err(err) => {
+                                rule_send_warning(
+                                    rule_name,
+                                    &format!("[Analysis Failed] {ticker}: {err}"),
+                                    date,
+                                    event_sender,
+                                )
+                                .await;
+                                continue;
+                            }
+                        }
+                    }
+
                     let daily = fetch_conv_bond_daily(ticker).await?;
                     if let (
                         Some((latest_date, price)),
@@ -0,0 +1,474 @@
+use std::{collections::HashMap, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use tokio::{sync::mpsc::Sender, time::Instant};
+
+use crate::{
+    PROGRESS_INTERVAL_SECS, REQUIRED_DATA_COMPLETENESS, STALE_DAYS_SHORT,
+    error::{VfError, VfResult},
+    financial::{
+        KlineField, PriceType, get_ticker_kline, get_ticker_price,
+        helper::{
+            calc_stock_cash_ratio, calc_stock_current_ratio, calc_stock_debt_ratio,
+            calc_stock_dividend_ratio_of_years, calc_stock_free_cash_ratio_of_years,
+            calc_stock_market_cap, calc_stock_pb, calc_stock_pe_ttm, calc_stock_ps_ttm,
+            calc_stock_roe_of_years,
+        },
+        stock::{StockIndicatorField, fetch_stock_indicators},
+    },
+    rule::{
+        BacktestEvent, FundBacktestContext, RuleDefinition, RuleExecutor, calc_weights,
+        rule_notify_calc_progress, rule_notify_indicators, rule_send_info, rule_send_warning,
+        select_by_indicators,
+    },
+    spec::RuleOptions,
+    ticker::Ticker,
+    utils::{
+        expr::{Expr, Term},
+        financial::{
+            calc_annualized_momentum, calc_annualized_return_rate, calc_annualized_volatility_std,
+        },
+    },
+};
+
+/// Tickers listed as samples in a warning of failures
+const FAILURE_SAMPLES: usize = 3;
+
+pub struct Executor {
+    #[allow(dead_code)]
+    options: RuleOptions,
+}
+
+impl Executor {
+    pub fn new(definition: &RuleDefinition) -> Self {
+        Self {
+            options: definition.options.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl RuleExecutor for Executor {
+    async fn exec(
+        &mut self,
+        context: &mut FundBacktestContext,
+        date: &NaiveDate,
+        event_sender: &Sender<BacktestEvent>,
+    ) -> VfResult<()> {
+        let rule_name = mod_name!();
+
+        let factor = Expr::parse(self.options.read_str("factor", ""))?;
+        let filters = self
+            .options
+            .read_array("filters")
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(Expr::parse)
+                    .collect::<VfResult<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let limit = self.options.read_u64_no_zero("limit", 5);
+        let skip_same_sector = self.options.read_bool("skip_same_sector", false);
+        let weight_method = self.options.read_str("weight_method", "equal");
+
+        // Terms of the factor also used by filters are calculated along with the filters
+        let mut filter_defs: Vec<(Term, Factor)> = vec![];
+        let mut factor_defs: Vec<(Term, Factor)> = vec![];
+        for term in filters.iter().flat_map(|filter| filter.terms()) {
+            if !filter_defs.iter().any(|(t, _)| *t == term) {
+                let factor = Factor::from_term(&term)?;
+                filter_defs.push((term, factor));
+            }
+        }
+        for term in factor.terms() {
+            if !filter_defs
+                .iter()
+                .chain(&factor_defs)
+                .any(|(t, _)| *t == term)
+            {
+                let factor = Factor::from_term(&term)?;
+                factor_defs.push((term, factor));
+            }
+        }
+
+        let tickers_map = context.all_tickers_map(date).await?;
+        if !tickers_map.is_empty() {
+            let tickers: Vec<Ticker> = tickers_map.keys().cloned().collect();
+
+            // Filters are applied before the factor is calculated, so that only the filtered
+            // universe is calculated and seen by cross-sectional functions
+            let filter_progress = if factor_defs.is_empty() { 100.0 } else { 50.0 };
+            let terms_values = calc_terms_values(
+                rule_name,
+                &filter_defs,
+                &tickers,
+                (0.0, filter_progress),
+                date,
+                event_sender,
+            )
+            .await;
+
+            let mut retained: Vec<bool> = vec![true; tickers.len()];
+            for filter in &filters {
+                let values = filter.eval(&terms_values, tickers.len())?;
+                for (i, v) in values.iter().enumerate() {
+                    if !matches!(v, Some(v) if *v != 0.0) {
+                        retained[i] = false;
+                    }
+                }
+            }
+
+            let retained_tickers: Vec<Ticker> = tickers
+                .iter()
+                .zip(&retained)
+                .filter(|&(_, r)| *r)
+                .map(|(t, _)| t.clone())
+                .collect();
+            let mut retained_terms_values: HashMap<String, Vec<Option<f64>>> = terms_values
+                .into_iter()
+                .map(|(k, values)| {
+                    (
+                        k,
+                        values
+                            .into_iter()
+                            .zip(&retained)
+                            .filter(|&(_, r)| *r)
+                            .map(|(v, _)| v)
+                            .collect(),
+                    )
+                })
+                .collect();
+            retained_terms_values.extend(
+                calc_terms_values(
+                    rule_name,
+                    &factor_defs,
+                    &retained_tickers,
+                    (filter_progress, 100.0),
+                    date,
+                    event_sender,
+                )
+                .await,
+            );
+
+            let factor_values = factor.eval(&retained_terms_values, retained_tickers.len())?;
+
+            let mut indicators: Vec<(Ticker, f64)> = retained_tickers
+                .into_iter()
+                .zip(factor_values)
+                .filter_map(|(ticker, v)| v.map(|v| (ticker, v)))
+                .collect();
+            indicators.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            rule_send_info(
+                rule_name,
+                &format!("[Universe] {}({})", tickers_map.len(), indicators.len()),
+                date,
+                event_sender,
+            )
+            .await;
+
+            let (targets_indicators, candidates_indicators) =
+                select_by_indicators(&indicators, limit as usize, skip_same_sector).await?;
+
+            rule_notify_indicators(
+                rule_name,
+                &targets_indicators
+                    .iter()
+                    .map(|&(ref t, v)| (t.clone(), format!("{v:.4}")))
+                    .collect::<Vec<_>>(),
+                &candidates_indicators
+                    .iter()
+                    .map(|&(ref t, v)| (t.clone(), format!("{v:.4}")))
+                    .collect::<Vec<_>>(),
+                date,
+                event_sender,
+            )
+            .await;
+
+            let weights = calc_weights(&targets_indicators, weight_method)?;
+            context.rebalance(&weights, date, event_sender).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Factor {
+    CashRatio,
+    CirculatingMarketCap,
+    Close,
+    CurrentRatio,
+    DebtRatio,
+    DividendYield(u32),
+    FreeCashRatio(u32),
+    Indicator(StockIndicatorField),
+    MarketCap,
+    Momentum(u32),
+    Pb,
+    PeTtm,
+    PsTtm,
+    Return(u32),
+    Roe(u32),
+    Volatility(u32),
+}
+
+impl Factor {
+    fn from_term(term: &Term) -> VfResult<Self> {
+        let arg = |default: u32| -> VfResult<u32> {
+            match term.args.as_slice() {
+                [] => Ok(default),
+                [v] if *v >= 1.0 && v.fract() == 0.0 => Ok(*v as u32),
+                _ => Err(VfError::Invalid {
+                    code: "INVALID_FACTOR",
+                    message: format!("Invalid arguments of factor '{term}'"),
+                }),
+            }
+        };
+
+        Ok(match term.name.as_str() {
+            "cash_ratio" => Factor::CashRatio,
+            "circulating_market_cap" => Factor::CirculatingMarketCap,
+            "close" => Factor::Close,
+            "current_ratio" => Factor::CurrentRatio,
+            "debt_ratio" => Factor::DebtRatio,
+            "dividend_yield" => Factor::DividendYield(arg(3)?),
+            "free_cash_ratio" => Factor::FreeCashRatio(arg(3)?),
+            "market_cap" => Factor::MarketCap,
+            "momentum" => Factor::Momentum(arg(250)?),
+            "pb" => Factor::Pb,
+            "pe_ttm" => Factor::PeTtm,
+            "ps_ttm" => Factor::PsTtm,
+            "return" => Factor::Return(arg(250)?),
+            "roe" => Factor::Roe(arg(3)?),
+            "volatility" => Factor::Volatility(arg(250)?),
+            name => {
+                if let Ok(field) = StockIndicatorField::from_str(&name.replace('_', ""))
+                    && term.args.is_empty()
+                {
+                    Factor::Indicator(field)
+                } else {
+                    return Err(VfError::Invalid {
+                        code: "INVALID_FACTOR",
+                        message: format!("Unsupported factor '{term}'"),
+                    });
+                }
+            }
+        })
+    }
+
+    /// `None` if the factor is unavailable, e.g. lacking enough data
+    async fn calc(&self, ticker: &Ticker, date: &NaiveDate) -> VfResult<Option<f64>> {
+        Ok(match self {
+            Factor::CashRatio => Some(calc_stock_cash_ratio(ticker, date).await?),
+            Factor::CirculatingMarketCap => {
+                Some(calc_stock_market_cap(ticker, date, true).await? / 1e8)
+            }
+            Factor::Close => get_ticker_price(ticker, date, false, &PriceType::Close).await?,
+            Factor::CurrentRatio => Some(calc_stock_current_ratio(ticker, date).await?),
+            Factor::DebtRatio => Some(calc_stock_debt_ratio(ticker, date).await?),
+            Factor::DividendYield(years) => {
+                Some(calc_stock_dividend_ratio_of_years(ticker, date, *years).await?)
+            }
+            Factor::FreeCashRatio(years) => {
+                Some(calc_stock_free_cash_ratio_of_years(ticker, date, *years).await?)
+            }
+            Factor::Indicator(field) => fetch_stock_indicators(ticker)
+                .await?
+                .get_latest_value::<f64>(date, STALE_DAYS_SHORT, false, &field.to_string())
+                .map(|(_, v)| v),
+            Factor::MarketCap => Some(calc_stock_market_cap(ticker, date, false).await? / 1e8),
+            Factor::Momentum(days) => latest_prices(ticker, date, *days)
+                .await?
+                .and_then(|prices| calc_annualized_momentum(&prices, false)),
+            Factor::Pb => Some(calc_stock_pb(ticker, date).await?),
+            Factor::PeTtm => Some(calc_stock_pe_ttm(ticker, date).await?),
+            Factor::PsTtm => Some(calc_stock_ps_ttm(ticker, date).await?),
+            Factor::Return(days) => latest_prices(ticker, date, *days)
+                .await?
+                .and_then(|prices| calc_annualized_return_rate(&prices)),
+            Factor::Roe(years) => calc_stock_roe_of_years(ticker, date, *years).await?,
+            Factor::Volatility(days) => latest_prices(ticker, date, *days)
+                .await?
+                .and_then(|prices| calc_annualized_volatility_std(&prices)),
+        })
+    }
+}
+
+/// Values of the terms for each ticker in order, with failures warned once for each term, and
+/// progress notified from the start to the end in percent
+async fn calc_terms_values(
+    rule_name: &str,
+    defs: &[(Term, Factor)],
+    tickers: &[Ticker],
+    (progress_start, progress_end): (f64, f64),
+    date: &NaiveDate,
+    event_sender: &Sender<BacktestEvent>,
+) -> HashMap<String, Vec<Option<f64>>> {
+    let mut terms_values: HashMap<String, Vec<Option<f64>>> = HashMap::new();
+    // Tickers failed of each term, with errors if not just unavailable
+    let mut terms_failures: Vec<Vec<(&Ticker, Option<String>)>> = vec![vec![]; defs.len()];
+
+    let mut last_time = Instant::now();
+    let mut calc_count: usize = 0;
+    for ticker in tickers {
+        calc_count += 1;
+
+        for ((term, factor), failures) in defs.iter().zip(terms_failures.iter_mut()) {
+            let value = match factor.calc(ticker, date).await {
+                Ok(Some(value)) => Some(value),
+                Ok(None) => {
+                    failures.push((ticker, None));
+                    None
+                }
+                Err(err) => {
+                    failures.push((ticker, Some(err.to_string())));
+                    None
+                }
+            };
+
+            terms_values
+                .entry(term.to_string())
+                .or_default()
+                .push(value);
+        }
+
+        if last_time.elapsed().as_secs() > PROGRESS_INTERVAL_SECS {
+            rule_notify_calc_progress(
+                rule_name,
+                progress_start
+                    + (progress_end - progress_start) * calc_count as f64 / tickers.len() as f64,
+                date,
+                event_sender,
+            )
+            .await;
+
+            last_time = Instant::now();
+        }
+    }
+
+    for ((term, _), failures) in defs.iter().zip(terms_failures) {
+        if !failures.is_empty() {
+            let samples: Vec<String> = failures
+                .iter()
+                .take(FAILURE_SAMPLES)
+                .map(|(ticker, err)| match err {
+                    Some(err) => format!("{ticker}: {err}"),
+                    None => ticker.to_string(),
+                })
+                .collect();
+            rule_send_warning(
+                rule_name,
+                &format!(
+                    "[Σ '{term}' Unavailable] {}/{} tickers, e.g. {}",
+                    failures.len(),
+                    tickers.len(),
+                    samples.join("; ")
+                ),
+                date,
+                event_sender,
+            )
+            .await;
+        }
+    }
+
+    rule_notify_calc_progress(rule_name, progress_end, date, event_sender).await;
+
+    terms_values
+}
+
+async fn latest_prices(ticker: &Ticker, date: &NaiveDate, days: u32) -> VfResult<Option<Vec<f64>>> {
+    let kline = get_ticker_kline(ticker, false).await?;
+    let prices: Vec<f64> = kline
+        .get_latest_values::<f64>(date, false, &KlineField::Close.to_string(), days)
+        .iter()
+        .map(|&(_, v)| v)
+        .collect();
+
+    if prices.len() < (days as f64 * REQUIRED_DATA_COMPLETENESS).round() as usize {
+        Ok(None)
+    } else {
+        Ok(Some(prices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backtest::{BacktestOptions, BacktestResult, fee::FeeSchedule, fund::backtest_fund},
+        financial::fx::Currency,
+        spec::FundDefinition,
+    };
+
+    async fn backtest_held_tickers(factor: &str) -> Vec<Ticker> {
+        let fund_definition: FundDefinition = serde_json::from_value(serde_json::json!({
+            "title": "Expression",
+            "tickers": ["600595", "002155"],
+            "rules": [{
+                "name": "hold_by_expression",
+                "frequency": "1m",
+                "options": {
+                    "factor": factor,
+                    "filters": ["close > 0"],
+                    "limit": 1,
+                },
+            }],
+        }))
+        .unwrap();
+
+        let options = BacktestOptions {
+            init_cash: 1_000_000.0,
+            start_date: NaiveDate::from_ymd_opt(2019, 2, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2019, 2, 28).unwrap(),
+            pessimistic: false,
+            buffer_ratio: 0.0,
+            position_tolerance: 0.0,
+            risk_free_rate: 0.02,
+            risk_free_series: None,
+            fees: FeeSchedule::default(),
+            base_currency: Currency::Cny,
+        };
+
+        let mut stream = backtest_fund(&fund_definition, &options).await.unwrap();
+
+        let mut result: Option<BacktestResult> = None;
+        while let Some(event) = stream.next().await {
+            match event {
+                BacktestEvent::Result(r) => result = Some(*r),
+                BacktestEvent::Error(err) => panic!("{err}"),
+                _ => {}
+            }
+        }
+
+        result.unwrap().final_positions_value.into_keys().collect()
+    }
+
+    #[tokio::test]
+    async fn test_hold_by_expression() {
+        let date = NaiveDate::from_ymd_opt(2019, 2, 1).unwrap();
+        let mut tickers_prices: Vec<(Ticker, f64)> = vec![];
+        for ticker_str in ["600595", "002155"] {
+            let ticker = Ticker::from_str(ticker_str).unwrap();
+            let price = get_ticker_price(&ticker, &date, false, &PriceType::Close)
+                .await
+                .unwrap()
+                .unwrap();
+            tickers_prices.push((ticker, price));
+        }
+        tickers_prices.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        assert_eq!(
+            backtest_held_tickers("close").await,
+            vec![tickers_prices[0].0.clone()]
+        );
+        assert_eq!(
+            backtest_held_tickers("-close").await,
+            vec![tickers_prices[1].0.clone()]
+        );
+    }
+}
@@ -73,7 +73,7 @@ impl RuleExecutor for Executor {
 
         let predict_trade_days = (self.frequency_days as f64 * TRADE_DAYS_FRACTION).round() as u32;
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut indicators: Vec<(Ticker, f64)> = vec![];
             {
@@ -290,7 +290,9 @@ async fn calc_factors(ticker: &Ticker, end_date: &NaiveDate) -> VfResult<Vec<(St
 
     let kline = fetch_stock_kline(ticker, StockDividendAdjust::Backward).await?;
     let indicators = fetch_stock_indicators(ticker).await?;
-    let report_pershare = fetch_stock_report_pershare(ticker).await?;
+    let report_pershare = fetch_stock_report_pershare(ticker)
+        .await?
+        .as_of(&StockReportPershareField::ReportDate.to_string(), end_date)?;
 
     // Momentum
     {
@@ -68,7 +68,7 @@ impl RuleExecutor for Executor {
 
         let predict_trade_days = (self.frequency_days as f64 * TRADE_DAYS_FRACTION).round() as u32;
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut indicators: Vec<(Ticker, f64)> = vec![];
             {
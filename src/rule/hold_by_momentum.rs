@@ -55,7 +55,7 @@ impl RuleExecutor for Executor {
         let regression_r2_adjust = self.options.read_bool("regression_r2_adjust", false);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut tickers_factors: Vec<(Ticker, Factors)> = vec![];
             {
@@ -52,7 +52,7 @@ impl RuleExecutor for Executor {
         let lookback_trade_days = self.options.read_u64_no_zero("lookback_trade_days", 21);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut indicators: Vec<(Ticker, f64)> = vec![];
 
@@ -52,12 +52,12 @@ impl RuleExecutor for Executor {
         let lookback_trade_days =
             ((lookback_periods * self.frequency.to_days()) as f64 * TRADE_DAYS_FRACTION) as u32;
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut tickers_arr: HashMap<Ticker, f64> = HashMap::new();
             {
                 for ticker in tickers_map.keys() {
-                    let kline = fetch_stock_kline(&ticker, StockDividendAdjust::Backward).await?;
+                    let kline = fetch_stock_kline(ticker, StockDividendAdjust::Backward).await?;
                     let prices: Vec<f64> = kline
                         .get_latest_values::<f64>(
                             date,
@@ -49,7 +49,7 @@ impl RuleExecutor for Executor {
             .options
             .read_f64_in_range("min_weight_scale", 0.25, 0.0..=1.0);
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut tickers_weight_and_inverse_vols: HashMap<Ticker, (f64, f64)> = HashMap::new();
 
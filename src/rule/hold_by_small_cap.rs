@@ -1,9 +1,11 @@
 use async_trait::async_trait;
 use chrono::NaiveDate;
+use futures::{StreamExt, stream};
 use tokio::{sync::mpsc::Sender, time::Instant};
 
 use crate::{
     PROGRESS_INTERVAL_SECS, STALE_DAYS_LONG,
+    ds::limiter::MAX_CONCURRENCY,
     error::VfResult,
     filter::{
         filter_invalid::has_invalid_price, filter_market_cap::is_circulating_ratio_low,
@@ -57,58 +59,33 @@ impl RuleExecutor for Executor {
         let skip_same_sector = self.options.read_bool("skip_same_sector", false);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let mut tickers_factors: Vec<(Ticker, Factors)> = vec![];
             {
                 let mut last_time = Instant::now();
                 let mut calc_count: usize = 0;
 
-                for ticker in tickers_map.keys() {
-                    calc_count += 1;
-
-                    if let Ok(st) = is_st(ticker, date, STALE_DAYS_LONG as u64).await {
-                        if st {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-
-                    if let Ok(invalid_price) = has_invalid_price(ticker, date).await {
-                        if invalid_price {
-                            rule_send_warning(
-                                rule_name,
-                                &format!("[Invalid Price] {ticker}"),
-                                date,
-                                event_sender,
-                            )
-                            .await;
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
+                // Tickers are calculated concurrently, as it's bound by fetching their data
+                let mut factors_stream = stream::iter(tickers_map.keys().cloned())
+                    .map(|ticker| async move {
+                        let factors = calc_factors(
+                            &ticker,
+                            date,
+                            circulating_ratio_lower,
+                            rule_name,
+                            event_sender,
+                        )
+                        .await;
+                        (ticker, factors)
+                    })
+                    .buffer_unordered(MAX_CONCURRENCY);
 
-                    if let Ok(circulating_ratio_low) =
-                        is_circulating_ratio_low(ticker, date, circulating_ratio_lower).await
-                    {
-                        if circulating_ratio_low {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
+                while let Some((ticker, factors)) = factors_stream.next().await {
+                    calc_count += 1;
 
-                    if let Ok(market_cap) = calc_stock_market_cap(ticker, date, true).await {
-                        tickers_factors.push((
-                            ticker.clone(),
-                            Factors {
-                                market_cap,
-                                pb: calc_stock_pb(ticker, date).await.ok(),
-                                ps_ttm: calc_stock_ps_ttm(ticker, date).await.ok(),
-                            },
-                        ));
+                    if let Some(factors) = factors {
+                        tickers_factors.push((ticker, factors));
                     }
 
                     if last_time.elapsed().as_secs() > PROGRESS_INTERVAL_SECS {
@@ -210,3 +187,42 @@ struct Factors {
     pb: Option<f64>,
     ps_ttm: Option<f64>,
 }
+
+/// Factors of the stock, none if it's excluded, e.g. ST
+async fn calc_factors(
+    ticker: &Ticker,
+    date: &NaiveDate,
+    circulating_ratio_lower: f64,
+    rule_name: &str,
+    event_sender: &Sender<BacktestEvent>,
+) -> Option<Factors> {
+    if is_st(ticker, date, STALE_DAYS_LONG as u64).await.ok()? {
+        return None;
+    }
+
+    if has_invalid_price(ticker, date).await.ok()? {
+        rule_send_warning(
+            rule_name,
+            &format!("[Invalid Price] {ticker}"),
+            date,
+            event_sender,
+        )
+        .await;
+        return None;
+    }
+
+    if is_circulating_ratio_low(ticker, date, circulating_ratio_lower)
+        .await
+        .ok()?
+    {
+        return None;
+    }
+
+    let market_cap = calc_stock_market_cap(ticker, date, true).await.ok()?;
+
+    Some(Factors {
+        market_cap,
+        pb: calc_stock_pb(ticker, date).await.ok(),
+        ps_ttm: calc_stock_ps_ttm(ticker, date).await.ok(),
+    })
+}
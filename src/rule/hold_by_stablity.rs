@@ -61,7 +61,7 @@ impl RuleExecutor for Executor {
         let skip_same_sector = self.options.read_bool("skip_same_sector", false);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let date_str = date_to_str(date);
 
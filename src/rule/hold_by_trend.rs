@@ -61,7 +61,7 @@ impl RuleExecutor for Executor {
         let regression_alpha = self.options.read_f64_gte("regression_alpha", 1.0, 0.0);
         let weight_method = self.options.read_str("weight_method", "equal");
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
         if !tickers_map.is_empty() {
             let date_str = date_to_str(date);
 
@@ -88,7 +88,7 @@ impl RuleExecutor for Executor {
                 }
             }
         } else {
-            let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+            let tickers_map = context.all_tickers_map(date).await?;
             if !tickers_map.is_empty() {
                 let targets_weight: Vec<(Ticker, f64)> = tickers_map
                     .iter()
@@ -9,12 +9,13 @@ use crate::{
     PROGRESS_INTERVAL_SECS,
     error::VfResult,
     financial::{
+        economy::get_equity_risk_premium,
         get_ticker_title,
-        index::{IndexIndicatorField, fetch_index_indicators},
+        index::{IndexIndicatorField, fetch_index_valuation},
     },
     rule::{
         BacktestEvent, FundBacktestContext, RuleDefinition, RuleExecutor,
-        rule_notify_calc_progress, rule_send_info,
+        rule_notify_calc_progress, rule_send_info, rule_send_warning,
     },
     spec::{RuleOptions, TickerSourceType},
     ticker::{Ticker, TickersIndex},
@@ -45,6 +46,8 @@ impl RuleExecutor for Executor {
         let rule_name = mod_name!();
 
         let allow_short = self.options.read_bool("allow_short", false);
+        // Buy signals also require the equity risk premium in percent to be at least it if set
+        let erp_lower = self.options.read_f64("erp_lower", f64::NEG_INFINITY);
         let lookback_years = self.options.read_u64_no_zero("lookback_years", 5);
         let pb_quantile_lower = self
             .options
@@ -87,7 +90,7 @@ impl RuleExecutor for Executor {
             }
         }
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
 
         let watching_tickers = context.watching_tickers();
         if !watching_tickers.is_empty() {
@@ -124,9 +127,8 @@ impl RuleExecutor for Executor {
                     };
 
                 if let Some(watch_index) = watch_index {
-                    let index_indicators = fetch_index_indicators(&watch_index).await?;
                     let lookback_index_indicators =
-                        index_indicators.slice_by_date_range(&date_from, &date_to)?;
+                        fetch_index_valuation(&watch_index, &date_from, &date_to).await?;
 
                     let pb_values = lookback_index_indicators
                         .get_values::<f64>(&IndexIndicatorField::Pb.to_string())
@@ -210,18 +212,64 @@ impl RuleExecutor for Executor {
                             if *pb < pb_undervalued && *pe < pe_undervalued {
                                 let ticker_title = get_ticker_title(ticker).await;
 
-                                if *pb < pb_buy && *pe < pe_buy {
-                                    rule_send_info(
-                                        rule_name,
-                                        &format!("[Buy Signal] {ticker_title} PB:{pb:.2}<{pb_buy:.2} && PE:{pe:.2}<{pe_buy:.2}"),
-                                        date,
-                                        event_sender,
-                                    )
-                                    .await;
+                                let is_buy = *pb < pb_buy && *pe < pe_buy;
 
-                                    context
-                                        .position_entry_reserved(ticker, date, event_sender)
-                                        .await?;
+                                // Buys are skipped unless the ERP is known to be at least the
+                                // lower bound if set
+                                let is_erp_passed = if is_buy && erp_lower.is_finite() {
+                                    match get_equity_risk_premium(&watch_index, date).await {
+                                        Ok(Some(erp)) if erp >= erp_lower => true,
+                                        Ok(Some(erp)) => {
+                                            rule_send_info(
+                                                rule_name,
+                                                &format!(
+                                                    "[Low ERP] {ticker_title} ERP:{erp:.2}<{erp_lower:.2}"
+                                                ),
+                                                date,
+                                                event_sender,
+                                            )
+                                            .await;
+                                            false
+                                        }
+                                        Ok(None) => {
+                                            rule_send_warning(
+                                                rule_name,
+                                                &format!("[ERP Unavailable] {ticker_title}"),
+                                                date,
+                                                event_sender,
+                                            )
+                                            .await;
+                                            false
+                                        }
+                                        Err(err) => {
+                                            rule_send_warning(
+                                                rule_name,
+                                                &format!("[ERP Failed] {ticker_title}: {err}"),
+                                                date,
+                                                event_sender,
+                                            )
+                                            .await;
+                                            false
+                                        }
+                                    }
+                                } else {
+                                    true
+                                };
+
+                                if is_buy {
+                                    if is_erp_passed {
+                                        rule_send_info(
+                                            rule_name,
+                                            &format!("[Buy Signal] {ticker_title} PB:{pb:.2}<{pb_buy:.2} && PE:{pe:.2}<{pe_buy:.2}"),
+                                            date,
+                                            event_sender,
+                                        )
+                                        .await;
+
+                                        context
+                                            .position_entry_reserved(ticker, date, event_sender)
+                                            .await?;
+                                    }
                                 } else {
                                     rule_send_info(
                                         rule_name,
@@ -97,7 +97,7 @@ impl RuleExecutor for Executor {
             }
         }
 
-        let tickers_map = context.fund_definition.all_tickers_map(date).await?;
+        let tickers_map = context.all_tickers_map(date).await?;
 
         let watching_tickers = context.watching_tickers();
         if !watching_tickers.is_empty() {
@@ -304,7 +304,10 @@ impl Executor {
                 calc_count += 1;
 
                 let kline = fetch_stock_kline(ticker, StockDividendAdjust::No).await?;
-                let report_capital = fetch_stock_report_capital(ticker).await?;
+                let report_capital = fetch_stock_report_capital(ticker).await?.as_of(
+                    &StockReportCapitalField::ReportDate.to_string(),
+                    &watch_date,
+                )?;
 
                 if let (Some((_, price)), Some((_, total_captical))) = (
                     kline.get_latest_value::<f64>(
@@ -0,0 +1,450 @@
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rhai::{
+    AST, Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope,
+    module_resolvers::DummyModuleResolver,
+    packages::{
+        BasicArrayPackage, BasicMapPackage, BasicMathPackage, CorePackage, LogicPackage,
+        MoreStringPackage, Package,
+    },
+};
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    sync::mpsc::Sender,
+};
+
+use crate::{
+    WORKSPACE,
+    error::{VfError, VfResult},
+    financial::{
+        KlineField, PriceType,
+        economy::{
+            InterestRate, MacroIndicator, get_equity_risk_premium, get_interest_rate,
+            get_macro_indicator,
+        },
+        get_ticker_kline, get_ticker_price, get_ticker_title,
+    },
+    rule::{
+        BacktestEvent, FundBacktestContext, RuleDefinition, RuleExecutor, rule_send_info,
+        rule_send_toast, rule_send_warning,
+    },
+    spec::RuleOptions,
+    ticker::{Ticker, TickersIndex},
+    utils::datetime::date_to_str,
+};
+
+/// Entry function the script must define, called as `exec(ctx)` on every rule date
+const SCRIPT_ENTRY_FN: &str = "exec";
+
+pub struct Executor {
+    #[allow(dead_code)]
+    options: RuleOptions,
+    engine: Engine,
+    /// Rule date shared with the functions registered to the engine
+    date: Arc<Mutex<NaiveDate>>,
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    ast: Option<AST>,
+    state: Dynamic,
+}
+
+impl Executor {
+    pub fn new(definition: &RuleDefinition) -> Self {
+        let max_operations = definition
+            .options
+            .read_u64_no_zero("max_operations", 100_000_000);
+        let date: Arc<Mutex<NaiveDate>> = Arc::new(Mutex::new(NaiveDate::default()));
+        let actions: Arc<Mutex<Vec<ScriptAction>>> = Arc::new(Mutex::new(vec![]));
+
+        Self {
+            options: definition.options.clone(),
+            engine: build_engine(max_operations, date.clone(), actions.clone()),
+            date,
+            actions,
+            ast: None,
+            state: Dynamic::from_map(Map::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl RuleExecutor for Executor {
+    async fn exec(
+        &mut self,
+        context: &mut FundBacktestContext,
+        date: &NaiveDate,
+        event_sender: &Sender<BacktestEvent>,
+    ) -> VfResult<()> {
+        let rule_name = mod_name!();
+
+        let path = self.options.read_str("path", "");
+
+        // Data queries of the script block in place, which is only allowed on a multi-thread runtime
+        if Handle::current().runtime_flavor() != RuntimeFlavor::MultiThread {
+            return Err(VfError::ScriptError(
+                "Script rule requires a multi-thread runtime".to_string(),
+            ));
+        }
+
+        if self.ast.is_none() {
+            let script_path = {
+                let path = PathBuf::from(path);
+                if path.is_absolute() {
+                    path
+                } else {
+                    WORKSPACE.read().await.join(path)
+                }
+            };
+            if !script_path.is_file() {
+                return Err(VfError::NotExists {
+                    code: "SCRIPT_NOT_EXISTS",
+                    message: format!("Script '{}' not exists", script_path.to_string_lossy()),
+                });
+            }
+
+            let script = std::fs::read_to_string(&script_path)?;
+            let ast = self
+                .engine
+                .compile(&script)
+                .map_err(|err| VfError::ScriptError(err.to_string()))?;
+            self.ast = Some(ast);
+        }
+
+        let ctx = {
+            let mut ctx = Map::new();
+            ctx.insert("date".into(), date_to_str(date).into());
+            ctx.insert("free_cash".into(), context.portfolio.free_cash.into());
+            ctx.insert(
+                "total_value".into(),
+                context
+                    .calc_total_value(date, &PriceType::Close)
+                    .await?
+                    .into(),
+            );
+
+            let mut positions = Map::new();
+            for (ticker, units) in &context.portfolio.positions {
+                positions.insert(ticker.to_string().into(), (*units as i64).into());
+            }
+            ctx.insert("positions".into(), positions.into());
+
+            let mut reserved_cash = Map::new();
+            for (ticker, (cash, _)) in &context.portfolio.reserved_cash {
+                reserved_cash.insert(ticker.to_string().into(), (*cash).into());
+            }
+            ctx.insert("reserved_cash".into(), reserved_cash.into());
+
+            let mut tickers = Map::new();
+            for (ticker, (weight, _)) in context.all_tickers_map(date).await? {
+                tickers.insert(ticker.to_string().into(), weight.into());
+            }
+            ctx.insert("tickers".into(), tickers.into());
+
+            let options = rhai::serde::to_dynamic(&self.options)
+                .map_err(|err| VfError::ScriptError(err.to_string()))?;
+            ctx.insert("options".into(), options);
+
+            ctx
+        };
+
+        *self
+            .date
+            .lock()
+            .map_err(|err| VfError::LockError(err.to_string()))? = *date;
+
+        if let Some(ast) = &self.ast {
+            let engine = &self.engine;
+            let state = &mut self.state;
+
+            // Data queries of the script are resolved synchronously on the current runtime
+            let _ = tokio::task::block_in_place(|| {
+                engine.call_fn_with_options::<Dynamic>(
+                    CallFnOptions::new().bind_this_ptr(state),
+                    &mut Scope::new(),
+                    ast,
+                    SCRIPT_ENTRY_FN,
+                    (ctx,),
+                )
+            })
+            .map_err(|err| VfError::ScriptError(err.to_string()))?;
+        }
+
+        let actions: Vec<ScriptAction> = self
+            .actions
+            .lock()
+            .map_err(|err| VfError::LockError(err.to_string()))?
+            .drain(..)
+            .collect();
+        for action in actions {
+            match action {
+                ScriptAction::Info(message) => {
+                    rule_send_info(rule_name, &message, date, event_sender).await;
+                }
+                ScriptAction::PositionClose(ticker) => {
+                    context
+                        .position_close(&ticker, false, date, event_sender)
+                        .await?;
+                }
+                ScriptAction::Rebalance(weights) => {
+                    context.rebalance(&weights, date, event_sender).await?;
+                }
+                ScriptAction::Toast(message) => {
+                    rule_send_toast(rule_name, &message, date, event_sender).await;
+                }
+                ScriptAction::Warning(message) => {
+                    rule_send_warning(rule_name, &message, date, event_sender).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Side effects requested by the script, applied in order after it returns
+enum ScriptAction {
+    Info(String),
+    PositionClose(Ticker),
+    Rebalance(Vec<(Ticker, f64)>),
+    Toast(String),
+    Warning(String),
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Build a sandboxed engine: no modules, no time or random functions, and bounded resources,
+/// data queries are as of the shared rule date
+fn build_engine(
+    max_operations: u64,
+    date: Arc<Mutex<NaiveDate>>,
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+) -> Engine {
+    let mut engine = Engine::new_raw();
+    engine.register_global_module(CorePackage::new().as_shared_module());
+    engine.register_global_module(LogicPackage::new().as_shared_module());
+    engine.register_global_module(BasicMathPackage::new().as_shared_module());
+    engine.register_global_module(BasicArrayPackage::new().as_shared_module());
+    engine.register_global_module(BasicMapPackage::new().as_shared_module());
+    engine.register_global_module(MoreStringPackage::new().as_shared_module());
+
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(1 << 20);
+    engine.set_max_map_size(1 << 20);
+
+    {
+        let date = date.clone();
+        engine.register_fn("price", move |ticker: &str| -> ScriptResult<Dynamic> {
+            let date = current_date(&date)?;
+            let ticker = parse_ticker(ticker)?;
+            let price = Handle::current()
+                .block_on(get_ticker_price(&ticker, &date, false, &PriceType::Close))
+                .map_err(|err| err.to_string())?;
+
+            Ok(price.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT))
+        });
+    }
+
+    {
+        let date = date.clone();
+        engine.register_fn(
+            "kline",
+            move |ticker: &str, field: &str, count: i64| -> ScriptResult<Array> {
+                let date = current_date(&date)?;
+                let ticker = parse_ticker(ticker)?;
+                let field = KlineField::from_str(field).map_err(|err| err.to_string())?;
+                let kline = Handle::current()
+                    .block_on(get_ticker_kline(&ticker, false))
+                    .map_err(|err| err.to_string())?;
+
+                Ok(kline
+                    .get_latest_values::<f64>(&date, false, &field.to_string(), count.max(0) as u32)
+                    .into_iter()
+                    .map(|(_, v)| Dynamic::from_float(v))
+                    .collect())
+            },
+        );
+    }
+
+    // Rates and macro indicators are in percent, `()` if unavailable
+    {
+        let date = date.clone();
+        engine.register_fn("rate", move |rate: &str| -> ScriptResult<Dynamic> {
+            let date = current_date(&date)?;
+            let rate = InterestRate::from_str(rate).map_err(|err| err.to_string())?;
+            let value = Handle::current()
+                .block_on(get_interest_rate(&rate, &date))
+                .map_err(|err| err.to_string())?;
+
+            Ok(value.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT))
+        });
+    }
+
+    {
+        let date = date.clone();
+        engine.register_fn(
+            "macro_indicator",
+            move |indicator: &str| -> ScriptResult<Dynamic> {
+                let date = current_date(&date)?;
+                let indicator =
+                    MacroIndicator::from_str(indicator).map_err(|err| err.to_string())?;
+                let value = Handle::current()
+                    .block_on(get_macro_indicator(&indicator, &date))
+                    .map_err(|err| err.to_string())?;
+
+                Ok(value.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT))
+            },
+        );
+    }
+
+    engine.register_fn("erp", move |index: &str| -> ScriptResult<Dynamic> {
+        let date = current_date(&date)?;
+        let index = TickersIndex::from_str(index).map_err(|err| err.to_string())?;
+        let value = Handle::current()
+            .block_on(get_equity_risk_premium(&index, &date))
+            .map_err(|err| err.to_string())?;
+
+        Ok(value.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT))
+    });
+
+    engine.register_fn("title", move |ticker: &str| -> ScriptResult<String> {
+        let ticker = parse_ticker(ticker)?;
+        Ok(Handle::current().block_on(get_ticker_title(&ticker)))
+    });
+
+    {
+        let actions = actions.clone();
+        engine.register_fn("rebalance", move |weights: Map| -> ScriptResult<()> {
+            let mut targets_weight: Vec<(Ticker, f64)> = vec![];
+            for (ticker, weight) in weights {
+                let ticker = parse_ticker(&ticker)?;
+                let weight = weight
+                    .as_float()
+                    .or_else(|_| weight.as_int().map(|v| v as f64))
+                    .map_err(|_| format!("Invalid weight of '{ticker}'"))?;
+                targets_weight.push((ticker, weight));
+            }
+
+            push_action(&actions, ScriptAction::Rebalance(targets_weight))
+        });
+    }
+
+    {
+        let actions = actions.clone();
+        engine.register_fn("position_close", move |ticker: &str| -> ScriptResult<()> {
+            let ticker = parse_ticker(ticker)?;
+            push_action(&actions, ScriptAction::PositionClose(ticker))
+        });
+    }
+
+    {
+        let actions = actions.clone();
+        engine.register_fn("info", move |message: &str| -> ScriptResult<()> {
+            push_action(&actions, ScriptAction::Info(message.to_string()))
+        });
+    }
+
+    {
+        let actions = actions.clone();
+        engine.register_fn("toast", move |message: &str| -> ScriptResult<()> {
+            push_action(&actions, ScriptAction::Toast(message.to_string()))
+        });
+    }
+
+    engine.register_fn("warning", move |message: &str| -> ScriptResult<()> {
+        push_action(&actions, ScriptAction::Warning(message.to_string()))
+    });
+
+    engine
+}
+
+fn current_date(date: &Mutex<NaiveDate>) -> ScriptResult<NaiveDate> {
+    Ok(*date.lock().map_err(|err| err.to_string())?)
+}
+
+fn parse_ticker(ticker: &str) -> ScriptResult<Ticker> {
+    Ticker::from_str(ticker).map_err(|err| err.to_string().into())
+}
+
+fn push_action(actions: &Mutex<Vec<ScriptAction>>, action: ScriptAction) -> ScriptResult<()> {
+    actions.lock().map_err(|err| err.to_string())?.push(action);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_script_sandbox() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let actions: Arc<Mutex<Vec<ScriptAction>>> = Arc::new(Mutex::new(vec![]));
+        let engine = build_engine(10_000, Arc::new(Mutex::new(date)), actions.clone());
+
+        let ast = engine
+            .compile(
+                r#"
+                fn exec(ctx) {
+                    this.count = if "count" in this { this.count + 1 } else { 1 };
+                    info(`${ctx.date} #${this.count}`);
+                    rebalance(#{ "600000.SH": 1, "000001.SZ": 0.5 });
+                }
+                "#,
+            )
+            .unwrap();
+
+        let mut state = Dynamic::from_map(Map::new());
+        for _ in 0..2 {
+            let mut ctx = Map::new();
+            ctx.insert("date".into(), date_to_str(&date).into());
+            let _ = engine
+                .call_fn_with_options::<Dynamic>(
+                    CallFnOptions::new().bind_this_ptr(&mut state),
+                    &mut Scope::new(),
+                    &ast,
+                    SCRIPT_ENTRY_FN,
+                    (ctx,),
+                )
+                .unwrap();
+        }
+
+        let actions = actions.lock().unwrap();
+        assert_eq!(actions.len(), 4);
+        assert!(matches!(&actions[2], ScriptAction::Info(message) if message == "2025-01-02 #2"));
+        assert!(matches!(&actions[3], ScriptAction::Rebalance(weights) if weights.len() == 2));
+
+        assert!(engine.run(r#"import "fs" as fs;"#).is_err());
+        assert!(engine.run("timestamp()").is_err());
+        assert!(engine.compile_file("example/rotation.rhai".into()).is_ok());
+        assert!(engine.run("loop {}").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_script_economy() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let engine = build_engine(
+            10_000,
+            Arc::new(Mutex::new(date)),
+            Arc::new(Mutex::new(vec![])),
+        );
+
+        tokio::task::block_in_place(|| {
+            assert_eq!(
+                engine
+                    .eval::<f64>(r#"rate("lpr1y") + macro_indicator("cpi_yoy")"#)
+                    .unwrap(),
+                3.1 + 0.2
+            );
+            assert!(engine.eval::<f64>(r#"rate("unknown")"#).is_err());
+        });
+    }
+}
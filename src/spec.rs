@@ -1,5 +1,10 @@
 use std::{
-    collections::HashMap, num::ParseIntError, ops::RangeInclusive, panic, path::Path, str::FromStr,
+    collections::{HashMap, HashSet},
+    num::ParseIntError,
+    ops::RangeInclusive,
+    panic,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use chrono::NaiveDate;
@@ -7,10 +12,19 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 use crate::{
-    DAYS_PER_YEAR,
+    DAYS_PER_YEAR, WORKSPACE,
     error::VfResult,
-    financial::{index::fetch_index_tickers, sector::fetch_sector_tickers},
+    filter::{check_filter, is_filtered_out, membership_fallback_date},
+    financial::{
+        bond::fetch_conv_bonds_basic,
+        fund::fetch_etfs,
+        index::fetch_index_membership,
+        search::resolve_ticker,
+        sector::fetch_sector_membership,
+        stock::{fetch_hk_stocks_basic, fetch_stocks_basic},
+    },
     ticker::{Ticker, TickersIndex},
+    utils::datetime::date_to_str,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -25,6 +39,9 @@ pub struct FofDefinition {
     #[serde(default)]
     pub funds: HashMap<String, f64>,
 
+    #[serde(default)]
+    pub rules: Vec<FofRuleDefinition>,
+
     #[serde(default)]
     pub search: FofSearch,
 }
@@ -35,6 +52,14 @@ impl FofDefinition {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FofRuleDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub options: RuleOptions,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct FofSearch {
     #[serde(default)]
@@ -55,6 +80,9 @@ pub struct FundDefinition {
     #[serde(default)]
     pub tickers: TickersDefinition,
 
+    #[serde(default)]
+    pub filters: Vec<FilterDefinition>,
+
     #[serde(default)]
     pub ticker_sources: Vec<TickerSourceDefinition>,
 
@@ -64,26 +92,118 @@ pub struct FundDefinition {
 
 impl FundDefinition {
     pub fn from_file(path: &Path) -> VfResult<Self> {
-        confy::load_path(path).map_err(Into::into)
+        let fund_definition: Self = confy::load_path(path)?;
+        for filter in &fund_definition.filters {
+            check_filter(filter)?;
+        }
+
+        Ok(fund_definition)
+    }
+
+    /// Exchanges in MIC of the tickers listed and those implied by the ticker sources, or `XSHG`
+    /// if none
+    pub async fn exchanges(&self) -> VfResult<HashSet<String>> {
+        let ticker_strs: Vec<&String> = match &self.tickers {
+            TickersDefinition::Array(array) => array.iter().collect(),
+            TickersDefinition::Map(map) => map.keys().collect(),
+        };
+
+        let mut exchanges: HashSet<String> = HashSet::new();
+        for ticker_str in ticker_strs {
+            let ticker = resolve_ticker(ticker_str).await?;
+            exchanges.insert(ticker.exchange_mic().to_string());
+        }
+        for ticker_source in &self.ticker_sources {
+            exchanges.extend(ticker_source.exchanges().await?);
+        }
+        if exchanges.is_empty() {
+            exchanges.insert("XSHG".to_string());
+        }
+
+        Ok(exchanges)
     }
 
     pub async fn all_tickers_map(
         &self,
         date: &NaiveDate,
     ) -> VfResult<HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>> {
+        let (all_tickers_map, _) = self.all_tickers_map_with_filters_report(date).await?;
+        Ok(all_tickers_map)
+    }
+
+    /// Also returns the universe size before filters, how many tickers each filter removed and the
+    /// filters failed on tickers
+    pub async fn all_tickers_map_with_filters_report(
+        &self,
+        date: &NaiveDate,
+    ) -> VfResult<(
+        HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>,
+        FiltersReport,
+    )> {
+        let (mut all_tickers_map, membership_fallbacks) = self.universe_tickers_map(date).await?;
+
+        let mut report = FiltersReport {
+            universe_count: all_tickers_map.len(),
+            removed_counts: vec![],
+            membership_fallbacks,
+            failures: vec![],
+        };
+        for filter in &self.filters {
+            if let Some(fallback_date) = membership_fallback_date(filter, date).await {
+                report.membership_fallbacks.push(format!(
+                    "filter '{}' (current as of {})",
+                    filter.name,
+                    date_to_str(&fallback_date)
+                ));
+            }
+
+            let mut removed_tickers: Vec<Ticker> = vec![];
+            for ticker in all_tickers_map.keys() {
+                match is_filtered_out(filter, ticker, date).await {
+                    Ok(true) => removed_tickers.push(ticker.clone()),
+                    Ok(false) => {}
+                    Err(err) => {
+                        report
+                            .failures
+                            .push(format!("filter '{}' on {ticker}: {err}", filter.name));
+                        removed_tickers.push(ticker.clone());
+                    }
+                }
+            }
+
+            for ticker in &removed_tickers {
+                all_tickers_map.remove(ticker);
+            }
+            report
+                .removed_counts
+                .push((filter.name.clone(), removed_tickers.len()));
+        }
+
+        Ok((all_tickers_map, report))
+    }
+
+    /// Also returns ticker sources resolved to the current membership
+    async fn universe_tickers_map(
+        &self,
+        date: &NaiveDate,
+    ) -> VfResult<(
+        HashMap<Ticker, (f64, Option<TickerSourceDefinition>)>,
+        Vec<String>,
+    )> {
         let mut all_tickers_map = HashMap::new();
+        let mut membership_fallbacks: Vec<String> = vec![];
 
         match &self.tickers {
             TickersDefinition::Array(array) => {
                 for ticker_str in array {
-                    let ticker = Ticker::from_str(ticker_str)?;
+                    let ticker = resolve_ticker(ticker_str).await?;
                     all_tickers_map.insert(ticker, (1.0, None));
                 }
             }
             TickersDefinition::Map(map) => {
                 for (ticker_str, weight) in map {
                     if *weight > 0.0 {
-                        let ticker = Ticker::from_str(ticker_str)?;
+                        let ticker = resolve_ticker(ticker_str).await?;
                         all_tickers_map.insert(ticker, (*weight, None));
                     }
                 }
@@ -91,27 +211,55 @@ impl FundDefinition {
         };
 
         for ticker_source in &self.ticker_sources {
-            match ticker_source.source_type {
-                TickerSourceType::Index => {
-                    let index = TickersIndex::from_str(&ticker_source.source)?;
-                    let index_tickers = fetch_index_tickers(&index, date).await?;
-                    for ticker in index_tickers {
+            let (source_tickers, fallback_date) = ticker_source.tickers(date).await?;
+            if let Some(fallback_date) = fallback_date {
+                membership_fallbacks.push(format!(
+                    "{:?} source '{}' (current as of {})",
+                    ticker_source.source_type,
+                    ticker_source.source,
+                    date_to_str(&fallback_date)
+                ));
+            }
+            match ticker_source.operation {
+                TickerSourceOperation::Union => {
+                    for ticker in source_tickers {
                         all_tickers_map.insert(ticker, (1.0, Some(ticker_source.clone())));
                     }
                 }
-                TickerSourceType::Sector => {
-                    let tickers_sector_map = fetch_sector_tickers(&ticker_source.source).await?;
-                    for ticker in tickers_sector_map.keys() {
-                        all_tickers_map.insert(ticker.clone(), (1.0, Some(ticker_source.clone())));
+                TickerSourceOperation::Intersection => {
+                    let source_tickers: HashSet<Ticker> = source_tickers.into_iter().collect();
+                    all_tickers_map.retain(|ticker, _| source_tickers.contains(ticker));
+                }
+                TickerSourceOperation::Difference => {
+                    for ticker in &source_tickers {
+                        all_tickers_map.remove(ticker);
                     }
                 }
             }
         }
 
-        Ok(all_tickers_map)
+        Ok((all_tickers_map, membership_fallbacks))
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FilterDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub options: RuleOptions,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FiltersReport {
+    pub universe_count: usize,
+    pub removed_counts: Vec<(String, usize)>,
+    /// Ticker sources and filters resolved to the current membership for lack of the historical one
+    pub membership_fallbacks: Vec<String>,
+    /// Filters failed on tickers, which are removed from the universe
+    pub failures: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Frequency {
     days: u64,
@@ -155,7 +303,13 @@ impl Frequency {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct FundOptions {
+    #[serde(default)]
     pub suspend_months: Vec<u32>,
+
+    /// Annual cost of holding indexes directly as proxies, e.g. `0.006` for fees and tracking
+    /// errors of funds tracking them, deducted from prices over time since they are bought
+    #[serde(default)]
+    pub index_tracking_cost: f64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -293,6 +447,7 @@ pub struct RuleSearch {
     pub options: HashMap<String, Vec<serde_json::Value>>,
 }
 
+/// Codes of tickers, or names, partial names or pinyin initials matching exactly one ticker
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum TickersDefinition {
@@ -308,15 +463,167 @@ impl Default for TickersDefinition {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TickerSourceDefinition {
+    #[serde(default)]
     pub source: String,
     pub source_type: TickerSourceType,
+
+    #[serde(default)]
+    pub operation: TickerSourceOperation,
+}
+
+impl TickerSourceDefinition {
+    /// Tickers as of the date, and the date of the current membership if they fall back to it
+    pub async fn tickers(&self, date: &NaiveDate) -> VfResult<(Vec<Ticker>, Option<NaiveDate>)> {
+        let tickers = match self.source_type {
+            TickerSourceType::ConvBonds => {
+                let conv_bonds = fetch_conv_bonds_basic().await?;
+                Ok(conv_bonds
+                    .into_iter()
+                    .filter(|b| {
+                        b.list_date.is_some_and(|d| d <= *date)
+                            && b.expire_date.is_none_or(|d| d > *date)
+                    })
+                    .map(|b| b.ticker)
+                    .collect())
+            }
+            TickerSourceType::Etfs => {
+                let etfs = fetch_etfs().await?;
+                Ok(etfs
+                    .into_iter()
+                    .filter(|etf| etf.is_listed(date) && etf.is_category(&self.source))
+                    .map(|etf| etf.ticker)
+                    .collect())
+            }
+            TickerSourceType::File => read_tickers_file(&self.file_path().await),
+            TickerSourceType::Index => {
+                let index = TickersIndex::from_str(&self.source)?;
+                let membership = fetch_index_membership(&index, date).await?;
+                return Ok((
+                    membership.members.into_keys().collect(),
+                    membership.is_fallback.then_some(membership.date),
+                ));
+            }
+            TickerSourceType::Sector => {
+                let membership = fetch_sector_membership(&self.source, date).await?;
+                return Ok((
+                    membership.members.into_keys().collect(),
+                    membership.is_fallback.then_some(membership.date),
+                ));
+            }
+            TickerSourceType::Stocks => {
+                // Optionally restricted to exchanges, e.g. "XSHG,XSHE", and HK stocks are
+                // included only if "XHKG" is listed
+                let exchanges: Vec<&str> = self
+                    .source
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+
+                let mut stocks = fetch_stocks_basic().await?;
+                if exchanges.contains(&"XHKG") {
+                    stocks.extend(fetch_hk_stocks_basic().await?);
+                }
+                Ok(stocks
+                    .into_iter()
+                    .filter(|s| {
+                        s.list_date.is_some_and(|d| d <= *date)
+                            && s.delist_date.is_none_or(|d| d > *date)
+                            && (exchanges.is_empty()
+                                || exchanges.contains(&s.ticker.exchange.as_str()))
+                    })
+                    .map(|s| s.ticker)
+                    .collect())
+            }
+        };
+
+        Ok((tickers?, None))
+    }
+
+    /// Exchanges in MIC the tickers are traded on, Southbound Connect lists are of HK stocks and
+    /// other sources are of A-shares unless restricted to exchanges or read from a file
+    pub async fn exchanges(&self) -> VfResult<Vec<String>> {
+        let exchanges = match self.source_type {
+            TickerSourceType::File => read_tickers_file(&self.file_path().await)?
+                .iter()
+                .map(|ticker| ticker.exchange_mic().to_string())
+                .collect(),
+            TickerSourceType::Index if TickersIndex::from_str(&self.source)?.provider == "HSGT" => {
+                vec!["XHKG".to_string()]
+            }
+            TickerSourceType::Stocks => self
+                .source
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => vec![],
+        };
+
+        if exchanges.is_empty() {
+            Ok(vec!["XSHG".to_string()])
+        } else {
+            Ok(exchanges)
+        }
+    }
+
+    /// Path of the tickers file, relative paths are of the workspace
+    async fn file_path(&self) -> PathBuf {
+        let path = PathBuf::from(&self.source);
+        if path.is_absolute() {
+            path
+        } else {
+            WORKSPACE.read().await.join(path)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TickerSourceOperation {
+    #[default]
+    Union,
+    Intersection,
+    Difference,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum TickerSourceType {
+    ConvBonds,
+    Etfs,
+    File,
     Index,
     Sector,
+    Stocks,
+}
+
+/// Read tickers from the first column of a CSV/TXT file, lines without digits (e.g. headers) are skipped
+fn read_tickers_file(path: &Path) -> VfResult<Vec<Ticker>> {
+    let content = std::fs::read_to_string(path)?;
+
+    let mut tickers = vec![];
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let ticker_str = line
+            .split([',', '\t', ' ', ';'])
+            .map(|s| s.trim().trim_matches('"'))
+            .find(|s| !s.is_empty())
+            .unwrap_or_default();
+
+        if !ticker_str.chars().any(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        let ticker = if let Some(ticker) = Ticker::from_tushare_str(ticker_str) {
+            ticker
+        } else {
+            Ticker::from_str(ticker_str)?
+        };
+        tickers.push(ticker);
+    }
+
+    Ok(tickers)
 }
 
 fn deserialize_frequency<'de, D>(deserializer: D) -> Result<Frequency, D::Error>
@@ -344,14 +651,143 @@ mod tests {
     use std::path::PathBuf;
 
     use super::*;
+    use crate::utils::datetime::date_from_str;
 
     #[tokio::test]
     async fn test_fof_definition() {
         assert!(FofDefinition::from_file(&PathBuf::from("example/afof.fof.toml")).is_ok());
+
+        let fof_definition =
+            FofDefinition::from_file(&PathBuf::from("example/strategies.fof.toml")).unwrap();
+        assert_eq!(fof_definition.rules.len(), 4);
+        assert_eq!(fof_definition.rules[0].options.read_u64("limit", 0), 2);
     }
 
     #[tokio::test]
     async fn test_fund_definition() {
         assert!(FundDefinition::from_file(&PathBuf::from("example/conv-bond.fund.toml")).is_ok());
+        assert!(
+            FundDefinition::from_file(&PathBuf::from("example/conv-bond-double-low.fund.toml"))
+                .is_ok()
+        );
+
+        let fund_definition =
+            FundDefinition::from_file(&PathBuf::from("example/expression.fund.toml")).unwrap();
+        assert_eq!(fund_definition.filters.len(), 5);
+        assert_eq!(
+            fund_definition.filters[1].options.read_u64("min_days", 0),
+            365
+        );
+
+        let fund_definition =
+            FundDefinition::from_file(&PathBuf::from("example/permanent-index.fund.toml")).unwrap();
+        assert_eq!(fund_definition.options.index_tracking_cost, 0.006);
+        assert!(fund_definition.options.suspend_months.is_empty());
+
+        let path = std::env::temp_dir().join("vfunds_test_invalid_filter.fund.toml");
+        std::fs::write(
+            &path,
+            "title = \"Invalid\"\n\n[[filters]]\nname = \"exclude_sT\"\n",
+        )
+        .unwrap();
+        assert!(FundDefinition::from_file(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_fund_exchanges() {
+        let fund_definition = |source: &str, source_type: TickerSourceType| FundDefinition {
+            tickers: TickersDefinition::Array(vec!["600000.SH".to_string()]),
+            ticker_sources: vec![TickerSourceDefinition {
+                source: source.to_string(),
+                source_type,
+                operation: TickerSourceOperation::Union,
+            }],
+            ..Default::default()
+        };
+
+        let exchanges = fund_definition("SH_HK.HSGT", TickerSourceType::Index)
+            .exchanges()
+            .await
+            .unwrap();
+        assert_eq!(
+            exchanges,
+            HashSet::from(["XSHG".to_string(), "XHKG".to_string()])
+        );
+
+        let exchanges = fund_definition("XSHE", TickerSourceType::Stocks)
+            .exchanges()
+            .await
+            .unwrap();
+        assert_eq!(
+            exchanges,
+            HashSet::from(["XSHG".to_string(), "XSHE".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_universe_membership() {
+        let fund_definition = |sector_prefix: &str| FundDefinition {
+            ticker_sources: vec![TickerSourceDefinition {
+                source: sector_prefix.to_string(),
+                source_type: TickerSourceType::Sector,
+                operation: TickerSourceOperation::Union,
+            }],
+            ..Default::default()
+        };
+
+        // No historical sectors, so falls back to the current ones
+        let date = date_from_str("2018-01-02").unwrap();
+        let (tickers_map, report) = fund_definition("SW1")
+            .all_tickers_map_with_filters_report(&date)
+            .await
+            .unwrap();
+        assert!(!tickers_map.is_empty());
+        assert_eq!(report.membership_fallbacks.len(), 1);
+
+        let path = std::env::temp_dir().join("vfunds_test_universe_membership.csv");
+        std::fs::write(
+            &path,
+            "date,ticker,sector\n2017-12-01,600383.SH,房地产\n2018-03-01,000001.SZ,银行\n",
+        )
+        .unwrap();
+        crate::api::import_sector_membership("TEST_UNIVERSE", &path)
+            .await
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let (tickers_map, report) = fund_definition("TEST_UNIVERSE")
+            .all_tickers_map_with_filters_report(&date)
+            .await
+            .unwrap();
+        assert_eq!(
+            tickers_map
+                .keys()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>(),
+            vec!["600383.XSHG"]
+        );
+        assert!(report.membership_fallbacks.is_empty());
+    }
+
+    #[test]
+    fn test_read_tickers_file() {
+        let path = std::env::temp_dir().join("vfunds_test_read_tickers_file.csv");
+        std::fs::write(
+            &path,
+            "ticker,name\n600000.SH,浦发银行\n000001 # 平安银行\n\n510300.XSHG\n",
+        )
+        .unwrap();
+
+        let tickers = read_tickers_file(&path).unwrap();
+        assert_eq!(
+            tickers.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+            vec!["600000.XSHG", "000001.XSHE", "510300.XSHG"]
+        );
+
+        std::fs::write(&path, "ticker\nabc1\n").unwrap();
+        assert!(read_tickers_file(&path).is_err());
+
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -2,7 +2,7 @@ use std::{fmt::Display, str::FromStr};
 
 use serde::Serialize;
 
-use crate::{error::VfError, utils::text::is_ascii_digits};
+use crate::{error::VfError, financial::fx::Currency, utils::text::is_ascii_digits};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
 pub struct Ticker {
@@ -21,14 +21,15 @@ impl FromStr for Ticker {
             exchange.map(|exchange| Self {
                 exchange: exchange.to_string(),
                 symbol: s.to_uppercase().to_string(),
-                r#type: detect_ticker_type(s),
+                r#type: detect_ticker_type(s, &exchange),
             })
         } else {
             if let Some((symbol, exchange)) = s.rsplit_once('.') {
+                let exchange = exchange.trim().to_uppercase();
                 Some(Self {
-                    exchange: exchange.trim().to_uppercase().to_string(),
                     symbol: symbol.trim().to_uppercase().to_string(),
-                    r#type: detect_ticker_type(symbol),
+                    r#type: detect_ticker_type(symbol.trim(), &exchange),
+                    exchange,
                 })
             } else {
                 None
@@ -61,7 +62,7 @@ impl Ticker {
             exchange.map(|exchange| Self {
                 exchange: exchange.to_string(),
                 symbol: s.to_uppercase().to_string(),
-                r#type: detect_ticker_type(s),
+                r#type: detect_ticker_type(s, &exchange),
             })
         } else {
             if let Some((symbol, qmt_exchange)) = s.rsplit_once('.') {
@@ -73,10 +74,11 @@ impl Ticker {
                     _ => qmt_exchange,
                 };
 
+                let exchange = exchange.trim().to_uppercase();
                 Some(Self {
-                    exchange: exchange.trim().to_uppercase().to_string(),
                     symbol: symbol.trim().to_uppercase().to_string(),
-                    r#type: detect_ticker_type(symbol),
+                    r#type: detect_ticker_type(symbol.trim(), &exchange),
+                    exchange,
                 })
             } else {
                 None
@@ -88,6 +90,30 @@ impl Ticker {
         Self::from_qmt_str(s)
     }
 
+    /// Exchange in ISO 10383 MIC, e.g. `XSHG` of both `600000` and `600000.SH`, and `XBSE` of
+    /// Beijing Stock Exchange whose tickers are kept with exchange `BSE`
+    pub fn exchange_mic(&self) -> &str {
+        match self.exchange.as_str() {
+            "SH" => "XSHG",
+            "SZ" => "XSHE",
+            "BJ" | "BSE" => "XBSE",
+            "HK" => "XHKG",
+            // Indexes of China Securities Index and China National Index are quoted on trading
+            // days of SSE and SZSE
+            "CSI" => "XSHG",
+            "CNI" => "XSHE",
+            _ => &self.exchange,
+        }
+    }
+
+    /// Currency of quotes, HK listings are quoted in HKD
+    pub fn currency(&self) -> Currency {
+        match self.exchange_mic() {
+            "XHKG" => Currency::Hkd,
+            _ => Currency::Cny,
+        }
+    }
+
     pub fn to_qmt_code(&self) -> String {
         let suffix = match self.exchange.as_str() {
             "XSHG" => "SH",
@@ -104,6 +130,17 @@ impl Ticker {
         self.to_qmt_code()
     }
 
+    /// Index quoted by the ticker, e.g. `000300.SH` of `000300.XSHG` or `H30269.CSI` as is
+    pub fn to_tickers_index(&self) -> TickersIndex {
+        let code = self.to_tushare_code();
+        let provider = code.rsplit_once('.').map(|(_, p)| p).unwrap_or_default();
+
+        TickersIndex {
+            provider: provider.to_string(),
+            symbol: self.symbol.clone(),
+        }
+    }
+
     pub fn to_sina_code(&self) -> String {
         let prefix = match self.exchange.as_str() {
             "XSHG" => "sh",
@@ -149,13 +186,11 @@ impl Display for TickersIndex {
 impl TickersIndex {
     pub fn to_tushare_code(&self) -> String {
         match self.provider.as_str() {
-            "CNI" | "CSI" => {
-                if self.symbol.len() == 6 {
-                    if self.symbol.starts_with("00") {
-                        return format!("{}.SH", self.symbol);
-                    } else if self.symbol.starts_with("39") {
-                        return format!("{}.SZ", self.symbol);
-                    }
+            "CNI" | "CSI" if self.symbol.len() == 6 => {
+                if self.symbol.starts_with("00") {
+                    return format!("{}.SH", self.symbol);
+                } else if self.symbol.starts_with("39") {
+                    return format!("{}.SZ", self.symbol);
                 }
             }
             _ => {}
@@ -165,12 +200,28 @@ impl TickersIndex {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
 pub enum TickerType {
     ConvBond,
+    Etf,
+    Index,
+    Lof,
+    MoneyFund,
+    Reit,
     Stock,
 }
 
+impl TickerType {
+    /// Exchange traded funds of any kind, which are quoted by fund klines and NAVs
+    pub fn is_fund(&self) -> bool {
+        matches!(
+            self,
+            TickerType::Etf | TickerType::Lof | TickerType::MoneyFund | TickerType::Reit
+        )
+    }
+}
+
 fn detect_ticker_exchange(symbol: &str) -> Option<String> {
     if symbol.len() == 6 {
         if symbol.starts_with("11")
@@ -187,7 +238,9 @@ fn detect_ticker_exchange(symbol: &str) -> Option<String> {
             || symbol.starts_with("12")
             || symbol.starts_with("15")
             || symbol.starts_with("16")
+            || symbol.starts_with("18")
             || symbol.starts_with("30")
+            || symbol.starts_with("39")
         {
             return Some("XSHE".to_string());
         } else if symbol.starts_with("43")
@@ -204,11 +257,58 @@ fn detect_ticker_exchange(symbol: &str) -> Option<String> {
     None
 }
 
-fn detect_ticker_type(symbol: &str) -> TickerType {
+/// Detect the type by symbol and exchange, a 6-digit index symbol without exchange is ambiguous
+/// with a stock (e.g. `000300`), so indexes are detected only with exchanges like `000300.SH`
+fn detect_ticker_type(symbol: &str, exchange: &str) -> TickerType {
+    if matches!(exchange, "CSI" | "CNI") {
+        return TickerType::Index;
+    }
+
     if symbol.len() == 6 {
+        let is_shanghai = matches!(exchange, "XSHG" | "SH");
+        let is_shenzhen = matches!(exchange, "XSHE" | "SZ");
+
         if symbol.starts_with("11") || symbol.starts_with("12") || symbol.starts_with("13") {
             return TickerType::ConvBond;
         }
+
+        if is_shanghai {
+            if symbol.starts_with("000") {
+                return TickerType::Index;
+            } else if symbol.starts_with("508") {
+                return TickerType::Reit;
+            } else if symbol.starts_with("5116")
+                || symbol.starts_with("5117")
+                || symbol.starts_with("5118")
+                || symbol.starts_with("5119")
+            {
+                return TickerType::MoneyFund;
+            } else if symbol.starts_with("500")
+                || symbol.starts_with("501")
+                || symbol.starts_with("502")
+                || symbol.starts_with("505")
+                || symbol.starts_with("506")
+            {
+                return TickerType::Lof;
+            } else if symbol.starts_with("51")
+                || symbol.starts_with("56")
+                || symbol.starts_with("58")
+            {
+                return TickerType::Etf;
+            }
+        } else if is_shenzhen {
+            if symbol.starts_with("399") {
+                return TickerType::Index;
+            } else if symbol.starts_with("180") {
+                return TickerType::Reit;
+            } else if symbol.starts_with("1590") {
+                return TickerType::MoneyFund;
+            } else if symbol.starts_with("150") || symbol.starts_with("16") {
+                return TickerType::Lof;
+            } else if symbol.starts_with("15") {
+                return TickerType::Etf;
+            }
+        }
     }
 
     TickerType::Stock
@@ -218,6 +318,60 @@ fn detect_ticker_type(symbol: &str) -> TickerType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_detect_ticker_type() {
+        let ticker_type = |s: &str| Ticker::from_str(s).unwrap().r#type;
+
+        assert_eq!(ticker_type("600000"), TickerType::Stock);
+        assert_eq!(ticker_type("000300"), TickerType::Stock);
+        assert_eq!(ticker_type("00700"), TickerType::Stock);
+        assert_eq!(ticker_type("123029"), TickerType::ConvBond);
+        assert_eq!(ticker_type("510300"), TickerType::Etf);
+        assert_eq!(ticker_type("518880"), TickerType::Etf);
+        assert_eq!(ticker_type("511260"), TickerType::Etf);
+        assert_eq!(ticker_type("588000"), TickerType::Etf);
+        assert_eq!(ticker_type("159545"), TickerType::Etf);
+        assert_eq!(ticker_type("511880"), TickerType::MoneyFund);
+        assert_eq!(ticker_type("159001"), TickerType::MoneyFund);
+        assert_eq!(ticker_type("501018"), TickerType::Lof);
+        assert_eq!(ticker_type("161725"), TickerType::Lof);
+        assert_eq!(ticker_type("508000"), TickerType::Reit);
+        assert_eq!(ticker_type("180101"), TickerType::Reit);
+        assert_eq!(ticker_type("399001"), TickerType::Index);
+        assert_eq!(ticker_type("000300.XSHG"), TickerType::Index);
+        assert_eq!(
+            Ticker::from_qmt_str("000300.SH").unwrap().r#type,
+            TickerType::Index
+        );
+        assert_eq!(ticker_type("000300.XSHE"), TickerType::Stock);
+        assert_eq!(ticker_type("931468.CSI"), TickerType::Index);
+    }
+
+    #[test]
+    fn test_exchange_mic() {
+        let exchange_mic = |s: &str| Ticker::from_str(s).unwrap().exchange_mic().to_string();
+        assert_eq!(exchange_mic("600000"), "XSHG");
+        assert_eq!(exchange_mic("000001.SZ"), "XSHE");
+        assert_eq!(exchange_mic("430047"), "XBSE");
+        assert_eq!(exchange_mic("430047.BJ"), "XBSE");
+        assert_eq!(exchange_mic("00700"), "XHKG");
+        assert_eq!(exchange_mic("000300.CSI"), "XSHG");
+        assert_eq!(exchange_mic("399006.CNI"), "XSHE");
+    }
+
+    #[test]
+    fn test_ticker_currency() {
+        assert_eq!(
+            Ticker::from_str("600000").unwrap().currency(),
+            Currency::Cny
+        );
+        assert_eq!(Ticker::from_str("00700").unwrap().currency(), Currency::Hkd);
+        assert_eq!(
+            Ticker::from_tushare_str("00700.HK").unwrap().currency(),
+            Currency::Hkd
+        );
+    }
+
     #[tokio::test]
     async fn test_tickers_index_to_tushare_code() {
         assert_eq!(
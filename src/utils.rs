@@ -1,5 +1,6 @@
 pub mod compress;
 pub mod datetime;
+pub mod expr;
 pub mod financial;
 pub mod math;
 pub mod net;
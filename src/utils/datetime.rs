@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use chrono::{DateTime, Datelike, NaiveDate};
+use chrono::{DateTime, Datelike, Months, NaiveDate};
 
 use crate::error::{VfError, VfResult};
 
@@ -42,6 +42,22 @@ pub fn date_to_fiscal_quarter(date: &NaiveDate) -> FiscalQuarter {
     FiscalQuarter { year, quarter }
 }
 
+/// Statutory deadline to announce the report of the period in China, i.e. Q1 by Apr 30, H1 by
+/// Aug 31, Q3 by Oct 31 and annual by Apr 30 of the next year
+pub fn report_deadline(period: &NaiveDate) -> NaiveDate {
+    let (year, month, day) = match (period.month(), period.day()) {
+        (3, 31) => (period.year(), 4, 30),
+        (6, 30) => (period.year(), 8, 31),
+        (9, 30) => (period.year(), 10, 31),
+        (12, 31) => (period.year() + 1, 4, 30),
+        _ => {
+            return period.checked_add_months(Months::new(4)).unwrap_or(*period);
+        }
+    };
+
+    NaiveDate::from_ymd_opt(year, month, day).unwrap_or(*period)
+}
+
 pub fn date_to_str(date: &NaiveDate) -> String {
     date.format("%Y-%m-%d").to_string()
 }
@@ -170,6 +186,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_report_deadline() {
+        assert_eq!(
+            date_to_str(&report_deadline(&date_from_str("20230331").unwrap())),
+            "2023-04-30"
+        );
+        assert_eq!(
+            date_to_str(&report_deadline(&date_from_str("20230630").unwrap())),
+            "2023-08-31"
+        );
+        assert_eq!(
+            date_to_str(&report_deadline(&date_from_str("20230930").unwrap())),
+            "2023-10-31"
+        );
+        assert_eq!(
+            date_to_str(&report_deadline(&date_from_str("20231231").unwrap())),
+            "2024-04-30"
+        );
+        assert_eq!(
+            date_to_str(&report_deadline(&date_from_str("20231115").unwrap())),
+            "2024-03-15"
+        );
+    }
+
     #[test]
     fn test_secs_to_human_str() {
         assert_eq!(secs_to_human_str(0), "0s");
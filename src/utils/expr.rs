@@ -0,0 +1,640 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    error::{VfError, VfResult},
+    utils::math::normalize_zscore,
+};
+
+/// Functions evaluated over the whole cross-section or element-wise, any other call is a data term
+const BUILTIN_FUNCTIONS: &[&str] = &["abs", "log", "max", "min", "rank", "sign", "sqrt", "zscore"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Term(Term),
+    Call(String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub name: String,
+    pub args: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+impl Expr {
+    pub fn parse(expr_str: &str) -> VfResult<Self> {
+        let tokens = tokenize(expr_str)?;
+        let mut parser = Parser {
+            source: expr_str,
+            tokens,
+            pos: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error(&format!("Unexpected token '{}'", parser.tokens[parser.pos])));
+        }
+
+        Ok(expr)
+    }
+
+    /// Evaluate the expression over a cross-section of `len` items, `terms_values` maps every term key to its values
+    pub fn eval(
+        &self,
+        terms_values: &HashMap<String, Vec<Option<f64>>>,
+        len: usize,
+    ) -> VfResult<Vec<Option<f64>>> {
+        let values = match self {
+            Expr::Number(v) => vec![Some(*v); len],
+            Expr::Term(term) => {
+                let key = term.to_string();
+                let values = terms_values.get(&key).ok_or(VfError::NoData {
+                    code: "NO_TERM_DATA",
+                    message: format!("Values of term '{key}' not exists"),
+                })?;
+                if values.len() != len {
+                    return Err(VfError::Invalid {
+                        code: "INVALID_TERM_DATA",
+                        message: format!(
+                            "Values count of term '{key}' is {} but {len} expected",
+                            values.len()
+                        ),
+                    });
+                }
+
+                values.clone()
+            }
+            Expr::Unary(op, operand) => {
+                let values = operand.eval(terms_values, len)?;
+                values
+                    .into_iter()
+                    .map(|v| {
+                        v.map(|v| match op {
+                            UnaryOp::Neg => -v,
+                            UnaryOp::Not => bool_to_f64(v == 0.0),
+                        })
+                    })
+                    .collect()
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs_values = lhs.eval(terms_values, len)?;
+                let rhs_values = rhs.eval(terms_values, len)?;
+                lhs_values
+                    .into_iter()
+                    .zip(rhs_values)
+                    .map(|(a, b)| match (a, b) {
+                        (Some(a), Some(b)) => Some(match op {
+                            BinaryOp::Add => a + b,
+                            BinaryOp::Sub => a - b,
+                            BinaryOp::Mul => a * b,
+                            BinaryOp::Div => a / b,
+                            BinaryOp::Pow => a.powf(b),
+                            BinaryOp::Lt => bool_to_f64(a < b),
+                            BinaryOp::Lte => bool_to_f64(a <= b),
+                            BinaryOp::Gt => bool_to_f64(a > b),
+                            BinaryOp::Gte => bool_to_f64(a >= b),
+                            BinaryOp::Eq => bool_to_f64(a == b),
+                            BinaryOp::Ne => bool_to_f64(a != b),
+                            BinaryOp::And => bool_to_f64(a != 0.0 && b != 0.0),
+                            BinaryOp::Or => bool_to_f64(a != 0.0 || b != 0.0),
+                        }),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            Expr::Call(name, args) => {
+                let mut args_values: Vec<Vec<Option<f64>>> = vec![];
+                for arg in args {
+                    args_values.push(arg.eval(terms_values, len)?);
+                }
+
+                match (name.as_str(), args_values.as_slice()) {
+                    ("abs", [x]) => map_values(x, f64::abs),
+                    ("log", [x]) => map_values(x, f64::ln),
+                    ("sign", [x]) => map_values(x, f64::signum),
+                    ("sqrt", [x]) => map_values(x, f64::sqrt),
+                    ("max", [a, b]) => zip_values(a, b, f64::max),
+                    ("min", [a, b]) => zip_values(a, b, f64::min),
+                    ("rank", [x]) => rank_values(x),
+                    ("zscore", [x]) => zscore_values(x),
+                    _ => {
+                        return Err(VfError::Invalid {
+                            code: "INVALID_EXPRESSION",
+                            message: format!(
+                                "Function '{name}' does not accept {} argument(s)",
+                                args.len()
+                            ),
+                        });
+                    }
+                }
+            }
+        };
+
+        Ok(values
+            .into_iter()
+            .map(|v| v.filter(|v| v.is_finite()))
+            .collect())
+    }
+
+    /// All distinct data terms referenced by the expression
+    pub fn terms(&self) -> Vec<Term> {
+        let mut terms: Vec<Term> = vec![];
+        self.collect_terms(&mut terms);
+        terms
+    }
+
+    fn collect_terms(&self, terms: &mut Vec<Term>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Term(term) => {
+                if !terms.contains(term) {
+                    terms.push(term.clone());
+                }
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    arg.collect_terms(terms);
+                }
+            }
+            Expr::Unary(_, operand) => operand.collect_terms(terms),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_terms(terms);
+                rhs.collect_terms(terms);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.args.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            let args_str = self
+                .args
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(f, "{}({args_str})", self.name)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(v) => write!(f, "{v}"),
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Op(s) => write!(f, "{s}"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+const OPERATORS: &[&str] = &[
+    "&&", "||", "<=", ">=", "==", "!=", "+", "-", "*", "/", "^", "<", ">", "!",
+];
+
+fn tokenize(expr_str: &str) -> VfResult<Vec<Token>> {
+    let chars: Vec<char> = expr_str.chars().collect();
+    let mut tokens: Vec<Token> = vec![];
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+
+            let num_str: String = chars[start..i].iter().collect();
+            let num = num_str.parse::<f64>().map_err(|_| VfError::Invalid {
+                code: "INVALID_EXPRESSION",
+                message: format!("Invalid number '{num_str}' in expression '{expr_str}'"),
+            })?;
+            tokens.push(Token::Number(num));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let ident: String = chars[start..i].iter().collect();
+            match ident.to_lowercase().as_str() {
+                "and" => tokens.push(Token::Op("&&")),
+                "or" => tokens.push(Token::Op("||")),
+                "not" => tokens.push(Token::Op("!")),
+                _ => tokens.push(Token::Ident(ident)),
+            }
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else if let Some(op) = OPERATORS.iter().find(|op| {
+            op.chars()
+                .enumerate()
+                .all(|(j, oc)| chars.get(i + j) == Some(&oc))
+        }) {
+            tokens.push(Token::Op(op));
+            i += op.len();
+        } else {
+            return Err(VfError::Invalid {
+                code: "INVALID_EXPRESSION",
+                message: format!("Unexpected character '{c}' in expression '{expr_str}'"),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> VfError {
+        VfError::Invalid {
+            code: "INVALID_EXPRESSION",
+            message: format!("{message} in expression '{}'", self.source),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        if let Some(Token::Op(op)) = self.peek()
+            && ops.contains(op)
+        {
+            let op = *op;
+            self.pos += 1;
+            return Some(op);
+        }
+
+        None
+    }
+
+    fn expect(&mut self, token: Token) -> VfResult<()> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expect '{token}'")))
+        }
+    }
+
+    fn parse_or(&mut self) -> VfResult<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.next_op(&["||"]).is_some() {
+            let rhs = self.parse_and()?;
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> VfResult<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.next_op(&["&&"]).is_some() {
+            let rhs = self.parse_not()?;
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> VfResult<Expr> {
+        if self.next_op(&["!"]).is_some() {
+            let operand = self.parse_not()?;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)));
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> VfResult<Expr> {
+        let lhs = self.parse_additive()?;
+        if let Some(op) = self.next_op(&["<=", ">=", "==", "!=", "<", ">"]) {
+            let rhs = self.parse_additive()?;
+            let op = match op {
+                "<=" => BinaryOp::Lte,
+                ">=" => BinaryOp::Gte,
+                "==" => BinaryOp::Eq,
+                "!=" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                _ => BinaryOp::Gt,
+            };
+            return Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_additive(&mut self) -> VfResult<Expr> {
+        let mut expr = self.parse_multiplicative()?;
+        while let Some(op) = self.next_op(&["+", "-"]) {
+            let rhs = self.parse_multiplicative()?;
+            let op = if op == "+" {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_multiplicative(&mut self) -> VfResult<Expr> {
+        let mut expr = self.parse_unary()?;
+        while let Some(op) = self.next_op(&["*", "/"]) {
+            let rhs = self.parse_unary()?;
+            let op = if op == "*" {
+                BinaryOp::Mul
+            } else {
+                BinaryOp::Div
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> VfResult<Expr> {
+        if self.next_op(&["-"]).is_some() {
+            let operand = self.parse_unary()?;
+            return Ok(match operand {
+                Expr::Number(v) => Expr::Number(-v),
+                _ => Expr::Unary(UnaryOp::Neg, Box::new(operand)),
+            });
+        }
+        if self.next_op(&["+"]).is_some() {
+            return self.parse_unary();
+        }
+
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> VfResult<Expr> {
+        let base = self.parse_primary()?;
+        if self.next_op(&["^"]).is_some() {
+            let exponent = self.parse_unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> VfResult<Expr> {
+        match self.peek().cloned() {
+            Some(Token::Number(v)) => {
+                self.pos += 1;
+                Ok(Expr::Number(v))
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+
+                let mut args: Vec<Expr> = vec![];
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    if self.peek() != Some(&Token::RParen) {
+                        loop {
+                            args.push(self.parse_or()?);
+                            if self.peek() == Some(&Token::Comma) {
+                                self.pos += 1;
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                    self.expect(Token::RParen)?;
+                }
+
+                let name = name.to_lowercase();
+                if BUILTIN_FUNCTIONS.contains(&name.as_str()) {
+                    Ok(Expr::Call(name, args))
+                } else {
+                    let mut term_args: Vec<f64> = vec![];
+                    for arg in &args {
+                        if let Expr::Number(v) = arg {
+                            term_args.push(*v);
+                        } else {
+                            return Err(
+                                self.error(&format!("Arguments of '{name}' must be numbers"))
+                            );
+                        }
+                    }
+
+                    Ok(Expr::Term(Term {
+                        name,
+                        args: term_args,
+                    }))
+                }
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(token) => Err(self.error(&format!("Unexpected token '{token}'"))),
+            None => Err(self.error("Unexpected end")),
+        }
+    }
+}
+
+fn bool_to_f64(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+fn map_values(values: &[Option<f64>], f: fn(f64) -> f64) -> Vec<Option<f64>> {
+    values.iter().map(|v| v.map(f)).collect()
+}
+
+fn zip_values(a: &[Option<f64>], b: &[Option<f64>], f: fn(f64, f64) -> f64) -> Vec<Option<f64>> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => Some(f(*a, *b)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Percentile rank in (0, 1], ties share the average rank
+fn rank_values(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut indexed: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.filter(|v| v.is_finite()).map(|v| (i, v)))
+        .collect();
+    indexed.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let n = indexed.len();
+    let mut ranks: Vec<Option<f64>> = vec![None; values.len()];
+
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && indexed[j + 1].1 == indexed[i].1 {
+            j += 1;
+        }
+
+        let avg_rank = (i + j) as f64 / 2.0 + 1.0;
+        for item in &indexed[i..=j] {
+            ranks[item.0] = Some(avg_rank / n as f64);
+        }
+
+        i = j + 1;
+    }
+
+    ranks
+}
+
+fn zscore_values(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let indexed: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.filter(|v| v.is_finite()).map(|v| (i, v)))
+        .collect();
+    let zscores = normalize_zscore(&indexed.iter().map(|&(_, v)| v).collect::<Vec<_>>());
+
+    let mut result: Vec<Option<f64>> = vec![None; values.len()];
+    for ((i, _), z) in indexed.iter().zip(zscores) {
+        result[*i] = Some(z);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let expr = Expr::parse("rank(-pb) * 0.5 + zscore(momentum(250)) - volatility(60)").unwrap();
+        assert_eq!(
+            expr.terms(),
+            vec![
+                Term {
+                    name: "pb".to_string(),
+                    args: vec![],
+                },
+                Term {
+                    name: "momentum".to_string(),
+                    args: vec![250.0],
+                },
+                Term {
+                    name: "volatility".to_string(),
+                    args: vec![60.0],
+                },
+            ]
+        );
+
+        assert!(Expr::parse("pb > 0 and roe(3) >= 0.1 or not close < 2").is_ok());
+        assert!(Expr::parse("rank(pb").is_err());
+        assert!(Expr::parse("momentum(pb)").is_err());
+        assert!(Expr::parse("pb $ 1").is_err());
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut terms_values: HashMap<String, Vec<Option<f64>>> = HashMap::new();
+        terms_values.insert(
+            "pb".to_string(),
+            vec![Some(1.0), Some(3.0), None, Some(2.0)],
+        );
+        terms_values.insert(
+            "momentum(250)".to_string(),
+            vec![Some(0.1), Some(0.2), Some(0.3), Some(0.4)],
+        );
+
+        let expr = Expr::parse("rank(-pb)").unwrap();
+        assert_eq!(
+            expr.eval(&terms_values, 4).unwrap(),
+            vec![Some(1.0), Some(1.0 / 3.0), None, Some(2.0 / 3.0)]
+        );
+
+        let expr = Expr::parse("2 ^ 2 * pb + momentum(250) * 10 - 1").unwrap();
+        assert_eq!(
+            expr.eval(&terms_values, 4).unwrap(),
+            vec![Some(4.0), Some(13.0), None, Some(11.0)]
+        );
+
+        let expr = Expr::parse("pb >= 2 && momentum(250) > 0.3").unwrap();
+        assert_eq!(
+            expr.eval(&terms_values, 4).unwrap(),
+            vec![Some(0.0), Some(0.0), None, Some(1.0)]
+        );
+
+        let expr = Expr::parse("pb / 0").unwrap();
+        assert_eq!(expr.eval(&terms_values, 4).unwrap(), vec![None; 4]);
+
+        let expr = Expr::parse("roe(3)").unwrap();
+        assert!(expr.eval(&terms_values, 4).is_err());
+    }
+}
@@ -168,7 +168,7 @@ mod tests {
 
     #[test]
     fn test_linear_regression() {
-        assert!((linear_regression(&vec![0.0, 2.0, 4.0]).unwrap().0 - 2.0).abs() < 1e-6);
+        assert!((linear_regression(&[0.0, 2.0, 4.0]).unwrap().0 - 2.0).abs() < 1e-6);
     }
 
     #[test]
@@ -82,17 +82,17 @@ mod tests {
 
     #[test]
     fn test_mad() {
-        assert_eq!(mad(&vec![1.0, -1.0, 1.0, -1.0]).unwrap(), 1.0);
+        assert_eq!(mad(&[1.0, -1.0, 1.0, -1.0]).unwrap(), 1.0);
     }
 
     #[test]
     fn test_mean() {
-        assert_eq!(mean(&vec![0.0, 1.0]).unwrap(), 0.5);
+        assert_eq!(mean(&[0.0, 1.0]).unwrap(), 0.5);
     }
 
     #[test]
     fn test_pct_change() {
-        assert_eq!(pct_change(&vec![1.0, 1.0, 2.0, 3.0]), [0.0, 1.0, 0.5]);
+        assert_eq!(pct_change(&[1.0, 1.0, 2.0, 3.0]), [0.0, 1.0, 0.5]);
     }
 
     #[test]
@@ -110,6 +110,6 @@ mod tests {
 
     #[test]
     fn test_std() {
-        assert_eq!(std(&vec![1.0, 1.0]).unwrap(), 0.0);
+        assert_eq!(std(&[1.0, 1.0]).unwrap(), 0.0);
     }
 }
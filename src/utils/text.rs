@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 
-use pinyin::ToPinyin;
+use pinyin::{ToPinyin, ToPinyinMulti};
 
 pub fn compare_phonetic(a: &str, b: &str) -> Ordering {
     natord::compare(&to_phonetic(a), &to_phonetic(b))
@@ -10,6 +10,75 @@ pub fn is_ascii_digits(text: &str) -> bool {
     !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
 }
 
+/// Number of ASCII digits or Chinese numerals up to thousands, e.g. `三十` or `十五`
+pub fn parse_chinese_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if is_ascii_digits(text) {
+        return text.parse().ok();
+    }
+
+    let mut total: u64 = 0;
+    let mut digit: Option<u64> = None;
+    for c in text.chars() {
+        let unit = match c {
+            '十' => 10,
+            '百' => 100,
+            '千' => 1000,
+            _ => {
+                digit = Some(match c {
+                    '零' | '〇' => 0,
+                    '一' => 1,
+                    '二' | '两' => 2,
+                    '三' => 3,
+                    '四' => 4,
+                    '五' => 5,
+                    '六' => 6,
+                    '七' => 7,
+                    '八' => 8,
+                    '九' => 9,
+                    _ => return None,
+                });
+                continue;
+            }
+        };
+        total += digit.take().unwrap_or(1) * unit;
+    }
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(total + digit.unwrap_or(0))
+    }
+}
+
+/// Whether the query is pinyin initials of consecutive characters in the text, e.g. `hldb` of
+/// `红利低波ETF`, any reading of polyphonic characters is accepted and other characters match
+/// themselves case-insensitively
+pub fn match_pinyin_initials(text: &str, query: &str) -> bool {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return false;
+    }
+
+    let initials: Vec<Vec<char>> = text
+        .chars()
+        .map(|c| match c.to_pinyin_multi() {
+            Some(multi) => multi
+                .into_iter()
+                .filter_map(|p| p.first_letter().chars().next())
+                .collect(),
+            None => c.to_lowercase().collect(),
+        })
+        .collect();
+
+    initials.windows(query.len()).any(|window| {
+        window
+            .iter()
+            .zip(&query)
+            .all(|(letters, q)| letters.contains(q))
+    })
+}
+
 fn to_phonetic(text: &str) -> String {
     let mut s = String::new();
 
@@ -42,7 +111,7 @@ mod tests {
     #[test]
     fn test_compare_phonetic() {
         let mut texts = ["你好", "世界", "こんにち", "저는", "Hello", "world"];
-        texts.sort_by(|a, b| compare_phonetic(&a, &b));
+        texts.sort_by(|a, b| compare_phonetic(a, b));
 
         assert_eq!(
             texts,
@@ -50,6 +119,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_match_pinyin_initials() {
+        assert!(match_pinyin_initials("红利低波ETF", "hldb"));
+        assert!(match_pinyin_initials("红利低波ETF", "DBetf"));
+        assert!(match_pinyin_initials("招商银行", "yh"));
+        assert!(!match_pinyin_initials("招商银行", "zsh"));
+        assert!(!match_pinyin_initials("招商银行", ""));
+    }
+
+    #[test]
+    fn test_parse_chinese_number() {
+        assert_eq!(parse_chinese_number("30"), Some(30));
+        assert_eq!(parse_chinese_number("十"), Some(10));
+        assert_eq!(parse_chinese_number("十五"), Some(15));
+        assert_eq!(parse_chinese_number("三十"), Some(30));
+        assert_eq!(parse_chinese_number("二十五"), Some(25));
+        assert_eq!(parse_chinese_number("一百零五"), Some(105));
+        assert_eq!(parse_chinese_number(""), None);
+        assert_eq!(parse_chinese_number("三十个"), None);
+    }
+
     #[test]
     fn test_is_ascii_digits() {
         assert!(is_ascii_digits("1234567890"));